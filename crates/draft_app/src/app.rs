@@ -1,11 +1,15 @@
 use std::mem::take;
 
-use draft_render::{IWorld, WorldRenderer};
+use draft_render::{
+    IWorld, WorldRenderer,
+    pipelined_renderer::{PipelinedRenderer, RenderMode},
+};
 use draft_window::{SystemWindow, SystemWindowManager};
+use fyrox_core::pool::Handle;
 
 use crate::{
-    GraphicsContext, InitializedGraphicsContext, Plugin, PluginContainer, RenderServerConstructor,
-    World,
+    AppEvent, GraphicsContext, InitializedGraphicsContext, Plugin, PluginContainer,
+    RenderServerConstructor, World,
 };
 
 type RunnerFn = Box<dyn FnOnce(App)>;
@@ -20,6 +24,7 @@ pub struct App {
     plugin_container: PluginContainer,
     system_window_manager: SystemWindowManager,
     world: World,
+    events: Vec<AppEvent>,
 
     pub(crate) runner: RunnerFn,
 }
@@ -28,6 +33,12 @@ fn run_once(_app: App) {
     println!("run_once")
 }
 
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl App {
     pub fn new() -> Self {
         App {
@@ -36,6 +47,7 @@ impl App {
             plugin_container: Default::default(),
             system_window_manager: Default::default(),
             world: World::empty(),
+            events: vec![],
         }
     }
 
@@ -57,13 +69,57 @@ impl App {
 
             renderer.initialize();
 
-            self.graphics_context = GraphicsContext::Initialized(InitializedGraphicsContext {
-                params: graphics_context_params.clone(),
-                renderer,
-            })
+            let renderer = PipelinedRenderer::new(renderer, graphics_context_params.render_mode);
+
+            self.graphics_context =
+                GraphicsContext::Initialized(Box::new(InitializedGraphicsContext {
+                    params: graphics_context_params.clone(),
+                    renderer,
+                }))
+        }
+    }
+
+    /// Where frames are rendered. Takes effect when the graphics context is
+    /// initialized, so it is set before the app runs.
+    pub fn set_render_mode(&mut self, render_mode: RenderMode) -> &mut Self {
+        if let GraphicsContext::Uninitialized(params) = &mut self.graphics_context {
+            params.render_mode = render_mode;
+        }
+        self
+    }
+
+    pub fn render_mode(&self) -> RenderMode {
+        self.graphics_context.params().render_mode
+    }
+
+    pub fn primary_window(&self) -> Handle<SystemWindow> {
+        self.system_window_manager.state().primary()
+    }
+
+    /// The windows of the app. Clones share the windows, so a world can keep one to
+    /// find the primary window while rendering.
+    pub fn system_window_manager(&self) -> &SystemWindowManager {
+        &self.system_window_manager
+    }
+
+    /// Despawns a window. Its surface is dropped by the renderer on the next frame.
+    pub fn close_window(&mut self, handle: Handle<SystemWindow>) {
+        let is_primary = handle == self.primary_window();
+
+        if self.system_window_manager.despawn_window(handle).is_some() {
+            self.events.push(AppEvent::WindowClosed(handle));
+
+            if is_primary {
+                self.events.push(AppEvent::PrimaryWindowClosed);
+            }
         }
     }
 
+    /// The events since the last call.
+    pub fn take_events(&mut self) -> Vec<AppEvent> {
+        take(&mut self.events)
+    }
+
     pub fn destroy(&mut self) {
         if let GraphicsContext::Initialized(initialized_graphics_context) = &self.graphics_context {
             let params = initialized_graphics_context.params.clone();
@@ -105,7 +161,7 @@ impl App {
 
     pub fn run(&mut self) {
         let runner = core::mem::replace(&mut self.runner, Box::new(run_once));
-        let app = core::mem::replace(self, App::new());
+        let app = take(self);
         (runner)(app)
     }
 }
@@ -0,0 +1,11 @@
+use draft_window::SystemWindow;
+use fyrox_core::pool::Handle;
+
+/// Something that happened to the app, collected until the runner takes the events
+/// with [`App::take_events`](crate::App::take_events).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppEvent {
+    WindowClosed(Handle<SystemWindow>),
+    /// The primary window was closed. Runners usually exit on this event.
+    PrimaryWindowClosed,
+}
@@ -1,5 +1,8 @@
 use draft_graphics::RenderServer;
-use draft_render::{IWorld, WorldRenderer};
+use draft_render::{
+    IWorld,
+    pipelined_renderer::{PipelinedRenderer, RenderMode},
+};
 use draft_window::SystemWindow;
 
 pub type RenderServerConstructor =
@@ -7,7 +10,7 @@ pub type RenderServerConstructor =
 
 pub enum GraphicsContext {
     /// Fully initialized graphics context. See [`InitializedGraphicsContext`] docs for more info.
-    Initialized(InitializedGraphicsContext),
+    Initialized(Box<InitializedGraphicsContext>),
 
     /// Uninitialized (suspended) graphics context. See [`GraphicsContextParams`] docs for more info.
     Uninitialized(GraphicsContextParams),
@@ -23,18 +26,30 @@ impl GraphicsContext {
             initialized_graphics_context.renderer.render(world);
         }
     }
+
+    pub fn params(&self) -> &GraphicsContextParams {
+        match self {
+            GraphicsContext::Initialized(initialized_graphics_context) => {
+                &initialized_graphics_context.params
+            }
+            GraphicsContext::Uninitialized(params) => params,
+        }
+    }
 }
 
 impl Default for GraphicsContext {
     fn default() -> Self {
-        GraphicsContext::Uninitialized(GraphicsContextParams {})
+        GraphicsContext::Uninitialized(GraphicsContextParams::default())
     }
 }
 
-#[derive(Clone)]
-pub struct GraphicsContextParams {}
+#[derive(Clone, Default)]
+pub struct GraphicsContextParams {
+    /// Whether frames are rendered on a render thread, see [`RenderMode`].
+    pub render_mode: RenderMode,
+}
 
 pub struct InitializedGraphicsContext {
     pub params: GraphicsContextParams,
-    pub renderer: WorldRenderer,
+    pub renderer: PipelinedRenderer,
 }
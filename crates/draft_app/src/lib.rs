@@ -1,9 +1,11 @@
 mod app;
+mod event;
 mod graphics_context;
 mod plugin;
 mod world;
 
 pub use app::*;
+pub use event::*;
 pub use graphics_context::*;
 pub use plugin::*;
-pub use world::*;
\ No newline at end of file
+pub use world::*;
@@ -12,7 +12,7 @@ pub struct VertexAttribute {
 }
 
 #[repr(C)]
-#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Reflect)]
+#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq, Reflect)]
 pub enum VertexFormat {
     /// One unsigned byte (u8). `u32` in shaders.
     Uint8 = 0,
@@ -39,6 +39,7 @@ pub enum VertexFormat {
     /// Four signed bytes (i8). [&minus;127, 127] converted to float [&minus;1, 1] `vec4<f32>` in shaders.
     Snorm8x4 = 11,
     /// One unsigned short (u16). `u32` in shaders.
+    #[default]
     Uint16 = 12,
     /// Two unsigned shorts (u16). `vec2<u32>` in shaders.
     Uint16x2 = 13,
@@ -144,9 +145,53 @@ impl VertexFormat {
     }
 }
 
-impl Default for VertexFormat {
-    fn default() -> Self {
-        VertexFormat::Uint16
+impl From<VertexFormat> for wgpu::VertexFormat {
+    fn from(value: VertexFormat) -> Self {
+        match value {
+            VertexFormat::Uint8 => wgpu::VertexFormat::Uint8,
+            VertexFormat::Uint8x2 => wgpu::VertexFormat::Uint8x2,
+            VertexFormat::Uint8x4 => wgpu::VertexFormat::Uint8x4,
+            VertexFormat::Sint8 => wgpu::VertexFormat::Sint8,
+            VertexFormat::Sint8x2 => wgpu::VertexFormat::Sint8x2,
+            VertexFormat::Sint8x4 => wgpu::VertexFormat::Sint8x4,
+            VertexFormat::Unorm8 => wgpu::VertexFormat::Unorm8,
+            VertexFormat::Unorm8x2 => wgpu::VertexFormat::Unorm8x2,
+            VertexFormat::Unorm8x4 => wgpu::VertexFormat::Unorm8x4,
+            VertexFormat::Snorm8 => wgpu::VertexFormat::Snorm8,
+            VertexFormat::Snorm8x2 => wgpu::VertexFormat::Snorm8x2,
+            VertexFormat::Snorm8x4 => wgpu::VertexFormat::Snorm8x4,
+            VertexFormat::Uint16 => wgpu::VertexFormat::Uint16,
+            VertexFormat::Uint16x2 => wgpu::VertexFormat::Uint16x2,
+            VertexFormat::Uint16x4 => wgpu::VertexFormat::Uint16x4,
+            VertexFormat::Sint16 => wgpu::VertexFormat::Sint16,
+            VertexFormat::Sint16x2 => wgpu::VertexFormat::Sint16x2,
+            VertexFormat::Sint16x4 => wgpu::VertexFormat::Sint16x4,
+            VertexFormat::Unorm16 => wgpu::VertexFormat::Unorm16,
+            VertexFormat::Unorm16x2 => wgpu::VertexFormat::Unorm16x2,
+            VertexFormat::Unorm16x4 => wgpu::VertexFormat::Unorm16x4,
+            VertexFormat::Snorm16 => wgpu::VertexFormat::Snorm16,
+            VertexFormat::Snorm16x2 => wgpu::VertexFormat::Snorm16x2,
+            VertexFormat::Snorm16x4 => wgpu::VertexFormat::Snorm16x4,
+            VertexFormat::Float16 => wgpu::VertexFormat::Float16,
+            VertexFormat::Float16x2 => wgpu::VertexFormat::Float16x2,
+            VertexFormat::Float16x4 => wgpu::VertexFormat::Float16x4,
+            VertexFormat::Float32 => wgpu::VertexFormat::Float32,
+            VertexFormat::Float32x2 => wgpu::VertexFormat::Float32x2,
+            VertexFormat::Float32x3 => wgpu::VertexFormat::Float32x3,
+            VertexFormat::Float32x4 => wgpu::VertexFormat::Float32x4,
+            VertexFormat::Uint32 => wgpu::VertexFormat::Uint32,
+            VertexFormat::Uint32x2 => wgpu::VertexFormat::Uint32x2,
+            VertexFormat::Uint32x3 => wgpu::VertexFormat::Uint32x3,
+            VertexFormat::Uint32x4 => wgpu::VertexFormat::Uint32x4,
+            VertexFormat::Sint32 => wgpu::VertexFormat::Sint32,
+            VertexFormat::Sint32x2 => wgpu::VertexFormat::Sint32x2,
+            VertexFormat::Sint32x3 => wgpu::VertexFormat::Sint32x3,
+            VertexFormat::Sint32x4 => wgpu::VertexFormat::Sint32x4,
+            VertexFormat::Float64 => wgpu::VertexFormat::Float64,
+            VertexFormat::Float64x2 => wgpu::VertexFormat::Float64x2,
+            VertexFormat::Float64x3 => wgpu::VertexFormat::Float64x3,
+            VertexFormat::Float64x4 => wgpu::VertexFormat::Float64x4,
+        }
     }
 }
 
@@ -173,3 +218,25 @@ pub enum PrimitiveTopology {
     /// Vertices `0 1 2 3 4 5` create four triangles `0 1 2`, `2 1 3`, `2 3 4`, and `4 3 5`
     TriangleStrip = 4,
 }
+
+impl From<PrimitiveTopology> for wgpu::PrimitiveTopology {
+    fn from(value: PrimitiveTopology) -> Self {
+        match value {
+            PrimitiveTopology::PointList => wgpu::PrimitiveTopology::PointList,
+            PrimitiveTopology::LineList => wgpu::PrimitiveTopology::LineList,
+            PrimitiveTopology::LineStrip => wgpu::PrimitiveTopology::LineStrip,
+            PrimitiveTopology::TriangleList => wgpu::PrimitiveTopology::TriangleList,
+            PrimitiveTopology::TriangleStrip => wgpu::PrimitiveTopology::TriangleStrip,
+        }
+    }
+}
+
+impl From<VertexAttribute> for wgpu::VertexAttribute {
+    fn from(value: VertexAttribute) -> Self {
+        wgpu::VertexAttribute {
+            format: value.format.into(),
+            offset: value.offset,
+            shader_location: value.shader_location,
+        }
+    }
+}
@@ -4,9 +4,14 @@ mod render_server;
 pub use common::*;
 pub use render_server::*;
 pub use wgpu::{
-    BufferAddress, CommandBuffer, DepthStencilState, MultisampleState, PrimitiveState,
-    RenderPipeline, RenderPipelineDescriptor, ShaderModule, ShaderModuleDescriptor, ShaderSource,
-    Surface, SurfaceConfiguration, SurfaceTexture, VertexStepMode, util::BufferInitDescriptor,
+    AddressMode, BindGroup, BindGroupLayout, BlendState, BufferAddress, Color, ColorTargetState,
+    ColorWrites, CommandBuffer, CompareFunction, ComputePipeline, DepthBiasState,
+    DepthStencilState, Extent3d, Face, FilterMode, FrontFace, MipmapFilterMode, MultisampleState,
+    PipelineLayout, PolygonMode, PrimitiveState, RenderPipeline, RenderPipelineDescriptor, Sampler,
+    SamplerDescriptor, ShaderModule, ShaderModuleDescriptor, ShaderSource, StencilState, Surface,
+    SurfaceConfiguration, SurfaceTexture, Texture, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureUsages, TextureView, TextureViewDescriptor, TextureViewDimension,
+    VertexStepMode, util::BufferInitDescriptor,
 };
 
 pub enum Pipeline {
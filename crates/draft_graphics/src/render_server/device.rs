@@ -1,19 +1,63 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+
+use fyrox_core::futures::executor::block_on;
 use wgpu::util::{BufferInitDescriptor, DeviceExt};
 
+/// Counts the bytes written from the CPU into buffers and textures. The device and
+/// the queue of a render server share one counter.
+#[derive(Clone, Default)]
+pub struct UploadCounter(Arc<AtomicU64>);
+
+impl UploadCounter {
+    pub fn add(&self, bytes: u64) {
+        self.0.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Bytes uploaded since the previous call.
+    pub fn take(&self) -> u64 {
+        self.0.swap(0, Ordering::Relaxed)
+    }
+}
+
 #[derive(Clone)]
 pub struct RenderDevice {
     device: wgpu::Device,
+    upload_counter: UploadCounter,
 }
 
 impl RenderDevice {
-    pub fn new(device: wgpu::Device) -> Self {
-        Self { device }
+    pub fn new(device: wgpu::Device, upload_counter: UploadCounter) -> Self {
+        Self {
+            device,
+            upload_counter,
+        }
     }
 
     pub fn wgpu_device(&self) -> &wgpu::Device {
         &self.device
     }
 
+    pub fn upload_counter(&self) -> &UploadCounter {
+        &self.upload_counter
+    }
+
+    /// Runs `func` inside a validation error scope and reports the first error it raised.
+    pub fn catch_validation_error<R>(
+        &self,
+        func: impl FnOnce(&wgpu::Device) -> R,
+    ) -> Result<R, wgpu::Error> {
+        let scope = self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let result = func(&self.device);
+
+        match block_on(scope.pop()) {
+            Some(error) => Err(error),
+            None => Ok(result),
+        }
+    }
+
     pub fn create_shader_module(&self, desc: wgpu::ShaderModuleDescriptor) -> wgpu::ShaderModule {
         self.device.create_shader_module(desc)
     }
@@ -25,6 +69,20 @@ impl RenderDevice {
         self.device.create_render_pipeline(desc)
     }
 
+    pub fn create_pipeline_layout(
+        &self,
+        desc: &wgpu::PipelineLayoutDescriptor,
+    ) -> wgpu::PipelineLayout {
+        self.device.create_pipeline_layout(desc)
+    }
+
+    pub fn create_compute_pipeline(
+        &self,
+        desc: &wgpu::ComputePipelineDescriptor,
+    ) -> wgpu::ComputePipeline {
+        self.device.create_compute_pipeline(desc)
+    }
+
     pub fn create_command_encoder(
         &self,
         desc: &wgpu::CommandEncoderDescriptor,
@@ -33,6 +91,7 @@ impl RenderDevice {
     }
 
     pub fn create_gpu_buffer_init(&self, desc: &BufferInitDescriptor) -> wgpu::Buffer {
+        self.upload_counter.add(desc.contents.len() as u64);
         self.device.create_buffer_init(desc)
     }
 
@@ -43,4 +102,19 @@ impl RenderDevice {
     pub fn create_gpu_texture(&self, desc: &wgpu::TextureDescriptor) -> wgpu::Texture {
         self.device.create_texture(desc)
     }
+
+    pub fn create_sampler(&self, desc: &wgpu::SamplerDescriptor) -> wgpu::Sampler {
+        self.device.create_sampler(desc)
+    }
+
+    pub fn create_bind_group_layout(
+        &self,
+        desc: &wgpu::BindGroupLayoutDescriptor,
+    ) -> wgpu::BindGroupLayout {
+        self.device.create_bind_group_layout(desc)
+    }
+
+    pub fn create_bind_group(&self, desc: &wgpu::BindGroupDescriptor) -> wgpu::BindGroup {
+        self.device.create_bind_group(desc)
+    }
 }
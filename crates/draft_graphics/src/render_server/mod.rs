@@ -3,24 +3,51 @@ mod device;
 use std::{ops::Deref, sync::Arc};
 
 use draft_window::SystemWindow;
-use wgpu::{Surface, SurfaceTargetUnsafe};
+use wgpu::{
+    DownlevelFlags, Features, PowerPreference, Surface, SurfaceTargetUnsafe, TextureFormat,
+    TextureFormatFeatures,
+};
 
 pub use device::*;
 
 #[derive(Clone)]
-pub struct RenderQueue(Arc<wgpu::Queue>);
+pub struct RenderQueue {
+    queue: Arc<wgpu::Queue>,
+    upload_counter: UploadCounter,
+}
 
 impl Deref for RenderQueue {
     type Target = wgpu::Queue;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.queue
     }
 }
 
 impl RenderQueue {
-    pub fn new(queue: wgpu::Queue) -> Self {
-        Self(Arc::new(queue))
+    pub fn new(queue: wgpu::Queue, upload_counter: UploadCounter) -> Self {
+        Self {
+            queue: Arc::new(queue),
+            upload_counter,
+        }
+    }
+
+    /// Like [`wgpu::Queue::write_buffer`], counting the written bytes as uploaded.
+    pub fn write_buffer(&self, buffer: &wgpu::Buffer, offset: wgpu::BufferAddress, data: &[u8]) {
+        self.upload_counter.add(data.len() as u64);
+        self.queue.write_buffer(buffer, offset, data);
+    }
+
+    /// Like [`wgpu::Queue::write_texture`], counting the written bytes as uploaded.
+    pub fn write_texture(
+        &self,
+        texture: wgpu::TexelCopyTextureInfo,
+        data: &[u8],
+        data_layout: wgpu::TexelCopyBufferLayout,
+        size: wgpu::Extent3d,
+    ) {
+        self.upload_counter.add(data.len() as u64);
+        self.queue.write_texture(texture, data, data_layout, size);
     }
 }
 
@@ -50,6 +77,15 @@ impl RenderAdapter {
     }
 }
 
+/// How [`RenderServer::initialize`] picks an adapter.
+#[derive(Debug, Clone, Default)]
+pub struct RenderServerSettings {
+    pub power_preference: PowerPreference,
+    /// Only use a software adapter, such as lavapipe or llvmpipe. Without it the
+    /// software adapter is still used when no hardware adapter is available.
+    pub force_fallback_adapter: bool,
+}
+
 #[derive(Clone)]
 pub struct RenderServer {
     pub device: RenderDevice,
@@ -59,41 +95,99 @@ pub struct RenderServer {
 }
 
 impl RenderServer {
+    /// Capabilities of a texture format on the device. Capabilities beyond the ones
+    /// guaranteed by WebGPU, such as more multisample counts, are only reported when
+    /// the adapter supports `TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES`.
+    pub fn texture_format_features(&self, format: TextureFormat) -> TextureFormatFeatures {
+        let features = self.device.wgpu_device().features();
+
+        if features.contains(Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES) {
+            self.adapter.get_texture_format_features(format)
+        } else {
+            format.guaranteed_format_features(features)
+        }
+    }
+
+    /// Whether the device can cull instances in a compute shader and draw them with
+    /// indirect draws that start at an instance other than zero.
+    pub fn supports_gpu_culling(&self) -> bool {
+        let downlevel = self.adapter.get_downlevel_capabilities();
+
+        self.device
+            .wgpu_device()
+            .features()
+            .contains(Features::INDIRECT_FIRST_INSTANCE)
+            && downlevel
+                .flags
+                .contains(DownlevelFlags::COMPUTE_SHADERS | DownlevelFlags::INDIRECT_EXECUTION)
+    }
+
+    /// Bytes written to buffers and textures through the device and the queue since
+    /// the previous call.
+    pub fn take_uploaded_bytes(&self) -> u64 {
+        self.device.upload_counter().take()
+    }
+
     pub fn create_surface(&self, window: &SystemWindow) -> Surface<'static> {
         let surface_target = SurfaceTargetUnsafe::RawHandle {
             raw_display_handle: Some(window.get_window().get_raw_display_handle()),
             raw_window_handle: window.get_window().get_raw_window_handle(),
         };
 
-        let surface = unsafe {
+        unsafe {
             // NOTE: On some OSes this MUST be called from the main thread.
             // As of wgpu 0.15, only fallible if the given window is a HTML canvas and obtaining a WebGPU or WebGL2 context fails.
             self.instance
                 .0
                 .create_surface_unsafe(surface_target)
                 .expect("Failed to create wgpu surface")
-        };
+        }
+    }
 
-        surface
+    /// Creates the device without any window, surfaces are created later for each
+    /// window that is rendered to.
+    pub async fn initialize(settings: &RenderServerSettings) -> Self {
+        Self::try_initialize(settings)
+            .await
+            .expect("No adapter available")
     }
 
-    pub async fn initialize(_window: SystemWindow) -> Self {
+    /// Like [`RenderServer::initialize`], `None` when no adapter or device is
+    /// available.
+    pub async fn try_initialize(settings: &RenderServerSettings) -> Option<Self> {
         let instance_descriptor = wgpu::InstanceDescriptor::new_without_display_handle();
         let instance = wgpu::Instance::new(instance_descriptor);
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions::default())
-            .await
-            .unwrap();
+        let mut options = wgpu::RequestAdapterOptions {
+            power_preference: settings.power_preference,
+            force_fallback_adapter: settings.force_fallback_adapter,
+            compatible_surface: None,
+        };
+        let adapter = instance.request_adapter(&options).await;
+        let adapter = match adapter {
+            Ok(adapter) => adapter,
+            Err(_) if !options.force_fallback_adapter => {
+                options.force_fallback_adapter = true;
+                instance.request_adapter(&options).await.ok()?
+            }
+            Err(_) => return None,
+        };
         let (device, queue) = adapter
-            .request_device(&wgpu::DeviceDescriptor::default())
+            .request_device(&wgpu::DeviceDescriptor {
+                required_features: adapter.features()
+                    & (Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+                        | Features::INDIRECT_FIRST_INSTANCE),
+                ..Default::default()
+            })
             .await
-            .unwrap();
+            .ok()?;
+
+        let upload_counter = UploadCounter::default();
 
-        RenderServer {
-            device: RenderDevice::new(device),
+        Some(RenderServer {
+            device: RenderDevice::new(device, upload_counter.clone()),
             instance: RenderInstance::new(instance),
             adapter: RenderAdapter::new(adapter),
-            queue: RenderQueue::new(queue)
-        }
+            queue: RenderQueue::new(queue, upload_counter),
+        })
     }
 }
@@ -0,0 +1,56 @@
+use draft_graphics::TextureFormat;
+use fyrox_core::{reflect::*, visitor::*};
+
+#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq, Reflect, Visit)]
+pub enum ImageFormat {
+    R8Unorm,
+    Rg8Unorm,
+    #[default]
+    Rgba8Unorm,
+    Rgba8UnormSrgb,
+    Bgra8Unorm,
+    Bgra8UnormSrgb,
+    R16Float,
+    Rg16Float,
+    Rgba16Float,
+    R32Float,
+    Rg32Float,
+    Rgba32Float,
+}
+
+impl ImageFormat {
+    /// Size of a single pixel in bytes.
+    pub fn pixel_size(&self) -> usize {
+        match self {
+            ImageFormat::R8Unorm => 1,
+            ImageFormat::Rg8Unorm | ImageFormat::R16Float => 2,
+            ImageFormat::Rgba8Unorm
+            | ImageFormat::Rgba8UnormSrgb
+            | ImageFormat::Bgra8Unorm
+            | ImageFormat::Bgra8UnormSrgb
+            | ImageFormat::Rg16Float
+            | ImageFormat::R32Float => 4,
+            ImageFormat::Rgba16Float | ImageFormat::Rg32Float => 8,
+            ImageFormat::Rgba32Float => 16,
+        }
+    }
+}
+
+impl From<ImageFormat> for TextureFormat {
+    fn from(value: ImageFormat) -> Self {
+        match value {
+            ImageFormat::R8Unorm => TextureFormat::R8Unorm,
+            ImageFormat::Rg8Unorm => TextureFormat::Rg8Unorm,
+            ImageFormat::Rgba8Unorm => TextureFormat::Rgba8Unorm,
+            ImageFormat::Rgba8UnormSrgb => TextureFormat::Rgba8UnormSrgb,
+            ImageFormat::Bgra8Unorm => TextureFormat::Bgra8Unorm,
+            ImageFormat::Bgra8UnormSrgb => TextureFormat::Bgra8UnormSrgb,
+            ImageFormat::R16Float => TextureFormat::R16Float,
+            ImageFormat::Rg16Float => TextureFormat::Rg16Float,
+            ImageFormat::Rgba16Float => TextureFormat::Rgba16Float,
+            ImageFormat::R32Float => TextureFormat::R32Float,
+            ImageFormat::Rg32Float => TextureFormat::Rg32Float,
+            ImageFormat::Rgba32Float => TextureFormat::Rgba32Float,
+        }
+    }
+}
@@ -1,5 +1,311 @@
-pub fn add(left: u64, right: u64) -> u64 {
-    left + right
+mod format;
+mod sampler;
+
+use std::{path::Path, sync::Arc};
+
+use fyrox_core::{
+    TypeUuidProvider, Uuid,
+    reflect::*,
+    sparse::AtomicIndex,
+    uuid,
+    visitor::{error::VisitError, *},
+};
+use fyrox_resource::{Resource, ResourceData};
+use thiserror::Error;
+
+pub use format::*;
+pub use sampler::*;
+
+pub type ImageResource = Resource<Image>;
+
+#[derive(Debug, Error)]
+pub enum ImageError {
+    #[error("Invalid image data size, expected {expected} bytes, got {actual} bytes.")]
+    InvalidDataSize { expected: usize, actual: usize },
+    #[error("Invalid mip count {mip_count}, the image supports at most {max} mips.")]
+    InvalidMipCount { mip_count: u32, max: u32 },
+}
+
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq, Reflect, Visit)]
+pub enum ImageKind {
+    D2 {
+        width: u32,
+        height: u32,
+    },
+    D2Array {
+        width: u32,
+        height: u32,
+        layers: u32,
+    },
+    Cube {
+        size: u32,
+    },
+    D3 {
+        width: u32,
+        height: u32,
+        depth: u32,
+    },
+}
+
+impl Default for ImageKind {
+    fn default() -> Self {
+        ImageKind::D2 {
+            width: 1,
+            height: 1,
+        }
+    }
+}
+
+impl ImageKind {
+    pub fn width(&self) -> u32 {
+        match *self {
+            ImageKind::D2 { width, .. }
+            | ImageKind::D2Array { width, .. }
+            | ImageKind::D3 { width, .. } => width,
+            ImageKind::Cube { size } => size,
+        }
+    }
+
+    pub fn height(&self) -> u32 {
+        match *self {
+            ImageKind::D2 { height, .. }
+            | ImageKind::D2Array { height, .. }
+            | ImageKind::D3 { height, .. } => height,
+            ImageKind::Cube { size } => size,
+        }
+    }
+
+    /// Depth of a 3D image, 1 otherwise.
+    pub fn depth(&self) -> u32 {
+        match *self {
+            ImageKind::D3 { depth, .. } => depth,
+            _ => 1,
+        }
+    }
+
+    /// Number of array layers, a cube image has 6 layers.
+    pub fn layer_count(&self) -> u32 {
+        match *self {
+            ImageKind::D2Array { layers, .. } => layers,
+            ImageKind::Cube { .. } => 6,
+            _ => 1,
+        }
+    }
+
+    pub fn max_mip_count(&self) -> u32 {
+        let size = self.width().max(self.height()).max(self.depth()).max(1);
+        u32::BITS - size.leading_zeros()
+    }
+
+    /// Width, height and depth of the given mip level.
+    pub fn mip_level_size(&self, mip_level: u32) -> (u32, u32, u32) {
+        (
+            (self.width() >> mip_level).max(1),
+            (self.height() >> mip_level).max(1),
+            (self.depth() >> mip_level).max(1),
+        )
+    }
+}
+
+/// Pixel data of an image. The data is stored layer by layer, and each layer stores
+/// its mip levels from the largest to the smallest one.
+#[derive(Debug, Clone, Default, Reflect, TypeUuidProvider)]
+#[type_uuid(id = "3c9f6d2e-54a1-4b8e-9d0f-7e2a1c5b8f43")]
+pub struct Image {
+    kind: ImageKind,
+    format: ImageFormat,
+    mip_count: u32,
+    data: Vec<u8>,
+    sampler: ImageSampler,
+    render_target: bool,
+
+    #[reflect(hidden)]
+    pub modifications_counter: u64,
+
+    #[reflect(hidden)]
+    pub sampler_modifications_counter: u64,
+
+    #[reflect(hidden)]
+    pub cache_index: Arc<AtomicIndex>,
+}
+
+impl Image {
+    pub fn new(
+        kind: ImageKind,
+        format: ImageFormat,
+        mip_count: u32,
+        data: Vec<u8>,
+    ) -> Result<Self, ImageError> {
+        let max = kind.max_mip_count();
+        if mip_count == 0 || mip_count > max {
+            return Err(ImageError::InvalidMipCount { mip_count, max });
+        }
+
+        let mut image = Self {
+            kind,
+            format,
+            mip_count,
+            data: vec![],
+            sampler: ImageSampler::default(),
+            render_target: false,
+            modifications_counter: 0,
+            sampler_modifications_counter: 0,
+            cache_index: Default::default(),
+        };
+
+        image.set_data(data)?;
+        image.modifications_counter = 0;
+
+        Ok(image)
+    }
+
+    /// Creates a 2D image without mips that cameras can render into. The pixel data is
+    /// zeroed.
+    pub fn new_render_target(width: u32, height: u32, format: ImageFormat) -> Self {
+        let kind = ImageKind::D2 { width, height };
+        let data = vec![0; width as usize * height as usize * format.pixel_size()];
+
+        let mut image =
+            Self::new(kind, format, 1, data).expect("Render target data matches its size.");
+        image.render_target = true;
+        image
+    }
+
+    pub fn kind(&self) -> ImageKind {
+        self.kind
+    }
+
+    pub fn format(&self) -> ImageFormat {
+        self.format
+    }
+
+    pub fn mip_count(&self) -> u32 {
+        self.mip_count
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn sampler(&self) -> &ImageSampler {
+        &self.sampler
+    }
+
+    pub fn is_render_target(&self) -> bool {
+        self.render_target
+    }
+
+    /// Replaces the pixel data. The data must match the size, format and mip count of
+    /// the image.
+    pub fn set_data(&mut self, data: Vec<u8>) -> Result<(), ImageError> {
+        let expected = self.layer_data_size() * self.kind.layer_count() as usize;
+        if data.len() != expected {
+            return Err(ImageError::InvalidDataSize {
+                expected,
+                actual: data.len(),
+            });
+        }
+
+        self.data = data;
+        self.modifications_counter += 1;
+
+        Ok(())
+    }
+
+    pub fn set_sampler(&mut self, sampler: ImageSampler) {
+        self.sampler = sampler;
+        self.sampler_modifications_counter += 1;
+    }
+
+    pub fn mip_level_data_size(&self, mip_level: u32) -> usize {
+        let (width, height, depth) = self.kind.mip_level_size(mip_level);
+        width as usize * height as usize * depth as usize * self.format.pixel_size()
+    }
+
+    /// Size of all mip levels of a single layer.
+    pub fn layer_data_size(&self) -> usize {
+        (0..self.mip_count)
+            .map(|mip_level| self.mip_level_data_size(mip_level))
+            .sum()
+    }
+
+    pub fn mip_level_data(&self, layer: u32, mip_level: u32) -> &[u8] {
+        let offset = self.layer_data_size() * layer as usize
+            + (0..mip_level)
+                .map(|mip_level| self.mip_level_data_size(mip_level))
+                .sum::<usize>();
+
+        &self.data[offset..offset + self.mip_level_data_size(mip_level)]
+    }
+}
+
+/// The pixel data is stored as a single binary blob. Loaded images are checked like
+/// the ones created with [`Image::new`], and count as modified so caches upload them
+/// again.
+impl Visit for Image {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        let mut region = visitor.enter_region(name)?;
+
+        self.kind.visit("Kind", &mut region)?;
+        self.format.visit("Format", &mut region)?;
+        self.mip_count.visit("MipCount", &mut region)?;
+        BinaryBlob {
+            vec: &mut self.data,
+        }
+        .visit("Data", &mut region)?;
+        self.sampler.visit("Sampler", &mut region)?;
+        self.render_target.visit("RenderTarget", &mut region)?;
+
+        if region.is_reading() {
+            let max = self.kind.max_mip_count();
+            if self.mip_count == 0 || self.mip_count > max {
+                return Err(VisitError::User(
+                    ImageError::InvalidMipCount {
+                        mip_count: self.mip_count,
+                        max,
+                    }
+                    .to_string(),
+                ));
+            }
+
+            let expected = self.layer_data_size() * self.kind.layer_count() as usize;
+            if self.data.len() != expected {
+                return Err(VisitError::User(
+                    ImageError::InvalidDataSize {
+                        expected,
+                        actual: self.data.len(),
+                    }
+                    .to_string(),
+                ));
+            }
+
+            self.modifications_counter += 1;
+            self.sampler_modifications_counter += 1;
+        }
+
+        Ok(())
+    }
+}
+
+impl ResourceData for Image {
+    fn type_uuid(&self) -> Uuid {
+        <Image as TypeUuidProvider>::type_uuid()
+    }
+
+    fn save(
+        &mut self,
+        #[allow(unused_variables)] path: &Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    fn can_be_saved(&self) -> bool {
+        false
+    }
+
+    fn try_clone_box(&self) -> Option<Box<dyn ResourceData>> {
+        Some(Box::new(self.clone()))
+    }
 }
 
 #[cfg(test)]
@@ -7,8 +313,70 @@ mod tests {
     use super::*;
 
     #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
+    fn mip_levels_are_stored_per_layer() {
+        let kind = ImageKind::D2Array {
+            width: 4,
+            height: 2,
+            layers: 2,
+        };
+        let layer_size = (4 * 2 + 2 + 1) * 4;
+        let data = (0..layer_size * 2).map(|i| i as u8).collect::<Vec<_>>();
+
+        let image = Image::new(kind, ImageFormat::Rgba8Unorm, 3, data).unwrap();
+
+        assert_eq!(kind.max_mip_count(), 3);
+        assert_eq!(image.layer_data_size(), layer_size);
+        assert_eq!(image.mip_level_data(1, 0)[0], layer_size as u8);
+        assert_eq!(image.mip_level_data(1, 2).len(), 4);
+    }
+
+    #[test]
+    fn rejects_invalid_data() {
+        let kind = ImageKind::Cube { size: 2 };
+
+        assert!(matches!(
+            Image::new(kind, ImageFormat::R8Unorm, 1, vec![0; 4]),
+            Err(ImageError::InvalidDataSize {
+                expected: 24,
+                actual: 4
+            })
+        ));
+        assert!(matches!(
+            Image::new(kind, ImageFormat::R8Unorm, 3, vec![]),
+            Err(ImageError::InvalidMipCount { .. })
+        ));
+    }
+
+    #[test]
+    fn visit_round_trips_the_image() {
+        let kind = ImageKind::D2 {
+            width: 2,
+            height: 2,
+        };
+        let mut image = Image::new(kind, ImageFormat::R8Unorm, 2, vec![1, 2, 3, 4, 5]).unwrap();
+        image.set_sampler(ImageSampler::nearest());
+
+        let mut visitor = Visitor::new();
+        image.visit("Image", &mut visitor).unwrap();
+        let bytes = visitor.save_binary_to_vec().unwrap();
+
+        let mut visitor = Visitor::load_binary_from_memory(&bytes).unwrap();
+        let mut loaded = Image::default();
+        loaded.visit("Image", &mut visitor).unwrap();
+
+        assert_eq!(loaded.kind(), kind);
+        assert_eq!(loaded.mip_count(), 2);
+        assert_eq!(loaded.data(), image.data());
+        assert_eq!(loaded.sampler(), &ImageSampler::nearest());
+        assert_eq!(loaded.modifications_counter, 1);
+    }
+
+    #[test]
+    fn render_target_has_zeroed_data() {
+        let image = Image::new_render_target(4, 2, ImageFormat::Rgba16Float);
+
+        assert!(image.is_render_target());
+        assert_eq!(image.mip_count(), 1);
+        assert_eq!(image.data(), &[0; 64][..]);
     }
 }
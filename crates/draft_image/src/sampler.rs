@@ -0,0 +1,70 @@
+use draft_graphics::{AddressMode, FilterMode, MipmapFilterMode};
+use fyrox_core::{reflect::*, visitor::*};
+
+#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq, Reflect, Visit)]
+pub enum ImageAddressMode {
+    #[default]
+    ClampToEdge,
+    Repeat,
+    MirrorRepeat,
+}
+
+impl From<ImageAddressMode> for AddressMode {
+    fn from(value: ImageAddressMode) -> Self {
+        match value {
+            ImageAddressMode::ClampToEdge => AddressMode::ClampToEdge,
+            ImageAddressMode::Repeat => AddressMode::Repeat,
+            ImageAddressMode::MirrorRepeat => AddressMode::MirrorRepeat,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq, Reflect, Visit)]
+pub enum ImageFilterMode {
+    Nearest,
+    #[default]
+    Linear,
+}
+
+impl From<ImageFilterMode> for FilterMode {
+    fn from(value: ImageFilterMode) -> Self {
+        match value {
+            ImageFilterMode::Nearest => FilterMode::Nearest,
+            ImageFilterMode::Linear => FilterMode::Linear,
+        }
+    }
+}
+
+impl From<ImageFilterMode> for MipmapFilterMode {
+    fn from(value: ImageFilterMode) -> Self {
+        match value {
+            ImageFilterMode::Nearest => MipmapFilterMode::Nearest,
+            ImageFilterMode::Linear => MipmapFilterMode::Linear,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq, Reflect, Visit)]
+pub struct ImageSampler {
+    pub address_mode_u: ImageAddressMode,
+    pub address_mode_v: ImageAddressMode,
+    pub address_mode_w: ImageAddressMode,
+    pub mag_filter: ImageFilterMode,
+    pub min_filter: ImageFilterMode,
+    pub mipmap_filter: ImageFilterMode,
+}
+
+impl ImageSampler {
+    pub fn nearest() -> Self {
+        Self {
+            mag_filter: ImageFilterMode::Nearest,
+            min_filter: ImageFilterMode::Nearest,
+            mipmap_filter: ImageFilterMode::Nearest,
+            ..Default::default()
+        }
+    }
+
+    pub fn linear() -> Self {
+        Self::default()
+    }
+}
@@ -1,5 +1,10 @@
 pub use draft_app as app;
+pub use draft_image as image;
+pub use draft_material as material;
+pub use draft_mesh as mesh;
 pub use draft_render as render;
+pub use draft_shader as shader;
+pub use draft_window as window;
 
 use draft_app::{App, Plugin};
 use draft_winit::WinitPlugin;
@@ -1,14 +1,180 @@
-pub fn add(left: u64, right: u64) -> u64 {
-    left + right
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use draft_image::ImageResource;
+use draft_shader::ShaderResource;
+use fyrox_core::{TypeUuidProvider, Uuid, reflect::*, sparse::AtomicIndex, uuid, visitor::*};
+use fyrox_resource::{Resource, ResourceData};
+
+pub type MaterialResource = Resource<Material>;
+
+/// How the material is blended with what was already drawn.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Reflect, Visit)]
+pub enum AlphaMode {
+    #[default]
+    Opaque,
+    /// Fragments with an alpha below the cutoff are discarded.
+    Mask(f32),
+    Blend,
+}
+
+#[derive(Debug, Clone, PartialEq, Reflect, Visit)]
+pub enum MaterialProperty {
+    Float(f32),
+    Vector2([f32; 2]),
+    Vector3([f32; 3]),
+    Vector4([f32; 4]),
+    UInt(u32),
+    Texture(Option<ImageResource>),
+}
+
+impl Default for MaterialProperty {
+    fn default() -> Self {
+        MaterialProperty::Float(0.0)
+    }
+}
+
+#[derive(Debug, Clone, Default, Reflect, TypeUuidProvider)]
+#[type_uuid(id = "b6d1f0a4-8e2c-4f57-a3d9-1c6e5b7a2f80")]
+pub struct Material {
+    shader: ShaderResource,
+    alpha_mode: AlphaMode,
+    properties: HashMap<String, MaterialProperty>,
+
+    #[reflect(hidden)]
+    pub modifications_counter: u64,
+
+    #[reflect(hidden)]
+    pub cache_index: Arc<AtomicIndex>,
+}
+
+impl Material {
+    pub fn new(shader: ShaderResource) -> Self {
+        Self {
+            shader,
+            ..Default::default()
+        }
+    }
+
+    pub fn shader(&self) -> &ShaderResource {
+        &self.shader
+    }
+
+    pub fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
+
+    pub fn set_alpha_mode(&mut self, alpha_mode: AlphaMode) {
+        self.alpha_mode = alpha_mode;
+        self.modifications_counter += 1;
+    }
+
+    pub fn property(&self, name: &str) -> Option<&MaterialProperty> {
+        self.properties.get(name)
+    }
+
+    pub fn properties(&self) -> &HashMap<String, MaterialProperty> {
+        &self.properties
+    }
+
+    pub fn set_property(&mut self, name: &str, property: MaterialProperty) {
+        self.properties.insert(name.to_string(), property);
+        self.modifications_counter += 1;
+    }
+}
+
+/// Loaded materials count as modified, so their render data is created again.
+impl Visit for Material {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        let mut region = visitor.enter_region(name)?;
+
+        self.shader.visit("Shader", &mut region)?;
+        self.alpha_mode.visit("AlphaMode", &mut region)?;
+        self.properties.visit("Properties", &mut region)?;
+
+        if region.is_reading() {
+            self.modifications_counter += 1;
+        }
+
+        Ok(())
+    }
+}
+
+impl ResourceData for Material {
+    fn type_uuid(&self) -> Uuid {
+        <Material as TypeUuidProvider>::type_uuid()
+    }
+
+    fn save(
+        &mut self,
+        #[allow(unused_variables)] path: &Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    fn can_be_saved(&self) -> bool {
+        false
+    }
+
+    fn try_clone_box(&self) -> Option<Box<dyn ResourceData>> {
+        Some(Box::new(self.clone()))
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use draft_shader::Shader;
+    use fyrox_core::task::TaskPool;
+    use fyrox_resource::{io::FsResourceIo, manager::ResourceManager};
+
     use super::*;
 
     #[test]
-    fn it_works() {
-        let result = add(2, 2);
-        assert_eq!(result, 4);
+    fn changes_are_counted() {
+        let mut material = Material::default();
+
+        material.set_property("color", MaterialProperty::Vector4([1.0; 4]));
+        material.set_alpha_mode(AlphaMode::Mask(0.5));
+
+        assert_eq!(material.modifications_counter, 2);
+        assert_eq!(
+            material.property("color"),
+            Some(&MaterialProperty::Vector4([1.0; 4]))
+        );
+    }
+
+    #[test]
+    fn visit_round_trips_the_material() {
+        let shader = ShaderResource::new_embedded(Shader {
+            source: "@vertex fn main() {}".into(),
+            ..Default::default()
+        });
+        let mut material = Material::new(shader);
+        material.set_alpha_mode(AlphaMode::Mask(0.5));
+        material.set_property("color", MaterialProperty::Vector4([0.5; 4]));
+        material.set_property("texture", MaterialProperty::Texture(None));
+
+        let mut visitor = Visitor::new();
+        material.visit("Material", &mut visitor).unwrap();
+        let bytes = visitor.save_binary_to_vec().unwrap();
+
+        let resource_manager =
+            ResourceManager::new(Arc::new(FsResourceIo), Arc::new(TaskPool::new()));
+        resource_manager
+            .state()
+            .constructors_container
+            .add::<Shader>();
+
+        let mut visitor = Visitor::load_binary_from_memory(&bytes).unwrap();
+        visitor.blackboard.register(Arc::new(resource_manager));
+        let mut loaded = Material::default();
+        loaded.visit("Material", &mut visitor).unwrap();
+
+        assert_eq!(loaded.alpha_mode(), AlphaMode::Mask(0.5));
+        assert_eq!(loaded.properties(), material.properties());
+        assert!(matches!(
+            &loaded.shader().data_ref().source,
+            draft_shader::Source::Wgsl(source) if source == "@vertex fn main() {}"
+        ));
+        assert_eq!(loaded.modifications_counter, 1);
     }
 }
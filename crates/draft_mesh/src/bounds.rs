@@ -0,0 +1,104 @@
+use fyrox_core::algebra::{Matrix4, Point3, Vector3};
+
+/// An axis-aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>,
+}
+
+impl Aabb {
+    /// The smallest box that contains every point, `None` when there are no points.
+    pub fn from_points(points: impl IntoIterator<Item = Vector3<f32>>) -> Option<Self> {
+        points.into_iter().fold(None, |aabb, point| {
+            Some(match aabb {
+                Some(Aabb { min, max }) => Aabb {
+                    min: min.inf(&point),
+                    max: max.sup(&point),
+                },
+                None => Aabb {
+                    min: point,
+                    max: point,
+                },
+            })
+        })
+    }
+
+    pub fn center(&self) -> Vector3<f32> {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn half_extents(&self) -> Vector3<f32> {
+        (self.max - self.min) * 0.5
+    }
+
+    /// The axis-aligned box that contains this box transformed by `matrix`.
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> Aabb {
+        let center = matrix.transform_point(&Point3::from(self.center())).coords;
+        let half_extents = self.half_extents();
+
+        // Every axis of the result is the sum of the transformed extents, see
+        // "Transforming Axis-Aligned Bounding Boxes" by James Arvo.
+        let basis = matrix.fixed_view::<3, 3>(0, 0).abs();
+        let half_extents = basis * half_extents;
+
+        Aabb {
+            min: center - half_extents,
+            max: center + half_extents,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Vector3<f32>,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    /// The sphere transformed by `matrix`, scaled by the largest scale of its axes.
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> BoundingSphere {
+        let basis = matrix.fixed_view::<3, 3>(0, 0);
+        let scale = basis
+            .column_iter()
+            .map(|axis| axis.norm())
+            .fold(0.0, f32::max);
+
+        BoundingSphere {
+            center: matrix.transform_point(&Point3::from(self.center)).coords,
+            radius: self.radius * scale,
+        }
+    }
+}
+
+/// Bounding volumes of the positions of a mesh.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshBounds {
+    pub aabb: Aabb,
+    /// Centered on the box, so it contains every position.
+    pub sphere: BoundingSphere,
+}
+
+impl MeshBounds {
+    pub fn from_points(points: impl IntoIterator<Item = Vector3<f32>>) -> Option<Self> {
+        let points = points.into_iter().collect::<Vec<_>>();
+        let aabb = Aabb::from_points(points.iter().copied())?;
+        let center = aabb.center();
+        let radius = points
+            .iter()
+            .map(|point| (point - center).norm())
+            .fold(0.0, f32::max);
+
+        Some(MeshBounds {
+            aabb,
+            sphere: BoundingSphere { center, radius },
+        })
+    }
+
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> MeshBounds {
+        MeshBounds {
+            aabb: self.aabb.transform(matrix),
+            sphere: self.sphere.transform(matrix),
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use std::{collections::VecDeque, ops::Range};
+
+/// Number of modifications whose byte ranges are kept. Consumers that fall further
+/// behind have to copy the whole buffer.
+const MAX_DIRTY_RANGES: usize = 16;
+
+/// Bytes of a buffer touched by a single modification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DirtyRange {
+    /// The modification didn't change the data.
+    None,
+    Bytes(Range<usize>),
+    /// The size or layout of the data may have changed.
+    All,
+}
+
+impl DirtyRange {
+    /// The smallest range that covers both ranges.
+    pub fn union(self, other: DirtyRange) -> DirtyRange {
+        match (self, other) {
+            (DirtyRange::All, _) | (_, DirtyRange::All) => DirtyRange::All,
+            (DirtyRange::None, range) | (range, DirtyRange::None) => range,
+            (DirtyRange::Bytes(a), DirtyRange::Bytes(b)) => {
+                DirtyRange::Bytes(a.start.min(b.start)..a.end.max(b.end))
+            }
+        }
+    }
+}
+
+/// The byte ranges changed by the recent modifications of a buffer, so copies of the
+/// buffer only update what changed since they were made.
+#[derive(Debug, Clone, Default)]
+pub struct DirtyRanges {
+    /// The modification count after each modification, with the bytes it touched.
+    modifications: VecDeque<(u64, DirtyRange)>,
+}
+
+impl DirtyRanges {
+    pub(crate) fn push(&mut self, modifications_count: u64, range: DirtyRange) {
+        if self.modifications.len() == MAX_DIRTY_RANGES {
+            self.modifications.pop_front();
+        }
+
+        self.modifications.push_back((modifications_count, range));
+    }
+
+    /// The bytes changed after the modification count `since`, up to the latest
+    /// modification. [`DirtyRange::All`] when the modifications are no longer known.
+    pub fn since(&self, since: u64) -> DirtyRange {
+        match self.modifications.front() {
+            Some((first, _)) if *first <= since + 1 => self
+                .modifications
+                .iter()
+                .filter(|(modifications_count, _)| *modifications_count > since)
+                .fold(DirtyRange::None, |dirty, (_, range)| {
+                    dirty.union(range.clone())
+                }),
+            _ => DirtyRange::All,
+        }
+    }
+}
@@ -13,6 +13,10 @@ impl IndexBuffer {
         IndexBufferMut { index_buffer: self }
     }
 
+    pub fn indices(&self) -> &Indices {
+        &self.indices
+    }
+
     pub fn create_packed_index_buffer_data(&self) -> Vec<u8> {
         match &self.indices {
             Indices::U16(indices) => cast_slice(indices).to_vec(),
@@ -25,6 +29,12 @@ pub struct IndexBufferMut<'a> {
     index_buffer: &'a mut IndexBuffer,
 }
 
+impl<'a> Drop for IndexBufferMut<'a> {
+    fn drop(&mut self) {
+        self.index_buffer.modifications_counter += 1;
+    }
+}
+
 impl<'a> IndexBufferMut<'a> {
     pub fn set_indices_with_u16(&mut self, indices: &[u16]) {
         self.index_buffer.indices = Indices::U16(indices.to_vec());
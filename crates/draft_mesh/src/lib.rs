@@ -1,11 +1,17 @@
+mod bounds;
+mod dirty;
 mod index;
 mod vertex;
 
-use draft_graphics::PrimitiveTopology;
-use fyrox_core::{TypeUuidProvider, Uuid, reflect::*, sparse::AtomicIndex, uuid, visitor::*};
+use draft_graphics::{PrimitiveTopology, VertexFormat};
+use fyrox_core::{
+    TypeUuidProvider, Uuid, algebra::Vector3, reflect::*, sparse::AtomicIndex, uuid, visitor::*,
+};
 use fyrox_resource::{Resource, ResourceData};
 use std::sync::Arc;
 
+pub use bounds::*;
+pub use dirty::*;
 pub use index::*;
 pub use vertex::*;
 
@@ -23,6 +29,76 @@ pub struct Mesh {
     pub cache_index: Arc<AtomicIndex>,
 }
 
+impl Mesh {
+    /// Position of a vertex in local space.
+    pub const ATTRIBUTE_POSITION: MeshVertexAttribute =
+        MeshVertexAttribute::new(0, VertexFormat::Float32x3);
+
+    /// Unit normal of a vertex in local space.
+    pub const ATTRIBUTE_NORMAL: MeshVertexAttribute =
+        MeshVertexAttribute::new(1, VertexFormat::Float32x3);
+
+    /// First texture coordinate set, with the origin at the top left corner.
+    pub const ATTRIBUTE_UV_0: MeshVertexAttribute =
+        MeshVertexAttribute::new(2, VertexFormat::Float32x2);
+
+    /// Tangent in xyz, with the sign of the bitangent in w.
+    pub const ATTRIBUTE_TANGENT: MeshVertexAttribute =
+        MeshVertexAttribute::new(3, VertexFormat::Float32x4);
+
+    pub fn new(primitive_topology: PrimitiveTopology) -> Self {
+        Self {
+            primitive_topology,
+            ..Default::default()
+        }
+    }
+
+    pub fn primitive_topology(&self) -> PrimitiveTopology {
+        self.primitive_topology
+    }
+
+    pub fn insert_attribute(
+        &mut self,
+        attribute: MeshVertexAttribute,
+        values: impl Into<VertexAttributeValues>,
+    ) {
+        self.vertex_buffer
+            .get_mut()
+            .insert_attribute(attribute, values);
+    }
+
+    /// Overwrites the values of an attribute from `first_vertex` on, see
+    /// [`VertexBufferMut::set_attribute_values`].
+    pub fn set_attribute_values(
+        &mut self,
+        attribute_id: impl Into<MeshVertexAttributeId>,
+        first_vertex: usize,
+        values: impl Into<VertexAttributeValues>,
+    ) {
+        self.vertex_buffer
+            .get_mut()
+            .set_attribute_values(attribute_id, first_vertex, values);
+    }
+
+    /// Bounding volumes of the position attribute, `None` when the mesh has no
+    /// positions.
+    pub fn compute_bounds(&self) -> Option<MeshBounds> {
+        let positions = self
+            .vertex_buffer
+            .attribute(Mesh::ATTRIBUTE_POSITION)?
+            .as_float3()?;
+
+        MeshBounds::from_points(positions.iter().map(|position| Vector3::from(*position)))
+    }
+
+    pub fn set_indices_with_u16(&mut self, indices: &[u16]) {
+        self.index_buffer
+            .get_or_insert_default()
+            .get_mut()
+            .set_indices_with_u16(indices);
+    }
+}
+
 impl Visit for Mesh {
     fn visit(&mut self, _name: &str, _visitor: &mut Visitor) -> VisitResult {
         todo!()
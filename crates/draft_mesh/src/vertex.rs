@@ -1,13 +1,16 @@
 use std::{
     collections::{HashMap, HashSet},
     hash::{Hash, Hasher},
+    ops::Deref,
     sync::Arc,
 };
 
-use bytemuck::cast_slice;
+use bytemuck::{cast_slice, cast_slice_mut};
 use draft_graphics::{BufferAddress, VertexAttribute, VertexFormat, VertexStepMode};
 use fyrox_core::reflect::*;
 
+use crate::{DirtyRange, DirtyRanges};
+
 #[derive(Default, Clone, Debug, Hash, Eq, PartialEq)]
 pub struct VertexBufferLayout {
     /// The stride, in bytes, between elements of this buffer.
@@ -24,9 +27,90 @@ pub struct MeshVertexBufferLayout {
     pub(crate) layout: VertexBufferLayout,
 }
 
+impl MeshVertexBufferLayout {
+    pub fn attribute_ids(&self) -> &[MeshVertexAttributeId] {
+        &self.attribute_ids
+    }
+
+    pub fn layout(&self) -> &VertexBufferLayout {
+        &self.layout
+    }
+
+    pub fn contains(&self, attribute_id: impl Into<MeshVertexAttributeId>) -> bool {
+        self.attribute_ids.contains(&attribute_id.into())
+    }
+
+    /// Builds a layout that only holds the requested attributes, placed at the shader
+    /// locations the pipeline expects.
+    pub fn get_layout(
+        &self,
+        attribute_descriptors: &[VertexAttributeDescriptor],
+    ) -> Result<VertexBufferLayout, MissingVertexAttributeError> {
+        let mut attributes = Vec::with_capacity(attribute_descriptors.len());
+
+        for attribute_descriptor in attribute_descriptors.iter() {
+            let Some(index) = self
+                .attribute_ids
+                .iter()
+                .position(|id| *id == attribute_descriptor.id)
+            else {
+                return Err(MissingVertexAttributeError {
+                    id: attribute_descriptor.id,
+                    pipeline_type: None,
+                });
+            };
+
+            let layout_attribute = &self.layout.attributes[index];
+            attributes.push(VertexAttribute {
+                format: layout_attribute.format,
+                offset: layout_attribute.offset,
+                shader_location: attribute_descriptor.shader_location,
+            });
+        }
+
+        Ok(VertexBufferLayout {
+            array_stride: self.layout.array_stride,
+            step_mode: self.layout.step_mode,
+            attributes,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VertexAttributeDescriptor {
+    pub shader_location: u32,
+    pub id: MeshVertexAttributeId,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingVertexAttributeError {
+    pub id: MeshVertexAttributeId,
+    pub pipeline_type: Option<&'static str>,
+}
+
+impl std::fmt::Display for MissingVertexAttributeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Mesh is missing requested attribute {:?}", self.id)?;
+        if let Some(pipeline_type) = self.pipeline_type {
+            write!(f, " (pipeline type: {pipeline_type})")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for MissingVertexAttributeError {}
+
 #[derive(Clone, Debug)]
 pub struct MeshVertexBufferLayoutRef(pub Arc<MeshVertexBufferLayout>);
 
+impl Deref for MeshVertexBufferLayoutRef {
+    type Target = MeshVertexBufferLayout;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
 impl PartialEq for MeshVertexBufferLayoutRef {
     fn eq(&self, other: &Self) -> bool {
         Arc::ptr_eq(&self.0, &other.0)
@@ -48,12 +132,13 @@ pub struct MeshVertexBufferLayouts(HashSet<Arc<MeshVertexBufferLayout>>);
 
 impl MeshVertexBufferLayouts {
     pub fn insert(&mut self, layout: MeshVertexBufferLayout) -> MeshVertexBufferLayoutRef {
-        let layout = Arc::new(layout);
-
-        if !self.0.contains(&layout) {
-            self.0.insert(layout.clone());
+        if let Some(layout) = self.0.get(&layout) {
+            return MeshVertexBufferLayoutRef(layout.clone());
         }
 
+        let layout = Arc::new(layout);
+        self.0.insert(layout.clone());
+
         MeshVertexBufferLayoutRef(layout)
     }
 }
@@ -65,6 +150,8 @@ pub struct VertexBuffer {
 
     #[reflect(hidden)]
     pub modifications_counter: u64,
+    #[reflect(hidden)]
+    dirty_ranges: DirtyRanges,
 }
 
 impl VertexBuffer {
@@ -79,7 +166,7 @@ impl VertexBuffer {
         let mut vertex_count: Option<usize> = None;
         let mesh_attributes = &self.attributes;
 
-        for (_attribute_id, attribute_data) in mesh_attributes {
+        for attribute_data in mesh_attributes.values() {
             let attribute_len = attribute_data.values.len();
             if let Some(previous_vertex_count) = vertex_count {
                 if previous_vertex_count != attribute_len {
@@ -106,7 +193,8 @@ impl VertexBuffer {
         let vertex_count = self.count_vertices();
         // bundle into interleaved buffers
         let mut attribute_offset = 0;
-        for attribute_data in mesh_attributes.values() {
+        for attribute_id in self.attribute_ids.iter() {
+            let attribute_data = &mesh_attributes[attribute_id];
             let attribute_size = attribute_data.attribute.format.size() as usize;
             let attributes_bytes = attribute_data.values.get_bytes();
             for (vertex_index, attribute_bytes) in attributes_bytes
@@ -128,12 +216,28 @@ impl VertexBuffer {
         attributes_interleaved_buffer
     }
 
+    pub fn attribute(
+        &self,
+        attribute_id: impl Into<MeshVertexAttributeId>,
+    ) -> Option<&VertexAttributeValues> {
+        self.attributes
+            .get(&attribute_id.into())
+            .map(|data| &data.values)
+    }
+
     pub fn get_mut<'a>(&'a mut self) -> VertexBufferMut<'a> {
         VertexBufferMut {
             vertex_buffer: self,
+            dirty: DirtyRange::None,
         }
     }
 
+    /// Bytes of the packed vertex data changed by the modifications after
+    /// `modifications_count`.
+    pub fn dirty_range_since(&self, modifications_count: u64) -> DirtyRange {
+        self.dirty_ranges.since(modifications_count)
+    }
+
     fn insert_attribute(&mut self, attribute: MeshVertexAttribute, values: VertexAttributeValues) {
         self.insert_attribute_id(&attribute.id);
         self.attributes
@@ -182,11 +286,18 @@ impl VertexBuffer {
 
 pub struct VertexBufferMut<'a> {
     vertex_buffer: &'a mut VertexBuffer,
+    /// Bytes of the packed vertex data changed through this borrow.
+    dirty: DirtyRange,
 }
 
 impl<'a> Drop for VertexBufferMut<'a> {
     fn drop(&mut self) {
         self.vertex_buffer.modifications_counter += 1;
+
+        let dirty = std::mem::replace(&mut self.dirty, DirtyRange::None);
+        self.vertex_buffer
+            .dirty_ranges
+            .push(self.vertex_buffer.modifications_counter, dirty);
     }
 }
 
@@ -206,6 +317,49 @@ impl<'a> VertexBufferMut<'a> {
         }
 
         self.vertex_buffer.insert_attribute(attribute, values);
+        self.dirty = DirtyRange::All;
+    }
+
+    /// Overwrites the values of an existing attribute from `first_vertex` on. Unlike
+    /// [`Self::insert_attribute`], only the changed vertices are uploaded again.
+    pub fn set_attribute_values(
+        &mut self,
+        attribute_id: impl Into<MeshVertexAttributeId>,
+        first_vertex: usize,
+        values: impl Into<VertexAttributeValues>,
+    ) {
+        let attribute_id = attribute_id.into();
+        let values = values.into();
+        let vertex_size = self.vertex_buffer.get_vertex_size() as usize;
+
+        let Some(data) = self.vertex_buffer.attributes.get_mut(&attribute_id) else {
+            panic!("Failed to set attribute values. The attribute {attribute_id:?} is missing");
+        };
+
+        let values_format = VertexFormat::from(&values);
+        if values_format != data.attribute.format {
+            panic!(
+                "Failed to set attribute values. Given format is {values_format:?} but expected {:?}",
+                data.attribute.format
+            );
+        }
+
+        let last_vertex = first_vertex + values.len();
+        if last_vertex > data.values.len() {
+            panic!(
+                "Failed to set attribute values. Vertices {first_vertex}..{last_vertex} are out of {} vertices",
+                data.values.len()
+            );
+        }
+
+        let attribute_size = data.attribute.format.size() as usize;
+        data.values.get_bytes_mut()[first_vertex * attribute_size..last_vertex * attribute_size]
+            .copy_from_slice(values.get_bytes());
+
+        let dirty = std::mem::replace(&mut self.dirty, DirtyRange::None);
+        self.dirty = dirty.union(DirtyRange::Bytes(
+            first_vertex * vertex_size..last_vertex * vertex_size,
+        ));
     }
 }
 
@@ -339,6 +493,44 @@ impl VertexAttributeValues {
             VertexAttributeValues::Unorm8x4(values) => cast_slice(values),
         }
     }
+
+    /// The [`VertexAttributeValues`] as mutable bytes, see [`Self::get_bytes`].
+    #[expect(
+        clippy::match_same_arms,
+        reason = "Although the `values` binding on some match arms may have matching types, each variant has different semantics; thus it's not guaranteed that they will use the same type forever."
+    )]
+    pub fn get_bytes_mut(&mut self) -> &mut [u8] {
+        match self {
+            VertexAttributeValues::Float32(values) => cast_slice_mut(values),
+            VertexAttributeValues::Sint32(values) => cast_slice_mut(values),
+            VertexAttributeValues::Uint32(values) => cast_slice_mut(values),
+            VertexAttributeValues::Float32x2(values) => cast_slice_mut(values),
+            VertexAttributeValues::Sint32x2(values) => cast_slice_mut(values),
+            VertexAttributeValues::Uint32x2(values) => cast_slice_mut(values),
+            VertexAttributeValues::Float32x3(values) => cast_slice_mut(values),
+            VertexAttributeValues::Sint32x3(values) => cast_slice_mut(values),
+            VertexAttributeValues::Uint32x3(values) => cast_slice_mut(values),
+            VertexAttributeValues::Float32x4(values) => cast_slice_mut(values),
+            VertexAttributeValues::Sint32x4(values) => cast_slice_mut(values),
+            VertexAttributeValues::Uint32x4(values) => cast_slice_mut(values),
+            VertexAttributeValues::Sint16x2(values) => cast_slice_mut(values),
+            VertexAttributeValues::Snorm16x2(values) => cast_slice_mut(values),
+            VertexAttributeValues::Uint16x2(values) => cast_slice_mut(values),
+            VertexAttributeValues::Unorm16x2(values) => cast_slice_mut(values),
+            VertexAttributeValues::Sint16x4(values) => cast_slice_mut(values),
+            VertexAttributeValues::Snorm16x4(values) => cast_slice_mut(values),
+            VertexAttributeValues::Uint16x4(values) => cast_slice_mut(values),
+            VertexAttributeValues::Unorm16x4(values) => cast_slice_mut(values),
+            VertexAttributeValues::Sint8x2(values) => cast_slice_mut(values),
+            VertexAttributeValues::Snorm8x2(values) => cast_slice_mut(values),
+            VertexAttributeValues::Uint8x2(values) => cast_slice_mut(values),
+            VertexAttributeValues::Unorm8x2(values) => cast_slice_mut(values),
+            VertexAttributeValues::Sint8x4(values) => cast_slice_mut(values),
+            VertexAttributeValues::Snorm8x4(values) => cast_slice_mut(values),
+            VertexAttributeValues::Uint8x4(values) => cast_slice_mut(values),
+            VertexAttributeValues::Unorm8x4(values) => cast_slice_mut(values),
+        }
+    }
 }
 
 impl From<&VertexAttributeValues> for VertexFormat {
@@ -387,5 +579,27 @@ pub struct MeshVertexAttribute {
     pub format: VertexFormat,
 }
 
+impl MeshVertexAttribute {
+    pub const fn new(id: u64, format: VertexFormat) -> Self {
+        Self {
+            id: MeshVertexAttributeId(id),
+            format,
+        }
+    }
+
+    pub const fn at_shader_location(&self, shader_location: u32) -> VertexAttributeDescriptor {
+        VertexAttributeDescriptor {
+            shader_location,
+            id: self.id,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Reflect, Eq, PartialOrd, Ord, Hash, Copy)]
 pub struct MeshVertexAttributeId(u64);
+
+impl From<MeshVertexAttribute> for MeshVertexAttributeId {
+    fn from(attribute: MeshVertexAttribute) -> Self {
+        attribute.id
+    }
+}
@@ -0,0 +1,105 @@
+use draft_mesh::{Aabb, BoundingSphere, MeshBounds};
+use fyrox_resource::core::algebra::{Matrix4, Vector3, Vector4};
+
+/// The volume a view projection sees, as six planes facing inwards. A plane is stored
+/// as its unit normal in xyz and the distance from the origin in w.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frustum {
+    pub planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes from a view projection with a depth range of zero to one,
+    /// see "Fast Extraction of Viewing Frustum Planes" by Gribb and Hartmann.
+    pub fn from_view_projection(view_projection: &Matrix4<f32>) -> Self {
+        let row = |index: usize| view_projection.row(index).transpose();
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+
+        let normalize = |plane: Vector4<f32>| {
+            let length = plane.xyz().norm();
+            if length > 0.0 { plane / length } else { plane }
+        };
+
+        Self {
+            planes: [w + x, w - x, w + y, w - y, z, w - z].map(normalize),
+        }
+    }
+
+    fn signed_distance(plane: &Vector4<f32>, point: &Vector3<f32>) -> f32 {
+        plane.xyz().dot(point) + plane.w
+    }
+
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| Self::signed_distance(plane, &sphere.center) >= -sphere.radius)
+    }
+
+    /// Tests the corner of the box furthest along the normal of each plane, so boxes
+    /// near the edges of the frustum may pass without being visible.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            let corner = Vector3::new(
+                if plane.x >= 0.0 {
+                    aabb.max.x
+                } else {
+                    aabb.min.x
+                },
+                if plane.y >= 0.0 {
+                    aabb.max.y
+                } else {
+                    aabb.min.y
+                },
+                if plane.z >= 0.0 {
+                    aabb.max.z
+                } else {
+                    aabb.min.z
+                },
+            );
+
+            Self::signed_distance(plane, &corner) >= 0.0
+        })
+    }
+
+    /// Tests the cheaper sphere first and the tighter box only when the sphere passes.
+    pub fn intersects(&self, bounds: &MeshBounds) -> bool {
+        self.intersects_sphere(&bounds.sphere) && self.intersects_aabb(&bounds.aabb)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::PerspectiveProjection;
+
+    #[test]
+    fn culls_bounds_outside_of_the_frustum() {
+        let projection = PerspectiveProjection {
+            near: 0.1,
+            far: 100.0,
+            ..Default::default()
+        };
+        let frustum = Frustum::from_view_projection(&projection.matrix(1.0));
+
+        let cube =
+            MeshBounds::from_points([Vector3::new(-0.5, -0.5, -0.5), Vector3::new(0.5, 0.5, 0.5)])
+                .unwrap();
+        let at = |x: f32, y: f32, z: f32| {
+            cube.transform(&Matrix4::new_translation(&Vector3::new(x, y, z)))
+        };
+
+        assert!(frustum.intersects(&at(0.0, 0.0, -10.0)));
+        assert!(!frustum.intersects(&at(0.0, 0.0, 10.0)));
+        assert!(!frustum.intersects(&at(0.0, 0.0, -200.0)));
+        assert!(!frustum.intersects(&at(50.0, 0.0, -10.0)));
+        // The right plane of the frustum is at x = 4.14 at the center of the cube.
+        assert!(frustum.intersects(&at(4.5, 0.0, -10.0)));
+        assert!(!frustum.intersects(&at(5.0, 0.0, -10.0)));
+
+        let scaled = cube.transform(&Matrix4::new_nonuniform_scaling(&Vector3::new(
+            4.0, 1.0, 1.0,
+        )));
+        assert_eq!(scaled.aabb.max, Vector3::new(2.0, 0.5, 0.5));
+        assert!(scaled.sphere.radius >= scaled.aabb.half_extents().norm());
+    }
+}
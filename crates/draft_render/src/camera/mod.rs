@@ -0,0 +1,369 @@
+mod frustum;
+mod msaa;
+mod projection;
+
+use bytemuck::{Pod, Zeroable};
+use draft_graphics::Color;
+use draft_image::ImageResource;
+use draft_window::SystemWindow;
+use fyrox_resource::core::{
+    algebra::{Matrix4, Vector2},
+    pool::Handle,
+};
+
+pub use frustum::*;
+pub use msaa::*;
+pub use projection::*;
+
+use crate::{
+    CORE_2D, CORE_3D, post_process::PostProcessSettings, render_phase::LayerMask,
+    render_world::OffscreenTarget,
+};
+
+/// What a camera renders into.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RenderTarget {
+    Window(Handle<SystemWindow>),
+    /// An offscreen image created with
+    /// [`Image::new_render_target`](draft_image::Image::new_render_target).
+    Image(ImageResource),
+    /// A texture created with
+    /// [`RenderWorld::create_offscreen_target`](crate::render_world::RenderWorld::create_offscreen_target).
+    Offscreen(Handle<OffscreenTarget>),
+}
+
+impl From<Handle<SystemWindow>> for RenderTarget {
+    fn from(handle: Handle<SystemWindow>) -> Self {
+        RenderTarget::Window(handle)
+    }
+}
+
+impl From<Handle<OffscreenTarget>> for RenderTarget {
+    fn from(handle: Handle<OffscreenTarget>) -> Self {
+        RenderTarget::Offscreen(handle)
+    }
+}
+
+impl From<ImageResource> for RenderTarget {
+    fn from(image: ImageResource) -> Self {
+        RenderTarget::Image(image)
+    }
+}
+
+/// A rectangle of the render target, in fractions of the target size. The origin is
+/// the top left corner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Viewport::FULL
+    }
+}
+
+impl Viewport {
+    pub const FULL: Viewport = Viewport {
+        x: 0.0,
+        y: 0.0,
+        width: 1.0,
+        height: 1.0,
+    };
+
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Converts the viewport to pixels of a target of the given size. The result is
+    /// clamped to the target.
+    pub fn physical(&self, target_width: u32, target_height: u32) -> PhysicalViewport {
+        let scale = |fraction: f32, size: u32| (fraction.clamp(0.0, 1.0) * size as f32) as u32;
+
+        let x = scale(self.x, target_width);
+        let y = scale(self.y, target_height);
+
+        PhysicalViewport {
+            x,
+            y,
+            width: scale(self.x + self.width, target_width).saturating_sub(x),
+            height: scale(self.y + self.height, target_height).saturating_sub(y),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PhysicalViewport {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl PhysicalViewport {
+    pub fn size(&self) -> Vector2<f32> {
+        Vector2::new(self.width as f32, self.height as f32)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+}
+
+/// A view into the world, submitted every frame through
+/// [`RenderContext::add_camera`](crate::RenderContext::add_camera).
+///
+/// Cameras are rendered in ascending priority. The clear color clears the whole
+/// target, so cameras that share a target usually clear only in the first one.
+#[derive(Debug, Clone)]
+pub struct Camera {
+    pub target: RenderTarget,
+    pub projection: Projection,
+    /// Placement of the camera in the world. The camera looks along its negative z
+    /// axis.
+    pub world_matrix: Matrix4<f32>,
+    pub viewport: Viewport,
+    pub clear_color: Option<Color>,
+    pub priority: i32,
+    /// Only draw items sharing a layer with the camera are rendered.
+    pub layer_mask: LayerMask,
+    /// Name of the [`RenderPipeline`](crate::render_pipeline::RenderPipeline) that
+    /// renders the view of the camera.
+    pub render_pipeline: String,
+    /// Multisampling of the view. Settings the formats of the view don't support on
+    /// the device fall back to lower ones.
+    pub msaa: Msaa,
+    /// Effects that resolve the HDR view into the target. Without them the view is
+    /// drawn straight into the target.
+    pub post_process: Option<PostProcessSettings>,
+    /// Culls the opaque and alpha-mask items of the view in a compute pass and draws
+    /// them indirectly. Falls back to culling on the CPU on devices without indirect
+    /// first instance support.
+    pub gpu_culling: bool,
+}
+
+impl Camera {
+    pub fn new(target: impl Into<RenderTarget>) -> Self {
+        Self {
+            target: target.into(),
+            projection: Projection::default(),
+            world_matrix: Matrix4::identity(),
+            viewport: Viewport::FULL,
+            clear_color: Some(Color::BLACK),
+            priority: 0,
+            layer_mask: LayerMask::ALL,
+            render_pipeline: CORE_3D.to_string(),
+            msaa: Msaa::default(),
+            post_process: None,
+            gpu_culling: false,
+        }
+    }
+
+    /// A camera for 2D scenes, with an orthographic projection that maps one world
+    /// unit to one pixel.
+    pub fn new_2d(target: impl Into<RenderTarget>) -> Self {
+        Self::new(target)
+            .with_projection(OrthographicProjection::default())
+            .with_render_pipeline(CORE_2D)
+    }
+
+    pub fn with_projection(mut self, projection: impl Into<Projection>) -> Self {
+        self.projection = projection.into();
+        self
+    }
+
+    pub fn with_world_matrix(mut self, world_matrix: Matrix4<f32>) -> Self {
+        self.world_matrix = world_matrix;
+        self
+    }
+
+    pub fn with_viewport(mut self, viewport: Viewport) -> Self {
+        self.viewport = viewport;
+        self
+    }
+
+    pub fn with_clear_color(mut self, clear_color: Option<Color>) -> Self {
+        self.clear_color = clear_color;
+        self
+    }
+
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_layer_mask(mut self, layer_mask: LayerMask) -> Self {
+        self.layer_mask = layer_mask;
+        self
+    }
+
+    pub fn with_render_pipeline(mut self, render_pipeline: &str) -> Self {
+        self.render_pipeline = render_pipeline.to_string();
+        self
+    }
+
+    pub fn with_msaa(mut self, msaa: Msaa) -> Self {
+        self.msaa = msaa;
+        self
+    }
+
+    pub fn with_post_process(mut self, post_process: Option<PostProcessSettings>) -> Self {
+        self.post_process = post_process;
+        self
+    }
+
+    pub fn with_gpu_culling(mut self, gpu_culling: bool) -> Self {
+        self.gpu_culling = gpu_culling;
+        self
+    }
+
+    /// Transforms from world space to the view space of the camera.
+    pub fn view_matrix(&self) -> Matrix4<f32> {
+        self.world_matrix
+            .try_inverse()
+            .unwrap_or_else(Matrix4::identity)
+    }
+}
+
+/// Per-view data bound at group 0 of every view pass. Matches the `View` struct of
+/// the shaders.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct ViewUniform {
+    pub view_projection: [[f32; 4]; 4],
+    pub view: [[f32; 4]; 4],
+    pub projection: [[f32; 4]; 4],
+    /// Position of the camera in the world, w is 1.
+    pub world_position: [f32; 4],
+    /// Position and size of the viewport in physical pixels.
+    pub viewport: [f32; 4],
+}
+
+impl ViewUniform {
+    pub fn new(camera: &Camera, viewport: &PhysicalViewport) -> Self {
+        let view = camera
+            .projection
+            .snap_view_matrix(camera.view_matrix(), viewport.size());
+        let projection = camera.projection.matrix(viewport.size());
+        let world_position = camera.world_matrix.column(3);
+
+        Self {
+            view_projection: (projection * view).into(),
+            view: view.into(),
+            projection: projection.into(),
+            world_position: [world_position.x, world_position.y, world_position.z, 1.0],
+            viewport: [
+                viewport.x as f32,
+                viewport.y as f32,
+                viewport.width as f32,
+                viewport.height as f32,
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fyrox_resource::core::algebra::{Vector3, Vector4};
+
+    fn project(matrix: &Matrix4<f32>, z: f32) -> f32 {
+        let clip = matrix * Vector4::new(0.0, 0.0, z, 1.0);
+        clip.z / clip.w
+    }
+
+    #[test]
+    fn projections_map_depth_to_unit_range() {
+        let perspective = PerspectiveProjection {
+            fov_y: 1.0,
+            near: 0.5,
+            far: 100.0,
+        };
+        let matrix = perspective.matrix(2.0);
+
+        assert!(project(&matrix, -0.5).abs() < 1e-5);
+        assert!((project(&matrix, -100.0) - 1.0).abs() < 1e-5);
+
+        let orthographic = OrthographicProjection {
+            near: 1.0,
+            far: 11.0,
+            ..Default::default()
+        };
+        let matrix = orthographic.matrix(Vector2::new(800.0, 600.0));
+
+        assert!(project(&matrix, -1.0).abs() < 1e-5);
+        assert!((project(&matrix, -11.0) - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn orthographic_area_follows_scaling_mode() {
+        let size = Vector2::new(800.0, 400.0);
+        let mut projection = OrthographicProjection::default();
+
+        assert_eq!(projection.area(size), size);
+
+        projection.scaling_mode = ScalingMode::FixedVertical(10.0);
+        projection.scale = 2.0;
+        assert_eq!(projection.area(size), Vector2::new(40.0, 20.0));
+    }
+
+    #[test]
+    fn viewport_is_clamped_to_target() {
+        let right_half = Viewport::new(0.5, 0.0, 0.5, 1.0);
+        assert_eq!(
+            right_half.physical(801, 600),
+            PhysicalViewport {
+                x: 400,
+                y: 0,
+                width: 401,
+                height: 600
+            }
+        );
+
+        let outside = Viewport::new(0.75, 0.75, 0.5, 0.5);
+        assert_eq!(outside.physical(100, 100).size(), Vector2::new(25.0, 25.0));
+    }
+
+    #[test]
+    fn view_uniform_uses_inverse_world_matrix() {
+        let camera = Camera::new(Handle::<SystemWindow>::NONE)
+            .with_world_matrix(Matrix4::new_translation(&Vector3::new(1.0, 2.0, 3.0)));
+        let viewport = Viewport::FULL.physical(100, 50);
+
+        let uniform = ViewUniform::new(&camera, &viewport);
+
+        assert_eq!(uniform.world_position, [1.0, 2.0, 3.0, 1.0]);
+        assert_eq!(uniform.view[3], [-1.0, -2.0, -3.0, 1.0]);
+        assert_eq!(uniform.viewport, [0.0, 0.0, 100.0, 50.0]);
+    }
+
+    #[test]
+    fn pixel_perfect_camera_snaps_to_pixels() {
+        let projection = OrthographicProjection {
+            scale: 0.3,
+            pixel_perfect: true,
+            ..Default::default()
+        };
+        let camera = Camera::new_2d(Handle::<SystemWindow>::NONE)
+            .with_projection(projection)
+            .with_world_matrix(Matrix4::new_translation(&Vector3::new(1.3, -0.4, 0.0)));
+        let viewport = Viewport::FULL.physical(101, 100);
+
+        let uniform = ViewUniform::new(&camera, &viewport);
+
+        // A scale of 0.3 is rounded to 3 pixels per unit.
+        assert!((projection.units_per_pixel(viewport.size()) - 1.0 / 3.0).abs() < 1e-6);
+        assert!((uniform.view[3][0] + 4.0 / 3.0).abs() < 1e-6);
+        assert!((uniform.view[3][1] - 1.0 / 3.0).abs() < 1e-6);
+        assert_eq!(uniform.projection[3][0], 1.0 / 101.0);
+        assert_eq!(uniform.projection[3][1], 0.0);
+    }
+}
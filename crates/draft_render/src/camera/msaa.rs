@@ -0,0 +1,57 @@
+/// Number of samples per pixel a camera renders with.
+///
+/// Multisampled views draw into a multisampled texture that every pass resolves into
+/// the target. Views without post-processing share that texture with the other
+/// cameras of the target, so cameras that don't clear keep what the previous ones drew
+/// as long as they use the same setting.
+#[derive(Debug, Default, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Msaa {
+    #[default]
+    Off = 1,
+    Sample2 = 2,
+    Sample4 = 4,
+    Sample8 = 8,
+}
+
+impl Msaa {
+    const ALL: [Msaa; 4] = [Msaa::Sample8, Msaa::Sample4, Msaa::Sample2, Msaa::Off];
+
+    pub fn samples(&self) -> u32 {
+        *self as u32
+    }
+
+    pub fn from_samples(samples: u32) -> Option<Self> {
+        Self::ALL.into_iter().find(|msaa| msaa.samples() == samples)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        *self != Msaa::Off
+    }
+
+    /// The highest setting up to this one whose sample count `is_supported` accepts.
+    /// Single sampling is always supported.
+    pub fn fallback(self, is_supported: impl Fn(u32) -> bool) -> Msaa {
+        Self::ALL
+            .into_iter()
+            .filter(|msaa| *msaa <= self)
+            .find(|msaa| !msaa.is_enabled() || is_supported(msaa.samples()))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsupported_sample_counts_fall_back_to_lower_ones() {
+        let guaranteed = |samples| samples == 4;
+
+        assert_eq!(Msaa::Sample8.fallback(guaranteed), Msaa::Sample4);
+        assert_eq!(Msaa::Sample4.fallback(guaranteed), Msaa::Sample4);
+        assert_eq!(Msaa::Sample2.fallback(guaranteed), Msaa::Off);
+        assert_eq!(Msaa::Sample8.fallback(|_| false), Msaa::Off);
+        assert_eq!(Msaa::from_samples(2), Some(Msaa::Sample2));
+        assert_eq!(Msaa::from_samples(3), None);
+    }
+}
@@ -0,0 +1,210 @@
+use std::f32::consts::FRAC_PI_4;
+
+use fyrox_resource::core::algebra::{Matrix4, Vector2};
+
+/// A perspective projection looking along the negative z axis. Depth is mapped to
+/// `0.0` at the near plane and `1.0` at the far plane.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PerspectiveProjection {
+    /// Vertical field of view in radians.
+    pub fov_y: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for PerspectiveProjection {
+    fn default() -> Self {
+        Self {
+            fov_y: FRAC_PI_4,
+            near: 0.1,
+            far: 1000.0,
+        }
+    }
+}
+
+impl PerspectiveProjection {
+    pub fn matrix(&self, aspect_ratio: f32) -> Matrix4<f32> {
+        let f = 1.0 / (self.fov_y * 0.5).tan();
+        let range = self.near - self.far;
+
+        Matrix4::new(
+            f / aspect_ratio,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            f,
+            0.0,
+            0.0,
+            0.0,
+            0.0,
+            self.far / range,
+            self.near * self.far / range,
+            0.0,
+            0.0,
+            -1.0,
+            0.0,
+        )
+    }
+}
+
+/// How the visible area of an orthographic projection follows the viewport size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScalingMode {
+    /// One world unit per physical pixel of the viewport.
+    WindowSize,
+    /// The given number of world units fit vertically, the width follows the aspect
+    /// ratio.
+    FixedVertical(f32),
+    /// The given number of world units fit horizontally, the height follows the aspect
+    /// ratio.
+    FixedHorizontal(f32),
+}
+
+/// An orthographic projection centered on the camera.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrthographicProjection {
+    pub scaling_mode: ScalingMode,
+    /// Multiplies the visible area, values above 1 zoom out.
+    pub scale: f32,
+    pub near: f32,
+    pub far: f32,
+    /// Snaps the camera to whole pixels and keeps texels square. With
+    /// [`ScalingMode::WindowSize`] the scale is also rounded so that every world unit
+    /// covers a whole number of pixels.
+    pub pixel_perfect: bool,
+}
+
+impl Default for OrthographicProjection {
+    fn default() -> Self {
+        Self {
+            scaling_mode: ScalingMode::WindowSize,
+            scale: 1.0,
+            near: -1000.0,
+            far: 1000.0,
+            pixel_perfect: false,
+        }
+    }
+}
+
+impl OrthographicProjection {
+    /// Width and height of the visible area in world units.
+    pub fn area(&self, viewport_size: Vector2<f32>) -> Vector2<f32> {
+        let aspect_ratio = viewport_size.x / viewport_size.y;
+
+        let area = match self.scaling_mode {
+            ScalingMode::WindowSize => viewport_size,
+            ScalingMode::FixedVertical(height) => Vector2::new(height * aspect_ratio, height),
+            ScalingMode::FixedHorizontal(width) => Vector2::new(width, width / aspect_ratio),
+        };
+
+        area * self.effective_scale()
+    }
+
+    fn effective_scale(&self) -> f32 {
+        if self.pixel_perfect && self.scaling_mode == ScalingMode::WindowSize {
+            1.0 / (1.0 / self.scale).round().max(1.0)
+        } else {
+            self.scale
+        }
+    }
+
+    /// Size of a physical pixel in world units.
+    pub fn units_per_pixel(&self, viewport_size: Vector2<f32>) -> f32 {
+        self.area(viewport_size).y / viewport_size.y
+    }
+
+    pub fn matrix(&self, viewport_size: Vector2<f32>) -> Matrix4<f32> {
+        let area = self.area(viewport_size);
+        let range = self.near - self.far;
+
+        // The center of a viewport with an odd size lies in the middle of a pixel, so
+        // it is moved to a pixel edge for texels to line up with pixels.
+        let half_pixel_offset = |size: f32| {
+            if self.pixel_perfect && size as u32 % 2 == 1 {
+                1.0 / size
+            } else {
+                0.0
+            }
+        };
+
+        Matrix4::new(
+            2.0 / area.x,
+            0.0,
+            0.0,
+            half_pixel_offset(viewport_size.x),
+            0.0,
+            2.0 / area.y,
+            0.0,
+            half_pixel_offset(viewport_size.y),
+            0.0,
+            0.0,
+            1.0 / range,
+            self.near / range,
+            0.0,
+            0.0,
+            0.0,
+            1.0,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    Perspective(PerspectiveProjection),
+    Orthographic(OrthographicProjection),
+}
+
+impl Default for Projection {
+    fn default() -> Self {
+        Projection::Perspective(PerspectiveProjection::default())
+    }
+}
+
+impl From<PerspectiveProjection> for Projection {
+    fn from(projection: PerspectiveProjection) -> Self {
+        Projection::Perspective(projection)
+    }
+}
+
+impl From<OrthographicProjection> for Projection {
+    fn from(projection: OrthographicProjection) -> Self {
+        Projection::Orthographic(projection)
+    }
+}
+
+impl Projection {
+    /// Projection matrix for a viewport of the given size in physical pixels.
+    pub fn matrix(&self, viewport_size: Vector2<f32>) -> Matrix4<f32> {
+        let viewport_size = viewport_size.map(|size| size.max(1.0));
+
+        match self {
+            Projection::Perspective(projection) => {
+                projection.matrix(viewport_size.x / viewport_size.y)
+            }
+            Projection::Orthographic(projection) => projection.matrix(viewport_size),
+        }
+    }
+
+    /// Rounds the translation of a view matrix to whole pixels when the projection is
+    /// pixel perfect.
+    pub fn snap_view_matrix(
+        &self,
+        mut view_matrix: Matrix4<f32>,
+        viewport_size: Vector2<f32>,
+    ) -> Matrix4<f32> {
+        if let Projection::Orthographic(projection) = self
+            && projection.pixel_perfect
+        {
+            let viewport_size = viewport_size.map(|size| size.max(1.0));
+            let units_per_pixel = projection.units_per_pixel(viewport_size);
+
+            for row in 0..2 {
+                let translation = &mut view_matrix[(row, 3)];
+                *translation = (*translation / units_per_pixel).round() * units_per_pixel;
+            }
+        }
+
+        view_matrix
+    }
+}
@@ -0,0 +1,9 @@
+mod sprite;
+mod sprite_node;
+mod sprite_pipeline;
+mod sprite_renderer;
+
+pub use sprite::*;
+pub use sprite_node::*;
+pub use sprite_pipeline::*;
+pub use sprite_renderer::*;
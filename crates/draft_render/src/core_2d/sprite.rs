@@ -0,0 +1,114 @@
+use draft_graphics::Color;
+use draft_image::ImageResource;
+use fyrox_resource::core::{
+    algebra::{Matrix4, Vector2},
+    math::Rect,
+};
+
+use crate::render_phase::LayerMask;
+
+/// The point of a sprite that is placed at its position.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Anchor {
+    #[default]
+    Center,
+    BottomLeft,
+    BottomCenter,
+    BottomRight,
+    CenterLeft,
+    CenterRight,
+    TopLeft,
+    TopCenter,
+    TopRight,
+    /// Offset from the center in fractions of the sprite size, `(-0.5, -0.5)` is the
+    /// bottom left corner.
+    Custom(Vector2<f32>),
+}
+
+impl Anchor {
+    pub fn as_vector(&self) -> Vector2<f32> {
+        match *self {
+            Anchor::Center => Vector2::new(0.0, 0.0),
+            Anchor::BottomLeft => Vector2::new(-0.5, -0.5),
+            Anchor::BottomCenter => Vector2::new(0.0, -0.5),
+            Anchor::BottomRight => Vector2::new(0.5, -0.5),
+            Anchor::CenterLeft => Vector2::new(-0.5, 0.0),
+            Anchor::CenterRight => Vector2::new(0.5, 0.0),
+            Anchor::TopLeft => Vector2::new(-0.5, 0.5),
+            Anchor::TopCenter => Vector2::new(0.0, 0.5),
+            Anchor::TopRight => Vector2::new(0.5, 0.5),
+            Anchor::Custom(anchor) => anchor,
+        }
+    }
+}
+
+/// A textured quad, submitted through
+/// [`RenderContext::draw_sprite`](crate::RenderContext::draw_sprite).
+///
+/// Sprites are drawn back to front along the view direction, so with a 2D camera a
+/// sprite with a larger z is drawn on top. Sprites at the same depth keep their
+/// submission order.
+#[derive(Debug, Clone)]
+pub struct Sprite {
+    pub image: ImageResource,
+    /// Multiplies the texture color.
+    pub color: Color,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    pub anchor: Anchor,
+    /// Region of the image to draw in pixels, with the origin at the top left corner.
+    /// The whole image is drawn when `None`.
+    pub rect: Option<Rect<f32>>,
+    /// Size of the sprite in world units. Defaults to the size of the drawn region in
+    /// pixels.
+    pub custom_size: Option<Vector2<f32>>,
+    pub world_matrix: Matrix4<f32>,
+    pub layer_mask: LayerMask,
+}
+
+impl Sprite {
+    pub fn new(image: ImageResource, world_matrix: Matrix4<f32>) -> Self {
+        Self {
+            image,
+            color: Color::WHITE,
+            flip_x: false,
+            flip_y: false,
+            anchor: Anchor::Center,
+            rect: None,
+            custom_size: None,
+            world_matrix,
+            layer_mask: LayerMask::DEFAULT,
+        }
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_flip(mut self, flip_x: bool, flip_y: bool) -> Self {
+        self.flip_x = flip_x;
+        self.flip_y = flip_y;
+        self
+    }
+
+    pub fn with_anchor(mut self, anchor: Anchor) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    pub fn with_rect(mut self, rect: Rect<f32>) -> Self {
+        self.rect = Some(rect);
+        self
+    }
+
+    pub fn with_custom_size(mut self, custom_size: Vector2<f32>) -> Self {
+        self.custom_size = Some(custom_size);
+        self
+    }
+
+    pub fn with_layer_mask(mut self, layer_mask: LayerMask) -> Self {
+        self.layer_mask = layer_mask;
+        self
+    }
+}
@@ -0,0 +1,73 @@
+use crate::{
+    FrameworkError,
+    core_2d::SPRITE_TEXTURE_BIND_GROUP,
+    frame_graph::{PassNodeBuilderExt, RenderPassExt},
+    render_pipeline::{Node, RenderPipelineRunContext, SlotLabel, VIEW_TARGET},
+};
+
+/// Draws the sprite batches of the view into its target.
+pub struct SpriteNode;
+
+impl Node for SpriteNode {
+    fn inputs(&self) -> Vec<SlotLabel> {
+        vec![VIEW_TARGET]
+    }
+
+    fn outputs(&self) -> Vec<SlotLabel> {
+        vec![VIEW_TARGET]
+    }
+
+    fn run(&self, context: &mut RenderPipelineRunContext) -> Result<(), FrameworkError> {
+        let render_world = &*context.render_world;
+        let sprite_renderer = render_world.sprite_renderer();
+
+        let Some(vertex_buffer) = sprite_renderer.vertex_buffer() else {
+            return Ok(());
+        };
+
+        let view = &render_world.views()[context.view_index];
+        let Some(view_batches) = sprite_renderer.view_batches(context.view_index) else {
+            return Ok(());
+        };
+
+        // The pipeline is compiled once its shader is loaded.
+        let Some(pipeline) = render_world
+            .pipeline_cache()
+            .get_render_pipeline(view_batches.pipeline)
+            .map(|_| view_batches.pipeline.id())
+        else {
+            return Ok(());
+        };
+
+        let main_texture = view.main_texture(context.frame_graph);
+        let pass_name = format!("{}_sprites", view.label);
+        let mut pass_builder = context.frame_graph.create_pass_builder(&pass_name);
+        let mut render_pass_builder = pass_builder.create_render_pass_builder(&pass_name);
+
+        let color_attachment = view.color_attachment(&mut render_pass_builder, &main_texture);
+        render_pass_builder.add_color_attachment(color_attachment);
+        let vertex_buffer_ref = render_pass_builder.read_material(vertex_buffer);
+
+        view.begin(&mut render_pass_builder);
+        RenderPassExt::set_render_pipeline(&mut render_pass_builder, pipeline);
+        RenderPassExt::set_vertex_buffer(
+            &mut render_pass_builder,
+            0,
+            &vertex_buffer_ref,
+            0,
+            vertex_buffer.size(),
+        );
+
+        for batch in view_batches.batches.iter() {
+            RenderPassExt::set_gpu_bind_group(
+                &mut render_pass_builder,
+                SPRITE_TEXTURE_BIND_GROUP,
+                &batch.bind_group,
+                &[],
+            );
+            RenderPassExt::draw(&mut render_pass_builder, batch.vertices.clone(), 0..1);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+use bytemuck::{Pod, Zeroable};
+use draft_graphics::{
+    BindGroupLayout, BlendState, ColorTargetState, ColorWrites, MultisampleState, PrimitiveState,
+    RenderDevice, TextureFormat, VertexAttribute, VertexFormat, VertexStepMode,
+};
+use draft_mesh::VertexBufferLayout;
+use draft_shader::{Shader, ShaderResource};
+use wgpu::{SamplerBindingType, ShaderStages, TextureSampleType, TextureViewDimension};
+
+use crate::render_world::{
+    GpuFragmentState, GpuRenderPipelineDescriptor, GpuVertexState, SpecializedRenderPipeline,
+};
+
+/// Bind group index of the sprite texture and sampler.
+pub const SPRITE_TEXTURE_BIND_GROUP: u32 = 1;
+
+/// A sprite corner, already transformed to world space.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct SpriteVertex {
+    pub position: [f32; 3],
+    pub uv: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl SpriteVertex {
+    pub fn layout() -> VertexBufferLayout {
+        VertexBufferLayout {
+            array_stride: size_of::<SpriteVertex>() as u64,
+            step_mode: VertexStepMode::Vertex,
+            attributes: vec![
+                VertexAttribute {
+                    format: VertexFormat::Float32x3,
+                    offset: 0,
+                    shader_location: 0,
+                },
+                VertexAttribute {
+                    format: VertexFormat::Float32x2,
+                    offset: 12,
+                    shader_location: 1,
+                },
+                VertexAttribute {
+                    format: VertexFormat::Float32x4,
+                    offset: 20,
+                    shader_location: 2,
+                },
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct SpritePipelineKey {
+    pub format: TextureFormat,
+    /// Sample count of the color attachment.
+    pub samples: u32,
+}
+
+pub struct SpritePipeline {
+    pub view_layout: BindGroupLayout,
+    pub texture_layout: BindGroupLayout,
+    pub shader: ShaderResource,
+}
+
+impl SpritePipeline {
+    pub fn new(device: &RenderDevice, view_layout: BindGroupLayout) -> Self {
+        let texture_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("sprite_texture_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = ShaderResource::new_embedded(Shader {
+            source: include_str!("sprite.wgsl").into(),
+            ..Default::default()
+        });
+
+        Self {
+            view_layout,
+            texture_layout,
+            shader,
+        }
+    }
+}
+
+impl SpecializedRenderPipeline for SpritePipeline {
+    type Key = SpritePipelineKey;
+
+    fn specialize(&self, key: Self::Key) -> GpuRenderPipelineDescriptor {
+        GpuRenderPipelineDescriptor {
+            label: "sprite_pipeline".to_string(),
+            layout: vec![self.view_layout.clone(), self.texture_layout.clone()],
+            vertex: GpuVertexState {
+                shader: self.shader.clone(),
+                entry_point: Some("vertex".to_string()),
+                shader_defs: vec![],
+                buffers: vec![SpriteVertex::layout()],
+            },
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState {
+                count: key.samples,
+                ..Default::default()
+            },
+            fragment: Some(GpuFragmentState {
+                shader: self.shader.clone(),
+                entry_point: Some("fragment".to_string()),
+                shader_defs: vec![],
+                targets: vec![Some(ColorTargetState {
+                    format: key.format,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+        }
+    }
+}
@@ -0,0 +1,372 @@
+use std::ops::Range;
+
+use bytemuck::cast_slice;
+use draft_graphics::{BindGroup, BindGroupLayout, RenderDevice, RenderQueue};
+use draft_image::Image;
+use fyrox_resource::core::{
+    algebra::{Matrix4, Vector2, Vector4},
+    log::Log,
+    math::Rect,
+};
+use wgpu::BufferUsages;
+
+use crate::{
+    FrameworkError,
+    core_2d::{Sprite, SpritePipeline, SpritePipelineKey, SpriteVertex},
+    frame_graph::ExternalBuffer,
+    render_phase::{LayerMask, RenderView},
+    render_world::{
+        BindGroupCache, CachedPipelineId, PipelineCache, ResourceId, SpecializedRenderPipelines,
+        TextureCache,
+    },
+};
+
+const VERTICES_PER_SPRITE: u32 = 6;
+
+/// Consecutive sprites of a view that share a texture, drawn with one draw call.
+pub struct SpriteBatch {
+    pub texture: ResourceId<Image>,
+    pub bind_group: BindGroup,
+    /// Range of the batch in the sprite vertex buffer.
+    pub vertices: Range<u32>,
+}
+
+pub struct SpriteViewBatches {
+    pub pipeline: CachedPipelineId,
+    pub batches: Vec<SpriteBatch>,
+}
+
+/// Builds the sprite vertices of every view each frame. The vertices of all views
+/// share one vertex buffer, which is reused across frames and grows when needed.
+#[derive(Default)]
+pub struct SpriteRenderer {
+    pipeline: Option<SpritePipeline>,
+    pipelines: SpecializedRenderPipelines<SpritePipeline>,
+    vertex_buffer: Option<ExternalBuffer>,
+    views: Vec<Option<SpriteViewBatches>>,
+}
+
+/// Returns the six vertices of the two triangles of a sprite, in world space.
+pub fn sprite_vertices(sprite: &Sprite, image_size: Vector2<f32>) -> [SpriteVertex; 6] {
+    let rect = sprite
+        .rect
+        .unwrap_or_else(|| Rect::new(0.0, 0.0, image_size.x, image_size.y));
+    let size = sprite.custom_size.unwrap_or(rect.size);
+    let anchor = sprite.anchor.as_vector();
+
+    let mut u = [
+        rect.position.x / image_size.x,
+        (rect.position.x + rect.size.x) / image_size.x,
+    ];
+    // Image rows start at the top, so the bottom edge has the larger v.
+    let mut v = [
+        (rect.position.y + rect.size.y) / image_size.y,
+        rect.position.y / image_size.y,
+    ];
+
+    if sprite.flip_x {
+        u.swap(0, 1);
+    }
+
+    if sprite.flip_y {
+        v.swap(0, 1);
+    }
+
+    let color = [
+        sprite.color.r as f32,
+        sprite.color.g as f32,
+        sprite.color.b as f32,
+        sprite.color.a as f32,
+    ];
+
+    let corner = |x: usize, y: usize| {
+        let local = Vector2::new(x as f32 - 0.5, y as f32 - 0.5) - anchor;
+        let position =
+            sprite.world_matrix * Vector4::new(local.x * size.x, local.y * size.y, 0.0, 1.0);
+
+        SpriteVertex {
+            position: [position.x, position.y, position.z],
+            uv: [u[x], v[y]],
+            color,
+        }
+    };
+
+    let (bottom_left, bottom_right, top_right, top_left) =
+        (corner(0, 0), corner(1, 0), corner(1, 1), corner(0, 1));
+
+    [
+        bottom_left,
+        bottom_right,
+        top_right,
+        bottom_left,
+        top_right,
+        top_left,
+    ]
+}
+
+/// Returns the indices of the sprites visible to a view, ordered back to front.
+/// Sprites at the same depth keep their submission order.
+pub fn queue_sprites(
+    sprites: &[Sprite],
+    textures: &[Option<ResourceId<Image>>],
+    view_matrix: &Matrix4<f32>,
+    layer_mask: LayerMask,
+) -> Vec<usize> {
+    let mut items = sprites
+        .iter()
+        .zip(textures.iter())
+        .enumerate()
+        .filter(|(_, (sprite, texture))| {
+            texture.is_some() && sprite.layer_mask.intersects(&layer_mask)
+        })
+        .map(|(index, (sprite, _))| {
+            let position = view_matrix * sprite.world_matrix * Vector4::new(0.0, 0.0, 0.0, 1.0);
+            (index, -position.z)
+        })
+        .collect::<Vec<_>>();
+
+    items.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    items.into_iter().map(|(index, _)| index).collect()
+}
+
+/// Merges consecutive sprites with the same texture. The ranges count vertices from
+/// `first_vertex`.
+pub fn batch_sprites(
+    order: &[usize],
+    textures: &[Option<ResourceId<Image>>],
+    first_vertex: u32,
+) -> Vec<(ResourceId<Image>, Range<u32>)> {
+    let mut batches: Vec<(ResourceId<Image>, Range<u32>)> = vec![];
+    let mut vertex = first_vertex;
+
+    for texture in order.iter().filter_map(|index| textures[*index]) {
+        match batches.last_mut() {
+            Some((batch_texture, vertices)) if *batch_texture == texture => {
+                vertices.end += VERTICES_PER_SPRITE;
+            }
+            _ => batches.push((texture, vertex..vertex + VERTICES_PER_SPRITE)),
+        }
+
+        vertex += VERTICES_PER_SPRITE;
+    }
+
+    batches
+}
+
+impl SpriteRenderer {
+    pub fn vertex_buffer(&self) -> Option<&ExternalBuffer> {
+        self.vertex_buffer.as_ref()
+    }
+
+    /// Batches of the view at `view_index` in
+    /// [`RenderWorld::views`](crate::render_world::RenderWorld::views).
+    pub fn view_batches(&self, view_index: usize) -> Option<&SpriteViewBatches> {
+        self.views.get(view_index).and_then(Option::as_ref)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn prepare(
+        &mut self,
+        sprites: &[Sprite],
+        views: &[RenderView],
+        view_layout: BindGroupLayout,
+        texture_cache: &mut TextureCache,
+        pipeline_cache: &mut PipelineCache,
+        bind_group_cache: &mut BindGroupCache,
+        device: &RenderDevice,
+        queue: &RenderQueue,
+    ) {
+        self.views.clear();
+
+        if sprites.is_empty() {
+            return;
+        }
+
+        let pipeline = self
+            .pipeline
+            .get_or_insert_with(|| SpritePipeline::new(device, view_layout));
+
+        let textures = sprites
+            .iter()
+            .map(
+                |sprite| match texture_cache.get_create_texture(&sprite.image, device, queue) {
+                    Ok(texture) => Some(texture),
+                    Err(FrameworkError::ImageNotLoaded) => None,
+                    Err(e) => {
+                        Log::err(format!("Failed to upload sprite image: {e}"));
+                        None
+                    }
+                },
+            )
+            .collect::<Vec<_>>();
+
+        let mut vertices: Vec<SpriteVertex> = vec![];
+
+        for view in views.iter() {
+            let order = queue_sprites(sprites, &textures, &view.view_matrix, view.layer_mask);
+
+            if order.is_empty() {
+                self.views.push(None);
+                continue;
+            }
+
+            let batches = batch_sprites(&order, &textures, vertices.len() as u32);
+
+            for index in order {
+                let kind = textures[index]
+                    .and_then(|texture| texture_cache.get(&texture))
+                    .map(|texture| texture.kind)
+                    .unwrap_or_default();
+                let image_size = Vector2::new(kind.width() as f32, kind.height() as f32);
+
+                vertices.extend_from_slice(&sprite_vertices(&sprites[index], image_size));
+            }
+
+            let batches = batches
+                .into_iter()
+                .filter_map(|(texture, vertices)| {
+                    let texture_render_data = texture_cache.get(&texture)?;
+
+                    let bind_group = bind_group_cache.get_or_create(
+                        "sprite_texture_bind_group",
+                        &pipeline.texture_layout,
+                        &[
+                            wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: wgpu::BindingResource::TextureView(
+                                    &texture_render_data.view,
+                                ),
+                            },
+                            wgpu::BindGroupEntry {
+                                binding: 1,
+                                resource: wgpu::BindingResource::Sampler(
+                                    &texture_render_data.sampler,
+                                ),
+                            },
+                        ],
+                        device,
+                    );
+
+                    Some(SpriteBatch {
+                        texture,
+                        bind_group,
+                        vertices,
+                    })
+                })
+                .collect();
+
+            let pipeline = self.pipelines.specialize(
+                pipeline_cache,
+                pipeline,
+                SpritePipelineKey {
+                    format: view.main_format(),
+                    samples: view.msaa.samples(),
+                },
+            );
+
+            self.views
+                .push(Some(SpriteViewBatches { pipeline, batches }));
+        }
+
+        self.write_vertices(&vertices, device, queue);
+    }
+
+    fn write_vertices(
+        &mut self,
+        vertices: &[SpriteVertex],
+        device: &RenderDevice,
+        queue: &RenderQueue,
+    ) {
+        if vertices.is_empty() {
+            return;
+        }
+
+        let data: &[u8] = cast_slice(vertices);
+
+        if self
+            .vertex_buffer
+            .as_ref()
+            .is_none_or(|buffer| buffer.size() < data.len() as u64)
+        {
+            let buffer = device.create_gpu_buffer(&wgpu::BufferDescriptor {
+                label: Some("sprite_vertex_buffer"),
+                size: (data.len() as u64).next_power_of_two(),
+                usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+
+            self.vertex_buffer = Some(ExternalBuffer::new("sprite_vertex_buffer", buffer));
+        }
+
+        if let Some(buffer) = &self.vertex_buffer {
+            queue.write_buffer(&buffer.buffer.resource, 0, data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_2d::Anchor;
+    use draft_image::{ImageFormat, ImageResource};
+    use fyrox_resource::core::algebra::Vector3;
+
+    fn sprite(z: f32) -> Sprite {
+        let image =
+            ImageResource::new_embedded(Image::new_render_target(1, 1, ImageFormat::R8Unorm));
+        Sprite::new(image, Matrix4::new_translation(&Vector3::new(0.0, 0.0, z)))
+    }
+
+    #[test]
+    fn vertices_follow_rect_anchor_and_flip() {
+        let sprite = sprite(0.0)
+            .with_rect(Rect::new(4.0, 0.0, 4.0, 2.0))
+            .with_anchor(Anchor::BottomLeft)
+            .with_flip(true, false);
+
+        let vertices = sprite_vertices(&sprite, Vector2::new(8.0, 8.0));
+        let (bottom_left, top_right) = (vertices[0], vertices[2]);
+
+        assert_eq!(bottom_left.position, [0.0, 0.0, 0.0]);
+        assert_eq!(top_right.position, [4.0, 2.0, 0.0]);
+        assert_eq!(bottom_left.uv, [1.0, 0.25]);
+        assert_eq!(top_right.uv, [0.5, 0.0]);
+    }
+
+    #[test]
+    fn sprites_are_drawn_back_to_front_and_batched_by_texture() {
+        let sprites = vec![sprite(1.0), sprite(0.0), sprite(1.0), sprite(2.0)];
+        let textures = vec![
+            Some(ResourceId::new(0)),
+            Some(ResourceId::new(0)),
+            Some(ResourceId::new(1)),
+            None,
+        ];
+
+        let order = queue_sprites(&sprites, &textures, &Matrix4::identity(), LayerMask::ALL);
+        assert_eq!(order, vec![1, 0, 2]);
+
+        let batches = batch_sprites(&order, &textures, 6);
+        assert_eq!(
+            batches,
+            vec![(ResourceId::new(0), 6..18), (ResourceId::new(1), 18..24)]
+        );
+    }
+
+    #[test]
+    fn sprites_outside_the_view_layers_are_skipped() {
+        let sprites = vec![sprite(0.0).with_layer_mask(LayerMask::layer(3))];
+        let textures = vec![Some(ResourceId::new(0))];
+
+        assert!(
+            queue_sprites(
+                &sprites,
+                &textures,
+                &Matrix4::identity(),
+                LayerMask::DEFAULT
+            )
+            .is_empty()
+        );
+    }
+}
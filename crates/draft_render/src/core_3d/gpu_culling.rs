@@ -0,0 +1,472 @@
+use std::{num::NonZeroU64, ops::Range};
+
+use bytemuck::{Pod, Zeroable, bytes_of, cast_slice};
+use draft_graphics::{BindGroup, BindGroupLayout, BufferInitDescriptor, RenderDevice};
+use draft_mesh::MeshBounds;
+use draft_shader::{Shader, ShaderResource};
+use fyrox_resource::core::algebra::Matrix4;
+use wgpu::{BufferUsages, ShaderStages};
+
+use crate::{
+    FrameworkError,
+    camera::Frustum,
+    frame_graph::{ComputePassExt, ExternalBuffer, PassNodeBuilderExt},
+    render_phase::{INSTANCE_BUFFER_STRIDE, RenderView},
+    render_pipeline::{Node, RenderPipelineRunContext, SlotLabel},
+    render_world::{CachedPipelineId, GpuComputePipelineDescriptor, PipelineCache},
+};
+
+/// Slot written by the node that culls the instances of the view on the GPU.
+pub const VIEW_DRAWS: SlotLabel = "view_draws";
+
+/// Number of instances a workgroup of the culling shader handles.
+pub const GPU_CULLING_WORKGROUP_SIZE: u32 = 64;
+
+/// Size of [`DrawIndexedIndirectArgs`](wgpu::util::DrawIndexedIndirectArgs) in the
+/// indirect buffer.
+pub const INDEXED_INDIRECT_ARGS_SIZE: u64 = 20;
+
+/// Marks the instances that are drawn without culling.
+pub const NO_CULL_BATCH: u32 = u32::MAX;
+
+/// Matches the `Culling` struct of the shader.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct GpuCulling {
+    pub planes: [[f32; 4]; 6],
+    pub instance_count: u32,
+    pub _padding: [u32; 3],
+}
+
+impl GpuCulling {
+    pub fn new(view_projection: &Matrix4<f32>, instance_count: u32) -> Self {
+        Self {
+            planes: Frustum::from_view_projection(view_projection)
+                .planes
+                .map(Into::into),
+            instance_count,
+            _padding: [0; 3],
+        }
+    }
+}
+
+/// A batch whose instances are culled on the GPU. Matches the `CullBatch` struct of
+/// the shader.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct GpuCullBatch {
+    pub center: [f32; 3],
+    /// Negative for meshes without bounds, which are never culled.
+    pub radius: f32,
+    /// Index of the first word of the draw arguments of the batch.
+    pub args_index: u32,
+    pub first_instance: u32,
+    pub _padding: [u32; 2],
+}
+
+/// How the mesh of a batch is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndirectDrawKind {
+    Indexed {
+        index_count: u32,
+        first_index: u32,
+        base_vertex: i32,
+    },
+    NonIndexed {
+        vertex_count: u32,
+        first_vertex: u32,
+    },
+}
+
+/// Draw arguments of a batch in the indirect buffer of its view.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndirectDraw {
+    /// Offset of the arguments in bytes.
+    pub offset: u64,
+    /// Indexed arguments are laid out as
+    /// [`DrawIndexedIndirectArgs`](wgpu::util::DrawIndexedIndirectArgs), the others as
+    /// [`DrawIndirectArgs`](wgpu::util::DrawIndirectArgs).
+    pub indexed: bool,
+}
+
+/// Collects the draw arguments and cull batches of the opaque and alpha-mask batches
+/// of a view. Every instance count starts at zero and is counted up by the culling
+/// shader.
+pub struct IndirectDraws {
+    pub args: Vec<u32>,
+    pub batches: Vec<GpuCullBatch>,
+    /// Index into `batches` of every instance of the view, [`NO_CULL_BATCH`] for the
+    /// instances of batches that are drawn directly.
+    pub instance_batches: Vec<u32>,
+}
+
+impl IndirectDraws {
+    pub fn new(instance_count: usize) -> Self {
+        Self {
+            args: vec![],
+            batches: vec![],
+            instance_batches: vec![NO_CULL_BATCH; instance_count],
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.batches.is_empty()
+    }
+
+    pub fn push(
+        &mut self,
+        instances: Range<u32>,
+        kind: IndirectDrawKind,
+        bounds: Option<&MeshBounds>,
+    ) -> IndirectDraw {
+        let args_index = self.args.len() as u32;
+        let batch_index = self.batches.len() as u32;
+
+        let indexed = match kind {
+            IndirectDrawKind::Indexed {
+                index_count,
+                first_index,
+                base_vertex,
+            } => {
+                self.args.extend_from_slice(&[
+                    index_count,
+                    0,
+                    first_index,
+                    base_vertex as u32,
+                    instances.start,
+                ]);
+                true
+            }
+            IndirectDrawKind::NonIndexed {
+                vertex_count,
+                first_vertex,
+            } => {
+                self.args
+                    .extend_from_slice(&[vertex_count, 0, first_vertex, instances.start]);
+                false
+            }
+        };
+
+        self.batches.push(GpuCullBatch {
+            center: bounds
+                .map(|bounds| bounds.sphere.center.into())
+                .unwrap_or_default(),
+            radius: bounds.map_or(-1.0, |bounds| bounds.sphere.radius),
+            args_index,
+            first_instance: instances.start,
+            _padding: [0; 2],
+        });
+
+        for instance in instances {
+            self.instance_batches[instance as usize] = batch_index;
+        }
+
+        IndirectDraw {
+            offset: args_index as u64 * 4,
+            indexed,
+        }
+    }
+}
+
+/// The culling dispatch of a view and the buffers its indirect draws read.
+pub struct GpuCullingView {
+    pub pipeline: CachedPipelineId,
+    pub bind_group: BindGroup,
+    pub instance_count: u32,
+    pub indirect_buffer: ExternalBuffer,
+    /// World matrices of the visible instances, drawn in place of the instance buffer
+    /// of the view.
+    pub culled_instance_buffer: ExternalBuffer,
+}
+
+pub struct GpuCullingPipeline {
+    pub layout: BindGroupLayout,
+    pub shader: ShaderResource,
+    pipeline: Option<CachedPipelineId>,
+}
+
+fn storage_entry(binding: u32, read_only: bool) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+impl GpuCullingPipeline {
+    pub fn new(device: &RenderDevice) -> Self {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("gpu_culling_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage_entry(1, true),
+                storage_entry(2, true),
+                storage_entry(3, true),
+                storage_entry(4, false),
+                storage_entry(5, false),
+            ],
+        });
+
+        Self {
+            layout,
+            shader: Self::shader(),
+            pipeline: None,
+        }
+    }
+
+    pub fn shader() -> ShaderResource {
+        ShaderResource::new_embedded(Shader {
+            source: include_str!("gpu_culling.wgsl").into(),
+            ..Default::default()
+        })
+    }
+
+    pub fn pipeline(&mut self, pipeline_cache: &mut PipelineCache) -> CachedPipelineId {
+        *self.pipeline.get_or_insert_with(|| {
+            pipeline_cache.queue_compute_pipeline(GpuComputePipelineDescriptor {
+                label: "gpu_culling_pipeline".to_string(),
+                layout: vec![self.layout.clone()],
+                shader: self.shader.clone(),
+                entry_point: Some("cull".to_string()),
+                shader_defs: vec![],
+            })
+        })
+    }
+
+    /// Uploads the draw arguments and cull batches of a view and binds them with its
+    /// instance buffer. `None` when the view has nothing to cull.
+    pub fn prepare_view(
+        &mut self,
+        view: &RenderView,
+        draws: &IndirectDraws,
+        pipeline_cache: &mut PipelineCache,
+        device: &RenderDevice,
+    ) -> Option<GpuCullingView> {
+        let instance_buffer = view.instance_buffer.as_ref()?;
+        if draws.is_empty() {
+            return None;
+        }
+
+        let instance_count = draws.instance_batches.len() as u32;
+        let uniform_buffer = device.create_gpu_buffer_init(&BufferInitDescriptor {
+            label: Some("gpu_culling_buffer"),
+            contents: bytes_of(&GpuCulling::new(
+                &Matrix4::from(view.uniform.view_projection),
+                instance_count,
+            )),
+            usage: BufferUsages::UNIFORM,
+        });
+        let batch_buffer = device.create_gpu_buffer_init(&BufferInitDescriptor {
+            label: Some("gpu_cull_batch_buffer"),
+            contents: cast_slice(&draws.batches),
+            usage: BufferUsages::STORAGE,
+        });
+        let instance_batch_buffer = device.create_gpu_buffer_init(&BufferInitDescriptor {
+            label: Some("gpu_cull_instance_batch_buffer"),
+            contents: cast_slice(&draws.instance_batches),
+            usage: BufferUsages::STORAGE,
+        });
+        let indirect_buffer = device.create_gpu_buffer_init(&BufferInitDescriptor {
+            label: Some("indirect_buffer"),
+            contents: cast_slice(&draws.args),
+            usage: BufferUsages::STORAGE | BufferUsages::INDIRECT,
+        });
+        let culled_instance_buffer = device.create_gpu_buffer(&wgpu::BufferDescriptor {
+            label: Some("culled_instance_buffer"),
+            size: instance_count as u64 * INSTANCE_BUFFER_STRIDE,
+            usage: BufferUsages::STORAGE | BufferUsages::VERTEX,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("gpu_culling_bind_group"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: batch_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: instance_batch_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                        buffer: &instance_buffer.buffer.resource,
+                        offset: view.instance_range.start,
+                        size: NonZeroU64::new(view.instance_range.end - view.instance_range.start),
+                    }),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: indirect_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: culled_instance_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        Some(GpuCullingView {
+            pipeline: self.pipeline(pipeline_cache),
+            bind_group,
+            instance_count,
+            indirect_buffer: ExternalBuffer::new("indirect_buffer", indirect_buffer),
+            culled_instance_buffer: ExternalBuffer::new(
+                "culled_instance_buffer",
+                culled_instance_buffer,
+            ),
+        })
+    }
+}
+
+/// Culls the opaque and alpha-mask instances of views with GPU culling in a compute
+/// pass, writing the indirect draws of the depth prepass and the opaque pass.
+pub struct GpuCullingNode;
+
+impl Node for GpuCullingNode {
+    fn outputs(&self) -> Vec<SlotLabel> {
+        vec![VIEW_DRAWS]
+    }
+
+    fn run(&self, context: &mut RenderPipelineRunContext) -> Result<(), FrameworkError> {
+        let render_world = &*context.render_world;
+        let view = &render_world.views()[context.view_index];
+
+        let Some(gpu_culling) = render_world
+            .mesh_renderer()
+            .view_batches(context.view_index)
+            .and_then(|view_batches| view_batches.ready_gpu_culling(render_world.pipeline_cache()))
+        else {
+            return Ok(());
+        };
+
+        let Some(instance_buffer) = view.instance_buffer.as_ref() else {
+            return Ok(());
+        };
+
+        let pass_name = format!("{}_gpu_culling", view.label);
+        let mut pass_builder = context.frame_graph.create_pass_builder(&pass_name);
+        let mut compute_pass_builder = pass_builder.create_compute_pass_builder(&pass_name);
+
+        compute_pass_builder.read_material(instance_buffer);
+        compute_pass_builder.write_material(&gpu_culling.indirect_buffer);
+        compute_pass_builder.write_material(&gpu_culling.culled_instance_buffer);
+
+        compute_pass_builder.set_compute_pipeline(gpu_culling.pipeline.id());
+        compute_pass_builder.set_gpu_bind_group(0, &gpu_culling.bind_group, &[]);
+        compute_pass_builder.dispatch_workgroups(
+            gpu_culling
+                .instance_count
+                .div_ceil(GPU_CULLING_WORKGROUP_SIZE),
+            1,
+            1,
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use draft_mesh::{Aabb, BoundingSphere};
+    use fyrox_resource::core::algebra::Vector3;
+    use wgpu::naga::{
+        front::wgsl,
+        valid::{Capabilities, ValidationFlags, Validator},
+    };
+
+    #[test]
+    fn shader_is_valid() {
+        let shader = GpuCullingPipeline::shader();
+        let draft_shader::Source::Wgsl(source) = shader.data_ref().source.clone();
+        let module = wgsl::parse_str(&source).unwrap();
+
+        Validator::new(ValidationFlags::all(), Capabilities::default())
+            .validate(&module)
+            .unwrap();
+
+        assert_eq!(size_of::<GpuCulling>(), 112);
+        assert_eq!(size_of::<GpuCullBatch>(), 32);
+    }
+
+    #[test]
+    fn batches_get_packed_draw_arguments() {
+        let bounds = MeshBounds {
+            aabb: Aabb {
+                min: Vector3::repeat(-1.0),
+                max: Vector3::repeat(1.0),
+            },
+            sphere: BoundingSphere {
+                center: Vector3::zeros(),
+                radius: 3.0_f32.sqrt(),
+            },
+        };
+
+        let mut draws = IndirectDraws::new(6);
+        let first = draws.push(
+            1..3,
+            IndirectDrawKind::Indexed {
+                index_count: 36,
+                first_index: 0,
+                base_vertex: 0,
+            },
+            Some(&bounds),
+        );
+        // Meshes in a slab start at their first index and base vertex.
+        let second = draws.push(
+            3..4,
+            IndirectDrawKind::Indexed {
+                index_count: 6,
+                first_index: 36,
+                base_vertex: 24,
+            },
+            None,
+        );
+        let third = draws.push(
+            4..6,
+            IndirectDrawKind::NonIndexed {
+                vertex_count: 3,
+                first_vertex: 28,
+            },
+            None,
+        );
+
+        assert_eq!(first.offset, 0);
+        // Indexed arguments are tightly packed, so they can be drawn with one call.
+        assert_eq!(second.offset, 20);
+        assert_eq!(
+            third,
+            IndirectDraw {
+                offset: 40,
+                indexed: false
+            }
+        );
+        assert_eq!(draws.args, [36, 0, 0, 0, 1, 6, 0, 36, 24, 3, 3, 0, 28, 4]);
+        assert_eq!(draws.instance_batches, [NO_CULL_BATCH, 0, 0, 1, 2, 2]);
+        assert_eq!(draws.batches[0].radius, 3.0_f32.sqrt());
+        assert_eq!(draws.batches[1].radius, -1.0);
+        assert_eq!(draws.batches[2].args_index, 10);
+    }
+}
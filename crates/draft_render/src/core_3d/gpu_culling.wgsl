@@ -0,0 +1,70 @@
+// Culls the instances of a view against its frustum. Every visible instance is
+// counted in the indirect draw arguments of its batch and its world matrix is copied
+// to the next free slot of the batch in the culled instance buffer.
+
+const NO_CULL_BATCH: u32 = 0xffffffffu;
+
+struct Culling {
+    // Frustum planes facing inwards, unit normal in xyz and distance in w.
+    planes: array<vec4<f32>, 6>,
+    instance_count: u32,
+}
+
+struct CullBatch {
+    // Bounding sphere of the mesh in local space. A negative radius disables culling.
+    center: vec3<f32>,
+    radius: f32,
+    // Index of the first word of the draw arguments of the batch.
+    args_index: u32,
+    first_instance: u32,
+}
+
+@group(0) @binding(0) var<uniform> culling: Culling;
+@group(0) @binding(1) var<storage, read> batches: array<CullBatch>;
+@group(0) @binding(2) var<storage, read> instance_batches: array<u32>;
+@group(0) @binding(3) var<storage, read> instances: array<mat4x4<f32>>;
+@group(0) @binding(4) var<storage, read_write> draw_args: array<atomic<u32>>;
+@group(0) @binding(5) var<storage, read_write> culled_instances: array<mat4x4<f32>>;
+
+fn is_visible(batch: CullBatch, world: mat4x4<f32>) -> bool {
+    if batch.radius < 0.0 {
+        return true;
+    }
+
+    let center = (world * vec4<f32>(batch.center, 1.0)).xyz;
+    let scale = max(length(world[0].xyz), max(length(world[1].xyz), length(world[2].xyz)));
+    let radius = batch.radius * scale;
+
+    for (var i = 0u; i < 6u; i += 1u) {
+        let plane = culling.planes[i];
+        if dot(plane.xyz, center) + plane.w < -radius {
+            return false;
+        }
+    }
+
+    return true;
+}
+
+@compute @workgroup_size(64)
+fn cull(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+    if index >= culling.instance_count {
+        return;
+    }
+
+    let batch_index = instance_batches[index];
+    if batch_index == NO_CULL_BATCH {
+        return;
+    }
+
+    let batch = batches[batch_index];
+    let world = instances[index];
+    if !is_visible(batch, world) {
+        return;
+    }
+
+    // The instance count is the second word of both the indexed and the non-indexed
+    // draw arguments.
+    let slot = atomicAdd(&draw_args[batch.args_index + 1u], 1u);
+    culled_instances[batch.first_instance + slot] = world;
+}
@@ -0,0 +1,304 @@
+use bytemuck::{Pod, Zeroable, bytes_of, cast_slice};
+use draft_graphics::{BindGroupLayout, Color, RenderDevice};
+use fyrox_resource::core::algebra::{Matrix4, Vector3, Vector4};
+use wgpu::{SamplerBindingType, ShaderStages, TextureSampleType};
+
+use crate::core_3d::{LightShadow, ShadowAllocation, ShadowMapKind};
+
+/// Bind group index of the light buffer and shadow maps in the mesh passes.
+pub const LIGHTS_BIND_GROUP: u32 = 1;
+
+/// Binding of the shadow map array of a kind in the lights bind group.
+pub fn shadow_map_binding(kind: ShadowMapKind) -> u32 {
+    match kind {
+        ShadowMapKind::Directional => 2,
+        ShadowMapKind::Spot => 3,
+        ShadowMapKind::Point => 4,
+    }
+}
+
+/// Binding of the comparison sampler of the shadow maps.
+pub const SHADOW_SAMPLER_BINDING: u32 = 5;
+
+/// Lights at binding 0, shadows at binding 1, followed by the shadow maps and their
+/// sampler.
+pub fn create_lights_bind_group_layout(device: &RenderDevice) -> BindGroupLayout {
+    let storage = |binding| wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only: true },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    };
+    let shadow_map = |kind: ShadowMapKind| wgpu::BindGroupLayoutEntry {
+        binding: shadow_map_binding(kind),
+        visibility: ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: TextureSampleType::Depth,
+            view_dimension: kind.view_dimension(),
+            multisampled: false,
+        },
+        count: None,
+    };
+
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("lights_bind_group_layout"),
+        entries: &[
+            storage(0),
+            storage(1),
+            shadow_map(ShadowMapKind::Directional),
+            shadow_map(ShadowMapKind::Spot),
+            shadow_map(ShadowMapKind::Point),
+            wgpu::BindGroupLayoutEntry {
+                binding: SHADOW_SAMPLER_BINDING,
+                visibility: ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(SamplerBindingType::Comparison),
+                count: None,
+            },
+        ],
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightKind {
+    /// Parallel rays along the negative z axis of the light, like sunlight.
+    Directional,
+    /// Shines in every direction from the position of the light.
+    Point,
+    /// A cone along the negative z axis of the light. The angles are measured from the
+    /// axis in radians, the light fades out between the inner and the outer angle.
+    Spot { inner_angle: f32, outer_angle: f32 },
+}
+
+/// A light, submitted every frame through
+/// [`RenderContext::add_light`](crate::RenderContext::add_light).
+#[derive(Debug, Clone)]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: Color,
+    pub intensity: f32,
+    /// Distance at which point and spot lights fade out completely.
+    pub range: f32,
+    pub world_matrix: Matrix4<f32>,
+    /// Lights without shadow settings cast no shadows.
+    pub shadow: Option<LightShadow>,
+}
+
+impl Light {
+    fn new(kind: LightKind, color: Color, intensity: f32, range: f32) -> Self {
+        Self {
+            kind,
+            color,
+            intensity,
+            range,
+            world_matrix: Matrix4::identity(),
+            shadow: None,
+        }
+    }
+
+    pub fn directional(color: Color, intensity: f32) -> Self {
+        Self::new(LightKind::Directional, color, intensity, f32::MAX)
+    }
+
+    pub fn point(color: Color, intensity: f32, range: f32) -> Self {
+        Self::new(LightKind::Point, color, intensity, range)
+    }
+
+    pub fn spot(
+        color: Color,
+        intensity: f32,
+        range: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+    ) -> Self {
+        Self::new(
+            LightKind::Spot {
+                inner_angle,
+                outer_angle,
+            },
+            color,
+            intensity,
+            range,
+        )
+    }
+
+    pub fn with_world_matrix(mut self, world_matrix: Matrix4<f32>) -> Self {
+        self.world_matrix = world_matrix;
+        self
+    }
+
+    pub fn with_shadow(mut self, shadow: LightShadow) -> Self {
+        self.shadow = Some(shadow);
+        self
+    }
+
+    pub fn position(&self) -> Vector3<f32> {
+        self.world_matrix.column(3).xyz()
+    }
+
+    /// Direction the light shines in, in world space.
+    pub fn direction(&self) -> Vector3<f32> {
+        (self.world_matrix * Vector4::new(0.0, 0.0, -1.0, 0.0))
+            .xyz()
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(|| -Vector3::z())
+    }
+}
+
+/// A light in the light buffer. Matches the `Light` struct of the standard shader.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct GpuLight {
+    pub position: [f32; 3],
+    pub range: f32,
+    pub direction: [f32; 3],
+    pub kind: u32,
+    pub color: [f32; 3],
+    pub intensity: f32,
+    pub spot_cos_inner: f32,
+    pub spot_cos_outer: f32,
+    /// First entry of the light in the shadow buffer, or `-1` without shadows.
+    pub shadow_index: i32,
+    pub _padding: f32,
+}
+
+impl GpuLight {
+    pub const DIRECTIONAL: u32 = 0;
+    pub const POINT: u32 = 1;
+    pub const SPOT: u32 = 2;
+}
+
+impl From<&Light> for GpuLight {
+    fn from(light: &Light) -> Self {
+        let (kind, spot_cos_inner, spot_cos_outer) = match light.kind {
+            LightKind::Directional => (GpuLight::DIRECTIONAL, 0.0, 0.0),
+            LightKind::Point => (GpuLight::POINT, 0.0, 0.0),
+            LightKind::Spot {
+                inner_angle,
+                outer_angle,
+            } => {
+                let outer_angle = outer_angle.max(inner_angle);
+                (GpuLight::SPOT, inner_angle.cos(), outer_angle.cos())
+            }
+        };
+
+        Self {
+            position: light.position().into(),
+            range: light.range.max(f32::EPSILON),
+            direction: light.direction().into(),
+            kind,
+            color: [
+                light.color.r as f32,
+                light.color.g as f32,
+                light.color.b as f32,
+            ],
+            intensity: light.intensity,
+            spot_cos_inner,
+            spot_cos_outer,
+            shadow_index: -1,
+            _padding: 0.0,
+        }
+    }
+}
+
+/// Start of the light buffer, followed by the lights.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct GpuLightsHeader {
+    pub ambient: [f32; 4],
+    pub count: u32,
+    pub cascade_count: u32,
+    pub _padding: [u32; 2],
+}
+
+/// Contents of the light buffer. The buffer always has room for one light, since a
+/// binding cannot be empty.
+pub fn lights_buffer_data(
+    ambient_light: Color,
+    lights: &[Light],
+    shadow_allocation: &ShadowAllocation,
+) -> Vec<u8> {
+    let header = GpuLightsHeader {
+        ambient: [
+            ambient_light.r as f32,
+            ambient_light.g as f32,
+            ambient_light.b as f32,
+            1.0,
+        ],
+        count: lights.len() as u32,
+        cascade_count: shadow_allocation.cascade_count,
+        _padding: [0; 2],
+    };
+
+    let mut gpu_lights = lights
+        .iter()
+        .enumerate()
+        .map(|(index, light)| GpuLight {
+            shadow_index: shadow_allocation
+                .shadow_index(index)
+                .map_or(-1, |shadow_index| shadow_index as i32),
+            ..GpuLight::from(light)
+        })
+        .collect::<Vec<_>>();
+    if gpu_lights.is_empty() {
+        gpu_lights.push(GpuLight::default());
+    }
+
+    let mut data = bytes_of(&header).to_vec();
+    data.extend_from_slice(cast_slice(&gpu_lights));
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::from_bytes;
+    use std::f32::consts::FRAC_PI_2;
+
+    #[test]
+    fn lights_shine_along_their_negative_z_axis() {
+        let rotation = Matrix4::from_axis_angle(&Vector3::x_axis(), -FRAC_PI_2);
+        let light = Light::spot(Color::WHITE, 2.0, 10.0, 0.5, 0.25)
+            .with_world_matrix(Matrix4::new_translation(&Vector3::new(1.0, 2.0, 3.0)) * rotation);
+
+        let gpu_light = GpuLight::from(&light);
+
+        assert_eq!(gpu_light.position, [1.0, 2.0, 3.0]);
+        assert!((Vector3::from(gpu_light.direction) - Vector3::new(0.0, -1.0, 0.0)).norm() < 1e-6);
+        assert_eq!(gpu_light.kind, GpuLight::SPOT);
+        // The outer angle is never smaller than the inner one.
+        assert_eq!(gpu_light.spot_cos_outer, gpu_light.spot_cos_inner);
+    }
+
+    #[test]
+    fn light_buffer_starts_with_header() {
+        let lights = [
+            Light::directional(Color::RED, 1.0),
+            Light::point(Color::BLUE, 5.0, 4.0).with_shadow(LightShadow::default()),
+        ];
+        let allocation = ShadowAllocation::new(&lights, &Default::default());
+
+        let data = lights_buffer_data(Color::WHITE, &lights, &allocation);
+        assert_eq!(
+            data.len(),
+            size_of::<GpuLightsHeader>() + 2 * size_of::<GpuLight>()
+        );
+
+        let header: &GpuLightsHeader = from_bytes(&data[..size_of::<GpuLightsHeader>()]);
+        assert_eq!(header.count, 2);
+        assert_eq!(header.ambient, [1.0; 4]);
+
+        let gpu_lights: &[GpuLight] = cast_slice(&data[size_of::<GpuLightsHeader>()..]);
+        assert_eq!(gpu_lights[0].shadow_index, -1);
+        assert_eq!(gpu_lights[1].shadow_index, 0);
+
+        let empty = lights_buffer_data(Color::BLACK, &[], &Default::default());
+        assert_eq!(
+            empty.len(),
+            size_of::<GpuLightsHeader>() + size_of::<GpuLight>()
+        );
+    }
+}
@@ -0,0 +1,510 @@
+use wgpu::{
+    Extent3d, LoadOp, Operations, StoreOp, TextureDimension, TextureUsages, TextureViewDimension,
+};
+
+use crate::{
+    FrameworkError,
+    core_3d::{
+        DEPTH_FORMAT, GpuCullingView, INDEXED_INDIRECT_ARGS_SIZE, IndirectDraw, LIGHTS_BIND_GROUP,
+        MATERIAL_BIND_GROUP, MeshBatch, MeshViewBatches, SHADOW_SAMPLER_BINDING, ShadowMapKind,
+        ShadowSettings, VIEW_DRAWS, shadow_map_binding, shadow_map_size,
+    },
+    frame_graph::{
+        FrameGraph, ManualTextureDescriptor, PassNodeBuilderExt, RenderPassBuilder, RenderPassExt,
+        ResourceHandle, TextureViewDescriptor, TransientBindGroup, TransientBindGroupEntry,
+        TransientBindingResource, TransientRenderPassDepthStencilAttachment, TransientTexture,
+        TransientTextureDescriptor, TransientTextureView, TransientTextureViewHandle,
+        TransientTextureViewHandleDescriptor,
+    },
+    render_phase::{RenderView, TrackedRenderPass, VIEW_BIND_GROUP},
+    render_pipeline::{Node, RenderPipelineRunContext, SlotLabel, VIEW_TARGET},
+    render_world::RenderWorld,
+};
+
+/// Slot written by the nodes that draw into the depth texture of the view.
+pub const VIEW_DEPTH: SlotLabel = "view_depth";
+
+/// Slot written by the node that renders the shadow maps of the view.
+pub const VIEW_SHADOWS: SlotLabel = "view_shadows";
+
+const SHADOW_MAP_KINDS: [ShadowMapKind; 3] = [
+    ShadowMapKind::Directional,
+    ShadowMapKind::Spot,
+    ShadowMapKind::Point,
+];
+
+/// The depth texture of a view, shared by the passes of the frame through its name.
+/// It has the sample count of the view.
+pub fn view_depth_texture(
+    frame_graph: &mut FrameGraph,
+    view: &RenderView,
+) -> ResourceHandle<TransientTexture> {
+    depth_texture(
+        frame_graph,
+        format!("{}_depth", view.label),
+        Extent3d {
+            width: view.view_target.width,
+            height: view.view_target.height,
+            depth_or_array_layers: 1,
+        },
+        view.msaa.samples(),
+        TextureUsages::RENDER_ATTACHMENT,
+    )
+}
+
+/// The shadow map array of a kind of light, shared by the shadow passes and the main
+/// passes of a view through its name.
+pub fn shadow_map_texture(
+    frame_graph: &mut FrameGraph,
+    view: &RenderView,
+    view_batches: &MeshViewBatches,
+    kind: ShadowMapKind,
+    settings: &ShadowSettings,
+) -> ResourceHandle<TransientTexture> {
+    depth_texture(
+        frame_graph,
+        format!("{}_{}_shadow_maps", view.label, kind.name()),
+        shadow_map_size(kind, &view_batches.shadow_allocation, settings),
+        1,
+        TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+    )
+}
+
+fn depth_texture(
+    frame_graph: &mut FrameGraph,
+    name: String,
+    size: Extent3d,
+    sample_count: u32,
+    usage: TextureUsages,
+) -> ResourceHandle<TransientTexture> {
+    frame_graph.get_or_create(
+        &name,
+        TransientTextureDescriptor::Manual(ManualTextureDescriptor {
+            label: Some(name.clone()),
+            size,
+            mip_level_count: 1,
+            sample_count,
+            dimension: TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage,
+        }),
+    )
+}
+
+/// Draws mesh batches with their pipelines and materials. Batches whose pipeline is
+/// not compiled yet are skipped.
+///
+/// With `gpu_culling`, batches with indirect draws are drawn from the culled
+/// instances. Consecutive indexed batches that share their pipeline, material and
+/// mesh buffers are drawn with a single multi-draw.
+fn draw_mesh_batches(
+    render_pass_builder: &mut RenderPassBuilder,
+    render_world: &RenderWorld,
+    view: &RenderView,
+    batches: &[MeshBatch],
+    gpu_culling: Option<&GpuCullingView>,
+) {
+    let Some(instance_buffer) = view.instance_buffer.as_ref() else {
+        return;
+    };
+
+    let instance_buffer_ref = render_pass_builder.read_material(instance_buffer);
+    let indirect_buffers = gpu_culling
+        .filter(|_| batches.iter().any(|batch| batch.indirect.is_some()))
+        .map(|gpu_culling| {
+            (
+                render_pass_builder.read_material(&gpu_culling.indirect_buffer),
+                render_pass_builder.read_material(&gpu_culling.culled_instance_buffer),
+                gpu_culling.culled_instance_buffer.size(),
+            )
+        });
+    let meshes = batches
+        .iter()
+        .map(|batch| {
+            render_world
+                .mesh_cache()
+                .get(&batch.batch.mesh)
+                .map(|mesh| mesh.read(render_pass_builder))
+        })
+        .collect::<Vec<_>>();
+
+    let mut render_pass = TrackedRenderPass::new(render_pass_builder);
+    let mut bound_material = None;
+    let mut index = 0;
+
+    while index < batches.len() {
+        let (batch, mesh) = (&batches[index], &meshes[index]);
+        index += 1;
+
+        let Some(mesh) = mesh else {
+            continue;
+        };
+
+        // The pipeline is compiled once its shader is loaded.
+        if render_world
+            .pipeline_cache()
+            .get_render_pipeline(batch.pipeline)
+            .is_none()
+        {
+            continue;
+        }
+
+        if bound_material != Some(batch.batch.material) {
+            bound_material = Some(batch.batch.material);
+            render_pass.set_gpu_bind_group(MATERIAL_BIND_GROUP, &batch.material_bind_group, &[]);
+        }
+
+        let Some(((indirect_buffer_ref, culled_instance_buffer_ref, culled_size), indirect)) =
+            indirect_buffers.as_ref().zip(batch.indirect)
+        else {
+            batch.batch.render(
+                &mut render_pass,
+                batch.pipeline.id(),
+                mesh,
+                &instance_buffer_ref,
+                view.instance_range.clone(),
+            );
+            continue;
+        };
+
+        render_pass.set_render_pipeline(batch.pipeline.id());
+        mesh.bind(
+            &mut render_pass,
+            culled_instance_buffer_ref,
+            0..*culled_size,
+        );
+
+        if !indirect.indexed {
+            render_pass.draw_indirect(indirect_buffer_ref, indirect.offset);
+            continue;
+        }
+
+        // Indexed arguments are tightly packed, so the following batches are drawn
+        // along as long as their arguments are adjacent.
+        let mut count = 1;
+        while let (Some(next), Some(Some(next_mesh))) = (batches.get(index), meshes.get(index)) {
+            let adjacent = next.indirect
+                == Some(IndirectDraw {
+                    offset: indirect.offset + count as u64 * INDEXED_INDIRECT_ARGS_SIZE,
+                    indexed: true,
+                });
+
+            if !adjacent
+                || next.pipeline != batch.pipeline
+                || next.batch.material != batch.batch.material
+                || !next_mesh.shares_buffers(mesh)
+            {
+                break;
+            }
+
+            count += 1;
+            index += 1;
+        }
+
+        render_pass.multi_draw_indexed_indirect(indirect_buffer_ref, indirect.offset, count);
+    }
+}
+
+/// Adds a pass that draws mesh batches of the current view. Passes without batches
+/// are only added when they clear the depth texture.
+fn add_mesh_pass(
+    context: &mut RenderPipelineRunContext,
+    name: &str,
+    batches: fn(&MeshViewBatches) -> &[MeshBatch],
+    color: bool,
+    depth_load: LoadOp<f32>,
+) {
+    let render_world = &*context.render_world;
+    let view = &render_world.views()[context.view_index];
+    let mesh_renderer = render_world.mesh_renderer();
+
+    let (Some(view_batches), Some(pipeline), Some(resources)) = (
+        mesh_renderer.view_batches(context.view_index),
+        mesh_renderer.pipeline(),
+        mesh_renderer.resources(),
+    ) else {
+        return;
+    };
+
+    let batches = batches(view_batches);
+    if batches.is_empty() && depth_load == LoadOp::Load {
+        return;
+    }
+
+    let main_texture = color.then(|| view.main_texture(context.frame_graph));
+    let depth_texture = view_depth_texture(context.frame_graph, view);
+    let shadow_maps = SHADOW_MAP_KINDS.map(|kind| {
+        (view_batches.shadow_allocation.layer_count(kind) > 0 && color).then(|| {
+            shadow_map_texture(
+                context.frame_graph,
+                view,
+                view_batches,
+                kind,
+                mesh_renderer.shadow_settings(),
+            )
+        })
+    });
+
+    let pass_name = format!("{}_{name}", view.label);
+    let mut pass_builder = context.frame_graph.create_pass_builder(&pass_name);
+    let mut render_pass_builder = pass_builder.create_render_pass_builder(&pass_name);
+
+    if let Some(main_texture) = &main_texture {
+        let color_attachment = view.color_attachment(&mut render_pass_builder, main_texture);
+        render_pass_builder.add_color_attachment(color_attachment);
+    }
+
+    // Loading the depth texture depends on the passes that wrote it before.
+    if depth_load == LoadOp::Load {
+        render_pass_builder.read(depth_texture.clone());
+    }
+
+    let depth_view = render_pass_builder.write_texture_handle(
+        &TransientTextureViewHandle::Descriptor(TransientTextureViewHandleDescriptor {
+            texture: depth_texture,
+            desc: TextureViewDescriptor::default(),
+        }),
+    );
+    render_pass_builder.set_depth_stencil_attachment(TransientRenderPassDepthStencilAttachment {
+        view: depth_view,
+        depth_ops: Some(Operations {
+            load: depth_load,
+            store: StoreOp::Store,
+        }),
+        stencil_ops: None,
+    });
+
+    view.begin(&mut render_pass_builder);
+
+    if color {
+        let mut entries = vec![
+            TransientBindGroupEntry {
+                binding: 0,
+                resource: TransientBindingResource::Buffer(view_batches.lights_buffer.clone()),
+            },
+            TransientBindGroupEntry {
+                binding: 1,
+                resource: TransientBindingResource::Buffer(view_batches.shadow_buffer.clone()),
+            },
+            TransientBindGroupEntry {
+                binding: SHADOW_SAMPLER_BINDING,
+                resource: TransientBindingResource::Sampler(
+                    resources.shadow_fallback.sampler.clone(),
+                ),
+            },
+        ];
+
+        for (kind, shadow_map) in SHADOW_MAP_KINDS.into_iter().zip(shadow_maps) {
+            let texture_view = match shadow_map {
+                Some(texture) => render_pass_builder.read_texture_handle(
+                    &TransientTextureViewHandle::Descriptor(TransientTextureViewHandleDescriptor {
+                        texture,
+                        desc: TextureViewDescriptor {
+                            dimension: Some(kind.view_dimension()),
+                            ..Default::default()
+                        },
+                    }),
+                ),
+                None => {
+                    TransientTextureView::TextureView(resources.shadow_fallback.view(kind).clone())
+                }
+            };
+
+            entries.push(TransientBindGroupEntry {
+                binding: shadow_map_binding(kind),
+                resource: TransientBindingResource::TextureView(texture_view),
+            });
+        }
+
+        render_pass_builder.set_bind_group(
+            LIGHTS_BIND_GROUP,
+            &TransientBindGroup {
+                label: Some("lights_bind_group".to_string()),
+                layout: pipeline.lights_layout.clone(),
+                entries,
+            },
+            &[],
+        );
+    } else {
+        RenderPassExt::set_gpu_bind_group(
+            &mut render_pass_builder,
+            LIGHTS_BIND_GROUP,
+            &resources.empty_bind_group,
+            &[],
+        );
+    }
+
+    draw_mesh_batches(
+        &mut render_pass_builder,
+        render_world,
+        view,
+        batches,
+        view_batches.ready_gpu_culling(render_world.pipeline_cache()),
+    );
+}
+
+/// Renders the shadow maps of the view with its shadow casters, a pass for every
+/// layer. The passes are culled by the frame graph when no main pass of the view
+/// samples the shadow maps.
+pub struct ShadowPassNode;
+
+impl Node for ShadowPassNode {
+    fn outputs(&self) -> Vec<SlotLabel> {
+        vec![VIEW_SHADOWS]
+    }
+
+    fn run(&self, context: &mut RenderPipelineRunContext) -> Result<(), FrameworkError> {
+        let render_world = &*context.render_world;
+        let view = &render_world.views()[context.view_index];
+        let mesh_renderer = render_world.mesh_renderer();
+
+        let (Some(view_batches), Some(resources)) = (
+            mesh_renderer.view_batches(context.view_index),
+            mesh_renderer.resources(),
+        ) else {
+            return Ok(());
+        };
+
+        for shadow_pass in view_batches.shadow_passes.iter() {
+            let kind = shadow_pass.view.kind;
+            let texture = shadow_map_texture(
+                context.frame_graph,
+                view,
+                view_batches,
+                kind,
+                mesh_renderer.shadow_settings(),
+            );
+
+            let pass_name = format!(
+                "{}_{}_shadow_{}",
+                view.label,
+                kind.name(),
+                shadow_pass.view.layer
+            );
+            let mut pass_builder = context.frame_graph.create_pass_builder(&pass_name);
+            let mut render_pass_builder = pass_builder.create_render_pass_builder(&pass_name);
+
+            let depth_view = render_pass_builder.write_texture_handle(
+                &TransientTextureViewHandle::Descriptor(TransientTextureViewHandleDescriptor {
+                    texture,
+                    desc: TextureViewDescriptor {
+                        dimension: Some(TextureViewDimension::D2),
+                        base_array_layer: shadow_pass.view.layer,
+                        array_layer_count: Some(1),
+                        ..Default::default()
+                    },
+                }),
+            );
+            render_pass_builder.set_depth_stencil_attachment(
+                TransientRenderPassDepthStencilAttachment {
+                    view: depth_view,
+                    depth_ops: Some(Operations {
+                        load: LoadOp::Clear(1.0),
+                        store: StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                },
+            );
+
+            RenderPassExt::set_gpu_bind_group(
+                &mut render_pass_builder,
+                VIEW_BIND_GROUP,
+                &shadow_pass.view_bind_group,
+                &[shadow_pass.view_uniform_offset],
+            );
+            RenderPassExt::set_gpu_bind_group(
+                &mut render_pass_builder,
+                LIGHTS_BIND_GROUP,
+                &resources.empty_bind_group,
+                &[],
+            );
+
+            draw_mesh_batches(
+                &mut render_pass_builder,
+                render_world,
+                view,
+                &view_batches.shadow_casters,
+                None,
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Clears the depth texture of the view and writes the depth of its opaque and
+/// alpha-mask items.
+pub struct DepthPrepassNode;
+
+impl Node for DepthPrepassNode {
+    fn inputs(&self) -> Vec<SlotLabel> {
+        vec![VIEW_DRAWS]
+    }
+
+    fn outputs(&self) -> Vec<SlotLabel> {
+        vec![VIEW_DEPTH]
+    }
+
+    fn run(&self, context: &mut RenderPipelineRunContext) -> Result<(), FrameworkError> {
+        add_mesh_pass(
+            context,
+            "depth_prepass",
+            |batches| &batches.depth_prepass,
+            false,
+            LoadOp::Clear(1.0),
+        );
+
+        Ok(())
+    }
+}
+
+/// Shades the opaque and alpha-mask items of the view against the prepass depth.
+pub struct OpaquePassNode;
+
+impl Node for OpaquePassNode {
+    fn inputs(&self) -> Vec<SlotLabel> {
+        vec![VIEW_TARGET, VIEW_DEPTH, VIEW_SHADOWS, VIEW_DRAWS]
+    }
+
+    fn outputs(&self) -> Vec<SlotLabel> {
+        vec![VIEW_TARGET]
+    }
+
+    fn run(&self, context: &mut RenderPipelineRunContext) -> Result<(), FrameworkError> {
+        add_mesh_pass(
+            context,
+            "opaque",
+            |batches| &batches.opaque,
+            true,
+            LoadOp::Load,
+        );
+
+        Ok(())
+    }
+}
+
+/// Blends the transparent items of the view back to front, testing but not writing
+/// depth.
+pub struct TransparentPassNode;
+
+impl Node for TransparentPassNode {
+    fn inputs(&self) -> Vec<SlotLabel> {
+        vec![VIEW_TARGET, VIEW_DEPTH, VIEW_SHADOWS]
+    }
+
+    fn outputs(&self) -> Vec<SlotLabel> {
+        vec![VIEW_TARGET]
+    }
+
+    fn run(&self, context: &mut RenderPipelineRunContext) -> Result<(), FrameworkError> {
+        add_mesh_pass(
+            context,
+            "transparent",
+            |batches| &batches.transparent,
+            true,
+            LoadOp::Load,
+        );
+
+        Ok(())
+    }
+}
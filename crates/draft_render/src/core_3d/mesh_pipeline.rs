@@ -0,0 +1,266 @@
+use draft_graphics::{
+    BindGroupLayout, BlendState, ColorTargetState, ColorWrites, CompareFunction, DepthBiasState,
+    DepthStencilState, Face, MultisampleState, PrimitiveState, PrimitiveTopology, RenderDevice,
+    StencilState, TextureFormat,
+};
+use draft_mesh::{
+    Mesh, MeshVertexBufferLayoutRef, MissingVertexAttributeError, VertexAttributeDescriptor,
+};
+use draft_shader::{ShaderDefVal, ShaderResource};
+
+use crate::{
+    core_3d::{create_lights_bind_group_layout, create_material_bind_group_layout},
+    render_phase::{PhaseKind, instance_buffer_layout},
+    render_world::{
+        GpuFragmentState, GpuRenderPipelineDescriptor, GpuVertexState, SpecializedMeshPipeline,
+    },
+};
+
+/// Format of the depth texture of a view.
+pub const DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
+
+/// First shader location of the instance world matrix, after the mesh attributes.
+pub const MESH_INSTANCE_SHADER_LOCATION: u32 = 4;
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum MeshPass {
+    /// Writes depth only, so the main passes shade each pixel once. Shadow maps are
+    /// rendered with the same pipelines.
+    DepthPrepass,
+    Main,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct MeshPipelineKey {
+    pub shader: ShaderResource,
+    pub format: TextureFormat,
+    pub topology: PrimitiveTopology,
+    pub phase: PhaseKind,
+    pub pass: MeshPass,
+    /// Sample count of the attachments.
+    pub samples: u32,
+}
+
+/// Picks the mesh attributes read by the standard shader. Positions and normals are
+/// required, UVs and tangents are enabled through shader defs when the mesh has them.
+pub fn mesh_vertex_attributes(
+    layout: &MeshVertexBufferLayoutRef,
+) -> (Vec<VertexAttributeDescriptor>, Vec<ShaderDefVal>) {
+    let mut attributes = vec![
+        Mesh::ATTRIBUTE_POSITION.at_shader_location(0),
+        Mesh::ATTRIBUTE_NORMAL.at_shader_location(1),
+    ];
+    let mut shader_defs = vec![];
+
+    if layout.contains(Mesh::ATTRIBUTE_UV_0) {
+        attributes.push(Mesh::ATTRIBUTE_UV_0.at_shader_location(2));
+        shader_defs.push("VERTEX_UVS".into());
+    }
+
+    if layout.contains(Mesh::ATTRIBUTE_TANGENT) {
+        attributes.push(Mesh::ATTRIBUTE_TANGENT.at_shader_location(3));
+        shader_defs.push("VERTEX_TANGENTS".into());
+    }
+
+    (attributes, shader_defs)
+}
+
+/// Pipelines of the core 3D mesh passes. The material shader is bound with the view
+/// at group 0, the lights at group 1 and the material at group 2. Depth-only passes
+/// bind an empty group 1 instead of the lights, since shadow passes write the shadow
+/// maps the lights group samples.
+pub struct MeshPipeline {
+    pub view_layout: BindGroupLayout,
+    pub lights_layout: BindGroupLayout,
+    pub material_layout: BindGroupLayout,
+    pub empty_layout: BindGroupLayout,
+}
+
+impl MeshPipeline {
+    pub fn new(device: &RenderDevice, view_layout: BindGroupLayout) -> Self {
+        Self {
+            view_layout,
+            lights_layout: create_lights_bind_group_layout(device),
+            material_layout: create_material_bind_group_layout(device),
+            empty_layout: device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("empty_bind_group_layout"),
+                entries: &[],
+            }),
+        }
+    }
+}
+
+impl SpecializedMeshPipeline for MeshPipeline {
+    type Key = MeshPipelineKey;
+
+    fn specialize(
+        &self,
+        key: Self::Key,
+        layout: &MeshVertexBufferLayoutRef,
+    ) -> Result<GpuRenderPipelineDescriptor, MissingVertexAttributeError> {
+        let (attributes, mut shader_defs) = mesh_vertex_attributes(layout);
+
+        let vertex_layout = layout.get_layout(&attributes).map_err(|mut e| {
+            e.pipeline_type = Some("MeshPipeline");
+            e
+        })?;
+
+        if key.phase == PhaseKind::AlphaMask {
+            shader_defs.push("ALPHA_MASK".into());
+        }
+
+        let fragment = match (key.pass, key.phase) {
+            (MeshPass::DepthPrepass, PhaseKind::AlphaMask) => Some(GpuFragmentState {
+                shader: key.shader.clone(),
+                entry_point: Some("prepass_fragment".to_string()),
+                shader_defs: shader_defs.clone(),
+                targets: vec![],
+            }),
+            (MeshPass::DepthPrepass, _) => None,
+            (MeshPass::Main, phase) => Some(GpuFragmentState {
+                shader: key.shader.clone(),
+                entry_point: Some("fragment".to_string()),
+                shader_defs: shader_defs.clone(),
+                targets: vec![Some(ColorTargetState {
+                    format: key.format,
+                    blend: (phase == PhaseKind::Transparent).then_some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+        };
+
+        let depth_prepass = key.pass == MeshPass::DepthPrepass;
+
+        Ok(GpuRenderPipelineDescriptor {
+            label: "mesh_pipeline".to_string(),
+            layout: vec![
+                self.view_layout.clone(),
+                if depth_prepass {
+                    self.empty_layout.clone()
+                } else {
+                    self.lights_layout.clone()
+                },
+                self.material_layout.clone(),
+            ],
+            vertex: GpuVertexState {
+                shader: key.shader.clone(),
+                entry_point: Some("vertex".to_string()),
+                shader_defs,
+                buffers: vec![
+                    vertex_layout,
+                    instance_buffer_layout(MESH_INSTANCE_SHADER_LOCATION),
+                ],
+            },
+            primitive: PrimitiveState {
+                topology: key.topology.into(),
+                cull_mode: Some(Face::Back),
+                ..Default::default()
+            },
+            // The main passes test against the depth written by the prepass, only
+            // transparent items are not in the prepass.
+            depth_stencil: Some(DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: Some(depth_prepass),
+                depth_compare: Some(if depth_prepass {
+                    CompareFunction::Less
+                } else {
+                    CompareFunction::LessEqual
+                }),
+                stencil: StencilState::default(),
+                bias: DepthBiasState::default(),
+            }),
+            multisample: MultisampleState {
+                count: key.samples,
+                ..Default::default()
+            },
+            fragment,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_3d::StandardMaterial;
+    use draft_mesh::{MeshVertexBufferLayouts, VertexAttributeValues};
+    use draft_shader::{Source, preprocess};
+    use wgpu::naga::{
+        front::wgsl,
+        valid::{Capabilities, ValidationFlags, Validator},
+    };
+
+    fn layout(mesh: &Mesh) -> MeshVertexBufferLayoutRef {
+        mesh.vertex_buffer
+            .get_mesh_vertex_buffer_layout(&mut MeshVertexBufferLayouts::default())
+    }
+
+    fn mesh() -> Mesh {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            VertexAttributeValues::Float32x3(vec![[0.0; 3]; 3]),
+        );
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_NORMAL,
+            VertexAttributeValues::Float32x3(vec![[0.0, 0.0, 1.0]; 3]),
+        );
+        mesh
+    }
+
+    #[test]
+    fn optional_attributes_enable_shader_defs() {
+        let mut mesh = mesh();
+
+        let (attributes, shader_defs) = mesh_vertex_attributes(&layout(&mesh));
+        assert_eq!(attributes.len(), 2);
+        assert!(shader_defs.is_empty());
+
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_TANGENT,
+            VertexAttributeValues::Float32x4(vec![[1.0, 0.0, 0.0, 1.0]; 3]),
+        );
+        let layout = layout(&mesh);
+        let (attributes, shader_defs) = mesh_vertex_attributes(&layout);
+
+        assert_eq!(shader_defs, vec![ShaderDefVal::from("VERTEX_TANGENTS")]);
+
+        let vertex_layout = layout.get_layout(&attributes).unwrap();
+        assert_eq!(vertex_layout.attributes[2].shader_location, 3);
+        assert_eq!(vertex_layout.attributes[2].offset, 24);
+    }
+
+    #[test]
+    fn meshes_without_normals_are_rejected() {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            VertexAttributeValues::Float32x3(vec![[0.0; 3]; 3]),
+        );
+        let layout = layout(&mesh);
+
+        let (attributes, _) = mesh_vertex_attributes(&layout);
+        let error = layout.get_layout(&attributes).unwrap_err();
+
+        assert_eq!(error.id, Mesh::ATTRIBUTE_NORMAL.id);
+    }
+
+    #[test]
+    fn standard_shader_is_valid_for_every_variant() {
+        let shader = StandardMaterial::shader();
+        let Source::Wgsl(source) = shader.data_ref().source.clone();
+
+        for defs in [
+            vec![],
+            vec!["VERTEX_UVS"],
+            vec!["VERTEX_UVS", "VERTEX_TANGENTS", "ALPHA_MASK"],
+        ] {
+            let defs = defs.into_iter().map(ShaderDefVal::from).collect::<Vec<_>>();
+            let source = preprocess(&source, &defs).unwrap();
+            let module = wgsl::parse_str(&source).unwrap();
+
+            Validator::new(ValidationFlags::all(), Capabilities::default())
+                .validate(&module)
+                .unwrap_or_else(|e| panic!("{defs:?}: {e:?}"));
+        }
+    }
+}
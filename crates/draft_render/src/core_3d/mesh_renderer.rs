@@ -0,0 +1,454 @@
+use std::collections::HashMap;
+
+use bytemuck::{bytes_of, cast_slice};
+use draft_graphics::{
+    BindGroup, BindGroupLayout, BufferInitDescriptor, Color, RenderDevice, RenderQueue,
+};
+use draft_image::ImageResource;
+use draft_material::Material;
+use fyrox_resource::core::{algebra::Matrix4, log::Log};
+use wgpu::{Buffer, BufferUsages};
+
+use crate::{
+    FrameworkError,
+    core_3d::{
+        DefaultMaterialTextures, GpuCullingPipeline, GpuCullingView, GpuShadow, IndirectDraw,
+        IndirectDrawKind, IndirectDraws, Light, MeshPass, MeshPipeline, MeshPipelineKey,
+        ShadowAllocation, ShadowFallback, ShadowSettings, ShadowView, StandardMaterial,
+        StandardMaterialUniform, lights_buffer_data, material_texture, prepare_shadow_views,
+    },
+    render_phase::{DrawBatch, DrawItem, PhaseKind, RenderView},
+    render_world::{
+        BindGroupCache, CachedPipelineId, DynamicBufferAllocator, MeshCache, PipelineCache,
+        SpecializedMeshPipelines, TextureCache,
+    },
+};
+
+/// A draw batch with the pipeline and material bind group it is drawn with.
+pub struct MeshBatch {
+    pub batch: DrawBatch,
+    pub pipeline: CachedPipelineId,
+    pub material_bind_group: BindGroup,
+    /// Draw arguments written by the culling pass of views with GPU culling. Shared by
+    /// the depth prepass and opaque batches of the same items.
+    pub indirect: Option<IndirectDraw>,
+}
+
+/// A layer of a shadow map, rendered with the depth prepass batches of the view.
+pub struct ShadowPass {
+    pub view: ShadowView,
+    /// Binds the view uniform of the shadow map in place of the camera.
+    pub view_bind_group: BindGroup,
+    /// Dynamic offset of the view uniform in the buffer of the bind group.
+    pub view_uniform_offset: u32,
+}
+
+/// Batches of a view for each of the core 3D mesh passes.
+pub struct MeshViewBatches {
+    /// Opaque and alpha-mask batches, drawn into the depth texture only.
+    pub depth_prepass: Vec<MeshBatch>,
+    /// The depth prepass batches, with pipelines for the single-sampled shadow maps.
+    pub shadow_casters: Vec<MeshBatch>,
+    /// Opaque and alpha-mask batches.
+    pub opaque: Vec<MeshBatch>,
+    pub transparent: Vec<MeshBatch>,
+    pub lights_buffer: Buffer,
+    pub shadow_buffer: Buffer,
+    /// Shadow maps of the view. Directional cascades follow the camera, so every view
+    /// renders its own shadow maps. Empty when the view has no shadow casters.
+    pub shadow_allocation: ShadowAllocation,
+    pub shadow_passes: Vec<ShadowPass>,
+    /// Culls the instances of the indirect batches, `None` for views that cull on the
+    /// CPU.
+    pub gpu_culling: Option<GpuCullingView>,
+}
+
+impl MeshViewBatches {
+    /// The culling pass of the view, once its pipeline is compiled. Until then the mesh
+    /// passes draw the indirect batches directly.
+    pub fn ready_gpu_culling(&self, pipeline_cache: &PipelineCache) -> Option<&GpuCullingView> {
+        self.gpu_culling.as_ref().filter(|gpu_culling| {
+            pipeline_cache
+                .get_compute_pipeline(gpu_culling.pipeline)
+                .is_some()
+        })
+    }
+}
+
+/// Resources that are created once and bound by the mesh passes of every view.
+pub struct MeshRendererResources {
+    pub shadow_fallback: ShadowFallback,
+    /// Bound at the lights group by depth-only passes.
+    pub empty_bind_group: BindGroup,
+}
+
+/// Prepares the pipelines, material bind groups, light buffers and shadow maps that
+/// the core 3D passes draw the phases of every view with.
+#[derive(Default)]
+pub struct MeshRenderer {
+    pipeline: Option<MeshPipeline>,
+    pipelines: SpecializedMeshPipelines<MeshPipeline>,
+    default_textures: DefaultMaterialTextures,
+    resources: Option<MeshRendererResources>,
+    shadow_settings: ShadowSettings,
+    gpu_culling: Option<GpuCullingPipeline>,
+    views: Vec<Option<MeshViewBatches>>,
+}
+
+impl MeshRenderer {
+    pub fn pipeline(&self) -> Option<&MeshPipeline> {
+        self.pipeline.as_ref()
+    }
+
+    pub fn resources(&self) -> Option<&MeshRendererResources> {
+        self.resources.as_ref()
+    }
+
+    /// Settings the shadow maps of the frame were prepared with.
+    pub fn shadow_settings(&self) -> &ShadowSettings {
+        &self.shadow_settings
+    }
+
+    /// Batches of the view at `view_index` in
+    /// [`RenderWorld::views`](crate::render_world::RenderWorld::views).
+    pub fn view_batches(&self, view_index: usize) -> Option<&MeshViewBatches> {
+        self.views.get(view_index).and_then(Option::as_ref)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn prepare(
+        &mut self,
+        draw_items: &[DrawItem],
+        lights: &[Light],
+        ambient_light: Color,
+        shadow_settings: &ShadowSettings,
+        views: &[RenderView],
+        view_layout: BindGroupLayout,
+        mesh_cache: &MeshCache,
+        texture_cache: &mut TextureCache,
+        pipeline_cache: &mut PipelineCache,
+        uniform_allocator: &mut DynamicBufferAllocator,
+        bind_group_cache: &mut BindGroupCache,
+        device: &RenderDevice,
+        queue: &RenderQueue,
+    ) {
+        self.views.clear();
+        self.shadow_settings = *shadow_settings;
+
+        if draw_items.is_empty() {
+            return;
+        }
+
+        let pipeline = &*self
+            .pipeline
+            .get_or_insert_with(|| MeshPipeline::new(device, view_layout.clone()));
+
+        self.resources.get_or_insert_with(|| MeshRendererResources {
+            shadow_fallback: ShadowFallback::new(device),
+            empty_bind_group: device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("empty_bind_group"),
+                layout: &pipeline.empty_layout,
+                entries: &[],
+            }),
+        });
+
+        let shadow_allocation = ShadowAllocation::new(lights, shadow_settings);
+
+        let mut material_bind_groups: HashMap<u64, BindGroup> = HashMap::new();
+
+        for view in views.iter() {
+            if [
+                PhaseKind::Opaque,
+                PhaseKind::AlphaMask,
+                PhaseKind::Transparent,
+            ]
+            .iter()
+            .all(|kind| view.phase(*kind).is_empty())
+            {
+                self.views.push(None);
+                continue;
+            }
+
+            let mut depth_prepass = vec![];
+            let mut shadow_casters = vec![];
+            let mut opaque = vec![];
+            let mut transparent = vec![];
+            let mut indirect_draws = view
+                .gpu_culling
+                .then(|| IndirectDraws::new(view.visible_count() + view.shadow_caster_phase.len()));
+
+            // Items outside of the frustum of the view are only drawn into shadow maps.
+            for (phase, is_shadow_caster_only) in [
+                (&view.opaque_phase, false),
+                (&view.alpha_mask_phase, false),
+                (&view.transparent_phase, false),
+                (&view.shadow_caster_phase, true),
+            ] {
+                for batch in phase.batches() {
+                    let draw_item = &draw_items[batch.draw_item];
+                    let Some(mesh) = mesh_cache.get(&batch.mesh) else {
+                        continue;
+                    };
+
+                    let material = draw_item.material.data_ref();
+                    let kind = PhaseKind::from(material.alpha_mode());
+                    let material_bind_group = material_bind_groups
+                        .entry(batch.material)
+                        .or_insert_with(|| {
+                            self.default_textures.create_bind_group(
+                                &material,
+                                pipeline,
+                                texture_cache,
+                                device,
+                                queue,
+                            )
+                        })
+                        .clone();
+
+                    let mut specialize = |pass, samples| {
+                        let key = MeshPipelineKey {
+                            shader: material.shader().clone(),
+                            format: view.main_format(),
+                            topology: draw_item.mesh.data_ref().primitive_topology(),
+                            phase: kind,
+                            pass,
+                            samples,
+                        };
+
+                        self.pipelines
+                            .specialize(pipeline_cache, pipeline, key, &mesh.vertex_buffer.layout)
+                            .map_err(|e| Log::err(format!("Failed to specialize mesh: {e}")))
+                            .ok()
+                    };
+
+                    if is_shadow_caster_only {
+                        if let Some(shadow_pipeline) = specialize(MeshPass::DepthPrepass, 1) {
+                            shadow_casters.push(MeshBatch {
+                                batch,
+                                pipeline: shadow_pipeline,
+                                material_bind_group,
+                                indirect: None,
+                            });
+                        }
+                        continue;
+                    }
+
+                    let samples = view.msaa.samples();
+                    let Some(main_pipeline) = specialize(MeshPass::Main, samples) else {
+                        continue;
+                    };
+
+                    let mut indirect = None;
+
+                    if kind != PhaseKind::Transparent
+                        && let Some(prepass_pipeline) = specialize(MeshPass::DepthPrepass, samples)
+                        && let Some(shadow_pipeline) = specialize(MeshPass::DepthPrepass, 1)
+                    {
+                        indirect = indirect_draws.as_mut().map(|draws| {
+                            let kind = match &mesh.index_buffer {
+                                Some(index_buffer) => IndirectDrawKind::Indexed {
+                                    index_count: index_buffer.index_count,
+                                    first_index: index_buffer.first_index(),
+                                    base_vertex: mesh.vertex_buffer.base_vertex() as i32,
+                                },
+                                None => IndirectDrawKind::NonIndexed {
+                                    vertex_count: mesh.vertex_buffer.vertex_count,
+                                    first_vertex: mesh.vertex_buffer.base_vertex(),
+                                },
+                            };
+
+                            draws.push(batch.instances.clone(), kind, mesh.bounds.as_ref())
+                        });
+
+                        depth_prepass.push(MeshBatch {
+                            batch: batch.clone(),
+                            pipeline: prepass_pipeline,
+                            material_bind_group: material_bind_group.clone(),
+                            indirect,
+                        });
+                        // Shadow maps see items outside of the frustum of the view.
+                        shadow_casters.push(MeshBatch {
+                            batch: batch.clone(),
+                            pipeline: shadow_pipeline,
+                            material_bind_group: material_bind_group.clone(),
+                            indirect: None,
+                        });
+                    }
+
+                    let batch = MeshBatch {
+                        batch,
+                        pipeline: main_pipeline,
+                        material_bind_group,
+                        indirect,
+                    };
+
+                    match kind {
+                        PhaseKind::Transparent => transparent.push(batch),
+                        _ => opaque.push(batch),
+                    }
+                }
+            }
+
+            // Views without shadow casters sample no shadow maps.
+            let shadow_allocation = if shadow_casters.is_empty() {
+                ShadowAllocation::default()
+            } else {
+                shadow_allocation.clone()
+            };
+
+            let (shadow_views, gpu_shadows) = prepare_shadow_views(
+                lights,
+                &shadow_allocation,
+                &view
+                    .view_matrix
+                    .try_inverse()
+                    .unwrap_or_else(Matrix4::identity),
+                &view.projection,
+                view.viewport.size(),
+                shadow_settings,
+            );
+
+            let shadow_passes = shadow_views
+                .into_iter()
+                .map(|shadow_view| {
+                    let uniform = uniform_allocator.allocate_value(&shadow_view.uniform(), device);
+
+                    ShadowPass {
+                        view: shadow_view,
+                        view_bind_group: bind_group_cache.get_or_create(
+                            "shadow_view_bind_group",
+                            &view_layout,
+                            &[wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: uniform.binding(),
+                            }],
+                            device,
+                        ),
+                        view_uniform_offset: uniform.offset,
+                    }
+                })
+                .collect();
+
+            let lights_buffer = device.create_gpu_buffer_init(&BufferInitDescriptor {
+                label: Some("lights_buffer"),
+                contents: &lights_buffer_data(ambient_light, lights, &shadow_allocation),
+                usage: BufferUsages::STORAGE,
+            });
+
+            // A binding cannot be empty, so the buffer has at least one entry.
+            let gpu_shadows = if gpu_shadows.is_empty() {
+                vec![GpuShadow::default()]
+            } else {
+                gpu_shadows
+            };
+            let shadow_buffer = device.create_gpu_buffer_init(&BufferInitDescriptor {
+                label: Some("shadow_buffer"),
+                contents: cast_slice(&gpu_shadows),
+                usage: BufferUsages::STORAGE,
+            });
+
+            let gpu_culling = indirect_draws.and_then(|draws| {
+                self.gpu_culling
+                    .get_or_insert_with(|| GpuCullingPipeline::new(device))
+                    .prepare_view(view, &draws, pipeline_cache, device)
+            });
+
+            self.views.push(Some(MeshViewBatches {
+                depth_prepass,
+                shadow_casters,
+                opaque,
+                transparent,
+                lights_buffer,
+                shadow_buffer,
+                shadow_allocation,
+                shadow_passes,
+                gpu_culling,
+            }));
+        }
+    }
+}
+
+impl DefaultMaterialTextures {
+    /// Creates the bind group of a material of the standard shader. Textures that are
+    /// not set or still loading are replaced by the default ones.
+    fn create_bind_group(
+        &self,
+        material: &Material,
+        pipeline: &MeshPipeline,
+        texture_cache: &mut TextureCache,
+        device: &RenderDevice,
+        queue: &RenderQueue,
+    ) -> BindGroup {
+        let uniform_buffer = device.create_gpu_buffer_init(&BufferInitDescriptor {
+            label: Some("material_uniform_buffer"),
+            contents: bytes_of(&StandardMaterialUniform::new(material)),
+            usage: BufferUsages::UNIFORM,
+        });
+
+        let mut texture = |name: &str, default: &ImageResource| {
+            let mut upload = |image: &ImageResource| match texture_cache
+                .get_create_texture(image, device, queue)
+            {
+                Ok(texture) => Some(texture),
+                Err(FrameworkError::ImageNotLoaded) => None,
+                Err(e) => {
+                    Log::err(format!("Failed to upload material texture: {e}"));
+                    None
+                }
+            };
+
+            let texture = match material_texture(material, name).and_then(|image| upload(&image)) {
+                Some(texture) => Some(texture),
+                None => upload(default),
+            }
+            .expect("Default material textures are always uploaded.");
+
+            let texture = texture_cache
+                .get(&texture)
+                .expect("Uploaded textures are in the cache.");
+            (texture.view.clone(), texture.sampler.clone())
+        };
+
+        let (base_color_view, base_color_sampler) =
+            texture(StandardMaterial::BASE_COLOR_TEXTURE, &self.white);
+        let (metallic_roughness_view, metallic_roughness_sampler) =
+            texture(StandardMaterial::METALLIC_ROUGHNESS_TEXTURE, &self.white);
+        let (normal_view, normal_sampler) =
+            texture(StandardMaterial::NORMAL_TEXTURE, &self.flat_normal);
+
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("material_bind_group"),
+            layout: &pipeline.material_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&base_color_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&base_color_sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&metallic_roughness_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::Sampler(&metallic_roughness_sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::TextureView(&normal_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 6,
+                    resource: wgpu::BindingResource::Sampler(&normal_sampler),
+                },
+            ],
+        })
+    }
+}
@@ -0,0 +1,15 @@
+mod gpu_culling;
+mod light;
+mod main_pass_node;
+mod mesh_pipeline;
+mod mesh_renderer;
+mod shadow;
+mod standard_material;
+
+pub use gpu_culling::*;
+pub use light::*;
+pub use main_pass_node::*;
+pub use mesh_pipeline::*;
+pub use mesh_renderer::*;
+pub use shadow::*;
+pub use standard_material::*;
@@ -0,0 +1,862 @@
+use std::f32::consts::FRAC_PI_2;
+
+use bytemuck::{Pod, Zeroable};
+use draft_graphics::{
+    AddressMode, CompareFunction, FilterMode, RenderDevice, Sampler, SamplerDescriptor,
+    TextureView, TextureViewDimension,
+};
+use fyrox_resource::core::algebra::{Matrix4, Point3, Vector2, Vector3};
+use wgpu::{Extent3d, TextureDimension, TextureUsages};
+
+use crate::{
+    camera::{PerspectiveProjection, Projection, ViewUniform},
+    core_3d::{DEPTH_FORMAT, Light, LightKind},
+};
+
+/// Highest number of cascades of a directional light.
+pub const MAX_CASCADES: u32 = 4;
+
+/// Shadow settings of a single light.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightShadow {
+    /// Distance in world units that surfaces are moved towards the light before they
+    /// are compared with the shadow map.
+    pub depth_bias: f32,
+    /// Distance in shadow map texels that surfaces are moved along their normal before
+    /// they are compared with the shadow map.
+    pub normal_bias: f32,
+    /// Near plane of the shadow maps of point and spot lights.
+    pub near: f32,
+}
+
+impl Default for LightShadow {
+    fn default() -> Self {
+        Self {
+            depth_bias: 0.02,
+            normal_bias: 1.0,
+            near: 0.1,
+        }
+    }
+}
+
+/// Shadow settings shared by every light, set through
+/// [`RenderContext::set_shadow_settings`](crate::RenderContext::set_shadow_settings).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowSettings {
+    /// Size of a cascade of a directional light, in texels.
+    pub directional_map_size: u32,
+    pub spot_map_size: u32,
+    /// Size of a face of the cube map of a point light, in texels.
+    pub point_map_size: u32,
+    /// Number of cascades of a directional light, at most [`MAX_CASCADES`].
+    pub cascade_count: u32,
+    /// Distance from the camera at which directional shadows end.
+    pub max_distance: f32,
+    /// Blends the cascade splits between uniform at `0.0` and logarithmic at `1.0`.
+    pub cascade_split_lambda: f32,
+    /// Distance behind a cascade that still casts shadows into it.
+    pub caster_distance: f32,
+    /// Lights past these limits render without shadows.
+    pub max_directional_lights: usize,
+    pub max_spot_lights: usize,
+    pub max_point_lights: usize,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            directional_map_size: 2048,
+            spot_map_size: 1024,
+            point_map_size: 512,
+            cascade_count: 4,
+            max_distance: 100.0,
+            cascade_split_lambda: 0.75,
+            caster_distance: 100.0,
+            max_directional_lights: 2,
+            max_spot_lights: 8,
+            max_point_lights: 4,
+        }
+    }
+}
+
+impl ShadowSettings {
+    pub fn cascade_count(&self) -> u32 {
+        self.cascade_count.clamp(1, MAX_CASCADES)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShadowMapKind {
+    /// A 2D array with a layer for every cascade of every directional light.
+    Directional,
+    /// A 2D array with a layer for every spot light.
+    Spot,
+    /// A cube array with a cube for every point light.
+    Point,
+}
+
+impl ShadowMapKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ShadowMapKind::Directional => "directional",
+            ShadowMapKind::Spot => "spot",
+            ShadowMapKind::Point => "point",
+        }
+    }
+
+    pub fn view_dimension(&self) -> TextureViewDimension {
+        match self {
+            ShadowMapKind::Directional | ShadowMapKind::Spot => TextureViewDimension::D2Array,
+            ShadowMapKind::Point => TextureViewDimension::CubeArray,
+        }
+    }
+}
+
+/// Assigns the shadow casting lights of a frame to the layers of the shadow maps.
+/// Every kind of light has its own array, lights take consecutive slots in the order
+/// they were added.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ShadowAllocation {
+    /// Indices of the lights in each array, in slot order.
+    pub directional: Vec<usize>,
+    pub spot: Vec<usize>,
+    pub point: Vec<usize>,
+    pub cascade_count: u32,
+}
+
+impl ShadowAllocation {
+    pub fn new(lights: &[Light], settings: &ShadowSettings) -> Self {
+        let mut allocation = Self {
+            cascade_count: settings.cascade_count(),
+            ..Default::default()
+        };
+
+        for (index, light) in lights.iter().enumerate() {
+            if light.shadow.is_none() || light.intensity <= 0.0 {
+                continue;
+            }
+
+            let (slots, max) = match light.kind {
+                LightKind::Directional => {
+                    (&mut allocation.directional, settings.max_directional_lights)
+                }
+                LightKind::Spot { .. } => (&mut allocation.spot, settings.max_spot_lights),
+                LightKind::Point => (&mut allocation.point, settings.max_point_lights),
+            };
+
+            if slots.len() < max {
+                slots.push(index);
+            }
+        }
+
+        allocation
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.directional.is_empty() && self.spot.is_empty() && self.point.is_empty()
+    }
+
+    /// Distance from a camera within which items outside of its frustum may still cast
+    /// shadows into it. Directional shadows reach the cascades and the casters behind
+    /// them, point and spot shadows the range of their light. `None` without shadows.
+    pub fn caster_distance(
+        &self,
+        lights: &[Light],
+        settings: &ShadowSettings,
+        camera_position: &Vector3<f32>,
+    ) -> Option<f32> {
+        let directional = (!self.directional.is_empty())
+            .then_some(settings.max_distance + settings.caster_distance);
+        let local = self.spot.iter().chain(self.point.iter()).map(|index| {
+            let light = &lights[*index];
+            let position = light.world_matrix.column(3).xyz();
+            (position - camera_position).norm() + light.range
+        });
+
+        directional.into_iter().chain(local).reduce(f32::max)
+    }
+
+    /// Number of layers of the shadow map array of a kind.
+    pub fn layer_count(&self, kind: ShadowMapKind) -> u32 {
+        match kind {
+            ShadowMapKind::Directional => self.directional.len() as u32 * self.cascade_count,
+            ShadowMapKind::Spot => self.spot.len() as u32,
+            ShadowMapKind::Point => self.point.len() as u32 * 6,
+        }
+    }
+
+    /// Number of entries in the shadow buffer. Directional lights take an entry for
+    /// every cascade, followed by an entry for every spot and point light.
+    pub fn shadow_count(&self) -> usize {
+        self.directional.len() * self.cascade_count as usize + self.spot.len() + self.point.len()
+    }
+
+    /// Index of the first shadow buffer entry of a light, if it casts shadows.
+    pub fn shadow_index(&self, light_index: usize) -> Option<usize> {
+        let cascades = self.directional.len() * self.cascade_count as usize;
+
+        if let Some(slot) = self.directional.iter().position(|i| *i == light_index) {
+            Some(slot * self.cascade_count as usize)
+        } else if let Some(slot) = self.spot.iter().position(|i| *i == light_index) {
+            Some(cascades + slot)
+        } else {
+            self.point
+                .iter()
+                .position(|i| *i == light_index)
+                .map(|slot| cascades + self.spot.len() + slot)
+        }
+    }
+}
+
+/// An entry of the shadow buffer. Matches the `Shadow` struct of the standard shader.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct GpuShadow {
+    /// Projects world positions into the shadow map. Unused by point lights, whose
+    /// cube maps are sampled by direction.
+    pub view_projection: [[f32; 4]; 4],
+    pub depth_bias: f32,
+    pub normal_bias: f32,
+    /// World size of a texel. Point and spot lights store the size at a distance of
+    /// one from the light.
+    pub texel_size: f32,
+    /// Layer of a 2D array or cube of a cube array.
+    pub layer: u32,
+    /// Distance from the camera at which a cascade ends.
+    pub split_far: f32,
+    pub near: f32,
+    pub far: f32,
+    pub _padding: f32,
+}
+
+/// Distance from the camera at which each cascade ends. Splits blend between a uniform
+/// and a logarithmic distribution, which gives close cascades more resolution.
+pub fn cascade_splits(near: f32, far: f32, count: u32, lambda: f32) -> Vec<f32> {
+    let near = near.max(f32::EPSILON);
+    let far = far.max(near);
+
+    (1..=count)
+        .map(|i| {
+            let t = i as f32 / count as f32;
+            let uniform = near + (far - near) * t;
+            let logarithmic = near * (far / near).powf(t);
+            lambda * logarithmic + (1.0 - lambda) * uniform
+        })
+        .collect()
+}
+
+/// Near and far planes of the part of a projection that receives directional shadows,
+/// along with the lambda its splits use. Orthographic projections split uniformly.
+fn shadow_range(projection: &Projection, settings: &ShadowSettings) -> (f32, f32, f32) {
+    match projection {
+        Projection::Perspective(projection) => (
+            projection.near,
+            projection.far.min(settings.max_distance),
+            settings.cascade_split_lambda,
+        ),
+        Projection::Orthographic(projection) => {
+            let near = projection.near.max(0.0);
+            (near, projection.far.min(near + settings.max_distance), 0.0)
+        }
+    }
+}
+
+/// Corners of the slice of a view frustum between two distances from the camera, in
+/// view space.
+fn frustum_slice_corners(
+    projection: &Projection,
+    viewport_size: Vector2<f32>,
+    near: f32,
+    far: f32,
+) -> [Vector3<f32>; 8] {
+    let viewport_size = viewport_size.map(|size| size.max(1.0));
+
+    let half_extents = |distance: f32| match projection {
+        Projection::Perspective(projection) => {
+            let tan_y = (projection.fov_y * 0.5).tan();
+            Vector2::new(tan_y * viewport_size.x / viewport_size.y, tan_y) * distance
+        }
+        Projection::Orthographic(projection) => projection.area(viewport_size) * 0.5,
+    };
+
+    let mut corners = [Vector3::zeros(); 8];
+    for (i, distance) in [near, far].into_iter().enumerate() {
+        let extents = half_extents(distance);
+        for (j, (x, y)) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]
+            .into_iter()
+            .enumerate()
+        {
+            corners[i * 4 + j] = Vector3::new(x * extents.x, y * extents.y, -distance);
+        }
+    }
+
+    corners
+}
+
+/// A view matrix at `eye` looking along `direction`.
+fn look_to(eye: Vector3<f32>, direction: Vector3<f32>) -> Matrix4<f32> {
+    let up = if direction.y.abs() > 0.99 {
+        Vector3::x()
+    } else {
+        Vector3::y()
+    };
+
+    Matrix4::look_at_rh(&Point3::from(eye), &Point3::from(eye + direction), &up)
+}
+
+/// Maps view space boxes to clip space, with depth `0.0` at `near` and `1.0` at `far`
+/// in front of the view.
+fn orthographic(min: Vector2<f32>, max: Vector2<f32>, near: f32, far: f32) -> Matrix4<f32> {
+    let size = max - min;
+    let depth = far - near;
+
+    Matrix4::new(
+        2.0 / size.x,
+        0.0,
+        0.0,
+        -(max.x + min.x) / size.x,
+        0.0,
+        2.0 / size.y,
+        0.0,
+        -(max.y + min.y) / size.y,
+        0.0,
+        0.0,
+        -1.0 / depth,
+        -near / depth,
+        0.0,
+        0.0,
+        0.0,
+        1.0,
+    )
+}
+
+/// A shadow map view: the matrices a shadow pass renders with and the shadow buffer
+/// entry the main passes sample it with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShadowView {
+    pub kind: ShadowMapKind,
+    /// Layer of the shadow map array rendered into.
+    pub layer: u32,
+    pub view: Matrix4<f32>,
+    pub projection: Matrix4<f32>,
+    pub world_position: Vector3<f32>,
+    pub size: u32,
+}
+
+impl ShadowView {
+    pub fn view_projection(&self) -> Matrix4<f32> {
+        self.projection * self.view
+    }
+
+    /// The view uniform the shadow pass binds in place of the camera.
+    pub fn uniform(&self) -> ViewUniform {
+        ViewUniform {
+            view_projection: self.view_projection().into(),
+            view: self.view.into(),
+            projection: self.projection.into(),
+            world_position: [
+                self.world_position.x,
+                self.world_position.y,
+                self.world_position.z,
+                1.0,
+            ],
+            viewport: [0.0, 0.0, self.size as f32, self.size as f32],
+        }
+    }
+}
+
+/// Fits the cascades of a directional light around the frustum slices of a camera.
+/// Every cascade covers the bounding sphere of its slice, so its size does not change
+/// as the camera turns, and moves in whole texels to keep shadow edges from shimmering.
+pub fn directional_cascades(
+    light: &Light,
+    slot: usize,
+    shadow: &LightShadow,
+    camera_world_matrix: &Matrix4<f32>,
+    projection: &Projection,
+    viewport_size: Vector2<f32>,
+    settings: &ShadowSettings,
+) -> Vec<(ShadowView, GpuShadow)> {
+    let cascade_count = settings.cascade_count();
+    let size = settings.directional_map_size.max(1);
+    let (near, far, lambda) = shadow_range(projection, settings);
+    let splits = cascade_splits(near, far, cascade_count, lambda);
+
+    let direction = light.direction();
+    let rotation = look_to(Vector3::zeros(), direction);
+
+    let mut split_near = near;
+    splits
+        .iter()
+        .enumerate()
+        .map(|(cascade, split_far)| {
+            let corners = frustum_slice_corners(projection, viewport_size, split_near, *split_far)
+                .map(|corner| {
+                    camera_world_matrix
+                        .transform_point(&Point3::from(corner))
+                        .coords
+                });
+            split_near = *split_far;
+
+            let center = corners.iter().sum::<Vector3<f32>>() / corners.len() as f32;
+            let radius = corners
+                .iter()
+                .map(|corner| (corner - center).norm())
+                .fold(0.0, f32::max);
+            let radius = (radius * 16.0).ceil() / 16.0;
+            let texel_size = 2.0 * radius / size as f32;
+
+            let eye = center - direction * (radius + settings.caster_distance);
+
+            let center = rotation.transform_point(&Point3::from(center)).coords;
+            let center_xy = center.xy().map(|c| (c / texel_size).floor() * texel_size);
+            let center_distance = -center.z;
+
+            let projection = orthographic(
+                center_xy.add_scalar(-radius),
+                center_xy.add_scalar(radius),
+                center_distance - radius - settings.caster_distance,
+                center_distance + radius,
+            );
+
+            let view = ShadowView {
+                kind: ShadowMapKind::Directional,
+                layer: slot as u32 * cascade_count + cascade as u32,
+                view: rotation,
+                projection,
+                world_position: eye,
+                size,
+            };
+            let gpu_shadow = GpuShadow {
+                view_projection: view.view_projection().into(),
+                depth_bias: shadow.depth_bias,
+                normal_bias: shadow.normal_bias,
+                texel_size,
+                layer: view.layer,
+                split_far: *split_far,
+                ..Default::default()
+            };
+
+            (view, gpu_shadow)
+        })
+        .collect()
+}
+
+/// The shadow map of a spot light, a perspective projection that covers its cone.
+pub fn spot_shadow(
+    light: &Light,
+    slot: usize,
+    shadow: &LightShadow,
+    outer_angle: f32,
+    settings: &ShadowSettings,
+) -> (ShadowView, GpuShadow) {
+    let size = settings.spot_map_size.max(1);
+    let fov_y = (outer_angle * 2.0).clamp(0.01, std::f32::consts::PI - 0.01);
+    let position = light.position();
+
+    let view = ShadowView {
+        kind: ShadowMapKind::Spot,
+        layer: slot as u32,
+        view: look_to(position, light.direction()),
+        projection: PerspectiveProjection {
+            fov_y,
+            near: shadow.near,
+            far: light.range,
+        }
+        .matrix(1.0),
+        world_position: position,
+        size,
+    };
+    let gpu_shadow = GpuShadow {
+        view_projection: view.view_projection().into(),
+        depth_bias: shadow.depth_bias,
+        normal_bias: shadow.normal_bias,
+        texel_size: 2.0 * (fov_y * 0.5).tan() / size as f32,
+        layer: view.layer,
+        near: shadow.near,
+        far: light.range,
+        ..Default::default()
+    };
+
+    (view, gpu_shadow)
+}
+
+/// Directions and up vectors of the faces of a cube map, in layer order. Cube maps
+/// are sampled in a left-handed space, so the shader flips the z axis of the lookup
+/// direction and the faces along z are swapped.
+pub const CUBE_FACES: [(Vector3<f32>, Vector3<f32>); 6] = [
+    (Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0)),
+    (Vector3::new(-1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0)),
+    (Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 0.0, 1.0)),
+    (Vector3::new(0.0, -1.0, 0.0), Vector3::new(0.0, 0.0, -1.0)),
+    (Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, 1.0, 0.0)),
+    (Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 1.0, 0.0)),
+];
+
+/// The six faces of the cube map of a point light.
+pub fn point_shadow(
+    light: &Light,
+    slot: usize,
+    shadow: &LightShadow,
+    settings: &ShadowSettings,
+) -> (Vec<ShadowView>, GpuShadow) {
+    let size = settings.point_map_size.max(1);
+    let position = light.position();
+    let projection = PerspectiveProjection {
+        fov_y: FRAC_PI_2,
+        near: shadow.near,
+        far: light.range,
+    }
+    .matrix(1.0);
+
+    let views = CUBE_FACES
+        .iter()
+        .enumerate()
+        .map(|(face, (direction, up))| ShadowView {
+            kind: ShadowMapKind::Point,
+            layer: slot as u32 * 6 + face as u32,
+            view: Matrix4::look_at_rh(
+                &Point3::from(position),
+                &Point3::from(position + direction),
+                up,
+            ),
+            projection,
+            world_position: position,
+            size,
+        })
+        .collect();
+
+    let gpu_shadow = GpuShadow {
+        view_projection: Matrix4::identity().into(),
+        depth_bias: shadow.depth_bias,
+        normal_bias: shadow.normal_bias,
+        texel_size: 2.0 / size as f32,
+        layer: slot as u32,
+        near: shadow.near,
+        far: light.range,
+        ..Default::default()
+    };
+
+    (views, gpu_shadow)
+}
+
+/// Views of every shadow map of a camera and the contents of its shadow buffer, in
+/// the order of [`ShadowAllocation::shadow_index`].
+pub fn prepare_shadow_views(
+    lights: &[Light],
+    allocation: &ShadowAllocation,
+    camera_world_matrix: &Matrix4<f32>,
+    projection: &Projection,
+    viewport_size: Vector2<f32>,
+    settings: &ShadowSettings,
+) -> (Vec<ShadowView>, Vec<GpuShadow>) {
+    let mut views = vec![];
+    let mut gpu_shadows = vec![];
+    let shadow = |index: usize| lights[index].shadow.unwrap_or_default();
+
+    for (slot, index) in allocation.directional.iter().enumerate() {
+        for (view, gpu_shadow) in directional_cascades(
+            &lights[*index],
+            slot,
+            &shadow(*index),
+            camera_world_matrix,
+            projection,
+            viewport_size,
+            settings,
+        ) {
+            views.push(view);
+            gpu_shadows.push(gpu_shadow);
+        }
+    }
+
+    for (slot, index) in allocation.spot.iter().enumerate() {
+        let light = &lights[*index];
+        let LightKind::Spot { outer_angle, .. } = light.kind else {
+            continue;
+        };
+
+        let (view, gpu_shadow) = spot_shadow(light, slot, &shadow(*index), outer_angle, settings);
+        views.push(view);
+        gpu_shadows.push(gpu_shadow);
+    }
+
+    for (slot, index) in allocation.point.iter().enumerate() {
+        let (faces, gpu_shadow) = point_shadow(&lights[*index], slot, &shadow(*index), settings);
+        views.extend(faces);
+        gpu_shadows.push(gpu_shadow);
+    }
+
+    (views, gpu_shadows)
+}
+
+/// Size and layer count of the shadow map array of a kind.
+pub fn shadow_map_size(
+    kind: ShadowMapKind,
+    allocation: &ShadowAllocation,
+    settings: &ShadowSettings,
+) -> Extent3d {
+    let size = match kind {
+        ShadowMapKind::Directional => settings.directional_map_size,
+        ShadowMapKind::Spot => settings.spot_map_size,
+        ShadowMapKind::Point => settings.point_map_size,
+    }
+    .max(1);
+
+    Extent3d {
+        width: size,
+        height: size,
+        depth_or_array_layers: allocation.layer_count(kind),
+    }
+}
+
+/// Resources the main passes bind in place of the shadow maps of kinds without shadow
+/// casting lights, and the comparison sampler all shadow maps are sampled with.
+pub struct ShadowFallback {
+    pub array_view: TextureView,
+    pub cube_array_view: TextureView,
+    pub sampler: Sampler,
+}
+
+impl ShadowFallback {
+    pub fn new(device: &RenderDevice) -> Self {
+        let texture = device.create_gpu_texture(&wgpu::TextureDescriptor {
+            label: Some("fallback_shadow_map"),
+            size: Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 6,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let view = |dimension, array_layer_count| {
+            texture.create_view(&wgpu::TextureViewDescriptor {
+                dimension: Some(dimension),
+                array_layer_count: Some(array_layer_count),
+                ..Default::default()
+            })
+        };
+
+        Self {
+            array_view: view(TextureViewDimension::D2Array, 1),
+            cube_array_view: view(TextureViewDimension::CubeArray, 6),
+            sampler: device.create_sampler(&SamplerDescriptor {
+                label: Some("shadow_sampler"),
+                address_mode_u: AddressMode::ClampToEdge,
+                address_mode_v: AddressMode::ClampToEdge,
+                address_mode_w: AddressMode::ClampToEdge,
+                mag_filter: FilterMode::Linear,
+                min_filter: FilterMode::Linear,
+                compare: Some(CompareFunction::LessEqual),
+                ..Default::default()
+            }),
+        }
+    }
+
+    pub fn view(&self, kind: ShadowMapKind) -> &TextureView {
+        match kind {
+            ShadowMapKind::Directional | ShadowMapKind::Spot => &self.array_view,
+            ShadowMapKind::Point => &self.cube_array_view,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use draft_graphics::Color;
+
+    fn shadowed(light: Light) -> Light {
+        light.with_shadow(LightShadow::default())
+    }
+
+    #[test]
+    fn lights_are_allocated_up_to_the_limits() {
+        let lights = [
+            shadowed(Light::point(Color::WHITE, 1.0, 10.0)),
+            shadowed(Light::directional(Color::WHITE, 1.0)),
+            Light::directional(Color::WHITE, 1.0),
+            shadowed(Light::point(Color::WHITE, 1.0, 10.0)),
+            shadowed(Light::spot(Color::WHITE, 1.0, 10.0, 0.2, 0.4)),
+        ];
+        let settings = ShadowSettings {
+            cascade_count: 2,
+            max_point_lights: 1,
+            ..Default::default()
+        };
+
+        let allocation = ShadowAllocation::new(&lights, &settings);
+
+        assert_eq!(allocation.directional, vec![1]);
+        assert_eq!(allocation.spot, vec![4]);
+        assert_eq!(allocation.point, vec![0]);
+        assert_eq!(allocation.layer_count(ShadowMapKind::Directional), 2);
+        assert_eq!(allocation.layer_count(ShadowMapKind::Point), 6);
+        assert_eq!(allocation.shadow_count(), 4);
+
+        // Cascades come first, then spot and point lights.
+        assert_eq!(allocation.shadow_index(1), Some(0));
+        assert_eq!(allocation.shadow_index(4), Some(2));
+        assert_eq!(allocation.shadow_index(0), Some(3));
+        assert_eq!(allocation.shadow_index(2), None);
+        assert_eq!(allocation.shadow_index(3), None);
+    }
+
+    #[test]
+    fn casters_reach_as_far_as_the_shadows() {
+        let settings = ShadowSettings {
+            max_distance: 50.0,
+            caster_distance: 20.0,
+            ..Default::default()
+        };
+        let camera_position = Vector3::zeros();
+
+        let lights = [shadowed(Light::point(Color::WHITE, 1.0, 10.0))
+            .with_world_matrix(Matrix4::new_translation(&Vector3::new(0.0, 0.0, 100.0)))];
+        let allocation = ShadowAllocation::new(&lights, &settings);
+        assert_eq!(
+            allocation.caster_distance(&lights, &settings, &camera_position),
+            Some(110.0)
+        );
+
+        let lights = [shadowed(Light::directional(Color::WHITE, 1.0))];
+        let allocation = ShadowAllocation::new(&lights, &settings);
+        assert_eq!(
+            allocation.caster_distance(&lights, &settings, &camera_position),
+            Some(70.0)
+        );
+
+        let lights = [Light::point(Color::WHITE, 1.0, 10.0)];
+        let allocation = ShadowAllocation::new(&lights, &settings);
+        assert_eq!(
+            allocation.caster_distance(&lights, &settings, &camera_position),
+            None
+        );
+    }
+
+    #[test]
+    fn cascade_splits_grow_towards_the_far_plane() {
+        let splits = cascade_splits(1.0, 100.0, 4, 1.0);
+        let expected = [3.1622777, 10.0, 31.622776, 100.0];
+
+        for (split, expected) in splits.iter().zip(expected) {
+            assert!((split - expected).abs() < 1e-3, "{splits:?}");
+        }
+
+        assert_eq!(cascade_splits(0.0, 8.0, 2, 0.0), vec![4.0, 8.0]);
+    }
+
+    #[test]
+    fn cascades_cover_their_frustum_slice() {
+        let light = shadowed(Light::directional(Color::WHITE, 1.0))
+            .with_world_matrix(Matrix4::from_axis_angle(&Vector3::x_axis(), -1.0));
+        let camera = Matrix4::new_translation(&Vector3::new(3.0, 2.0, 1.0));
+        let projection = Projection::default();
+        let viewport_size = Vector2::new(800.0, 600.0);
+        let settings = ShadowSettings {
+            max_distance: 50.0,
+            ..Default::default()
+        };
+
+        let cascades = directional_cascades(
+            &light,
+            1,
+            &LightShadow::default(),
+            &camera,
+            &projection,
+            viewport_size,
+            &settings,
+        );
+        assert_eq!(cascades.len(), 4);
+        assert_eq!(cascades[0].0.layer, 4);
+
+        let mut split_near = 0.1;
+        for (view, gpu_shadow) in cascades.iter() {
+            for corner in
+                frustum_slice_corners(&projection, viewport_size, split_near, gpu_shadow.split_far)
+            {
+                let world = camera.transform_point(&Point3::from(corner));
+                let clip = view.view_projection() * world.to_homogeneous();
+
+                assert!(clip.x.abs() <= 1.0 && clip.y.abs() <= 1.0, "{clip:?}");
+                assert!((0.0..=1.0).contains(&clip.z), "{clip:?}");
+            }
+            split_near = gpu_shadow.split_far;
+        }
+        assert_eq!(split_near, 50.0);
+    }
+
+    /// Face and texture coordinates that sampling a cube map with `direction` reads,
+    /// following the cube map face selection of the graphics APIs.
+    fn cube_map_lookup(direction: Vector3<f32>) -> (usize, f32, f32) {
+        let [x, y, z] = [direction.x, direction.y, direction.z];
+        let (face, sc, tc, ma) = if x.abs() >= y.abs() && x.abs() >= z.abs() {
+            if x > 0.0 {
+                (0, -z, -y, x)
+            } else {
+                (1, z, -y, -x)
+            }
+        } else if y.abs() >= z.abs() {
+            if y > 0.0 {
+                (2, x, z, y)
+            } else {
+                (3, x, -z, -y)
+            }
+        } else if z > 0.0 {
+            (4, x, -y, z)
+        } else {
+            (5, -x, -y, -z)
+        };
+
+        (face, (sc / ma + 1.0) * 0.5, (tc / ma + 1.0) * 0.5)
+    }
+
+    #[test]
+    fn point_shadow_faces_match_cube_map_lookups() {
+        let position = Vector3::new(1.0, -2.0, 0.5);
+        let light = shadowed(Light::point(Color::WHITE, 1.0, 20.0))
+            .with_world_matrix(Matrix4::new_translation(&position));
+
+        let (faces, gpu_shadow) =
+            point_shadow(&light, 1, &LightShadow::default(), &Default::default());
+        assert_eq!(gpu_shadow.layer, 1);
+
+        for offset in [
+            Vector3::new(3.0, 1.0, -0.5),
+            Vector3::new(-2.0, 0.3, 0.6),
+            Vector3::new(0.2, 4.0, 1.0),
+            Vector3::new(-0.4, -3.0, 0.7),
+            Vector3::new(0.5, -0.2, 2.0),
+            Vector3::new(-0.6, 0.9, -5.0),
+        ] {
+            // The shader flips z, since cube maps are sampled in a left-handed space.
+            let (face, u, v) = cube_map_lookup(offset.component_mul(&Vector3::new(1.0, 1.0, -1.0)));
+            let view = &faces[face];
+            assert_eq!(view.layer, 6 + face as u32);
+
+            let clip = view.view_projection() * (position + offset).push(1.0);
+            let ndc = clip.xyz() / clip.w;
+            let (expected_u, expected_v) = (ndc.x * 0.5 + 0.5, ndc.y * -0.5 + 0.5);
+
+            assert!(
+                (u - expected_u).abs() < 1e-5,
+                "{offset:?}: {u} {expected_u}"
+            );
+            assert!(
+                (v - expected_v).abs() < 1e-5,
+                "{offset:?}: {v} {expected_v}"
+            );
+
+            // The shader computes the depth from the major axis of the offset.
+            let distance = offset.abs().max();
+            let depth = gpu_shadow.far * (distance - gpu_shadow.near)
+                / ((gpu_shadow.far - gpu_shadow.near) * distance);
+            assert!((ndc.z - depth).abs() < 1e-5);
+        }
+    }
+}
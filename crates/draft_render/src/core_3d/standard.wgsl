@@ -0,0 +1,337 @@
+struct View {
+    view_projection: mat4x4<f32>,
+    view: mat4x4<f32>,
+    projection: mat4x4<f32>,
+    world_position: vec4<f32>,
+    viewport: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> view: View;
+
+const LIGHT_DIRECTIONAL: u32 = 0u;
+const LIGHT_POINT: u32 = 1u;
+const LIGHT_SPOT: u32 = 2u;
+
+struct Light {
+    position: vec3<f32>,
+    range: f32,
+    direction: vec3<f32>,
+    kind: u32,
+    color: vec3<f32>,
+    intensity: f32,
+    spot_cos_inner: f32,
+    spot_cos_outer: f32,
+    // First entry of the light in the shadow buffer, negative without shadows.
+    shadow_index: i32,
+};
+
+struct Lights {
+    ambient: vec4<f32>,
+    count: u32,
+    cascade_count: u32,
+    lights: array<Light>,
+};
+
+struct Shadow {
+    view_projection: mat4x4<f32>,
+    depth_bias: f32,
+    normal_bias: f32,
+    texel_size: f32,
+    layer: u32,
+    split_far: f32,
+    near: f32,
+    far: f32,
+};
+
+@group(1) @binding(0)
+var<storage, read> lights: Lights;
+@group(1) @binding(1)
+var<storage, read> shadows: array<Shadow>;
+@group(1) @binding(2)
+var directional_shadow_maps: texture_depth_2d_array;
+@group(1) @binding(3)
+var spot_shadow_maps: texture_depth_2d_array;
+@group(1) @binding(4)
+var point_shadow_maps: texture_depth_cube_array;
+@group(1) @binding(5)
+var shadow_sampler: sampler_comparison;
+
+struct StandardMaterial {
+    base_color: vec4<f32>,
+    emissive: vec4<f32>,
+    metallic: f32,
+    roughness: f32,
+    alpha_cutoff: f32,
+};
+
+@group(2) @binding(0)
+var<uniform> material: StandardMaterial;
+@group(2) @binding(1)
+var base_color_texture: texture_2d<f32>;
+@group(2) @binding(2)
+var base_color_sampler: sampler;
+@group(2) @binding(3)
+var metallic_roughness_texture: texture_2d<f32>;
+@group(2) @binding(4)
+var metallic_roughness_sampler: sampler;
+@group(2) @binding(5)
+var normal_texture: texture_2d<f32>;
+@group(2) @binding(6)
+var normal_sampler: sampler;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+#ifdef VERTEX_UVS
+    @location(2) uv: vec2<f32>,
+#endif
+#ifdef VERTEX_TANGENTS
+    @location(3) tangent: vec4<f32>,
+#endif
+    @location(4) world_0: vec4<f32>,
+    @location(5) world_1: vec4<f32>,
+    @location(6) world_2: vec4<f32>,
+    @location(7) world_3: vec4<f32>,
+};
+
+struct VertexOutput {
+    // The depth prepass and the main passes must produce the same depth.
+    @invariant @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+#ifdef VERTEX_TANGENTS
+    @location(3) world_tangent: vec4<f32>,
+#endif
+};
+
+@vertex
+fn vertex(in: VertexInput) -> VertexOutput {
+    let world = mat4x4<f32>(in.world_0, in.world_1, in.world_2, in.world_3);
+    // Normals stay perpendicular to the surface as long as the scale is uniform.
+    let world_3x3 = mat3x3<f32>(world[0].xyz, world[1].xyz, world[2].xyz);
+    let world_position = world * vec4<f32>(in.position, 1.0);
+
+    var out: VertexOutput;
+    out.clip_position = view.view_projection * world_position;
+    out.world_position = world_position.xyz;
+    out.world_normal = world_3x3 * in.normal;
+#ifdef VERTEX_UVS
+    out.uv = in.uv;
+#else
+    out.uv = vec2<f32>(0.0);
+#endif
+#ifdef VERTEX_TANGENTS
+    out.world_tangent = vec4<f32>(world_3x3 * in.tangent.xyz, in.tangent.w);
+#endif
+    return out;
+}
+
+@fragment
+fn prepass_fragment(in: VertexOutput) {
+    let base_color = material.base_color * textureSample(base_color_texture, base_color_sampler, in.uv);
+    if base_color.a < material.alpha_cutoff {
+        discard;
+    }
+}
+
+const PI: f32 = 3.14159265359;
+
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+// Height-correlated Smith visibility, the geometry term divided by 4 n.l n.v.
+fn visibility_smith_ggx(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let a2 = roughness * roughness * roughness * roughness;
+    let v = n_dot_l * sqrt(n_dot_v * n_dot_v * (1.0 - a2) + a2);
+    let l = n_dot_v * sqrt(n_dot_l * n_dot_l * (1.0 - a2) + a2);
+    return 0.5 / (v + l);
+}
+
+fn fresnel_schlick(f0: vec3<f32>, v_dot_h: f32) -> vec3<f32> {
+    return f0 + (vec3<f32>(1.0) - f0) * pow(1.0 - v_dot_h, 5.0);
+}
+
+// Inverse square falloff that reaches zero at the range of the light.
+fn distance_attenuation(distance: f32, range: f32) -> f32 {
+    let factor = distance / range;
+    let window = saturate(1.0 - factor * factor * factor * factor);
+    return window * window / max(distance * distance, 0.0001);
+}
+
+// Percentage-closer filtering over 3x3 texels. Every tap is also filtered bilinearly
+// by the comparison sampler.
+fn sample_shadow_map(shadow_maps: texture_depth_2d_array, uv: vec2<f32>, layer: u32, depth: f32) -> f32 {
+    let texel = 1.0 / vec2<f32>(textureDimensions(shadow_maps));
+    var lit = 0.0;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let offset = vec2<f32>(f32(x), f32(y)) * texel;
+            lit += textureSampleCompareLevel(shadow_maps, shadow_sampler, uv + offset, layer, depth);
+        }
+    }
+    return lit / 9.0;
+}
+
+// Shadow map coordinates in xy and depth in z.
+fn project_to_shadow_map(shadow: Shadow, position: vec3<f32>) -> vec3<f32> {
+    let clip = shadow.view_projection * vec4<f32>(position, 1.0);
+    let ndc = clip.xyz / clip.w;
+    return vec3<f32>(ndc.x * 0.5 + 0.5, ndc.y * -0.5 + 0.5, ndc.z);
+}
+
+fn is_outside_shadow_map(coords: vec3<f32>) -> bool {
+    return any(coords.xy < vec2<f32>(0.0)) || any(coords.xy > vec2<f32>(1.0)) || coords.z > 1.0;
+}
+
+fn directional_shadow(light: Light, position: vec3<f32>, normal: vec3<f32>) -> f32 {
+    let view_depth = -(view.view * vec4<f32>(position, 1.0)).z;
+
+    for (var cascade = 0u; cascade < lights.cascade_count; cascade++) {
+        let shadow = shadows[u32(light.shadow_index) + cascade];
+        if view_depth > shadow.split_far {
+            continue;
+        }
+
+        let biased = position + normal * shadow.normal_bias * shadow.texel_size
+            - light.direction * shadow.depth_bias;
+        let coords = project_to_shadow_map(shadow, biased);
+        if is_outside_shadow_map(coords) {
+            return 1.0;
+        }
+        return sample_shadow_map(directional_shadow_maps, coords.xy, shadow.layer, coords.z);
+    }
+
+    // Past the last cascade.
+    return 1.0;
+}
+
+fn spot_shadow(light: Light, position: vec3<f32>, normal: vec3<f32>, l: vec3<f32>, distance: f32) -> f32 {
+    let shadow = shadows[u32(light.shadow_index)];
+    let biased = position + normal * shadow.normal_bias * shadow.texel_size * distance
+        + l * shadow.depth_bias;
+    let coords = project_to_shadow_map(shadow, biased);
+    if is_outside_shadow_map(coords) {
+        return 1.0;
+    }
+    return sample_shadow_map(spot_shadow_maps, coords.xy, shadow.layer, coords.z);
+}
+
+fn point_shadow(light: Light, position: vec3<f32>, normal: vec3<f32>, l: vec3<f32>, distance: f32) -> f32 {
+    let shadow = shadows[u32(light.shadow_index)];
+    let biased = position + normal * shadow.normal_bias * shadow.texel_size * distance
+        + l * shadow.depth_bias;
+    let offset = biased - light.position;
+
+    // Each face is a perspective projection along the major axis of the offset.
+    let face_distance = max(abs(offset.x), max(abs(offset.y), abs(offset.z)));
+    let depth = shadow.far * (face_distance - shadow.near)
+        / ((shadow.far - shadow.near) * face_distance);
+
+    // Cube maps are sampled in a left-handed space.
+    let direction = offset * vec3<f32>(1.0, 1.0, -1.0);
+    let up = select(vec3<f32>(0.0, 1.0, 0.0), vec3<f32>(1.0, 0.0, 0.0), abs(direction.y) >= face_distance);
+    let texel = shadow.texel_size * face_distance;
+    let tangent = normalize(cross(direction, up)) * texel;
+    let bitangent = normalize(cross(direction, tangent)) * texel;
+
+    var lit = 0.0;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let tap = direction + tangent * f32(x) + bitangent * f32(y);
+            lit += textureSampleCompareLevel(point_shadow_maps, shadow_sampler, tap, shadow.layer, depth);
+        }
+    }
+    return lit / 9.0;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Textures are sampled before any non-uniform control flow.
+    let base_color = material.base_color * textureSample(base_color_texture, base_color_sampler, in.uv);
+    let metallic_roughness = textureSample(metallic_roughness_texture, metallic_roughness_sampler, in.uv);
+    let tangent_normal = textureSample(normal_texture, normal_sampler, in.uv).xyz * 2.0 - 1.0;
+
+#ifdef ALPHA_MASK
+    if base_color.a < material.alpha_cutoff {
+        discard;
+    }
+#endif
+
+    let metallic = saturate(material.metallic * metallic_roughness.b);
+    let roughness = clamp(material.roughness * metallic_roughness.g, 0.045, 1.0);
+
+    // Shadow biases follow the surface, not the normal map.
+    let geometric_normal = normalize(in.world_normal);
+    var n = geometric_normal;
+#ifdef VERTEX_TANGENTS
+    let t = normalize(in.world_tangent.xyz);
+    let b = cross(n, t) * in.world_tangent.w;
+    n = normalize(mat3x3<f32>(t, b, n) * tangent_normal);
+#endif
+
+    let v = normalize(view.world_position.xyz - in.world_position);
+    let n_dot_v = max(dot(n, v), 0.0001);
+    let f0 = mix(vec3<f32>(0.04), base_color.rgb, metallic);
+    let diffuse_color = base_color.rgb * (1.0 - metallic);
+
+    var color = lights.ambient.rgb * (diffuse_color + f0) + material.emissive.rgb;
+
+    for (var i = 0u; i < lights.count; i++) {
+        let light = lights.lights[i];
+
+        var l = -light.direction;
+        var intensity = light.intensity;
+        var distance = 0.0;
+
+        if light.kind != LIGHT_DIRECTIONAL {
+            let to_light = light.position - in.world_position;
+            distance = length(to_light);
+            l = to_light / max(distance, 0.0001);
+            intensity *= distance_attenuation(distance, light.range);
+
+            if light.kind == LIGHT_SPOT {
+                let cos_angle = dot(-l, light.direction);
+                let spot = saturate((cos_angle - light.spot_cos_outer)
+                    / max(light.spot_cos_inner - light.spot_cos_outer, 0.0001));
+                intensity *= spot * spot;
+            }
+        }
+
+        let n_dot_l = saturate(dot(n, l));
+        if n_dot_l <= 0.0 || intensity <= 0.0 {
+            continue;
+        }
+
+        if light.shadow_index >= 0 {
+            if light.kind == LIGHT_DIRECTIONAL {
+                intensity *= directional_shadow(light, in.world_position, geometric_normal);
+            } else if light.kind == LIGHT_SPOT {
+                intensity *= spot_shadow(light, in.world_position, geometric_normal, l, distance);
+            } else {
+                intensity *= point_shadow(light, in.world_position, geometric_normal, l, distance);
+            }
+            if intensity <= 0.0 {
+                continue;
+            }
+        }
+
+        let h = normalize(l + v);
+        let n_dot_h = saturate(dot(n, h));
+        let v_dot_h = saturate(dot(v, h));
+
+        let fresnel = fresnel_schlick(f0, v_dot_h);
+        let specular = fresnel * distribution_ggx(n_dot_h, roughness)
+            * visibility_smith_ggx(n_dot_v, n_dot_l, roughness);
+        let diffuse = (vec3<f32>(1.0) - fresnel) * diffuse_color / PI;
+
+        color += (diffuse + specular) * light.color * intensity * n_dot_l;
+    }
+
+    return vec4<f32>(color, base_color.a);
+}
@@ -0,0 +1,280 @@
+use std::sync::LazyLock;
+
+use bytemuck::{Pod, Zeroable};
+use draft_graphics::{BindGroupLayout, Color, RenderDevice};
+use draft_image::{Image, ImageFormat, ImageKind, ImageResource};
+use draft_material::{AlphaMode, Material, MaterialProperty};
+use draft_shader::{Shader, ShaderResource};
+use wgpu::{
+    BufferBindingType, SamplerBindingType, ShaderStages, TextureSampleType, TextureViewDimension,
+};
+
+/// Bind group index of the material uniform and textures in the mesh passes.
+pub const MATERIAL_BIND_GROUP: u32 = 2;
+
+static STANDARD_SHADER: LazyLock<ShaderResource> = LazyLock::new(|| {
+    ShaderResource::new_embedded(Shader {
+        source: include_str!("standard.wgsl").into(),
+        ..Default::default()
+    })
+});
+
+pub fn create_material_bind_group_layout(device: &RenderDevice) -> BindGroupLayout {
+    let texture = |binding| wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: TextureSampleType::Float { filterable: true },
+            view_dimension: TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    };
+    let sampler = |binding| wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Sampler(SamplerBindingType::Filtering),
+        count: None,
+    };
+
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("material_bind_group_layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            },
+            texture(1),
+            sampler(2),
+            texture(3),
+            sampler(4),
+            texture(5),
+            sampler(6),
+        ],
+    })
+}
+
+/// The metallic-roughness material of the standard shader. Converts into a
+/// [`Material`] whose properties use the names below, so the properties can also be
+/// set on any material that uses [`StandardMaterial::shader`].
+#[derive(Debug, Clone)]
+pub struct StandardMaterial {
+    pub base_color: Color,
+    /// Multiplies the base color.
+    pub base_color_texture: Option<ImageResource>,
+    pub metallic: f32,
+    pub roughness: f32,
+    /// Roughness in the green channel and metallic in the blue channel, multiplying
+    /// the factors.
+    pub metallic_roughness_texture: Option<ImageResource>,
+    /// Tangent space normals, used by meshes with tangents.
+    pub normal_texture: Option<ImageResource>,
+    pub emissive: Color,
+    pub alpha_mode: AlphaMode,
+}
+
+impl Default for StandardMaterial {
+    fn default() -> Self {
+        Self {
+            base_color: Color::WHITE,
+            base_color_texture: None,
+            metallic: 0.0,
+            roughness: 0.5,
+            metallic_roughness_texture: None,
+            normal_texture: None,
+            emissive: Color::BLACK,
+            alpha_mode: AlphaMode::Opaque,
+        }
+    }
+}
+
+impl StandardMaterial {
+    pub const BASE_COLOR: &str = "base_color";
+    pub const BASE_COLOR_TEXTURE: &str = "base_color_texture";
+    pub const METALLIC: &str = "metallic";
+    pub const ROUGHNESS: &str = "roughness";
+    pub const METALLIC_ROUGHNESS_TEXTURE: &str = "metallic_roughness_texture";
+    pub const NORMAL_TEXTURE: &str = "normal_texture";
+    pub const EMISSIVE: &str = "emissive";
+
+    /// The PBR shader drawn by the core 3D passes.
+    pub fn shader() -> ShaderResource {
+        STANDARD_SHADER.clone()
+    }
+}
+
+fn color_to_array(color: Color) -> [f32; 4] {
+    [
+        color.r as f32,
+        color.g as f32,
+        color.b as f32,
+        color.a as f32,
+    ]
+}
+
+impl From<StandardMaterial> for Material {
+    fn from(standard: StandardMaterial) -> Self {
+        let mut material = Material::new(StandardMaterial::shader());
+
+        material.set_alpha_mode(standard.alpha_mode);
+        material.set_property(
+            StandardMaterial::BASE_COLOR,
+            MaterialProperty::Vector4(color_to_array(standard.base_color)),
+        );
+        material.set_property(
+            StandardMaterial::BASE_COLOR_TEXTURE,
+            MaterialProperty::Texture(standard.base_color_texture),
+        );
+        material.set_property(
+            StandardMaterial::METALLIC,
+            MaterialProperty::Float(standard.metallic),
+        );
+        material.set_property(
+            StandardMaterial::ROUGHNESS,
+            MaterialProperty::Float(standard.roughness),
+        );
+        material.set_property(
+            StandardMaterial::METALLIC_ROUGHNESS_TEXTURE,
+            MaterialProperty::Texture(standard.metallic_roughness_texture),
+        );
+        material.set_property(
+            StandardMaterial::NORMAL_TEXTURE,
+            MaterialProperty::Texture(standard.normal_texture),
+        );
+        material.set_property(
+            StandardMaterial::EMISSIVE,
+            MaterialProperty::Vector4(color_to_array(standard.emissive)),
+        );
+
+        material
+    }
+}
+
+/// Material data bound at binding 0 of [`MATERIAL_BIND_GROUP`]. Matches the
+/// `StandardMaterial` struct of the standard shader.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct StandardMaterialUniform {
+    pub base_color: [f32; 4],
+    pub emissive: [f32; 4],
+    pub metallic: f32,
+    pub roughness: f32,
+    /// Fragments with a lower alpha are discarded by alpha-mask materials.
+    pub alpha_cutoff: f32,
+    pub _padding: f32,
+}
+
+impl StandardMaterialUniform {
+    /// Reads the standard properties of a material. Missing properties keep the values
+    /// of [`StandardMaterial::default`].
+    pub fn new(material: &Material) -> Self {
+        let defaults = StandardMaterial::default();
+
+        let vector = |name, default: Color| match material.property(name) {
+            Some(MaterialProperty::Vector4(value)) => *value,
+            Some(MaterialProperty::Vector3([r, g, b])) => [*r, *g, *b, 1.0],
+            _ => color_to_array(default),
+        };
+        let float = |name, default| match material.property(name) {
+            Some(MaterialProperty::Float(value)) => *value,
+            _ => default,
+        };
+
+        Self {
+            base_color: vector(StandardMaterial::BASE_COLOR, defaults.base_color),
+            emissive: vector(StandardMaterial::EMISSIVE, defaults.emissive),
+            metallic: float(StandardMaterial::METALLIC, defaults.metallic),
+            roughness: float(StandardMaterial::ROUGHNESS, defaults.roughness),
+            alpha_cutoff: match material.alpha_mode() {
+                AlphaMode::Mask(cutoff) => cutoff,
+                _ => 0.0,
+            },
+            _padding: 0.0,
+        }
+    }
+}
+
+/// Returns the texture set on a material property, if any.
+pub fn material_texture(material: &Material, name: &str) -> Option<ImageResource> {
+    match material.property(name) {
+        Some(MaterialProperty::Texture(texture)) => texture.clone(),
+        _ => None,
+    }
+}
+
+/// Textures bound in place of the material textures that are missing or still
+/// loading.
+pub struct DefaultMaterialTextures {
+    /// Opaque white, used for the base color and metallic-roughness textures.
+    pub white: ImageResource,
+    /// Points along the z axis in tangent space.
+    pub flat_normal: ImageResource,
+}
+
+impl Default for DefaultMaterialTextures {
+    fn default() -> Self {
+        let pixel = |data: [u8; 4]| {
+            let image = Image::new(
+                ImageKind::D2 {
+                    width: 1,
+                    height: 1,
+                },
+                ImageFormat::Rgba8Unorm,
+                1,
+                data.to_vec(),
+            )
+            .expect("A single pixel matches a 1x1 image.");
+
+            ImageResource::new_embedded(image)
+        };
+
+        Self {
+            white: pixel([255; 4]),
+            flat_normal: pixel([128, 128, 255, 255]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standard_material_properties_round_trip() {
+        let material = Material::from(StandardMaterial {
+            base_color: Color::RED,
+            metallic: 1.0,
+            roughness: 0.25,
+            alpha_mode: AlphaMode::Mask(0.3),
+            ..Default::default()
+        });
+
+        let uniform = StandardMaterialUniform::new(&material);
+
+        assert_eq!(material.shader(), &StandardMaterial::shader());
+        assert_eq!(uniform.base_color, [1.0, 0.0, 0.0, 1.0]);
+        assert_eq!(uniform.metallic, 1.0);
+        assert_eq!(uniform.roughness, 0.25);
+        assert_eq!(uniform.alpha_cutoff, 0.3);
+        assert_eq!(
+            material_texture(&material, StandardMaterial::NORMAL_TEXTURE),
+            None
+        );
+    }
+
+    #[test]
+    fn missing_properties_use_defaults() {
+        let uniform = StandardMaterialUniform::new(&Material::new(StandardMaterial::shader()));
+
+        assert_eq!(uniform.base_color, [1.0; 4]);
+        assert_eq!(uniform.emissive, [0.0, 0.0, 0.0, 1.0]);
+        assert_eq!(uniform.roughness, 0.5);
+        assert_eq!(uniform.alpha_cutoff, 0.0);
+    }
+}
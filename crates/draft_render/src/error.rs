@@ -4,6 +4,16 @@ use thiserror::Error;
 pub enum FrameworkError {
     #[error("Mesh not loaded.")]
     MeshNotLoaded,
+    #[error("Image not loaded.")]
+    ImageNotLoaded,
     #[error("Shader not loaded.")]
     ShaderNotLoaded,
+    #[error("Failed to compile shader: {0}")]
+    ShaderCompilation(String),
+    #[error("Render pipeline nodes form a cycle: {0:?}.")]
+    RenderPipelineCycle(Vec<String>),
+    #[error("Render pipeline node {node} reads slot {slot} that no node writes.")]
+    RenderPipelineUnsatisfiedInput { node: String, slot: String },
+    #[error("Failed to read back render target: {0}")]
+    Readback(String),
 }
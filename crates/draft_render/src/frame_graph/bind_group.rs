@@ -0,0 +1,68 @@
+use wgpu::{BindGroupLayout, Buffer, Sampler};
+
+use crate::frame_graph::{PassContext, TransientTextureView};
+
+#[derive(Clone)]
+pub enum TransientBindingResource {
+    Buffer(Buffer),
+    Sampler(Sampler),
+    TextureView(TransientTextureView),
+}
+
+#[derive(Clone)]
+pub struct TransientBindGroupEntry {
+    pub binding: u32,
+    pub resource: TransientBindingResource,
+}
+
+/// A bind group that refers to textures of the frame graph. It is created when its
+/// pass executes, once the textures exist.
+#[derive(Clone)]
+pub struct TransientBindGroup {
+    pub label: Option<String>,
+    pub layout: BindGroupLayout,
+    pub entries: Vec<TransientBindGroupEntry>,
+}
+
+impl PassContext<'_> {
+    pub fn create_bind_group(&self, bind_group: &TransientBindGroup) -> wgpu::BindGroup {
+        let texture_views = bind_group
+            .entries
+            .iter()
+            .map(|entry| match &entry.resource {
+                TransientBindingResource::TextureView(texture_view) => {
+                    Some(self.create_texture_view(texture_view))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        let entries = bind_group
+            .entries
+            .iter()
+            .zip(texture_views.iter())
+            .map(|(entry, texture_view)| wgpu::BindGroupEntry {
+                binding: entry.binding,
+                resource: match (&entry.resource, texture_view) {
+                    (TransientBindingResource::Buffer(buffer), _) => buffer.as_entire_binding(),
+                    (TransientBindingResource::Sampler(sampler), _) => {
+                        wgpu::BindingResource::Sampler(sampler)
+                    }
+                    (TransientBindingResource::TextureView(_), texture_view) => {
+                        wgpu::BindingResource::TextureView(
+                            texture_view
+                                .as_ref()
+                                .expect("Texture views are created for every texture entry."),
+                        )
+                    }
+                },
+            })
+            .collect::<Vec<_>>();
+
+        self.device().create_bind_group(&wgpu::BindGroupDescriptor {
+            label: bind_group.label.as_deref(),
+            layout: &bind_group.layout,
+            entries: &entries,
+        })
+    }
+}
@@ -17,6 +17,7 @@ impl DevicePass {
                 resource,
                 &context.device,
                 context.transient_resource_cache,
+                &mut context.stats,
             );
         }
     }
@@ -38,7 +39,9 @@ impl DevicePass {
                 &context.device,
                 &context.resource_table,
                 context.pipeline_container,
+                &mut context.stats,
             );
+            context.stats.passes_executed += 1;
         }
         self.release_resources(context);
     }
@@ -10,9 +10,9 @@ pub use pass_node_builder::*;
 pub use resource_material::*;
 
 use crate::frame_graph::{
-    Index, IntoArcAnyTransientResource, PassNode, PipelineContainer, ResourceBoard, ResourceHandle,
-    ResourceNode, ResourceTable, TransientResource, TransientResourceCache,
-    TransientResourceDescriptor, TypeEquals, VirtualResource,
+    FrameGraphStats, Index, IntoArcAnyTransientResource, PassNode, PipelineContainer,
+    ResourceBoard, ResourceHandle, ResourceNode, ResourceTable, TransientResource,
+    TransientResourceCache, TransientResourceDescriptor, TypeEquals, VirtualResource,
 };
 
 pub struct FrameGraphContext<'a> {
@@ -20,6 +20,7 @@ pub struct FrameGraphContext<'a> {
     pub pipeline_container: &'a PipelineContainer,
     pub device: RenderDevice,
     pub transient_resource_cache: &'a mut TransientResourceCache,
+    pub stats: FrameGraphStats,
     pub(crate) command_buffers: Vec<CommandBuffer>,
 }
 
@@ -34,6 +35,7 @@ impl<'a> FrameGraphContext<'a> {
             pipeline_container,
             device: device.clone(),
             transient_resource_cache,
+            stats: FrameGraphStats::default(),
             command_buffers: vec![],
         }
     }
@@ -49,10 +51,13 @@ impl<'a> FrameGraphContext<'a> {
 
 pub struct CompiledFrameGraph {
     device_passes: Vec<DevicePass>,
+    culled_passes: usize,
 }
 
 impl CompiledFrameGraph {
     pub fn execute(&self, context: &mut FrameGraphContext) {
+        context.stats.passes_culled += self.culled_passes;
+
         for device_pass in self.device_passes.iter() {
             device_pass.execute(context);
         }
@@ -87,8 +92,40 @@ impl FrameGraph {
         self.reset();
     }
 
+    /// Culls the passes whose results are never used. Walking back from the last pass,
+    /// a pass is kept when it has a side effect, writes an imported resource or writes
+    /// a resource that a kept pass reads. Passes that write no resource are kept too,
+    /// since their effects are not known to the graph.
+    fn cull(&mut self) {
+        let mut read_by_kept_pass = vec![false; self.resource_nodes.len()];
+
+        for pass_node in self.pass_nodes.iter_mut().rev() {
+            let kept = pass_node.has_side_effect
+                || pass_node.writes.is_empty()
+                || pass_node.writes.iter().any(|handle| {
+                    read_by_kept_pass[handle.index.slot]
+                        || matches!(
+                            self.resource_nodes[handle.index.slot].resource,
+                            VirtualResource::Imported(_)
+                        )
+                });
+
+            pass_node.culled = !kept;
+
+            if kept {
+                for handle in pass_node.reads.iter() {
+                    read_by_kept_pass[handle.index.slot] = true;
+                }
+            }
+        }
+    }
+
     fn compute_resource_lifetime(&mut self) {
-        for pass_node in self.pass_nodes.iter_mut() {
+        for pass_node in self
+            .pass_nodes
+            .iter_mut()
+            .filter(|pass_node| !pass_node.culled)
+        {
             for resource_handle in pass_node.reads.iter() {
                 let resource_node = &mut self.resource_nodes[resource_handle.index.slot];
                 resource_node.update_lifetime(pass_node.index);
@@ -129,6 +166,10 @@ impl FrameGraph {
         let mut device_passes = vec![];
 
         for index in 0..self.pass_nodes.len() {
+            if self.pass_nodes[index].culled {
+                continue;
+            }
+
             let handle = self.pass_nodes[index].index;
 
             let mut device_pass = DevicePass::default();
@@ -137,15 +178,20 @@ impl FrameGraph {
             device_passes.push(device_pass);
         }
 
-        self.compiled_frame_graph = Some(CompiledFrameGraph { device_passes });
+        let culled_passes = self.pass_nodes.len() - device_passes.len();
+
+        self.compiled_frame_graph = Some(CompiledFrameGraph {
+            device_passes,
+            culled_passes,
+        });
     }
 
     pub fn compile(&mut self) {
         if self.pass_nodes.is_empty() {
             return;
         }
-        //todo cull
 
+        self.cull();
         self.compute_resource_lifetime();
         self.generate_compiled_frame_graph();
     }
@@ -265,3 +311,93 @@ impl FrameGraph {
         handle
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame_graph::{
+        ManualTextureDescriptor, PassNodeBuilderExt, TransientTexture, TransientTextureDescriptor,
+    };
+    use wgpu::{Extent3d, TextureDimension, TextureFormat, TextureUsages};
+
+    fn texture(graph: &mut FrameGraph, name: &str) -> ResourceHandle<TransientTexture> {
+        graph.get_or_create(
+            name,
+            TransientTextureDescriptor::Manual(ManualTextureDescriptor {
+                label: Some(name.to_string()),
+                size: Extent3d {
+                    width: 4,
+                    height: 4,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::Depth32Float,
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            }),
+        )
+    }
+
+    fn write(graph: &mut FrameGraph, pass: &str, resource: &str) {
+        let handle = texture(graph, resource);
+        graph.create_pass_builder(pass).write(handle);
+    }
+
+    fn kept_passes(graph: &FrameGraph) -> Vec<&str> {
+        graph
+            .compiled_frame_graph
+            .as_ref()
+            .unwrap()
+            .device_passes
+            .iter()
+            .map(|device_pass| device_pass.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn passes_with_unread_results_are_culled() {
+        let mut graph = FrameGraph::default();
+
+        write(&mut graph, "shadow_0", "shadow_maps");
+        write(&mut graph, "shadow_1", "shadow_maps");
+        write(&mut graph, "unused", "unused_texture");
+
+        let shadow_maps = texture(&mut graph, "shadow_maps");
+        let mut pass_builder = graph.create_pass_builder("main");
+        pass_builder.read(shadow_maps);
+        pass_builder.mark_side_effect();
+        drop(pass_builder);
+
+        graph.compile();
+
+        assert_eq!(kept_passes(&graph), vec!["shadow_0", "shadow_1", "main"]);
+        assert!(graph.pass_nodes[2].culled);
+        assert_eq!(
+            graph.compiled_frame_graph.as_ref().unwrap().culled_passes,
+            1
+        );
+        // Culled passes neither request nor release resources.
+        // The texture of the culled pass is never created.
+        assert!(graph.resource_nodes[1].first_use_pass.is_none());
+    }
+
+    #[test]
+    fn culling_follows_reads_back_through_the_graph() {
+        let mut graph = FrameGraph::default();
+
+        write(&mut graph, "shadow", "shadow_maps");
+
+        let shadow_maps = texture(&mut graph, "shadow_maps");
+        let depth = texture(&mut graph, "depth");
+        let mut pass_builder = graph.create_pass_builder("prepass");
+        pass_builder.read(shadow_maps);
+        pass_builder.write(depth);
+        drop(pass_builder);
+
+        graph.compile();
+
+        // Nothing reads the depth texture, so both passes are culled.
+        assert!(kept_passes(&graph).is_empty());
+    }
+}
@@ -0,0 +1,13 @@
+use crate::frame_graph::{ComputePassCommand, ComputePassContext};
+
+pub struct DispatchWorkgroupsParameter {
+    pub x: u32,
+    pub y: u32,
+    pub z: u32,
+}
+
+impl ComputePassCommand for DispatchWorkgroupsParameter {
+    fn execute(&self, compute_pass_context: &mut ComputePassContext) {
+        compute_pass_context.dispatch_workgroups(self.x, self.y, self.z);
+    }
+}
@@ -0,0 +1,46 @@
+mod dispatch_workgroups_parameter;
+mod set_compute_bind_group_parameter;
+mod set_compute_gpu_bind_group_parameter;
+mod set_compute_pipeline_parameter;
+
+use crate::frame_graph::{ComputePass, ComputePassCommand, TransientBindGroup};
+use dispatch_workgroups_parameter::*;
+use set_compute_bind_group_parameter::*;
+use set_compute_gpu_bind_group_parameter::*;
+use set_compute_pipeline_parameter::*;
+
+pub trait ComputePassExt {
+    fn push<T: ComputePassCommand>(&mut self, value: T);
+
+    fn set_gpu_bind_group(&mut self, index: u32, bind_group: &wgpu::BindGroup, offsets: &[u32]) {
+        self.push(SetComputeGpuBindGroupParameter {
+            index,
+            bind_group: bind_group.clone(),
+            offsets: offsets.to_vec(),
+        });
+    }
+
+    /// Binds a bind group that refers to frame graph textures. The textures must be
+    /// read or written by the pass.
+    fn set_bind_group(&mut self, index: u32, bind_group: &TransientBindGroup, offsets: &[u32]) {
+        self.push(SetComputeBindGroupParameter {
+            index,
+            bind_group: bind_group.clone(),
+            offsets: offsets.to_vec(),
+        });
+    }
+
+    fn set_compute_pipeline(&mut self, pipeline_id: usize) {
+        self.push(SetComputePipelineParameter { pipeline_id });
+    }
+
+    fn dispatch_workgroups(&mut self, x: u32, y: u32, z: u32) {
+        self.push(DispatchWorkgroupsParameter { x, y, z });
+    }
+}
+
+impl ComputePassExt for ComputePass {
+    fn push<T: ComputePassCommand>(&mut self, value: T) {
+        self.commands.push(Box::new(value));
+    }
+}
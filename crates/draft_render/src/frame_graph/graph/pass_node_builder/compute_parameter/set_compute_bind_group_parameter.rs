@@ -0,0 +1,13 @@
+use crate::frame_graph::{ComputePassCommand, ComputePassContext, TransientBindGroup};
+
+pub struct SetComputeBindGroupParameter {
+    pub index: u32,
+    pub bind_group: TransientBindGroup,
+    pub offsets: Vec<u32>,
+}
+
+impl ComputePassCommand for SetComputeBindGroupParameter {
+    fn execute(&self, compute_pass_context: &mut ComputePassContext) {
+        compute_pass_context.set_bind_group(self.index, &self.bind_group, &self.offsets);
+    }
+}
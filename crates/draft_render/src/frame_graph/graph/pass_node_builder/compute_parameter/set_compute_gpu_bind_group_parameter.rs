@@ -0,0 +1,13 @@
+use crate::frame_graph::{ComputePassCommand, ComputePassContext};
+
+pub struct SetComputeGpuBindGroupParameter {
+    pub index: u32,
+    pub bind_group: wgpu::BindGroup,
+    pub offsets: Vec<u32>,
+}
+
+impl ComputePassCommand for SetComputeGpuBindGroupParameter {
+    fn execute(&self, compute_pass_context: &mut ComputePassContext) {
+        compute_pass_context.set_gpu_bind_group(self.index, &self.bind_group, &self.offsets);
+    }
+}
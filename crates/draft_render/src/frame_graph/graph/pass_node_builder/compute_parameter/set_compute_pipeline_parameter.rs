@@ -0,0 +1,11 @@
+use crate::frame_graph::{ComputePassCommand, ComputePassContext};
+
+pub struct SetComputePipelineParameter {
+    pub pipeline_id: usize,
+}
+
+impl ComputePassCommand for SetComputePipelineParameter {
+    fn execute(&self, compute_pass_context: &mut ComputePassContext) {
+        compute_pass_context.set_compute_pipeline(self.pipeline_id);
+    }
+}
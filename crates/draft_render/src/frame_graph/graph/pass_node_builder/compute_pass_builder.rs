@@ -0,0 +1,87 @@
+use core::mem::take;
+
+use crate::frame_graph::{
+    ComputePass, ComputePassCommand, PassNodeBuilderExt, ResourceHandle, ResourceMaterial,
+    ResourceRead, ResourceRef, ResourceWrite, TransientResource, TransientTextureView,
+    TransientTextureViewHandle,
+};
+
+use super::{ComputePassExt, PassBuilder};
+
+pub struct ComputePassBuilder<'a, 'b> {
+    compute_pass: ComputePass,
+    pass_builder: &'b mut PassBuilder<'a>,
+}
+
+impl Drop for ComputePassBuilder<'_, '_> {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+impl PassNodeBuilderExt for ComputePassBuilder<'_, '_> {
+    fn read_material<M: ResourceMaterial>(
+        &mut self,
+        material: &M,
+    ) -> ResourceRef<M::ResourceType, ResourceRead> {
+        self.pass_builder.read_material(material)
+    }
+
+    fn write_material<M: ResourceMaterial>(
+        &mut self,
+        material: &M,
+    ) -> ResourceRef<M::ResourceType, ResourceWrite> {
+        self.pass_builder.write_material(material)
+    }
+
+    fn read<ResourceType: TransientResource>(
+        &mut self,
+        resource_handle: ResourceHandle<ResourceType>,
+    ) -> ResourceRef<ResourceType, ResourceRead> {
+        self.pass_builder.read(resource_handle)
+    }
+
+    fn write<ResourceType: TransientResource>(
+        &mut self,
+        resource_handle: ResourceHandle<ResourceType>,
+    ) -> ResourceRef<ResourceType, ResourceWrite> {
+        self.pass_builder.write(resource_handle)
+    }
+
+    fn read_texture_handle(
+        &mut self,
+        texture_handle: &TransientTextureViewHandle,
+    ) -> TransientTextureView {
+        self.pass_builder.read_texture_handle(texture_handle)
+    }
+
+    fn write_texture_handle(
+        &mut self,
+        texture_handle: &TransientTextureViewHandle,
+    ) -> TransientTextureView {
+        self.pass_builder.write_texture_handle(texture_handle)
+    }
+}
+
+impl ComputePassExt for ComputePassBuilder<'_, '_> {
+    fn push<T: ComputePassCommand>(&mut self, value: T) {
+        self.compute_pass.push(value);
+    }
+}
+
+impl<'a, 'b> ComputePassBuilder<'a, 'b> {
+    pub fn new(pass_builder: &'b mut PassBuilder<'a>, name: &str) -> Self {
+        let mut compute_pass = ComputePass::default();
+        compute_pass.set_pass_name(name);
+
+        Self {
+            compute_pass,
+            pass_builder,
+        }
+    }
+
+    pub fn finish(&mut self) {
+        let compute_pass = take(&mut self.compute_pass);
+        self.pass_builder.push(compute_pass);
+    }
+}
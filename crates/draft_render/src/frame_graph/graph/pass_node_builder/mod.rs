@@ -1,7 +1,11 @@
+mod compute_parameter;
+mod compute_pass_builder;
 mod parameter;
 mod pass_builder;
 mod render_pass_builder;
 
+pub use compute_parameter::ComputePassExt;
+pub use compute_pass_builder::*;
 pub use parameter::RenderPassExt;
 pub use pass_builder::*;
 pub use render_pass_builder::*;
@@ -18,6 +22,7 @@ pub struct PassNodeBuilder<'a> {
     writes: Vec<RawResourceHandle>,
     reads: Vec<RawResourceHandle>,
     pass: Option<Pass>,
+    has_side_effect: bool,
 }
 
 impl Drop for PassNodeBuilder<'_> {
@@ -26,6 +31,7 @@ impl Drop for PassNodeBuilder<'_> {
         pass_node.writes = self.writes.clone();
         pass_node.reads = self.reads.clone();
         pass_node.pass = self.pass.take();
+        pass_node.has_side_effect = self.has_side_effect;
     }
 }
 
@@ -156,6 +162,12 @@ impl<'a> PassNodeBuilder<'a> {
         self.pass = Some(pass);
     }
 
+    /// Keeps the pass when the graph is culled, for passes whose results are not
+    /// resources of the graph.
+    pub fn mark_side_effect(&mut self) {
+        self.has_side_effect = true;
+    }
+
     pub fn new(name: &str, graph: &'a mut FrameGraph) -> Self {
         Self {
             graph,
@@ -163,6 +175,7 @@ impl<'a> PassNodeBuilder<'a> {
             writes: vec![],
             reads: vec![],
             pass: None,
+            has_side_effect: false,
         }
     }
 }
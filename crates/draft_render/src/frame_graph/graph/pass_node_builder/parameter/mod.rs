@@ -1,22 +1,30 @@
 mod draw_indexed_parameter;
+mod draw_indirect_parameter;
 mod draw_parameter;
+mod multi_draw_indexed_indirect_parameter;
+mod set_bind_group_parameter;
 mod set_gpu_bind_group_parameter;
 mod set_index_buffer_parameter;
 mod set_render_pipeline_parameter;
 mod set_scissor_rect_parameter;
 mod set_vertex_buffer_parameter;
+mod set_viewport_parameter;
 
 use crate::frame_graph::{
-    RenderPass, RenderPassCommand, ResourceRead, ResourceRef, TransientBuffer,
+    RenderPass, RenderPassCommand, ResourceRead, ResourceRef, TransientBindGroup, TransientBuffer,
 };
 use core::ops::Range;
 use draw_indexed_parameter::*;
+use draw_indirect_parameter::*;
 use draw_parameter::*;
+use multi_draw_indexed_indirect_parameter::*;
+use set_bind_group_parameter::*;
 use set_gpu_bind_group_parameter::*;
 use set_index_buffer_parameter::*;
 use set_render_pipeline_parameter::*;
 use set_scissor_rect_parameter::*;
 use set_vertex_buffer_parameter::*;
+use set_viewport_parameter::*;
 use wgpu::IndexFormat;
 
 pub trait RenderPassExt {
@@ -31,6 +39,25 @@ pub trait RenderPassExt {
         });
     }
 
+    fn set_viewport(
+        &mut self,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        min_depth: f32,
+        max_depth: f32,
+    ) {
+        self.push(SetViewportParameter {
+            x,
+            y,
+            width,
+            height,
+            min_depth,
+            max_depth,
+        });
+    }
+
     fn set_gpu_bind_group(&mut self, index: u32, bind_group: &wgpu::BindGroup, offsets: &[u32]) {
         self.push(SetGpuBindGroupParameter {
             index,
@@ -39,6 +66,16 @@ pub trait RenderPassExt {
         });
     }
 
+    /// Binds a bind group that refers to frame graph textures. The textures must be
+    /// read or written by the pass.
+    fn set_bind_group(&mut self, index: u32, bind_group: &TransientBindGroup, offsets: &[u32]) {
+        self.push(SetBindGroupParameter {
+            index,
+            bind_group: bind_group.clone(),
+            offsets: offsets.to_vec(),
+        });
+    }
+
     fn draw_indexed(&mut self, indices: Range<u32>, base_vertex: i32, instances: Range<u32>) {
         self.push(DrawIndexedParameter {
             indices,
@@ -54,6 +91,35 @@ pub trait RenderPassExt {
         });
     }
 
+    /// Draws with the arguments at `indirect_offset`, laid out as
+    /// [`DrawIndirectArgs`](wgpu::util::DrawIndirectArgs).
+    fn draw_indirect(
+        &mut self,
+        indirect_buffer_ref: &ResourceRef<TransientBuffer, ResourceRead>,
+        indirect_offset: u64,
+    ) {
+        self.push(DrawIndirectParameter {
+            indirect_buffer_ref: indirect_buffer_ref.clone(),
+            indirect_offset,
+        });
+    }
+
+    /// Issues `count` indexed draws with the tightly packed
+    /// [`DrawIndexedIndirectArgs`](wgpu::util::DrawIndexedIndirectArgs) that start at
+    /// `indirect_offset`.
+    fn multi_draw_indexed_indirect(
+        &mut self,
+        indirect_buffer_ref: &ResourceRef<TransientBuffer, ResourceRead>,
+        indirect_offset: u64,
+        count: u32,
+    ) {
+        self.push(MultiDrawIndexedIndirectParameter {
+            indirect_buffer_ref: indirect_buffer_ref.clone(),
+            indirect_offset,
+            count,
+        });
+    }
+
     fn set_render_pipeline(&mut self, pipeline_id: usize) {
         self.push(SetRenderPipelineParameter { pipeline_id });
     }
@@ -1,9 +1,9 @@
 use core::mem::take;
 
 use crate::frame_graph::{
-    Pass, PassCommand, PassNodeBuilderExt, RenderPassBuilder, ResourceHandle, ResourceMaterial,
-    ResourceRead, ResourceRef, ResourceWrite, TransientResource, TransientTextureView,
-    TransientTextureViewHandle,
+    ComputePassBuilder, Pass, PassCommand, PassNodeBuilderExt, RenderPassBuilder, ResourceHandle,
+    ResourceMaterial, ResourceRead, ResourceRef, ResourceWrite, TransientResource,
+    TransientTextureView, TransientTextureViewHandle,
 };
 
 use super::PassNodeBuilder;
@@ -76,6 +76,15 @@ impl<'a> PassBuilder<'a> {
         RenderPassBuilder::new(self, name)
     }
 
+    pub fn create_compute_pass_builder(&mut self, name: &str) -> ComputePassBuilder<'a, '_> {
+        ComputePassBuilder::new(self, name)
+    }
+
+    /// See [`PassNodeBuilder::mark_side_effect`].
+    pub fn mark_side_effect(&mut self) {
+        self.pass_node_builder.mark_side_effect();
+    }
+
     pub fn push<T: PassCommand>(&mut self, command: T) {
         self.pass.push(command);
     }
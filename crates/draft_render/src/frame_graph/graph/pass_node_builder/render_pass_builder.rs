@@ -3,13 +3,20 @@ use core::{mem::take, ops::Range};
 use wgpu::IndexFormat;
 
 use crate::frame_graph::{
-    PassNodeBuilderExt, RenderPass, ResourceHandle, ResourceMaterial, ResourceRead, ResourceRef,
-    ResourceWrite, TransientBuffer, TransientRenderPassColorAttachment, TransientResource,
-    TransientTextureView, TransientTextureViewHandle,
+    PassNodeBuilderExt, RenderPass, RenderPassCommand, ResourceHandle, ResourceMaterial,
+    ResourceRead, ResourceRef, ResourceWrite, TransientBuffer, TransientRenderPassColorAttachment,
+    TransientRenderPassDepthStencilAttachment, TransientResource, TransientTextureView,
+    TransientTextureViewHandle,
 };
 
 use super::{PassBuilder, RenderPassExt};
 
+/// Texture views that are not resources of the graph, so drawing into them is a side
+/// effect of the pass.
+fn is_external(texture_view: &TransientTextureView) -> bool {
+    matches!(texture_view, TransientTextureView::TextureView(_))
+}
+
 pub struct RenderPassBuilder<'a, 'b> {
     render_pass: RenderPass,
     pass_builder: &'b mut PassBuilder<'a>,
@@ -65,6 +72,12 @@ impl PassNodeBuilderExt for RenderPassBuilder<'_, '_> {
     }
 }
 
+impl RenderPassExt for RenderPassBuilder<'_, '_> {
+    fn push<T: RenderPassCommand>(&mut self, value: T) {
+        self.render_pass.push(value);
+    }
+}
+
 impl<'a, 'b> RenderPassBuilder<'a, 'b> {
     pub fn new(pass_builder: &'b mut PassBuilder<'a>, name: &str) -> Self {
         let mut render_pass = RenderPass::default();
@@ -104,11 +117,33 @@ impl<'a, 'b> RenderPassBuilder<'a, 'b> {
         &mut self,
         color_attachment: TransientRenderPassColorAttachment,
     ) -> &mut Self {
+        if is_external(&color_attachment.view)
+            || color_attachment
+                .resolve_target
+                .as_ref()
+                .is_some_and(is_external)
+        {
+            self.pass_builder.mark_side_effect();
+        }
+
         self.render_pass
             .add_color_attachment(Some(color_attachment));
         self
     }
 
+    pub fn set_depth_stencil_attachment(
+        &mut self,
+        depth_stencil_attachment: TransientRenderPassDepthStencilAttachment,
+    ) -> &mut Self {
+        if is_external(&depth_stencil_attachment.view) {
+            self.pass_builder.mark_side_effect();
+        }
+
+        self.render_pass
+            .set_depth_stencil_attachment(Some(depth_stencil_attachment));
+        self
+    }
+
     pub fn set_gpu_bind_group(
         &mut self,
         index: u32,
@@ -1,7 +1,54 @@
-use crate::frame_graph::{FrameGraph, ResourceHandle, TransientResource};
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+
+use wgpu::Buffer;
+
+use crate::frame_graph::{
+    FrameGraph, ResourceHandle, TransientBuffer, TransientBufferDescriptor, TransientResource,
+};
 
 pub trait ResourceMaterial {
     type ResourceType: TransientResource;
 
     fn imported(&self, frame_graph: &mut FrameGraph) -> ResourceHandle<Self::ResourceType>;
 }
+
+static NEXT_EXTERNAL_BUFFER_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A buffer owned outside of the frame graph. Every buffer gets a unique name, so it
+/// can be imported more than once per frame. Clones share the name, so they import the
+/// same resource.
+#[derive(Clone)]
+pub struct ExternalBuffer {
+    pub name: String,
+    pub buffer: Arc<TransientBuffer>,
+}
+
+impl ExternalBuffer {
+    pub fn new(label: &str, buffer: Buffer) -> Self {
+        Self {
+            name: format!(
+                "{label}_{}",
+                NEXT_EXTERNAL_BUFFER_ID.fetch_add(1, Ordering::Relaxed)
+            ),
+            buffer: Arc::new(TransientBuffer {
+                resource: buffer,
+                desc: TransientBufferDescriptor::External,
+            }),
+        }
+    }
+
+    pub fn size(&self) -> u64 {
+        self.buffer.resource.size()
+    }
+}
+
+impl ResourceMaterial for ExternalBuffer {
+    type ResourceType = TransientBuffer;
+
+    fn imported(&self, frame_graph: &mut FrameGraph) -> ResourceHandle<Self::ResourceType> {
+        frame_graph.import(&self.name, self.buffer.clone())
+    }
+}
@@ -1,3 +1,4 @@
+mod bind_group;
 mod graph;
 mod index;
 mod pass;
@@ -6,11 +7,13 @@ mod pipeline_container;
 mod resource_board;
 mod resource_node;
 mod resource_table;
+mod stats;
 mod texture_view;
 mod transient_resource;
 
 use draft_graphics::RenderDevice;
 
+pub use bind_group::*;
 pub use graph::*;
 pub use index::*;
 pub use pass::*;
@@ -19,6 +22,7 @@ pub use pipeline_container::*;
 pub use resource_board::*;
 pub use resource_node::*;
 pub use resource_table::*;
+pub use stats::*;
 pub use texture_view::*;
 pub use transient_resource::*;
 
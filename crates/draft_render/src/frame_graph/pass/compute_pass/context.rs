@@ -0,0 +1,52 @@
+use crate::frame_graph::{PassContext, TransientBindGroup};
+
+pub struct ComputePassContext<'a, 'b> {
+    compute_pass: wgpu::ComputePass<'static>,
+    pass_context: &'b mut PassContext<'a>,
+}
+
+impl<'a, 'b> ComputePassContext<'a, 'b> {
+    pub fn new(
+        compute_pass: wgpu::ComputePass<'static>,
+        pass_context: &'b mut PassContext<'a>,
+    ) -> Self {
+        ComputePassContext {
+            compute_pass,
+            pass_context,
+        }
+    }
+
+    pub fn set_gpu_bind_group(
+        &mut self,
+        index: u32,
+        bind_group: &wgpu::BindGroup,
+        offsets: &[u32],
+    ) {
+        self.pass_context.stats.bind_group_switches += 1;
+        self.compute_pass
+            .set_bind_group(index, Some(bind_group), offsets);
+    }
+
+    pub fn set_bind_group(&mut self, index: u32, bind_group: &TransientBindGroup, offsets: &[u32]) {
+        let bind_group = self.pass_context.create_bind_group(bind_group);
+
+        self.pass_context.stats.bind_group_switches += 1;
+        self.compute_pass
+            .set_bind_group(index, Some(&bind_group), offsets);
+    }
+
+    pub fn set_compute_pipeline(&mut self, pipeline_id: usize) {
+        let pipeline = self
+            .pass_context
+            .pipeline_container
+            .get_compute_pipeline(pipeline_id)
+            .expect("Compute pipeline must have.");
+
+        self.pass_context.stats.pipeline_switches += 1;
+        self.compute_pass.set_pipeline(pipeline);
+    }
+
+    pub fn dispatch_workgroups(&mut self, x: u32, y: u32, z: u32) {
+        self.compute_pass.dispatch_workgroups(x, y, z);
+    }
+}
@@ -0,0 +1,38 @@
+mod context;
+
+pub use context::*;
+
+use crate::frame_graph::{PassCommand, PassContext};
+
+pub trait ComputePassCommand: Sync + Send + 'static {
+    fn execute(&self, compute_pass_context: &mut ComputePassContext);
+}
+
+#[derive(Default)]
+pub struct ComputePass {
+    label: Option<String>,
+    pub(crate) commands: Vec<Box<dyn ComputePassCommand>>,
+}
+
+impl ComputePass {
+    pub fn set_pass_name(&mut self, name: &str) {
+        self.label = Some(name.to_string());
+    }
+}
+
+impl PassCommand for ComputePass {
+    fn execute(&self, context: &mut PassContext) {
+        let compute_pass = context
+            .command_encoder
+            .begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: self.label.as_deref(),
+                timestamp_writes: None,
+            })
+            .forget_lifetime();
+        let mut compute_pass_context = ComputePassContext::new(compute_pass, context);
+
+        for command in self.commands.iter() {
+            command.execute(&mut compute_pass_context);
+        }
+    }
+}
@@ -1,12 +1,14 @@
+mod compute_pass;
 mod render_pass;
 
 use crate::frame_graph::{
-    PipelineContainer, ResourceRef, ResourceTable, ResourceView, TransientResource,
-    TransientTextureView, TransientTextureViewDescriptor,
+    FrameGraphStats, PipelineContainer, ResourceRef, ResourceTable, ResourceView,
+    TransientResource, TransientTextureView, TransientTextureViewDescriptor,
 };
 use draft_graphics::RenderDevice;
 use wgpu::{CommandBuffer, CommandEncoder, CommandEncoderDescriptor, RenderPipeline};
 
+pub use compute_pass::*;
 pub use render_pass::*;
 
 pub struct PassContext<'a> {
@@ -14,6 +16,7 @@ pub struct PassContext<'a> {
     command_encoder: CommandEncoder,
     resource_table: &'a ResourceTable,
     pipeline_container: &'a PipelineContainer,
+    stats: &'a mut FrameGraphStats,
 }
 
 impl PassContext<'_> {
@@ -129,6 +132,7 @@ impl Pass {
         device: &RenderDevice,
         resource_table: &ResourceTable,
         pipeline_container: &PipelineContainer,
+        stats: &mut FrameGraphStats,
     ) {
         let command_encoder = device.create_command_encoder(&CommandEncoderDescriptor {
             label: self.label.as_deref(),
@@ -139,6 +143,7 @@ impl Pass {
             command_encoder,
             resource_table,
             pipeline_container,
+            stats,
         };
 
         for command in self.commands.iter() {
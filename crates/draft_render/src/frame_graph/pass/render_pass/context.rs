@@ -2,7 +2,9 @@ use core::ops::Range;
 
 use wgpu::IndexFormat;
 
-use crate::frame_graph::{GpuRenderPass, PassContext, ResourceRead, ResourceRef, TransientBuffer};
+use crate::frame_graph::{
+    GpuRenderPass, PassContext, ResourceRead, ResourceRef, TransientBindGroup, TransientBuffer,
+};
 
 pub struct RenderPassContext<'a, 'b> {
     render_pass: GpuRenderPass,
@@ -23,24 +25,87 @@ impl<'a, 'b> RenderPassContext<'a, 'b> {
             .set_scissor_rect(x, y, width, height);
     }
 
+    pub fn set_viewport(
+        &mut self,
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+        min_depth: f32,
+        max_depth: f32,
+    ) {
+        self.render_pass
+            .get_render_pass_mut()
+            .set_viewport(x, y, width, height, min_depth, max_depth);
+    }
+
     pub fn set_gpu_bind_group(
         &mut self,
         index: u32,
         bind_group: &wgpu::BindGroup,
         offsets: &[u32],
     ) {
+        self.pass_context.stats.bind_group_switches += 1;
         self.render_pass
             .get_render_pass_mut()
             .set_bind_group(index, Some(bind_group), offsets);
     }
 
+    pub fn set_bind_group(&mut self, index: u32, bind_group: &TransientBindGroup, offsets: &[u32]) {
+        let bind_group = self.pass_context.create_bind_group(bind_group);
+
+        self.pass_context.stats.bind_group_switches += 1;
+        self.render_pass
+            .get_render_pass_mut()
+            .set_bind_group(index, Some(&bind_group), offsets);
+    }
+
     pub fn draw_indexed(&mut self, indices: Range<u32>, base_vertex: i32, instances: Range<u32>) {
+        self.pass_context
+            .stats
+            .record_draw(indices.clone(), instances.clone());
         self.render_pass
             .get_render_pass_mut()
             .draw_indexed(indices, base_vertex, instances);
     }
 
+    pub fn draw_indirect(
+        &mut self,
+        indirect_buffer_ref: &ResourceRef<TransientBuffer, ResourceRead>,
+        indirect_offset: u64,
+    ) {
+        let buffer = self
+            .pass_context
+            .resource_table
+            .get_resource(indirect_buffer_ref);
+
+        self.pass_context.stats.record_indirect_draws(1);
+        self.render_pass
+            .get_render_pass_mut()
+            .draw_indirect(&buffer.resource, indirect_offset);
+    }
+
+    pub fn multi_draw_indexed_indirect(
+        &mut self,
+        indirect_buffer_ref: &ResourceRef<TransientBuffer, ResourceRead>,
+        indirect_offset: u64,
+        count: u32,
+    ) {
+        let buffer = self
+            .pass_context
+            .resource_table
+            .get_resource(indirect_buffer_ref);
+
+        self.pass_context.stats.record_indirect_draws(count);
+        self.render_pass
+            .get_render_pass_mut()
+            .multi_draw_indexed_indirect(&buffer.resource, indirect_offset, count);
+    }
+
     pub fn draw(&mut self, vertices: Range<u32>, instances: Range<u32>) {
+        self.pass_context
+            .stats
+            .record_draw(vertices.clone(), instances.clone());
         self.render_pass
             .get_render_pass_mut()
             .draw(vertices, instances);
@@ -53,6 +118,7 @@ impl<'a, 'b> RenderPassContext<'a, 'b> {
             .get_render_pipeline(pipeline_id)
             .expect("Render pipeline must have.");
 
+        self.pass_context.stats.pipeline_switches += 1;
         self.render_pass
             .get_render_pass_mut()
             .set_pipeline(pipeline);
@@ -80,9 +146,8 @@ impl<'a, 'b> RenderPassContext<'a, 'b> {
     ) {
         let buffer = self.pass_context.resource_table.get_resource(buffer_ref);
 
-        self.render_pass.get_render_pass_mut().set_index_buffer(
-            buffer.resource.slice(offset..(offset + size)),
-            index_format,
-        );
+        self.render_pass
+            .get_render_pass_mut()
+            .set_index_buffer(buffer.resource.slice(offset..(offset + size)), index_format);
     }
 }
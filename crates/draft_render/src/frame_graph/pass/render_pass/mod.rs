@@ -49,6 +49,13 @@ impl RenderPass {
     ) {
         self.desc.color_attachments.push(color_attachment);
     }
+
+    pub fn set_depth_stencil_attachment(
+        &mut self,
+        depth_stencil_attachment: Option<TransientRenderPassDepthStencilAttachment>,
+    ) {
+        self.desc.depth_stencil_attachment = depth_stencil_attachment;
+    }
 }
 
 impl PassCommand for RenderPass {
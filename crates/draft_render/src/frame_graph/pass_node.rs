@@ -8,6 +8,12 @@ pub struct PassNode {
     pub resource_request_array: Vec<Index<ResourceNode>>,
     pub resource_release_array: Vec<Index<ResourceNode>>,
     pub pass: Option<Pass>,
+    /// The pass has effects outside the graph, like drawing into a texture that is
+    /// not a resource of the graph. Such passes are never culled.
+    pub has_side_effect: bool,
+    /// Set by [`FrameGraph::compile`](crate::frame_graph::FrameGraph::compile) when no
+    /// kept pass uses the results of the pass.
+    pub culled: bool,
 }
 
 impl PassNode {
@@ -20,6 +26,8 @@ impl PassNode {
             resource_request_array: Default::default(),
             resource_release_array: Default::default(),
             pass: Default::default(),
+            has_side_effect: false,
+            culled: false,
         }
     }
 }
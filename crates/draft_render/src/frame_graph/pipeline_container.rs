@@ -35,6 +35,14 @@ impl PipelineContainer {
         self.0.push(pipeline);
     }
 
+    pub fn set(&mut self, id: usize, pipeline: Pipeline) {
+        if self.0.len() <= id {
+            self.0.resize_with(id + 1, || None);
+        }
+
+        self.0[id] = Some(pipeline);
+    }
+
     pub fn get_render_pipeline(&self, id: usize) -> Option<&RenderPipeline> {
         self.0.get(id).and_then(|pipeline| {
             pipeline
@@ -1,9 +1,9 @@
 use std::collections::HashMap;
 
 use crate::frame_graph::{
-    AnyTransientResource, ArcAnyTransientResource, Index, ResourceNode, ResourceRef,
-    ResourceRelease, ResourceRequese, ResourceView, TransientResource, TransientResourceCache,
-    TransientResourceCreator, VirtualResource,
+    AnyTransientResource, ArcAnyTransientResource, FrameGraphStats, Index, ResourceNode,
+    ResourceRef, ResourceRelease, ResourceRequese, ResourceView, TransientResource,
+    TransientResourceCache, TransientResourceCreator, VirtualResource,
 };
 
 #[derive(Default)]
@@ -27,6 +27,7 @@ impl ResourceTable {
         request: &ResourceRequese,
         device: &T,
         transient_resource_cache: &mut TransientResourceCache,
+        stats: &mut FrameGraphStats,
     ) {
         let index = request.index;
         let resource = match &request.resource {
@@ -38,9 +39,16 @@ impl ResourceTable {
                     AnyTransientResource::ImportedBuffer(resource.clone())
                 }
             },
-            VirtualResource::Setuped(desc) => transient_resource_cache
-                .get_resource(desc)
-                .unwrap_or_else(|| device.create_resource(desc)),
+            VirtualResource::Setuped(desc) => match transient_resource_cache.get_resource(desc) {
+                Some(resource) => {
+                    stats.transient_resources_reused += 1;
+                    resource
+                }
+                None => {
+                    stats.transient_resources_created += 1;
+                    device.create_resource(desc)
+                }
+            },
         };
 
         self.resources.insert(index, resource);
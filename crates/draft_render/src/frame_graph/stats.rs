@@ -0,0 +1,52 @@
+use core::ops::Range;
+
+/// Work recorded while executing a compiled frame graph.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FrameGraphStats {
+    /// Direct and indirect draws. A multi draw counts once per draw it issues.
+    pub draw_calls: usize,
+    /// Direct draws of more than one instance.
+    pub instanced_draws: usize,
+    /// Triangles of the direct draws, counted as triangle lists. The arguments of
+    /// indirect draws stay on the GPU and are not counted.
+    pub triangles: u64,
+    pub pipeline_switches: usize,
+    pub bind_group_switches: usize,
+    pub passes_executed: usize,
+    pub passes_culled: usize,
+    /// Transient buffers and textures created because the cache had none to reuse.
+    pub transient_resources_created: usize,
+    pub transient_resources_reused: usize,
+}
+
+impl FrameGraphStats {
+    pub(crate) fn record_draw(&mut self, vertices: Range<u32>, instances: Range<u32>) {
+        self.draw_calls += 1;
+        if instances.len() > 1 {
+            self.instanced_draws += 1;
+        }
+        self.triangles += (vertices.len() / 3) as u64 * instances.len() as u64;
+    }
+
+    pub(crate) fn record_indirect_draws(&mut self, count: u32) {
+        self.draw_calls += count as usize;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draws_count_triangles_of_every_instance() {
+        let mut stats = FrameGraphStats::default();
+
+        stats.record_draw(0..36, 0..1);
+        stats.record_draw(6..12, 4..8);
+        stats.record_indirect_draws(3);
+
+        assert_eq!(stats.draw_calls, 5);
+        assert_eq!(stats.instanced_draws, 1);
+        assert_eq!(stats.triangles, 12 + 2 * 4);
+    }
+}
@@ -19,7 +19,17 @@ pub struct TextureViewDescriptor {
 
 impl TextureViewDescriptor {
     pub fn get_desc(&self) -> wgpu::TextureViewDescriptor<'_> {
-        todo!()
+        wgpu::TextureViewDescriptor {
+            label: self.label.as_deref(),
+            format: self.format,
+            dimension: self.dimension,
+            usage: self.usage,
+            aspect: self.aspect,
+            base_mip_level: self.base_mip_level,
+            mip_level_count: self.mip_level_count,
+            base_array_layer: self.base_array_layer,
+            array_layer_count: self.array_layer_count,
+        }
     }
 }
 
@@ -45,7 +45,17 @@ pub enum TransientBufferDescriptor {
 
 impl TransientBufferDescriptor {
     pub fn get_desc(&self) -> wgpu::BufferDescriptor<'_> {
-        todo!()
+        match self {
+            TransientBufferDescriptor::External => {
+                unreachable!("External buffers are imported and never created.")
+            }
+            TransientBufferDescriptor::Manual(desc) => wgpu::BufferDescriptor {
+                label: desc.label.as_deref(),
+                size: desc.size,
+                usage: desc.usage,
+                mapped_at_creation: false,
+            },
+        }
     }
 }
 
@@ -30,4 +30,11 @@ impl TransientResourceCache {
             self.resources.insert(desc, vec![resource]);
         }
     }
+
+    /// Drops the cached textures, most of them are sized after the view they were
+    /// created for.
+    pub fn remove_textures(&mut self) {
+        self.resources
+            .retain(|desc, _| !matches!(desc, AnyTransientResourceDescriptor::Texture(_)));
+    }
 }
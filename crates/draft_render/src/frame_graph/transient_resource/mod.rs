@@ -6,8 +6,8 @@ pub use buffer::*;
 pub use cache::*;
 pub use texture::*;
 
-use std::sync::Arc;
 use core::fmt::Debug;
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub enum VirtualResource {
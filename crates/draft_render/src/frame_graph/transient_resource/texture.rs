@@ -42,7 +42,21 @@ pub enum TransientTextureDescriptor {
 
 impl TransientTextureDescriptor {
     pub fn get_desc(&self) -> wgpu::TextureDescriptor<'_> {
-        todo!()
+        match self {
+            TransientTextureDescriptor::External => {
+                unreachable!("External textures are imported and never created.")
+            }
+            TransientTextureDescriptor::Manual(desc) => wgpu::TextureDescriptor {
+                label: desc.label.as_deref(),
+                size: desc.size,
+                mip_level_count: desc.mip_level_count,
+                sample_count: desc.sample_count,
+                dimension: desc.dimension,
+                format: desc.format,
+                usage: desc.usage,
+                view_formats: &[],
+            },
+        }
     }
 }
 
@@ -0,0 +1,342 @@
+//! Golden-image regression tests. A scene is rendered without any window and the
+//! final frame is compared with a reference PNG.
+//!
+//! References are written instead of compared when [`GOLDEN_BLESS_ENV`] is set. On
+//! a mismatch the actual, expected and diff images are written to the output
+//! directory of the test.
+
+mod png;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use draft_graphics::{RenderServer, RenderServerSettings};
+use fyrox_resource::core::{futures::executor::block_on, log::Log, pool::Handle};
+use thiserror::Error;
+
+pub use png::*;
+
+use crate::{FrameworkError, IWorld, RenderContext, WorldRenderer, render_world::OffscreenTarget};
+
+/// Set to write the rendered frames as the new references.
+pub const GOLDEN_BLESS_ENV: &str = "DRAFT_BLESS";
+
+/// Set to fail instead of skipping the tests when no adapter is available, as on CI
+/// where a software adapter is installed.
+pub const GOLDEN_REQUIRE_ADAPTER_ENV: &str = "DRAFT_GOLDEN_REQUIRE_ADAPTER";
+
+#[derive(Debug, Error)]
+pub enum GoldenError {
+    #[error("No adapter is available to render golden images.")]
+    NoAdapter,
+    #[error("Failed to render golden image: {0}")]
+    Render(#[from] FrameworkError),
+    #[error("Failed to access golden image: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid PNG: {0}")]
+    InvalidPng(&'static str),
+    #[error("Missing reference image {0:?}, run with {GOLDEN_BLESS_ENV}=1 to create it.")]
+    MissingReference(PathBuf),
+    #[error(
+        "Golden image {name} differs from its reference in {mismatched_pixels} pixels, \
+        images written to {output_dir:?}."
+    )]
+    Mismatch {
+        name: String,
+        mismatched_pixels: usize,
+        output_dir: PathBuf,
+    },
+}
+
+/// Tightly packed RGBA8 pixels, rows from top to bottom.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+impl RgbaImage {
+    pub fn load(path: &Path) -> Result<Self, GoldenError> {
+        decode_png(&fs::read(path)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), GoldenError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        Ok(fs::write(path, encode_png(self))?)
+    }
+
+    /// Compares the images pixel by pixel. Pixels match when no channel differs by
+    /// more than `tolerance`. Images of different sizes don't match anywhere.
+    pub fn compare(&self, expected: &RgbaImage, tolerance: u8) -> ImageComparison {
+        if self.width != expected.width || self.height != expected.height {
+            return ImageComparison {
+                mismatched_pixels: self.width.max(expected.width) as usize
+                    * self.height.max(expected.height) as usize,
+                diff: None,
+            };
+        }
+
+        let mut mismatched_pixels = 0;
+        let mut diff = Vec::with_capacity(self.data.len());
+        for (actual, expected) in self.data.chunks(4).zip(expected.data.chunks(4)) {
+            let mismatch = actual
+                .iter()
+                .zip(expected)
+                .any(|(a, b)| a.abs_diff(*b) > tolerance);
+
+            if mismatch {
+                mismatched_pixels += 1;
+                diff.extend_from_slice(&[0xff, 0, 0, 0xff]);
+            } else {
+                // Matching pixels are dimmed so mismatches stand out.
+                diff.extend(actual[..3].iter().map(|channel| channel / 4));
+                diff.push(0xff);
+            }
+        }
+
+        ImageComparison {
+            mismatched_pixels,
+            diff: Some(RgbaImage {
+                width: self.width,
+                height: self.height,
+                data: diff,
+            }),
+        }
+    }
+}
+
+pub struct ImageComparison {
+    pub mismatched_pixels: usize,
+    /// Mismatching pixels in red over the dimmed actual image, `None` when the sizes
+    /// differ.
+    pub diff: Option<RgbaImage>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GoldenOutcome {
+    Matched,
+    /// The reference was written from the rendered frame.
+    Blessed,
+    /// No adapter is available and [`GOLDEN_REQUIRE_ADAPTER_ENV`] is not set.
+    Skipped,
+}
+
+struct GoldenWorld<F> {
+    scene: F,
+    target: Handle<OffscreenTarget>,
+}
+
+impl<F> IWorld for GoldenWorld<F>
+where
+    F: Fn(&mut RenderContext, Handle<OffscreenTarget>) + 'static,
+{
+    fn render(&self, context: &mut RenderContext) {
+        (self.scene)(context, self.target);
+    }
+}
+
+/// Renders a scene into an offscreen target on a software adapter and compares the
+/// last frame with `<reference_dir>/<name>.png`.
+pub struct GoldenTest {
+    name: String,
+    reference_dir: PathBuf,
+    output_dir: PathBuf,
+    width: u32,
+    height: u32,
+    /// Pipelines are compiled in the frame after they are first requested, so the
+    /// scene has to be rendered more than once.
+    frames: u32,
+    tolerance: u8,
+    bless: bool,
+}
+
+impl GoldenTest {
+    pub fn new(name: &str, reference_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            name: name.to_string(),
+            reference_dir: reference_dir.into(),
+            output_dir: std::env::temp_dir().join("draft_golden"),
+            width: 64,
+            height: 64,
+            frames: 3,
+            tolerance: 2,
+            bless: std::env::var_os(GOLDEN_BLESS_ENV).is_some(),
+        }
+    }
+
+    /// Where the actual, expected and diff images of a mismatch are written.
+    pub fn with_output_dir(mut self, output_dir: impl Into<PathBuf>) -> Self {
+        self.output_dir = output_dir.into();
+        self
+    }
+
+    pub fn with_size(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn with_frames(mut self, frames: u32) -> Self {
+        self.frames = frames;
+        self
+    }
+
+    /// Largest difference of a channel that still matches the reference.
+    pub fn with_tolerance(mut self, tolerance: u8) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn with_bless(mut self, bless: bool) -> Self {
+        self.bless = bless;
+        self
+    }
+
+    pub fn reference_path(&self) -> PathBuf {
+        self.reference_dir.join(format!("{}.png", self.name))
+    }
+
+    /// Renders the scene for the configured number of frames and checks the last
+    /// one. The scene adds its cameras with the given offscreen target.
+    pub fn run<F>(&self, scene: F) -> Result<GoldenOutcome, GoldenError>
+    where
+        F: Fn(&mut RenderContext, Handle<OffscreenTarget>) + 'static,
+    {
+        let settings = RenderServerSettings {
+            force_fallback_adapter: true,
+            ..Default::default()
+        };
+        let Some(render_server) = block_on(RenderServer::try_initialize(&settings)) else {
+            if std::env::var_os(GOLDEN_REQUIRE_ADAPTER_ENV).is_some() {
+                return Err(GoldenError::NoAdapter);
+            }
+
+            Log::warn(format!(
+                "Skipped golden image {}, no software adapter is available.",
+                self.name
+            ));
+            return Ok(GoldenOutcome::Skipped);
+        };
+
+        let mut renderer = WorldRenderer::headless(render_server);
+        renderer.initialize();
+
+        let target = renderer.create_offscreen_target(self.width, self.height);
+        let world = GoldenWorld { scene, target };
+        for _ in 0..self.frames.max(1) {
+            renderer.render(&world);
+        }
+
+        let data = renderer
+            .read_offscreen_target(target)
+            .expect("Golden image target was removed")?;
+
+        self.check(&RgbaImage {
+            width: self.width,
+            height: self.height,
+            data,
+        })
+    }
+
+    /// Compares a rendered frame with the reference, or writes it as the reference
+    /// when blessing.
+    pub fn check(&self, actual: &RgbaImage) -> Result<GoldenOutcome, GoldenError> {
+        let reference_path = self.reference_path();
+        if self.bless {
+            actual.save(&reference_path)?;
+            return Ok(GoldenOutcome::Blessed);
+        }
+
+        if !reference_path.exists() {
+            return Err(GoldenError::MissingReference(reference_path));
+        }
+
+        let expected = RgbaImage::load(&reference_path)?;
+        let comparison = actual.compare(&expected, self.tolerance);
+        if comparison.mismatched_pixels == 0 {
+            return Ok(GoldenOutcome::Matched);
+        }
+
+        let output = |suffix: &str| self.output_dir.join(format!("{}_{suffix}.png", self.name));
+        actual.save(&output("actual"))?;
+        expected.save(&output("expected"))?;
+        if let Some(diff) = comparison.diff {
+            diff.save(&output("diff"))?;
+        }
+
+        Err(GoldenError::Mismatch {
+            name: self.name.clone(),
+            mismatched_pixels: comparison.mismatched_pixels,
+            output_dir: self.output_dir.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(pixels: &[[u8; 4]]) -> RgbaImage {
+        RgbaImage {
+            width: pixels.len() as u32,
+            height: 1,
+            data: pixels.concat(),
+        }
+    }
+
+    #[test]
+    fn pixels_within_tolerance_match() {
+        let actual = image(&[[10, 20, 30, 255], [100, 100, 100, 255]]);
+        let expected = image(&[[12, 18, 30, 255], [100, 100, 104, 255]]);
+
+        let comparison = actual.compare(&expected, 2);
+
+        assert_eq!(comparison.mismatched_pixels, 1);
+        assert_eq!(
+            comparison.diff.unwrap().data,
+            [2, 5, 7, 255, 255, 0, 0, 255]
+        );
+    }
+
+    #[test]
+    fn mismatches_write_actual_expected_and_diff_images() {
+        let directory = std::env::temp_dir().join(format!("draft_golden_{}", std::process::id()));
+        let test = GoldenTest::new("check", directory.join("references"))
+            .with_output_dir(directory.join("output"))
+            .with_bless(false);
+
+        let reference = image(&[[0, 0, 0, 255]]);
+        assert!(matches!(
+            test.check(&reference),
+            Err(GoldenError::MissingReference(_))
+        ));
+
+        let blessed = GoldenTest::new("check", directory.join("references")).with_bless(true);
+        assert_eq!(blessed.check(&reference).unwrap(), GoldenOutcome::Blessed);
+        assert_eq!(test.check(&reference).unwrap(), GoldenOutcome::Matched);
+
+        let result = test.check(&image(&[[255, 0, 0, 255]]));
+        assert!(matches!(
+            result,
+            Err(GoldenError::Mismatch {
+                mismatched_pixels: 1,
+                ..
+            })
+        ));
+        for suffix in ["actual", "expected", "diff"] {
+            assert!(
+                directory
+                    .join(format!("output/check_{suffix}.png"))
+                    .exists()
+            );
+        }
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,526 @@
+//! A minimal PNG codec for the golden images. Images are written as uncompressed
+//! 8-bit RGBA, any non-interlaced 8-bit RGB or RGBA image can be read.
+
+use super::{GoldenError, RgbaImage};
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// Largest payload of a stored deflate block.
+const MAX_STORED_BLOCK: usize = u16::MAX as usize;
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+fn invalid(reason: &'static str) -> GoldenError {
+    GoldenError::InvalidPng(reason)
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                0xedb8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in bytes {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Wraps the data in a zlib stream of stored deflate blocks.
+fn zlib_store(data: &[u8]) -> Vec<u8> {
+    let mut stream = vec![0x78, 0x01];
+
+    let mut blocks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        stream.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let length = block.len() as u16;
+        stream.push(blocks.peek().is_none() as u8);
+        stream.extend_from_slice(&length.to_le_bytes());
+        stream.extend_from_slice(&(!length).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+    stream
+}
+
+pub fn encode_png(image: &RgbaImage) -> Vec<u8> {
+    let row_size = image.width as usize * 4;
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&image.width.to_be_bytes());
+    header.extend_from_slice(&image.height.to_be_bytes());
+    // 8 bits per channel, RGBA, deflate, adaptive filtering, no interlacing.
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut scanlines = Vec::with_capacity((row_size + 1) * image.height as usize);
+    for row in image.data.chunks(row_size.max(1)) {
+        scanlines.push(0);
+        scanlines.extend_from_slice(row);
+    }
+
+    let mut png = SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(&mut png, b"IDAT", &zlib_store(&scanlines));
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+pub fn decode_png(png: &[u8]) -> Result<RgbaImage, GoldenError> {
+    let mut chunks = png
+        .strip_prefix(&SIGNATURE)
+        .ok_or_else(|| invalid("missing signature"))?;
+
+    let mut header = None;
+    let mut compressed = Vec::new();
+    while chunks.len() >= 12 {
+        let length = u32::from_be_bytes(chunks[..4].try_into().unwrap()) as usize;
+        if chunks.len() < length + 12 {
+            return Err(invalid("truncated chunk"));
+        }
+
+        let kind = &chunks[4..8];
+        let data = &chunks[8..8 + length];
+        let crc = u32::from_be_bytes(chunks[8 + length..12 + length].try_into().unwrap());
+        if crc32(&chunks[4..8 + length]) != crc {
+            return Err(invalid("chunk checksum mismatch"));
+        }
+
+        match kind {
+            b"IHDR" => header = Some(data),
+            b"IDAT" => compressed.extend_from_slice(data),
+            b"IEND" => break,
+            _ => {}
+        }
+
+        chunks = &chunks[12 + length..];
+    }
+
+    let header = header.ok_or_else(|| invalid("missing header"))?;
+    if header.len() != 13 {
+        return Err(invalid("malformed header"));
+    }
+    let width = u32::from_be_bytes(header[..4].try_into().unwrap());
+    let height = u32::from_be_bytes(header[4..8].try_into().unwrap());
+    let channels = match (header[8], header[9]) {
+        (8, 2) => 3,
+        (8, 6) => 4,
+        _ => return Err(invalid("only 8-bit RGB and RGBA images are supported")),
+    };
+    if header[12] != 0 {
+        return Err(invalid("interlaced images are not supported"));
+    }
+
+    let scanlines = zlib_inflate(&compressed)?;
+    let pixels = unfilter(
+        &scanlines,
+        width as usize * channels,
+        height as usize,
+        channels,
+    )?;
+
+    let data = if channels == 4 {
+        pixels
+    } else {
+        pixels
+            .chunks(3)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 0xff])
+            .collect()
+    };
+
+    Ok(RgbaImage {
+        width,
+        height,
+        data,
+    })
+}
+
+/// Reverses the per row filters of the scanlines.
+fn unfilter(
+    scanlines: &[u8],
+    row_size: usize,
+    height: usize,
+    pixel_size: usize,
+) -> Result<Vec<u8>, GoldenError> {
+    if scanlines.len() < (row_size + 1) * height {
+        return Err(invalid("truncated image data"));
+    }
+
+    let mut pixels = vec![0u8; row_size * height];
+    for y in 0..height {
+        let line = &scanlines[y * (row_size + 1)..(y + 1) * (row_size + 1)];
+        let (previous, current) = pixels.split_at_mut(y * row_size);
+        let previous = (y > 0).then(|| &previous[(y - 1) * row_size..]);
+        let row = &mut current[..row_size];
+
+        for x in 0..row_size {
+            let a = if x >= pixel_size {
+                row[x - pixel_size]
+            } else {
+                0
+            };
+            let b = previous.map_or(0, |previous| previous[x]);
+            let c = match previous {
+                Some(previous) if x >= pixel_size => previous[x - pixel_size],
+                _ => 0,
+            };
+
+            let predictor = match line[0] {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((a as u16 + b as u16) / 2) as u8,
+                4 => paeth(a, b, c),
+                _ => return Err(invalid("unknown filter type")),
+            };
+            row[x] = line[x + 1].wrapping_add(predictor);
+        }
+    }
+
+    Ok(pixels)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    /// Position in bits.
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn bit(&mut self) -> Result<u32, GoldenError> {
+        let byte = self
+            .data
+            .get(self.position / 8)
+            .ok_or_else(|| invalid("truncated deflate stream"))?;
+        let bit = (byte >> (self.position % 8)) & 1;
+        self.position += 1;
+
+        Ok(bit as u32)
+    }
+
+    /// Reads `count` bits, least significant bit first.
+    fn bits(&mut self, count: u8) -> Result<u32, GoldenError> {
+        let mut value = 0;
+        for i in 0..count {
+            value |= self.bit()? << i;
+        }
+        Ok(value)
+    }
+
+    fn align_to_byte(&mut self) {
+        self.position = self.position.div_ceil(8) * 8;
+    }
+}
+
+/// Canonical Huffman code of a deflate block.
+struct Huffman {
+    /// Number of codes of each length.
+    counts: [u16; 16],
+    /// Symbols ordered by code.
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; 16];
+        for length in lengths {
+            counts[*length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut offsets = [0u16; 16];
+        for length in 1..16 {
+            offsets[length] = offsets[length - 1] + counts[length - 1];
+        }
+
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, length) in lengths.iter().enumerate() {
+            if *length != 0 {
+                symbols[offsets[*length as usize] as usize] = symbol as u16;
+                offsets[*length as usize] += 1;
+            }
+        }
+
+        Self { counts, symbols }
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, GoldenError> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for length in 1..16 {
+            code |= reader.bit()? as i32;
+            let count = self.counts[length] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(invalid("invalid Huffman code"))
+    }
+}
+
+fn fixed_codes() -> (Huffman, Huffman) {
+    let mut lengths = [8u8; 288];
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+
+    (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+}
+
+fn dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman), GoldenError> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+
+    let mut code_lengths = [0u8; 19];
+    for index in CODE_LENGTH_ORDER.iter().take(code_length_count) {
+        code_lengths[*index] = reader.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths);
+
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let (length, repeat) = match code_length_code.decode(reader)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => (
+                *lengths
+                    .last()
+                    .ok_or_else(|| invalid("repeated code length without a previous one"))?,
+                3 + reader.bits(2)?,
+            ),
+            17 => (0, 3 + reader.bits(3)?),
+            18 => (0, 11 + reader.bits(7)?),
+            _ => return Err(invalid("invalid code length symbol")),
+        };
+        lengths.extend(std::iter::repeat_n(length, repeat as usize));
+    }
+    if lengths.len() != literal_count + distance_count {
+        return Err(invalid("too many code lengths"));
+    }
+
+    Ok((
+        Huffman::new(&lengths[..literal_count]),
+        Huffman::new(&lengths[literal_count..]),
+    ))
+}
+
+fn inflate_block(
+    reader: &mut BitReader,
+    output: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), GoldenError> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+        match symbol {
+            0..=255 => output.push(symbol as u8),
+            256 => return Ok(()),
+            257..=285 => {
+                let index = symbol - 257;
+                let length =
+                    LENGTH_BASE[index] as usize + reader.bits(LENGTH_EXTRA[index])? as usize;
+
+                let index = distances.decode(reader)? as usize;
+                if index >= DISTANCE_BASE.len() {
+                    return Err(invalid("invalid distance symbol"));
+                }
+                let distance =
+                    DISTANCE_BASE[index] as usize + reader.bits(DISTANCE_EXTRA[index])? as usize;
+                if distance > output.len() {
+                    return Err(invalid("distance before the start of the data"));
+                }
+
+                let start = output.len() - distance;
+                for i in 0..length {
+                    output.push(output[start + i]);
+                }
+            }
+            _ => return Err(invalid("invalid literal symbol")),
+        }
+    }
+}
+
+fn zlib_inflate(stream: &[u8]) -> Result<Vec<u8>, GoldenError> {
+    if stream.len() < 6 {
+        return Err(invalid("truncated zlib stream"));
+    }
+    let (method, flags) = (stream[0], stream[1]);
+    if method & 0x0f != 8 || !(u16::from_be_bytes([method, flags])).is_multiple_of(31) {
+        return Err(invalid("invalid zlib header"));
+    }
+    if flags & 0x20 != 0 {
+        return Err(invalid("zlib preset dictionaries are not supported"));
+    }
+
+    let mut reader = BitReader {
+        data: &stream[2..],
+        position: 0,
+    };
+    let mut output = Vec::new();
+    loop {
+        let last = reader.bit()? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align_to_byte();
+                let start = reader.position / 8;
+                let header = reader
+                    .data
+                    .get(start..start + 4)
+                    .ok_or_else(|| invalid("truncated stored block"))?;
+                let length = u16::from_le_bytes([header[0], header[1]]);
+                if length != !u16::from_le_bytes([header[2], header[3]]) {
+                    return Err(invalid("stored block length mismatch"));
+                }
+
+                let data = reader
+                    .data
+                    .get(start + 4..start + 4 + length as usize)
+                    .ok_or_else(|| invalid("truncated stored block"))?;
+                output.extend_from_slice(data);
+                reader.position = (start + 4 + length as usize) * 8;
+            }
+            1 => {
+                let (literals, distances) = fixed_codes();
+                inflate_block(&mut reader, &mut output, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut reader)?;
+                inflate_block(&mut reader, &mut output, &literals, &distances)?;
+            }
+            _ => return Err(invalid("invalid deflate block type")),
+        }
+
+        if last {
+            break;
+        }
+    }
+
+    reader.align_to_byte();
+    let checksum = reader
+        .data
+        .get(reader.position / 8..reader.position / 8 + 4)
+        .ok_or_else(|| invalid("missing zlib checksum"))?;
+    if u32::from_be_bytes(checksum.try_into().unwrap()) != adler32(&output) {
+        return Err(invalid("zlib checksum mismatch"));
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoded_images_decode_to_the_same_pixels() {
+        let image = RgbaImage {
+            width: 3,
+            height: 2,
+            data: (0..24).collect(),
+        };
+
+        let decoded = decode_png(&encode_png(&image)).unwrap();
+
+        assert_eq!(decoded, image);
+    }
+
+    #[test]
+    fn fixed_huffman_streams_inflate() {
+        // zlib.compress(b"golden golden golden image", 9)
+        let stream = [
+            0x78, 0xda, 0x4b, 0xcf, 0xcf, 0x49, 0x49, 0xcd, 0x53, 0x48, 0x47, 0xa1, 0x32, 0x73,
+            0x13, 0xd3, 0x53, 0x01, 0x85, 0x7b, 0x09, 0xcf,
+        ];
+
+        assert_eq!(
+            zlib_inflate(&stream).unwrap(),
+            b"golden golden golden image"
+        );
+    }
+
+    #[test]
+    fn dynamic_huffman_streams_inflate() {
+        let data = (0..200)
+            .map(|i: u32| ((i * i * 7 + i / 3) % 16 + 97) as u8)
+            .collect::<Vec<_>>();
+        // zlib.compress(data, 9)
+        let stream = [
+            0x78, 0xda, 0xcd, 0xca, 0xb9, 0x01, 0x00, 0x20, 0x08, 0x04, 0xb0, 0x59, 0x8f, 0x57,
+            0x44, 0x84, 0xfd, 0x2b, 0xd7, 0x30, 0x75, 0xb0, 0x0a, 0x84, 0xde, 0x9c, 0xc3, 0x2a,
+            0x28, 0x2b, 0x36, 0x77, 0xe9, 0x18, 0x8d, 0x1d, 0x4e, 0xc9, 0x2b, 0xeb, 0x84, 0x5e,
+            0xcd, 0xdb, 0x7d, 0x1c, 0x9f, 0xfe, 0x07, 0xf1, 0x7f, 0x51, 0x74,
+        ];
+
+        assert_eq!(zlib_inflate(&stream).unwrap(), data);
+    }
+
+    #[test]
+    fn filters_predict_from_neighbours() {
+        // Rows of two single channel pixels, filtered with Sub, Up, Average and Paeth.
+        let scanlines = [1, 10, 20, 2, 5, 15, 3, 3, 1, 4, 1, 2];
+
+        assert_eq!(
+            unfilter(&scanlines, 2, 4, 1).unwrap(),
+            [10, 30, 15, 45, 10, 28, 11, 30]
+        );
+    }
+}
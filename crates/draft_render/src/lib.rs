@@ -1,25 +1,82 @@
+pub mod camera;
+pub mod core_2d;
+pub mod core_3d;
 pub mod error;
 pub mod frame_graph;
+pub mod golden;
+pub mod pipelined_renderer;
+pub mod post_process;
+pub mod render_phase;
 pub mod render_pipeline;
 pub mod render_world;
+pub mod stats;
 
-use draft_graphics::RenderServer;
+use std::time::Instant;
+
+use draft_graphics::{Color, RenderServer};
 use draft_window::SystemWindowManager;
+use fyrox_resource::core::{log::Log, pool::Handle};
 
 use crate::{
-    render_pipeline::{RenderPipeline, RenderPipelineContainer, RenderPipelineRunContext},
-    render_world::RenderWorld,
+    camera::Camera,
+    core_2d::{Sprite, SpriteNode},
+    core_3d::{
+        DepthPrepassNode, GpuCullingNode, Light, OpaquePassNode, ShadowPassNode, ShadowSettings,
+        TransparentPassNode,
+    },
+    frame_graph::{FrameGraph, FrameGraphContext, FrameGraphStats, TransientResourceCache},
+    post_process::PostProcessNode,
+    render_phase::DrawItem,
+    render_pipeline::{
+        ClearNode, RenderPipeline, RenderPipelineContainer, RenderPipelineRunContext,
+    },
+    render_world::{ExtractedFrame, OffscreenTarget, RenderWorld},
+    stats::{RenderStats, RenderStatsHistory},
 };
 
-pub const CORE_2D: &'static str = "core_2d";
+pub const CORE_2D: &str = "core_2d";
+pub const CORE_3D: &str = "core_3d";
 pub use error::FrameworkError;
 
 pub trait IWorld: 'static {
     fn render(&self, context: &mut RenderContext);
 }
 
+/// Collects what a world submits for a frame, see [`ExtractedFrame::extract`].
 pub struct RenderContext<'a> {
-    pub render_world: &'a mut RenderWorld,
+    pub frame: &'a mut ExtractedFrame,
+}
+
+impl RenderContext<'_> {
+    /// Submits a draw item for the current frame.
+    pub fn draw(&mut self, draw_item: DrawItem) {
+        self.frame.draw(draw_item);
+    }
+
+    /// Submits a sprite for the current frame.
+    pub fn draw_sprite(&mut self, sprite: Sprite) {
+        self.frame.draw_sprite(sprite);
+    }
+
+    /// Adds a camera for the current frame. Every camera renders the submitted draw
+    /// items into its own target.
+    pub fn add_camera(&mut self, camera: Camera) {
+        self.frame.add_camera(camera);
+    }
+
+    /// Adds a light for the current frame. Lights shade the meshes of every view
+    /// rendered with [`CORE_3D`].
+    pub fn add_light(&mut self, light: Light) {
+        self.frame.add_light(light);
+    }
+
+    pub fn set_ambient_light(&mut self, ambient_light: Color) {
+        self.frame.set_ambient_light(ambient_light);
+    }
+
+    pub fn set_shadow_settings(&mut self, shadow_settings: ShadowSettings) {
+        self.frame.set_shadow_settings(shadow_settings);
+    }
 }
 
 pub struct WorldRenderer {
@@ -27,6 +84,10 @@ pub struct WorldRenderer {
     pub system_window_manager: SystemWindowManager,
     pub render_pipeline_container: RenderPipelineContainer,
     pub render_world: RenderWorld,
+    pub frame_graph: FrameGraph,
+    pub transient_resource_cache: TransientResourceCache,
+    stats_history: RenderStatsHistory,
+    last_frame_time: Option<Instant>,
 }
 
 impl WorldRenderer {
@@ -36,31 +97,173 @@ impl WorldRenderer {
             system_window_manager,
             render_pipeline_container: RenderPipelineContainer::default(),
             render_world: RenderWorld::empty(),
+            frame_graph: FrameGraph::default(),
+            transient_resource_cache: TransientResourceCache::default(),
+            stats_history: RenderStatsHistory::default(),
+            last_frame_time: None,
         }
     }
 
+    /// A renderer without any window. Cameras render into offscreen targets, which
+    /// are read back with [`WorldRenderer::read_offscreen_target`].
+    pub fn headless(render_server: RenderServer) -> Self {
+        Self::new(render_server, SystemWindowManager::default())
+    }
+
+    /// Creates a texture owned by the render world that cameras can target with
+    /// [`RenderTarget::Offscreen`](camera::RenderTarget::Offscreen).
+    pub fn create_offscreen_target(&mut self, width: u32, height: u32) -> Handle<OffscreenTarget> {
+        self.render_world
+            .create_offscreen_target(width, height, &self.render_server.device)
+    }
+
+    /// Reads the last frame rendered into an offscreen target back as tightly packed
+    /// RGBA8 rows, waiting for the GPU to finish it.
+    pub fn read_offscreen_target(
+        &self,
+        handle: Handle<OffscreenTarget>,
+    ) -> Option<Result<Vec<u8>, FrameworkError>> {
+        self.render_world
+            .read_offscreen_target(handle, &self.render_server)
+    }
+
+    /// Statistics of the last rendered frame.
+    pub fn stats(&self) -> Option<&RenderStats> {
+        self.stats_history.latest()
+    }
+
+    /// Statistics of the last rendered frames, for diagnostics overlays and logs.
+    pub fn stats_history(&self) -> &RenderStatsHistory {
+        &self.stats_history
+    }
+
+    pub fn stats_history_mut(&mut self) -> &mut RenderStatsHistory {
+        &mut self.stats_history
+    }
+
     pub fn initialize(&mut self) {
-        self.render_pipeline_container
-            .insert(CORE_2D, RenderPipeline::default());
+        let mut pipeline = RenderPipeline::default();
+        pipeline
+            .add_node("clear", ClearNode)
+            .add_node("sprite", SpriteNode)
+            .add_node("post_process", PostProcessNode);
+
+        self.render_pipeline_container.insert(CORE_2D, pipeline);
+
+        let mut pipeline = RenderPipeline::default();
+        pipeline
+            .add_node("clear", ClearNode)
+            .add_node("shadow", ShadowPassNode)
+            .add_node("gpu_culling", GpuCullingNode)
+            .add_node("depth_prepass", DepthPrepassNode)
+            .add_node("opaque", OpaquePassNode)
+            .add_node("transparent", TransparentPassNode)
+            .add_node("post_process", PostProcessNode);
+
+        self.render_pipeline_container.insert(CORE_3D, pipeline);
     }
 
+    /// Extracts and renders a frame of the world on the calling thread.
     pub fn render<W: IWorld>(&mut self, world: &W) {
+        let frame = ExtractedFrame::extract(world);
+
+        self.prepare_windows();
+        self.render_frame(frame);
+    }
+
+    /// Follows the size and preferences of the system windows and acquires the
+    /// textures of the next frame. Surfaces are created here, which some platforms
+    /// only allow on the main thread.
+    pub fn prepare_windows(&mut self) {
         self.render_world
             .prepare_windows(&self.render_server, &self.system_window_manager);
 
-        let mut context = RenderContext {
-            render_world: &mut self.render_world,
-        };
+        // Textures sized after the old or closed windows would never be reused.
+        if !self.render_world.window_events().is_empty() {
+            self.transient_resource_cache.remove_textures();
+        }
+    }
+
+    /// Renders an extracted frame into the targets of its cameras and presents the
+    /// windows prepared by [`WorldRenderer::prepare_windows`].
+    pub fn render_frame(&mut self, frame: ExtractedFrame) {
+        let now = Instant::now();
+        let dt = self
+            .last_frame_time
+            .map(|last_frame_time| now.duration_since(last_frame_time).as_secs_f32())
+            .unwrap_or_default();
+        self.last_frame_time = Some(now);
+
+        self.render_world.update(dt);
+        self.render_world.recycle_dynamic_buffers();
+
+        self.render_world
+            .process_pipeline_queue(&self.render_server.device);
+
+        self.render_world.insert_frame(frame);
+
+        self.render_world.prepare_views(&self.render_server);
 
-        world.render(&mut context);
+        // Views are rendered in camera priority order, each by its own pipeline.
+        for view_index in 0..self.render_world.views().len() {
+            let name = &self.render_world.views()[view_index].render_pipeline;
+            let Some(pipeline) = self.render_pipeline_container.get(name) else {
+                Log::err(format!("Render pipeline {name} does not exist."));
+                continue;
+            };
+            let name = name.clone();
 
-        let mut context = RenderPipelineRunContext {};
+            let mut context = RenderPipelineRunContext {
+                frame_graph: &mut self.frame_graph,
+                render_world: &mut self.render_world,
+                render_server: &self.render_server,
+                view_index,
+            };
 
-        if let Some(pipeline) = self.render_pipeline_container.get(CORE_2D) {
-            pipeline.run(&mut context);
+            if let Err(e) = pipeline.run(&mut context) {
+                Log::err(format!("Failed to run render pipeline {name}: {e}"));
+            }
+        }
+
+        let frame_graph_stats = self.execute_frame_graph();
+
+        self.render_world.clear_draws();
+
+        self.render_world.clear_windows();
+
+        self.stats_history.push(RenderStats {
+            frame_time: dt,
+            frame_graph: frame_graph_stats,
+            visibility: self.render_world.visibility_stats(),
+            caches: self.render_world.cache_stats(),
+            bytes_uploaded: self.render_server.take_uploaded_bytes(),
+        });
+    }
+
+    fn execute_frame_graph(&mut self) -> FrameGraphStats {
+        self.frame_graph.compile();
+
+        self.render_world
+            .write_dynamic_buffers(&self.render_server.queue);
+
+        let mut context = FrameGraphContext::new(
+            self.render_world.pipeline_container(),
+            &self.render_server.device,
+            &mut self.transient_resource_cache,
+        );
+
+        self.frame_graph.execute(&mut context);
+
+        let stats = context.stats;
+        let command_buffers = context.finish();
+
+        if !command_buffers.is_empty() {
+            self.render_server.queue.submit(command_buffers);
         }
 
         self.render_world
-            .clear_windows(&self.render_server, &self.system_window_manager);
+            .retire_dynamic_buffers(&self.render_server.queue);
+
+        stats
     }
 }
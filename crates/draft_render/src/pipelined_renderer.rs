@@ -0,0 +1,228 @@
+//! Pipelined rendering. The main thread extracts a frame of the world while the
+//! render thread builds, records and submits the previous one.
+//!
+//! The renderer is owned by one thread at a time. It is sent to the render thread
+//! together with an extracted frame and sent back once the frame is submitted, so
+//! the main thread waits for it before handing over the next frame or touching the
+//! renderer in any other way. At most one frame is in flight.
+
+use std::{
+    sync::mpsc::{Receiver, SyncSender, sync_channel},
+    thread::{self, JoinHandle},
+};
+
+use fyrox_resource::core::log::Log;
+
+use crate::{IWorld, WorldRenderer, render_world::ExtractedFrame};
+
+/// Where frames are rendered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenderMode {
+    /// Frames are rendered on a render thread while the next one is simulated.
+    #[default]
+    Pipelined,
+    /// Frames are rendered on the calling thread before [`PipelinedRenderer::render`]
+    /// returns, which is easier to debug and profile.
+    SingleThreaded,
+}
+
+struct RenderThread {
+    frame_sender: Option<SyncSender<(WorldRenderer, ExtractedFrame)>>,
+    renderer_receiver: Receiver<WorldRenderer>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl RenderThread {
+    fn spawn() -> Self {
+        let (frame_sender, frame_receiver) = sync_channel::<(WorldRenderer, ExtractedFrame)>(1);
+        let (renderer_sender, renderer_receiver) = sync_channel(1);
+
+        let handle = thread::Builder::new()
+            .name("render".to_string())
+            .spawn(move || {
+                while let Ok((mut renderer, frame)) = frame_receiver.recv() {
+                    renderer.render_frame(frame);
+
+                    if renderer_sender.send(renderer).is_err() {
+                        break;
+                    }
+                }
+            })
+            .expect("Failed to spawn the render thread");
+
+        Self {
+            frame_sender: Some(frame_sender),
+            renderer_receiver,
+            handle: Some(handle),
+        }
+    }
+
+    /// Takes the panic of the render thread over to the calling thread.
+    fn resume_panic(&mut self) -> ! {
+        self.frame_sender = None;
+
+        match self.handle.take().map(JoinHandle::join) {
+            Some(Err(payload)) => std::panic::resume_unwind(payload),
+            _ => panic!("The render thread stopped without returning the renderer"),
+        }
+    }
+}
+
+impl Drop for RenderThread {
+    fn drop(&mut self) {
+        // Closing the channel ends the loop of the thread after the frame in flight.
+        self.frame_sender = None;
+
+        if let Some(handle) = self.handle.take()
+            && handle.join().is_err()
+        {
+            Log::err("The render thread panicked.");
+        }
+    }
+}
+
+/// Renders the frames of a world either on a render thread or on the calling
+/// thread, see [`RenderMode`].
+pub struct PipelinedRenderer {
+    mode: RenderMode,
+    /// `None` while a frame is rendered on the render thread.
+    renderer: Option<WorldRenderer>,
+    render_thread: Option<RenderThread>,
+}
+
+impl PipelinedRenderer {
+    pub fn new(renderer: WorldRenderer, mode: RenderMode) -> Self {
+        Self {
+            mode,
+            renderer: Some(renderer),
+            render_thread: match mode {
+                RenderMode::Pipelined => Some(RenderThread::spawn()),
+                RenderMode::SingleThreaded => None,
+            },
+        }
+    }
+
+    pub fn mode(&self) -> RenderMode {
+        self.mode
+    }
+
+    /// Whether a frame is being rendered on the render thread.
+    pub fn is_frame_in_flight(&self) -> bool {
+        self.renderer.is_none()
+    }
+
+    /// Extracts a frame of the world on the calling thread and renders it. In
+    /// pipelined mode this only waits for the previous frame and returns as soon as
+    /// the new one is handed to the render thread.
+    pub fn render<W: IWorld>(&mut self, world: &W) {
+        let frame = ExtractedFrame::extract(world);
+
+        self.renderer_mut().prepare_windows();
+
+        match &self.render_thread {
+            Some(render_thread) => {
+                let renderer = self.renderer.take().expect("Renderer is returned");
+                let frame_sender = render_thread
+                    .frame_sender
+                    .as_ref()
+                    .expect("Render thread is running");
+
+                if let Err(error) = frame_sender.send((renderer, frame)) {
+                    // The thread is gone, keep rendering on this one.
+                    let (mut renderer, frame) = error.0;
+                    renderer.render_frame(frame);
+                    self.renderer = Some(renderer);
+                }
+            }
+            None => self.renderer_mut().render_frame(frame),
+        }
+    }
+
+    /// Waits until the frame in flight is submitted.
+    pub fn finish(&mut self) {
+        if self.renderer.is_some() {
+            return;
+        }
+
+        if let Some(render_thread) = &mut self.render_thread {
+            match render_thread.renderer_receiver.recv() {
+                Ok(renderer) => self.renderer = Some(renderer),
+                Err(_) => render_thread.resume_panic(),
+            }
+        }
+    }
+
+    /// The renderer, after waiting for the frame in flight.
+    pub fn renderer_mut(&mut self) -> &mut WorldRenderer {
+        self.finish();
+
+        self.renderer.as_mut().expect("Renderer is returned")
+    }
+
+    /// Waits for the frame in flight and stops the render thread.
+    pub fn into_inner(mut self) -> WorldRenderer {
+        self.finish();
+        self.render_thread = None;
+
+        self.renderer.take().expect("Renderer is returned")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use draft_graphics::{Color, RenderServer, RenderServerSettings};
+    use fyrox_resource::core::{futures::executor::block_on, pool::Handle};
+
+    use super::*;
+    use crate::{RenderContext, camera::Camera, render_world::OffscreenTarget};
+
+    struct ClearWorld {
+        target: Handle<OffscreenTarget>,
+    }
+
+    impl IWorld for ClearWorld {
+        fn render(&self, context: &mut RenderContext) {
+            context.add_camera(Camera::new_2d(self.target).with_clear_color(Some(Color {
+                r: 1.0,
+                g: 0.0,
+                b: 0.0,
+                a: 1.0,
+            })));
+        }
+    }
+
+    fn render_frames(mode: RenderMode) -> Option<Vec<u8>> {
+        let settings = RenderServerSettings {
+            force_fallback_adapter: true,
+            ..Default::default()
+        };
+        let render_server = block_on(RenderServer::try_initialize(&settings))?;
+
+        let mut renderer = WorldRenderer::headless(render_server);
+        renderer.initialize();
+        let target = renderer.create_offscreen_target(4, 4);
+
+        let mut renderer = PipelinedRenderer::new(renderer, mode);
+        let world = ClearWorld { target };
+        for _ in 0..3 {
+            renderer.render(&world);
+        }
+        assert_eq!(renderer.is_frame_in_flight(), mode == RenderMode::Pipelined);
+
+        let renderer = renderer.into_inner();
+        assert_eq!(renderer.stats_history().len(), 3);
+
+        Some(renderer.read_offscreen_target(target).unwrap().unwrap())
+    }
+
+    #[test]
+    fn pipelined_frames_match_single_threaded_frames() {
+        let Some(single_threaded) = render_frames(RenderMode::SingleThreaded) else {
+            return;
+        };
+        let pipelined = render_frames(RenderMode::Pipelined).unwrap();
+
+        assert_eq!(&single_threaded[..4], [255, 0, 0, 255]);
+        assert_eq!(pipelined, single_threaded);
+    }
+}
@@ -0,0 +1,9 @@
+mod post_process_node;
+mod post_process_pipeline;
+mod post_process_renderer;
+mod settings;
+
+pub use post_process_node::*;
+pub use post_process_pipeline::*;
+pub use post_process_renderer::*;
+pub use settings::*;
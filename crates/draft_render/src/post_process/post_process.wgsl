@@ -0,0 +1,306 @@
+struct PostProcess {
+    // Viewport of the view as fractions of the target: x, y, width, height.
+    viewport: vec4<f32>,
+    exposure: f32,
+    bloom_intensity: f32,
+    bloom_filter_radius: f32,
+    vignette_intensity: f32,
+    vignette_radius: f32,
+    vignette_smoothness: f32,
+    _padding: vec2<f32>,
+}
+
+@group(0) @binding(0) var source_texture: texture_2d<f32>;
+@group(0) @binding(1) var source_sampler: sampler;
+@group(0) @binding(2) var<uniform> post_process: PostProcess;
+
+#ifdef TONEMAPPING
+#ifdef BLOOM
+@group(0) @binding(3) var bloom_texture: texture_2d<f32>;
+#endif
+#endif
+
+#ifdef COLOR_GRADING
+@group(0) @binding(3) var lut_texture: texture_3d<f32>;
+#endif
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    // Position within the drawn viewport, from the top left corner.
+    @location(0) uv: vec2<f32>,
+}
+
+// A triangle that covers the viewport.
+@vertex
+fn vertex(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+// Maps a position in the viewport to the textures that cover the whole target.
+fn target_uv(uv: vec2<f32>) -> vec2<f32> {
+    return post_process.viewport.xy + uv * post_process.viewport.zw;
+}
+
+fn sample_source(uv: vec2<f32>) -> vec3<f32> {
+    return textureSampleLevel(source_texture, source_sampler, uv, 0.0).rgb;
+}
+
+fn luminance(color: vec3<f32>) -> f32 {
+    return dot(color, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+#ifdef BLOOM_DOWNSAMPLE
+// 13 taps in 5 overlapping boxes, from Jimenez's "Next Generation Post Processing in
+// Call of Duty: Advanced Warfare".
+@fragment
+fn bloom_downsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(source_texture));
+
+#ifdef FIRST_DOWNSAMPLE
+    // Reads the viewport of the HDR texture, without bleeding in what is around it.
+    let uv = target_uv(in.uv);
+    let uv_min = post_process.viewport.xy + texel * 0.5;
+    let uv_max = post_process.viewport.xy + post_process.viewport.zw - texel * 0.5;
+#else
+    let uv = in.uv;
+    let uv_min = vec2<f32>(0.0);
+    let uv_max = vec2<f32>(1.0);
+#endif
+
+    let a = sample_source(clamp(uv + texel * vec2<f32>(-2.0, -2.0), uv_min, uv_max));
+    let b = sample_source(clamp(uv + texel * vec2<f32>(0.0, -2.0), uv_min, uv_max));
+    let c = sample_source(clamp(uv + texel * vec2<f32>(2.0, -2.0), uv_min, uv_max));
+    let d = sample_source(clamp(uv + texel * vec2<f32>(-2.0, 0.0), uv_min, uv_max));
+    let e = sample_source(clamp(uv, uv_min, uv_max));
+    let f = sample_source(clamp(uv + texel * vec2<f32>(2.0, 0.0), uv_min, uv_max));
+    let g = sample_source(clamp(uv + texel * vec2<f32>(-2.0, 2.0), uv_min, uv_max));
+    let h = sample_source(clamp(uv + texel * vec2<f32>(0.0, 2.0), uv_min, uv_max));
+    let i = sample_source(clamp(uv + texel * vec2<f32>(2.0, 2.0), uv_min, uv_max));
+    let j = sample_source(clamp(uv + texel * vec2<f32>(-1.0, -1.0), uv_min, uv_max));
+    let k = sample_source(clamp(uv + texel * vec2<f32>(1.0, -1.0), uv_min, uv_max));
+    let l = sample_source(clamp(uv + texel * vec2<f32>(-1.0, 1.0), uv_min, uv_max));
+    let m = sample_source(clamp(uv + texel * vec2<f32>(1.0, 1.0), uv_min, uv_max));
+
+    let boxes = array<vec3<f32>, 5>(
+        (a + b + d + e) * 0.25,
+        (b + c + e + f) * 0.25,
+        (d + e + g + h) * 0.25,
+        (e + f + h + i) * 0.25,
+        (j + k + l + m) * 0.25,
+    );
+    let weights = array<f32, 5>(0.125, 0.125, 0.125, 0.125, 0.5);
+
+    var color = vec3<f32>(0.0);
+    var total_weight = 0.0;
+    for (var index = 0; index < 5; index += 1) {
+#ifdef FIRST_DOWNSAMPLE
+        // Karis average: bright boxes weigh less, so single bright pixels don't
+        // flicker through the whole chain.
+        let weight = weights[index] / (1.0 + luminance(boxes[index]));
+#else
+        let weight = weights[index];
+#endif
+        color += boxes[index] * weight;
+        total_weight += weight;
+    }
+
+    return vec4<f32>(color / total_weight, 1.0);
+}
+#endif
+
+#ifdef BLOOM_UPSAMPLE
+// 3x3 tent filter, blended additively into the larger mip.
+@fragment
+fn bloom_upsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    let offset = post_process.bloom_filter_radius / vec2<f32>(textureDimensions(source_texture));
+    let uv = in.uv;
+
+    let a = sample_source(uv + offset * vec2<f32>(-1.0, -1.0));
+    let b = sample_source(uv + offset * vec2<f32>(0.0, -1.0));
+    let c = sample_source(uv + offset * vec2<f32>(1.0, -1.0));
+    let d = sample_source(uv + offset * vec2<f32>(-1.0, 0.0));
+    let e = sample_source(uv);
+    let f = sample_source(uv + offset * vec2<f32>(1.0, 0.0));
+    let g = sample_source(uv + offset * vec2<f32>(-1.0, 1.0));
+    let h = sample_source(uv + offset * vec2<f32>(0.0, 1.0));
+    let i = sample_source(uv + offset * vec2<f32>(1.0, 1.0));
+
+    let color = (e * 4.0 + (b + d + f + h) * 2.0 + (a + c + g + i)) / 16.0;
+    return vec4<f32>(color, 1.0);
+}
+#endif
+
+#ifdef TONEMAPPING
+fn tonemap_reinhard(color: vec3<f32>) -> vec3<f32> {
+    return color / (1.0 + color);
+}
+
+fn tonemap_aces_fitted(color: vec3<f32>) -> vec3<f32> {
+    // sRGB to the ACES reference rendering transform input space.
+    let input = mat3x3<f32>(
+        0.59719, 0.07600, 0.02840,
+        0.35458, 0.90834, 0.13383,
+        0.04823, 0.01566, 0.83777,
+    );
+    // Output device transform space back to sRGB.
+    let output = mat3x3<f32>(
+        1.60475, -0.10208, -0.00327,
+        -0.53108, 1.10813, -0.07276,
+        -0.07367, -0.00605, 1.07602,
+    );
+
+    let v = input * color;
+    let a = v * (v + 0.0245786) - 0.000090537;
+    let b = v * (0.983729 * v + 0.4329510) + 0.238081;
+    return output * (a / b);
+}
+
+// Polynomial fit of the default AgX contrast curve.
+fn agx_contrast(x: vec3<f32>) -> vec3<f32> {
+    let x2 = x * x;
+    let x4 = x2 * x2;
+    return 15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2
+        + 0.1191 * x - 0.00232;
+}
+
+fn tonemap_agx(color: vec3<f32>) -> vec3<f32> {
+    let inset = mat3x3<f32>(
+        0.842479062253094, 0.0423282422610123, 0.0423756549057051,
+        0.0784335999999992, 0.878468636469772, 0.0784336,
+        0.0792237451477643, 0.0791661274605434, 0.879142973793104,
+    );
+    let outset = mat3x3<f32>(
+        1.19687900512017, -0.0528968517574562, -0.0529716355144438,
+        -0.0980208811401368, 1.15190312990417, -0.0980434501171241,
+        -0.0990297440797205, -0.0989611768448433, 1.15107367264116,
+    );
+    let min_ev = -12.47393;
+    let max_ev = 4.026069;
+
+    var v = inset * color;
+    v = clamp(log2(max(v, vec3<f32>(1e-10))), vec3<f32>(min_ev), vec3<f32>(max_ev));
+    v = (v - min_ev) / (max_ev - min_ev);
+    v = agx_contrast(v);
+    v = outset * v;
+    // The curve outputs display encoded values.
+    return pow(max(v, vec3<f32>(0.0)), vec3<f32>(2.2));
+}
+
+@fragment
+fn tonemapping(in: VertexOutput) -> @location(0) vec4<f32> {
+    var color = sample_source(target_uv(in.uv));
+
+#ifdef BLOOM
+    let bloom = textureSampleLevel(bloom_texture, source_sampler, in.uv, 0.0).rgb;
+    color = mix(color, bloom, post_process.bloom_intensity);
+#endif
+
+    color *= post_process.exposure;
+
+#ifdef TONEMAP_REINHARD
+    color = tonemap_reinhard(color);
+#endif
+#ifdef TONEMAP_ACES_FITTED
+    color = tonemap_aces_fitted(color);
+#endif
+#ifdef TONEMAP_AGX
+    color = tonemap_agx(color);
+#endif
+
+    return vec4<f32>(saturate(color), 1.0);
+}
+#endif
+
+#ifdef COLOR_GRADING
+fn linear_to_srgb(color: vec3<f32>) -> vec3<f32> {
+    let low = color * 12.92;
+    let high = 1.055 * pow(color, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, color <= vec3<f32>(0.0031308));
+}
+
+fn srgb_to_linear(color: vec3<f32>) -> vec3<f32> {
+    let low = color / 12.92;
+    let high = pow((color + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(high, low, color <= vec3<f32>(0.04045));
+}
+
+@fragment
+fn color_grading(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = linear_to_srgb(saturate(sample_source(target_uv(in.uv))));
+
+    // Samples the centers of the first and last texels at the ends of the range.
+    let size = vec3<f32>(textureDimensions(lut_texture));
+    let uvw = color * (size - 1.0) / size + 0.5 / size;
+    let graded = textureSampleLevel(lut_texture, source_sampler, uvw, 0.0).rgb;
+
+    return vec4<f32>(srgb_to_linear(graded), 1.0);
+}
+#endif
+
+#ifdef VIGNETTE
+@fragment
+fn vignette(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = sample_source(target_uv(in.uv));
+
+    // 0 at the center of the viewport and 1 at its corners.
+    let distance = length(in.uv - 0.5) * sqrt(2.0);
+    let radius = post_process.vignette_radius;
+    let darkening = smoothstep(radius - post_process.vignette_smoothness, radius, distance);
+
+    return vec4<f32>(color * (1.0 - post_process.vignette_intensity * darkening), 1.0);
+}
+#endif
+
+#ifdef FXAA
+const FXAA_REDUCE_MIN: f32 = 1.0 / 128.0;
+const FXAA_REDUCE_MUL: f32 = 1.0 / 8.0;
+const FXAA_SPAN_MAX: f32 = 8.0;
+
+fn perceptual_luma(color: vec3<f32>) -> f32 {
+    return sqrt(luminance(color));
+}
+
+// Blurs along the local edge direction, estimated from the luma of the corners.
+@fragment
+fn fxaa(in: VertexOutput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(source_texture));
+    let uv = target_uv(in.uv);
+
+    let luma_nw = perceptual_luma(sample_source(uv + texel * vec2<f32>(-1.0, -1.0)));
+    let luma_ne = perceptual_luma(sample_source(uv + texel * vec2<f32>(1.0, -1.0)));
+    let luma_sw = perceptual_luma(sample_source(uv + texel * vec2<f32>(-1.0, 1.0)));
+    let luma_se = perceptual_luma(sample_source(uv + texel * vec2<f32>(1.0, 1.0)));
+    let luma_m = perceptual_luma(sample_source(uv));
+
+    let luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    let luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    var direction = vec2<f32>(
+        (luma_sw + luma_se) - (luma_nw + luma_ne),
+        (luma_nw + luma_sw) - (luma_ne + luma_se),
+    );
+    let reduce = max(
+        (luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * FXAA_REDUCE_MUL,
+        FXAA_REDUCE_MIN,
+    );
+    let scale = 1.0 / (min(abs(direction.x), abs(direction.y)) + reduce);
+    direction = clamp(direction * scale, vec2<f32>(-FXAA_SPAN_MAX), vec2<f32>(FXAA_SPAN_MAX))
+        * texel;
+
+    let inner = 0.5 * (sample_source(uv + direction * (1.0 / 3.0 - 0.5))
+        + sample_source(uv + direction * (2.0 / 3.0 - 0.5)));
+    let outer = inner * 0.5 + 0.25 * (sample_source(uv - direction * 0.5)
+        + sample_source(uv + direction * 0.5));
+
+    // The wider blur is rejected when it reaches past the local contrast.
+    let luma_outer = perceptual_luma(outer);
+    let color = select(outer, inner, luma_outer < luma_min || luma_outer > luma_max);
+
+    return vec4<f32>(color, 1.0);
+}
+#endif
@@ -0,0 +1,320 @@
+use draft_graphics::{BindGroupLayout, Color, Sampler, TextureFormat};
+use wgpu::{Buffer, Extent3d, LoadOp, Operations, StoreOp, TextureDimension, TextureUsages};
+
+use crate::{
+    FrameworkError,
+    camera::PhysicalViewport,
+    frame_graph::{
+        FrameGraph, ManualTextureDescriptor, PassNodeBuilderExt, RenderPassExt, ResourceHandle,
+        TextureViewDescriptor, TransientBindGroup, TransientBindGroupEntry,
+        TransientBindingResource, TransientRenderPassColorAttachment, TransientTexture,
+        TransientTextureDescriptor, TransientTextureViewHandle,
+        TransientTextureViewHandleDescriptor,
+    },
+    post_process::POST_PROCESS_EXTRA_TEXTURE_BINDING,
+    render_phase::HDR_FORMAT,
+    render_pipeline::{Node, RenderPipelineRunContext, SlotLabel, VIEW_TARGET},
+    render_world::CachedPipelineId,
+};
+
+fn post_process_texture(
+    frame_graph: &mut FrameGraph,
+    name: String,
+    size: Extent3d,
+    mip_level_count: u32,
+    format: TextureFormat,
+) -> ResourceHandle<TransientTexture> {
+    frame_graph.get_or_create(
+        &name,
+        TransientTextureDescriptor::Manual(ManualTextureDescriptor {
+            label: Some(name.clone()),
+            size,
+            mip_level_count,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+        }),
+    )
+}
+
+fn mip_view(texture: &ResourceHandle<TransientTexture>, mip: u32) -> TransientTextureViewHandle {
+    TransientTextureViewHandle::Descriptor(TransientTextureViewHandleDescriptor {
+        texture: texture.clone(),
+        desc: TextureViewDescriptor {
+            base_mip_level: mip,
+            mip_level_count: Some(1),
+            ..Default::default()
+        },
+    })
+}
+
+/// Bindings shared by the passes of a view.
+struct EffectBindings<'a> {
+    settings_buffer: &'a Buffer,
+    sampler: &'a Sampler,
+}
+
+/// A fullscreen pass that reads `source` and draws into `target`.
+struct EffectPass<'a> {
+    name: String,
+    pipeline: CachedPipelineId,
+    layout: &'a BindGroupLayout,
+    source: TransientTextureViewHandle,
+    /// Bound at [`POST_PROCESS_EXTRA_TEXTURE_BINDING`].
+    extra_texture: Option<TransientTextureViewHandle>,
+    target: TransientTextureViewHandle,
+    load: LoadOp<Color>,
+    /// Drawn area of the target, the whole target when `None`.
+    viewport: Option<PhysicalViewport>,
+}
+
+fn add_effect_pass(frame_graph: &mut FrameGraph, pass: EffectPass, bindings: &EffectBindings) {
+    let mut pass_builder = frame_graph.create_pass_builder(&pass.name);
+    let mut render_pass_builder = pass_builder.create_render_pass_builder(&pass.name);
+
+    let source = render_pass_builder.read_texture_handle(&pass.source);
+    let extra_texture = pass
+        .extra_texture
+        .map(|texture| render_pass_builder.read_texture_handle(&texture));
+
+    // Loading the target depends on the passes that wrote it before.
+    if let (LoadOp::Load, TransientTextureViewHandle::Descriptor(target)) =
+        (pass.load, &pass.target)
+    {
+        render_pass_builder.read(target.texture.clone());
+    }
+
+    let target = render_pass_builder.write_texture_handle(&pass.target);
+    render_pass_builder.add_color_attachment(TransientRenderPassColorAttachment {
+        view: target,
+        depth_slice: None,
+        resolve_target: None,
+        ops: Operations {
+            load: pass.load,
+            store: StoreOp::Store,
+        },
+    });
+
+    if let Some(viewport) = pass.viewport {
+        RenderPassExt::set_viewport(
+            &mut render_pass_builder,
+            viewport.x as f32,
+            viewport.y as f32,
+            viewport.width as f32,
+            viewport.height as f32,
+            0.0,
+            1.0,
+        );
+    }
+
+    let mut entries = vec![
+        TransientBindGroupEntry {
+            binding: 0,
+            resource: TransientBindingResource::TextureView(source),
+        },
+        TransientBindGroupEntry {
+            binding: 1,
+            resource: TransientBindingResource::Sampler(bindings.sampler.clone()),
+        },
+        TransientBindGroupEntry {
+            binding: 2,
+            resource: TransientBindingResource::Buffer(bindings.settings_buffer.clone()),
+        },
+    ];
+    entries.extend(extra_texture.map(|texture| TransientBindGroupEntry {
+        binding: POST_PROCESS_EXTRA_TEXTURE_BINDING,
+        resource: TransientBindingResource::TextureView(texture),
+    }));
+
+    RenderPassExt::set_render_pipeline(&mut render_pass_builder, pass.pipeline.id());
+    render_pass_builder.set_bind_group(
+        0,
+        &TransientBindGroup {
+            label: Some(format!("{}_bind_group", pass.name)),
+            layout: pass.layout.clone(),
+            entries,
+        },
+        &[],
+    );
+    RenderPassExt::draw(&mut render_pass_builder, 0..3, 0..1);
+}
+
+/// Resolves the HDR texture of a view with post-processing into its target. Every
+/// enabled effect is a pass of the frame graph: the bloom mip chain, tonemapping,
+/// color grading, vignette and FXAA, in that order. Effects whose pipeline is not
+/// compiled yet are skipped.
+pub struct PostProcessNode;
+
+impl Node for PostProcessNode {
+    fn inputs(&self) -> Vec<SlotLabel> {
+        vec![VIEW_TARGET]
+    }
+
+    fn outputs(&self) -> Vec<SlotLabel> {
+        vec![VIEW_TARGET]
+    }
+
+    fn run(&self, context: &mut RenderPipelineRunContext) -> Result<(), FrameworkError> {
+        let render_world = &*context.render_world;
+        let view = &render_world.views()[context.view_index];
+        let renderer = render_world.post_process_renderer();
+
+        let (Some(post_process), Some(pipeline), Some(resources)) = (
+            renderer.view(context.view_index),
+            renderer.pipeline(),
+            renderer.resources(),
+        ) else {
+            return Ok(());
+        };
+
+        let pipeline_cache = render_world.pipeline_cache();
+        let is_ready = |id: CachedPipelineId| pipeline_cache.get_render_pipeline(id).is_some();
+
+        if !is_ready(post_process.tonemapping) {
+            return Ok(());
+        }
+
+        let bindings = EffectBindings {
+            settings_buffer: &post_process.settings_buffer,
+            sampler: &resources.sampler,
+        };
+        let hdr_texture = view.main_texture(context.frame_graph).texture;
+
+        let bloom = post_process.bloom.as_ref().filter(|bloom| {
+            is_ready(bloom.downsample_first)
+                && is_ready(bloom.downsample)
+                && is_ready(bloom.upsample)
+        });
+        let bloom_texture = bloom.map(|bloom| {
+            let texture = post_process_texture(
+                context.frame_graph,
+                format!("{}_bloom", view.label),
+                bloom.size,
+                bloom.mip_count,
+                HDR_FORMAT,
+            );
+
+            for mip in 0..bloom.mip_count {
+                let (pipeline_id, source) = match mip {
+                    0 => (bloom.downsample_first, hdr_texture.clone()),
+                    _ => (bloom.downsample, mip_view(&texture, mip - 1)),
+                };
+
+                add_effect_pass(
+                    context.frame_graph,
+                    EffectPass {
+                        name: format!("{}_bloom_downsample_{mip}", view.label),
+                        pipeline: pipeline_id,
+                        layout: &pipeline.layout,
+                        source,
+                        extra_texture: None,
+                        target: mip_view(&texture, mip),
+                        load: LoadOp::Clear(Color::BLACK),
+                        viewport: None,
+                    },
+                    &bindings,
+                );
+            }
+
+            for mip in (0..bloom.mip_count - 1).rev() {
+                add_effect_pass(
+                    context.frame_graph,
+                    EffectPass {
+                        name: format!("{}_bloom_upsample_{mip}", view.label),
+                        pipeline: bloom.upsample,
+                        layout: &pipeline.layout,
+                        source: mip_view(&texture, mip + 1),
+                        extra_texture: None,
+                        target: mip_view(&texture, mip),
+                        load: LoadOp::Load,
+                        viewport: None,
+                    },
+                    &bindings,
+                );
+            }
+
+            mip_view(&texture, 0)
+        });
+
+        let bloom_texture = bloom_texture.unwrap_or_else(|| {
+            TransientTextureViewHandle::TextureView(resources.bloom_fallback.clone())
+        });
+        let tonemapping = Some((
+            post_process.tonemapping,
+            "tonemapping",
+            &pipeline.tonemapping_layout,
+            Some(bloom_texture),
+        ));
+        let color_grading = post_process.color_grading.as_ref().map(|color_grading| {
+            (
+                color_grading.pipeline,
+                "color_grading",
+                &pipeline.color_grading_layout,
+                Some(TransientTextureViewHandle::TextureView(
+                    color_grading.lut.clone(),
+                )),
+            )
+        });
+        let vignette = post_process
+            .vignette
+            .map(|id| (id, "vignette", &pipeline.layout, None));
+        let fxaa = post_process
+            .fxaa
+            .map(|id| (id, "fxaa", &pipeline.layout, None));
+
+        let effects = [tonemapping, color_grading, vignette, fxaa]
+            .into_iter()
+            .flatten()
+            .filter(|(id, ..)| is_ready(*id))
+            .collect::<Vec<_>>();
+        let effects_len = effects.len();
+
+        // The effects after tonemapping read the result of the previous one, so they
+        // alternate between two low dynamic range textures. The last one draws into
+        // the viewport of the view target and keeps the rest of it.
+        let mut source = hdr_texture;
+        for (index, (id, name, layout, extra_texture)) in effects.into_iter().enumerate() {
+            let is_last = index + 1 == effects_len;
+            let target = if is_last {
+                TransientTextureViewHandle::TextureView(view.view_target.view.clone())
+            } else {
+                let texture = post_process_texture(
+                    context.frame_graph,
+                    format!("{}_post_process_{}", view.label, index % 2),
+                    Extent3d {
+                        width: view.view_target.width,
+                        height: view.view_target.height,
+                        depth_or_array_layers: 1,
+                    },
+                    1,
+                    view.view_target.format,
+                );
+                mip_view(&texture, 0)
+            };
+
+            add_effect_pass(
+                context.frame_graph,
+                EffectPass {
+                    name: format!("{}_{name}", view.label),
+                    pipeline: id,
+                    layout,
+                    source,
+                    extra_texture,
+                    target: target.clone(),
+                    load: if is_last {
+                        LoadOp::Load
+                    } else {
+                        LoadOp::Clear(Color::BLACK)
+                    },
+                    viewport: Some(view.viewport),
+                },
+                &bindings,
+            );
+
+            source = target;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,317 @@
+use std::num::NonZeroU64;
+
+use bytemuck::{Pod, Zeroable};
+use draft_graphics::{
+    BindGroupLayout, BlendState, ColorTargetState, ColorWrites, MultisampleState, PrimitiveState,
+    RenderDevice, TextureFormat,
+};
+use draft_shader::{Shader, ShaderDefVal, ShaderResource};
+use wgpu::{
+    BlendComponent, BlendFactor, BlendOperation, SamplerBindingType, ShaderStages,
+    TextureSampleType, TextureViewDimension,
+};
+
+use crate::{
+    post_process::{PostProcessSettings, Tonemapping},
+    render_phase::RenderView,
+    render_world::{
+        GpuFragmentState, GpuRenderPipelineDescriptor, GpuVertexState, SpecializedRenderPipeline,
+    },
+};
+
+/// Binding of the texture an effect reads besides its source: the bloom texture for
+/// tonemapping and the lookup table for color grading.
+pub const POST_PROCESS_EXTRA_TEXTURE_BINDING: u32 = 3;
+
+/// Settings of the effects of a view. Matches the `PostProcess` struct of the shader.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Pod, Zeroable)]
+pub struct GpuPostProcess {
+    /// Viewport of the view as fractions of its target: x, y, width and height.
+    pub viewport: [f32; 4],
+    /// Linear scale of the colors.
+    pub exposure: f32,
+    pub bloom_intensity: f32,
+    pub bloom_filter_radius: f32,
+    pub vignette_intensity: f32,
+    pub vignette_radius: f32,
+    pub vignette_smoothness: f32,
+    pub _padding: [f32; 2],
+}
+
+impl GpuPostProcess {
+    pub fn new(settings: &PostProcessSettings, view: &RenderView) -> Self {
+        let width = view.view_target.width.max(1) as f32;
+        let height = view.view_target.height.max(1) as f32;
+        let bloom = settings.bloom.unwrap_or_default();
+        let vignette = settings.vignette.unwrap_or_default();
+
+        Self {
+            viewport: [
+                view.viewport.x as f32 / width,
+                view.viewport.y as f32 / height,
+                view.viewport.width as f32 / width,
+                view.viewport.height as f32 / height,
+            ],
+            exposure: settings.exposure.exp2(),
+            bloom_intensity: bloom.intensity.clamp(0.0, 1.0),
+            bloom_filter_radius: bloom.filter_radius,
+            vignette_intensity: vignette.intensity.clamp(0.0, 1.0),
+            vignette_radius: vignette.radius,
+            vignette_smoothness: vignette.smoothness,
+            _padding: [0.0; 2],
+        }
+    }
+}
+
+/// A fullscreen pass of the post-processing chain.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum PostProcessEffect {
+    /// Downsamples the HDR texture into the first bloom mip, with a Karis average
+    /// against flickering.
+    BloomDownsampleFirst,
+    BloomDownsample,
+    /// Blurs a bloom mip and adds it to the next larger one.
+    BloomUpsample,
+    /// Applies exposure and tonemapping, mixing in the bloom texture when `bloom` is
+    /// set. Writes the first low dynamic range texture of the chain.
+    Tonemapping {
+        tonemapping: Tonemapping,
+        bloom: bool,
+    },
+    ColorGrading,
+    Vignette,
+    Fxaa,
+}
+
+impl PostProcessEffect {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PostProcessEffect::BloomDownsampleFirst | PostProcessEffect::BloomDownsample => {
+                "bloom_downsample"
+            }
+            PostProcessEffect::BloomUpsample => "bloom_upsample",
+            PostProcessEffect::Tonemapping { .. } => "tonemapping",
+            PostProcessEffect::ColorGrading => "color_grading",
+            PostProcessEffect::Vignette => "vignette",
+            PostProcessEffect::Fxaa => "fxaa",
+        }
+    }
+
+    pub fn shader_defs(&self) -> Vec<ShaderDefVal> {
+        let mut shader_defs = vec![ShaderDefVal::from(self.name().to_uppercase())];
+
+        match self {
+            PostProcessEffect::BloomDownsampleFirst => {
+                shader_defs.push("FIRST_DOWNSAMPLE".into());
+            }
+            PostProcessEffect::Tonemapping { tonemapping, bloom } => {
+                shader_defs.extend(tonemapping.shader_def().map(ShaderDefVal::from));
+                if *bloom {
+                    shader_defs.push("BLOOM".into());
+                }
+            }
+            _ => {}
+        }
+
+        shader_defs
+    }
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub struct PostProcessPipelineKey {
+    pub effect: PostProcessEffect,
+    pub format: TextureFormat,
+}
+
+pub struct PostProcessPipeline {
+    /// Source texture, sampler and settings, bound by every effect.
+    pub layout: BindGroupLayout,
+    /// Adds the bloom texture.
+    pub tonemapping_layout: BindGroupLayout,
+    /// Adds the 3D lookup table.
+    pub color_grading_layout: BindGroupLayout,
+    pub shader: ShaderResource,
+}
+
+fn texture_entry(binding: u32, view_dimension: TextureViewDimension) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type: TextureSampleType::Float { filterable: true },
+            view_dimension,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+fn create_layout(
+    device: &RenderDevice,
+    label: &str,
+    extra_texture: Option<TextureViewDimension>,
+) -> BindGroupLayout {
+    let mut entries = vec![
+        texture_entry(0, TextureViewDimension::D2),
+        wgpu::BindGroupLayoutEntry {
+            binding: 1,
+            visibility: ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(SamplerBindingType::Filtering),
+            count: None,
+        },
+        wgpu::BindGroupLayoutEntry {
+            binding: 2,
+            visibility: ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: NonZeroU64::new(size_of::<GpuPostProcess>() as u64),
+            },
+            count: None,
+        },
+    ];
+    entries.extend(
+        extra_texture.map(|view_dimension| {
+            texture_entry(POST_PROCESS_EXTRA_TEXTURE_BINDING, view_dimension)
+        }),
+    );
+
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some(label),
+        entries: &entries,
+    })
+}
+
+impl PostProcessPipeline {
+    pub fn new(device: &RenderDevice) -> Self {
+        Self {
+            layout: create_layout(device, "post_process_bind_group_layout", None),
+            tonemapping_layout: create_layout(
+                device,
+                "tonemapping_bind_group_layout",
+                Some(TextureViewDimension::D2),
+            ),
+            color_grading_layout: create_layout(
+                device,
+                "color_grading_bind_group_layout",
+                Some(TextureViewDimension::D3),
+            ),
+            shader: Self::shader(),
+        }
+    }
+
+    pub fn shader() -> ShaderResource {
+        ShaderResource::new_embedded(Shader {
+            source: include_str!("post_process.wgsl").into(),
+            ..Default::default()
+        })
+    }
+
+    pub fn layout(&self, effect: PostProcessEffect) -> &BindGroupLayout {
+        match effect {
+            PostProcessEffect::Tonemapping { .. } => &self.tonemapping_layout,
+            PostProcessEffect::ColorGrading => &self.color_grading_layout,
+            _ => &self.layout,
+        }
+    }
+}
+
+impl SpecializedRenderPipeline for PostProcessPipeline {
+    type Key = PostProcessPipelineKey;
+
+    fn specialize(&self, key: Self::Key) -> GpuRenderPipelineDescriptor {
+        let shader_defs = key.effect.shader_defs();
+
+        // Upsampled mips are added to the downsampled ones.
+        let blend = (key.effect == PostProcessEffect::BloomUpsample).then_some(BlendState {
+            color: BlendComponent {
+                src_factor: BlendFactor::One,
+                dst_factor: BlendFactor::One,
+                operation: BlendOperation::Add,
+            },
+            alpha: BlendComponent::REPLACE,
+        });
+
+        GpuRenderPipelineDescriptor {
+            label: format!("{}_pipeline", key.effect.name()),
+            layout: vec![self.layout(key.effect).clone()],
+            vertex: GpuVertexState {
+                shader: self.shader.clone(),
+                entry_point: Some("vertex".to_string()),
+                shader_defs: shader_defs.clone(),
+                buffers: vec![],
+            },
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            fragment: Some(GpuFragmentState {
+                shader: self.shader.clone(),
+                entry_point: Some(key.effect.name().to_string()),
+                shader_defs,
+                targets: vec![Some(ColorTargetState {
+                    format: key.format,
+                    blend,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use draft_shader::{Source, preprocess};
+    use wgpu::naga::{
+        front::wgsl,
+        valid::{Capabilities, ValidationFlags, Validator},
+    };
+
+    #[test]
+    fn shader_is_valid_for_every_effect() {
+        let shader = PostProcessPipeline::shader();
+        let Source::Wgsl(source) = shader.data_ref().source.clone();
+
+        let mut effects = vec![
+            PostProcessEffect::BloomDownsampleFirst,
+            PostProcessEffect::BloomDownsample,
+            PostProcessEffect::BloomUpsample,
+            PostProcessEffect::ColorGrading,
+            PostProcessEffect::Vignette,
+            PostProcessEffect::Fxaa,
+        ];
+        for tonemapping in [
+            Tonemapping::None,
+            Tonemapping::Reinhard,
+            Tonemapping::AcesFitted,
+            Tonemapping::AgX,
+        ] {
+            for bloom in [false, true] {
+                effects.push(PostProcessEffect::Tonemapping { tonemapping, bloom });
+            }
+        }
+
+        for effect in effects {
+            let source = preprocess(&source, &effect.shader_defs()).unwrap();
+            let module = wgsl::parse_str(&source).unwrap_or_else(|e| panic!("{effect:?}: {e}"));
+
+            Validator::new(ValidationFlags::all(), Capabilities::default())
+                .validate(&module)
+                .unwrap_or_else(|e| panic!("{effect:?}: {e:?}"));
+
+            assert!(
+                module
+                    .entry_points
+                    .iter()
+                    .any(|entry_point| entry_point.name == effect.name()),
+                "{effect:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn uniform_matches_the_shader_layout() {
+        assert_eq!(size_of::<GpuPostProcess>(), 48);
+    }
+}
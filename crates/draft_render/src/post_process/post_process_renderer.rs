@@ -0,0 +1,240 @@
+use bytemuck::bytes_of;
+use draft_graphics::{
+    AddressMode, BufferInitDescriptor, Extent3d, FilterMode, RenderDevice, RenderQueue, Sampler,
+    SamplerDescriptor, TextureDimension, TextureUsages, TextureView,
+};
+use draft_image::ImageKind;
+use fyrox_resource::core::log::Log;
+use wgpu::{Buffer, BufferUsages};
+
+use crate::{
+    FrameworkError,
+    post_process::{
+        GpuPostProcess, PostProcessEffect, PostProcessPipeline, PostProcessPipelineKey,
+        PostProcessSettings,
+    },
+    render_phase::{HDR_FORMAT, RenderView},
+    render_world::{CachedPipelineId, PipelineCache, SpecializedRenderPipelines, TextureCache},
+};
+
+/// Number of mips of a bloom chain whose first mip has the given size. The chain stops
+/// at a single texel or at `max_mip_count`.
+pub fn bloom_mip_count(width: u32, height: u32, max_mip_count: u32) -> u32 {
+    let levels = u32::BITS - width.min(height).max(1).leading_zeros();
+    levels.min(max_mip_count).max(1)
+}
+
+pub struct BloomView {
+    /// Size of the first mip, half the viewport of the view.
+    pub size: Extent3d,
+    pub mip_count: u32,
+    pub downsample_first: CachedPipelineId,
+    pub downsample: CachedPipelineId,
+    pub upsample: CachedPipelineId,
+}
+
+pub struct ColorGradingView {
+    pub pipeline: CachedPipelineId,
+    pub lut: TextureView,
+}
+
+/// Pipelines and settings of the enabled effects of a view.
+pub struct PostProcessView {
+    pub settings_buffer: Buffer,
+    pub bloom: Option<BloomView>,
+    pub tonemapping: CachedPipelineId,
+    pub color_grading: Option<ColorGradingView>,
+    pub vignette: Option<CachedPipelineId>,
+    pub fxaa: Option<CachedPipelineId>,
+}
+
+/// Resources that are created once and bound by the effects of every view.
+pub struct PostProcessResources {
+    /// Linear sampler that clamps to the edges of the textures.
+    pub sampler: Sampler,
+    /// Black texture bound in place of the bloom texture when bloom is disabled.
+    pub bloom_fallback: TextureView,
+}
+
+impl PostProcessResources {
+    pub fn new(device: &RenderDevice) -> Self {
+        let texture = device.create_gpu_texture(&wgpu::TextureDescriptor {
+            label: Some("fallback_bloom_texture"),
+            size: Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: HDR_FORMAT,
+            usage: TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        Self {
+            sampler: device.create_sampler(&SamplerDescriptor {
+                label: Some("post_process_sampler"),
+                address_mode_u: AddressMode::ClampToEdge,
+                address_mode_v: AddressMode::ClampToEdge,
+                address_mode_w: AddressMode::ClampToEdge,
+                mag_filter: FilterMode::Linear,
+                min_filter: FilterMode::Linear,
+                ..Default::default()
+            }),
+            bloom_fallback: texture.create_view(&Default::default()),
+        }
+    }
+}
+
+/// Prepares the pipelines and settings the post-processing passes of every view with
+/// post-processing run with.
+#[derive(Default)]
+pub struct PostProcessRenderer {
+    pipeline: Option<PostProcessPipeline>,
+    pipelines: SpecializedRenderPipelines<PostProcessPipeline>,
+    resources: Option<PostProcessResources>,
+    views: Vec<Option<PostProcessView>>,
+}
+
+impl PostProcessRenderer {
+    pub fn pipeline(&self) -> Option<&PostProcessPipeline> {
+        self.pipeline.as_ref()
+    }
+
+    pub fn resources(&self) -> Option<&PostProcessResources> {
+        self.resources.as_ref()
+    }
+
+    /// Effects of the view at `view_index` in
+    /// [`RenderWorld::views`](crate::render_world::RenderWorld::views).
+    pub fn view(&self, view_index: usize) -> Option<&PostProcessView> {
+        self.views.get(view_index).and_then(Option::as_ref)
+    }
+
+    pub fn prepare(
+        &mut self,
+        views: &[RenderView],
+        texture_cache: &mut TextureCache,
+        pipeline_cache: &mut PipelineCache,
+        device: &RenderDevice,
+        queue: &RenderQueue,
+    ) {
+        self.views.clear();
+
+        for view in views.iter() {
+            let view = view.post_process.as_ref().map(|settings| {
+                self.prepare_view(view, settings, texture_cache, pipeline_cache, device, queue)
+            });
+            self.views.push(view);
+        }
+    }
+
+    fn prepare_view(
+        &mut self,
+        view: &RenderView,
+        settings: &PostProcessSettings,
+        texture_cache: &mut TextureCache,
+        pipeline_cache: &mut PipelineCache,
+        device: &RenderDevice,
+        queue: &RenderQueue,
+    ) -> PostProcessView {
+        let pipeline = self
+            .pipeline
+            .get_or_insert_with(|| PostProcessPipeline::new(device));
+        self.resources
+            .get_or_insert_with(|| PostProcessResources::new(device));
+
+        let mut specialize = |effect, format| {
+            self.pipelines.specialize(
+                pipeline_cache,
+                pipeline,
+                PostProcessPipelineKey { effect, format },
+            )
+        };
+
+        let bloom = settings.bloom.map(|bloom| {
+            let width = (view.viewport.width / 2).max(1);
+            let height = (view.viewport.height / 2).max(1);
+
+            BloomView {
+                size: Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_count: bloom_mip_count(width, height, bloom.max_mip_count),
+                downsample_first: specialize(PostProcessEffect::BloomDownsampleFirst, HDR_FORMAT),
+                downsample: specialize(PostProcessEffect::BloomDownsample, HDR_FORMAT),
+                upsample: specialize(PostProcessEffect::BloomUpsample, HDR_FORMAT),
+            }
+        });
+
+        let format = view.view_target.format;
+        let tonemapping = specialize(
+            PostProcessEffect::Tonemapping {
+                tonemapping: settings.tonemapping,
+                bloom: bloom.is_some(),
+            },
+            format,
+        );
+
+        let color_grading = settings.color_grading.as_ref().and_then(|color_grading| {
+            let id = match texture_cache.get_create_texture(&color_grading.lut, device, queue) {
+                Ok(id) => id,
+                Err(FrameworkError::ImageNotLoaded) => return None,
+                Err(e) => {
+                    Log::err(format!("Failed to upload color grading lookup table: {e}"));
+                    return None;
+                }
+            };
+
+            let texture = texture_cache.get(&id)?;
+            if !matches!(texture.kind, ImageKind::D3 { .. }) {
+                Log::err("Color grading lookup table is not a 3D image.");
+                return None;
+            }
+
+            Some(ColorGradingView {
+                pipeline: specialize(PostProcessEffect::ColorGrading, format),
+                lut: texture.view.clone(),
+            })
+        });
+
+        let vignette = settings
+            .vignette
+            .map(|_| specialize(PostProcessEffect::Vignette, format));
+        let fxaa = settings
+            .fxaa
+            .then(|| specialize(PostProcessEffect::Fxaa, format));
+
+        let settings_buffer = device.create_gpu_buffer_init(&BufferInitDescriptor {
+            label: Some("post_process_buffer"),
+            contents: bytes_of(&GpuPostProcess::new(settings, view)),
+            usage: BufferUsages::UNIFORM,
+        });
+
+        PostProcessView {
+            settings_buffer,
+            bloom,
+            tonemapping,
+            color_grading,
+            vignette,
+            fxaa,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bloom_chain_stops_at_a_single_texel() {
+        assert_eq!(bloom_mip_count(960, 540, 6), 6);
+        assert_eq!(bloom_mip_count(8, 300, 6), 4);
+        assert_eq!(bloom_mip_count(1, 1, 6), 1);
+        assert_eq!(bloom_mip_count(0, 0, 0), 1);
+    }
+}
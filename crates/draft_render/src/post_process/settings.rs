@@ -0,0 +1,147 @@
+use draft_image::ImageResource;
+
+/// Maps the HDR colors of a view to the displayable range.
+#[derive(Debug, Default, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum Tonemapping {
+    /// Clamps colors to the displayable range.
+    None,
+    /// Simple and cheap, but desaturates bright colors.
+    Reinhard,
+    /// Stephen Hill's fit of the ACES reference rendering and output transforms.
+    #[default]
+    AcesFitted,
+    /// Troy Sobotka's AgX, which keeps bright saturated colors from skewing in hue.
+    AgX,
+}
+
+impl Tonemapping {
+    pub fn shader_def(&self) -> Option<&'static str> {
+        match self {
+            Tonemapping::None => None,
+            Tonemapping::Reinhard => Some("TONEMAP_REINHARD"),
+            Tonemapping::AcesFitted => Some("TONEMAP_ACES_FITTED"),
+            Tonemapping::AgX => Some("TONEMAP_AGX"),
+        }
+    }
+}
+
+/// Energy conserving bloom, blurred through a chain of downsampled mips of the HDR
+/// image and mixed back into the scene before tonemapping.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BloomSettings {
+    /// How much of the blurred image replaces the scene, from 0 to 1.
+    pub intensity: f32,
+    /// Maximum number of mips of the chain. More mips spread the bloom further.
+    pub max_mip_count: u32,
+    /// Radius of the upsampling filter, in texels of the smaller mip.
+    pub filter_radius: f32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self {
+            intensity: 0.04,
+            max_mip_count: 6,
+            filter_radius: 1.0,
+        }
+    }
+}
+
+/// Darkens the image towards its corners.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VignetteSettings {
+    /// Darkening at the corners, from 0 to 1.
+    pub intensity: f32,
+    /// Distance from the center where the darkening ends, where 1 is a corner.
+    pub radius: f32,
+    /// Width of the transition from full brightness to full darkening.
+    pub smoothness: f32,
+}
+
+impl Default for VignetteSettings {
+    fn default() -> Self {
+        Self {
+            intensity: 0.3,
+            radius: 1.0,
+            smoothness: 0.6,
+        }
+    }
+}
+
+/// Remaps the tonemapped colors through a 3D lookup table.
+///
+/// The table is a [`D3`](draft_image::ImageKind::D3) image indexed by sRGB encoded
+/// colors, holding sRGB encoded colors. It should use a linear format such as
+/// `Rgba8Unorm`, so the stored values are read unchanged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorGrading {
+    pub lut: ImageResource,
+}
+
+impl ColorGrading {
+    pub fn new(lut: ImageResource) -> Self {
+        Self { lut }
+    }
+}
+
+/// The post-processing effects of a camera, run in order after its view is drawn.
+///
+/// Cameras with post-processing draw into their own HDR texture, which the effects
+/// resolve into the viewport of the camera target. They don't blend with what
+/// earlier cameras drew into the same viewport.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PostProcessSettings {
+    /// Exposure in stops. Colors are scaled by `2^exposure` before tonemapping.
+    pub exposure: f32,
+    pub tonemapping: Tonemapping,
+    pub bloom: Option<BloomSettings>,
+    pub color_grading: Option<ColorGrading>,
+    pub vignette: Option<VignetteSettings>,
+    /// Fast approximate anti-aliasing of the final image.
+    pub fxaa: bool,
+}
+
+impl Default for PostProcessSettings {
+    fn default() -> Self {
+        Self {
+            exposure: 0.0,
+            tonemapping: Tonemapping::default(),
+            bloom: Some(BloomSettings::default()),
+            color_grading: None,
+            vignette: None,
+            fxaa: true,
+        }
+    }
+}
+
+impl PostProcessSettings {
+    pub fn with_exposure(mut self, exposure: f32) -> Self {
+        self.exposure = exposure;
+        self
+    }
+
+    pub fn with_tonemapping(mut self, tonemapping: Tonemapping) -> Self {
+        self.tonemapping = tonemapping;
+        self
+    }
+
+    pub fn with_bloom(mut self, bloom: Option<BloomSettings>) -> Self {
+        self.bloom = bloom;
+        self
+    }
+
+    pub fn with_color_grading(mut self, color_grading: Option<ColorGrading>) -> Self {
+        self.color_grading = color_grading;
+        self
+    }
+
+    pub fn with_vignette(mut self, vignette: Option<VignetteSettings>) -> Self {
+        self.vignette = vignette;
+        self
+    }
+
+    pub fn with_fxaa(mut self, fxaa: bool) -> Self {
+        self.fxaa = fxaa;
+        self
+    }
+}
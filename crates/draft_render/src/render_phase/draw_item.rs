@@ -0,0 +1,66 @@
+use draft_material::MaterialResource;
+use draft_mesh::MeshResource;
+use fyrox_resource::core::algebra::Matrix4;
+
+/// A set of up to 32 render layers. A draw item is visible in a view when their masks
+/// share at least one layer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LayerMask(pub u32);
+
+impl LayerMask {
+    pub const NONE: LayerMask = LayerMask(0);
+    pub const DEFAULT: LayerMask = LayerMask(1);
+    pub const ALL: LayerMask = LayerMask(u32::MAX);
+
+    pub const fn layer(index: u32) -> Self {
+        LayerMask(1 << index)
+    }
+
+    pub const fn with(self, index: u32) -> Self {
+        LayerMask(self.0 | (1 << index))
+    }
+
+    pub const fn intersects(&self, other: &LayerMask) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl Default for LayerMask {
+    fn default() -> Self {
+        LayerMask::DEFAULT
+    }
+}
+
+/// A request to draw a mesh with a material, submitted through
+/// [`RenderContext::draw`](crate::RenderContext::draw).
+#[derive(Clone)]
+pub struct DrawItem {
+    pub mesh: MeshResource,
+    pub material: MaterialResource,
+    pub world_matrix: Matrix4<f32>,
+    /// Items with a smaller key are drawn first within a phase.
+    pub sort_key: i32,
+    pub layer_mask: LayerMask,
+}
+
+impl DrawItem {
+    pub fn new(mesh: MeshResource, material: MaterialResource, world_matrix: Matrix4<f32>) -> Self {
+        Self {
+            mesh,
+            material,
+            world_matrix,
+            sort_key: 0,
+            layer_mask: LayerMask::DEFAULT,
+        }
+    }
+
+    pub fn with_sort_key(mut self, sort_key: i32) -> Self {
+        self.sort_key = sort_key;
+        self
+    }
+
+    pub fn with_layer_mask(mut self, layer_mask: LayerMask) -> Self {
+        self.layer_mask = layer_mask;
+        self
+    }
+}
@@ -0,0 +1,439 @@
+mod draw_item;
+mod render_view;
+mod tracked_render_pass;
+
+use std::ops::Range;
+
+use draft_graphics::{VertexAttribute, VertexFormat, VertexStepMode};
+use draft_material::AlphaMode;
+use draft_mesh::{Mesh, VertexBufferLayout};
+use wgpu::IndexFormat;
+
+pub use draw_item::*;
+pub use render_view::*;
+pub use tracked_render_pass::*;
+
+use crate::{
+    frame_graph::{RenderPassExt, ResourceRead, ResourceRef, TransientBuffer},
+    render_world::ResourceId,
+};
+
+/// Size of the per-instance data, a column-major world matrix.
+pub const INSTANCE_BUFFER_STRIDE: u64 = 64;
+
+/// Vertex buffer slot of the instance buffer, the mesh uses slot 0.
+pub const INSTANCE_BUFFER_SLOT: u32 = 1;
+
+/// Layout of the instance buffer of a view. The world matrix takes four consecutive
+/// shader locations starting at `shader_location`.
+pub fn instance_buffer_layout(shader_location: u32) -> VertexBufferLayout {
+    VertexBufferLayout {
+        array_stride: INSTANCE_BUFFER_STRIDE,
+        step_mode: VertexStepMode::Instance,
+        attributes: (0..4)
+            .map(|column| VertexAttribute {
+                format: VertexFormat::Float32x4,
+                offset: column as u64 * 16,
+                shader_location: shader_location + column,
+            })
+            .collect(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhaseItem {
+    /// Index of the item in [`RenderWorld::draw_items`](crate::render_world::RenderWorld::draw_items).
+    pub draw_item: usize,
+    pub mesh: ResourceId<Mesh>,
+    /// Key of the material shader. Together with the mesh layout it selects the pipeline.
+    pub shader: u64,
+    /// Key of the material resource.
+    pub material: u64,
+    pub sort_key: i32,
+    /// Distance from the camera along the view direction.
+    pub depth: f32,
+    /// Index of the world matrix of the item in the instance buffer of the view.
+    pub instance_index: u32,
+}
+
+impl PhaseItem {
+    fn state_key(&self) -> (u64, u64, usize) {
+        (self.shader, self.material, self.mesh.slot)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PhaseKind {
+    Opaque,
+    AlphaMask,
+    Transparent,
+}
+
+impl From<AlphaMode> for PhaseKind {
+    fn from(alpha_mode: AlphaMode) -> Self {
+        match alpha_mode {
+            AlphaMode::Opaque => PhaseKind::Opaque,
+            AlphaMode::Mask(_) => PhaseKind::AlphaMask,
+            AlphaMode::Blend => PhaseKind::Transparent,
+        }
+    }
+}
+
+/// Consecutive phase items that share a shader, material and mesh, drawn with a
+/// single instanced draw call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrawBatch {
+    /// The first item of the batch.
+    pub draw_item: usize,
+    pub mesh: ResourceId<Mesh>,
+    pub shader: u64,
+    pub material: u64,
+    pub instances: Range<u32>,
+}
+
+pub struct IndexBufferRef {
+    pub buffer: ResourceRef<TransientBuffer, ResourceRead>,
+    pub size: u64,
+    pub format: IndexFormat,
+    pub count: u32,
+    /// Index of the first index of the mesh in the buffer.
+    pub first_index: u32,
+}
+
+/// Mesh buffers imported into the pass that draws a batch.
+pub struct MeshBufferRefs {
+    pub vertex_buffer: ResourceRef<TransientBuffer, ResourceRead>,
+    pub vertex_buffer_size: u64,
+    pub vertex_count: u32,
+    /// Index of the first vertex of the mesh in the buffer.
+    pub base_vertex: u32,
+    pub index_buffer: Option<IndexBufferRef>,
+}
+
+impl MeshBufferRefs {
+    /// Binds the vertex and index buffers of the mesh along with the byte range of an
+    /// instance buffer that holds the instances.
+    pub fn bind<P: RenderPassExt>(
+        &self,
+        render_pass: &mut P,
+        instance_buffer: &ResourceRef<TransientBuffer, ResourceRead>,
+        instance_range: Range<u64>,
+    ) {
+        render_pass.set_vertex_buffer(0, &self.vertex_buffer, 0, self.vertex_buffer_size);
+        render_pass.set_vertex_buffer(
+            INSTANCE_BUFFER_SLOT,
+            instance_buffer,
+            instance_range.start,
+            instance_range.end - instance_range.start,
+        );
+
+        if let Some(index_buffer) = &self.index_buffer {
+            render_pass.set_index_buffer(
+                &index_buffer.buffer,
+                index_buffer.format,
+                0,
+                index_buffer.size,
+            );
+        }
+    }
+
+    /// Whether both meshes are drawn from the same buffers.
+    pub fn shares_buffers(&self, other: &MeshBufferRefs) -> bool {
+        self.vertex_buffer.raw == other.vertex_buffer.raw
+            && match (&self.index_buffer, &other.index_buffer) {
+                (Some(a), Some(b)) => a.buffer.raw == b.buffer.raw && a.format == b.format,
+                (None, None) => true,
+                _ => false,
+            }
+    }
+}
+
+impl DrawBatch {
+    /// Binds the pipeline and buffers of the batch and records its draw call.
+    pub fn render<P: RenderPassExt>(
+        &self,
+        render_pass: &mut P,
+        pipeline_id: usize,
+        mesh: &MeshBufferRefs,
+        instance_buffer: &ResourceRef<TransientBuffer, ResourceRead>,
+        instance_range: Range<u64>,
+    ) {
+        render_pass.set_render_pipeline(pipeline_id);
+        mesh.bind(render_pass, instance_buffer, instance_range);
+
+        match &mesh.index_buffer {
+            Some(index_buffer) => {
+                let first_index = index_buffer.first_index;
+                render_pass.draw_indexed(
+                    first_index..first_index + index_buffer.count,
+                    mesh.base_vertex as i32,
+                    self.instances.clone(),
+                );
+            }
+            None => {
+                let base_vertex = mesh.base_vertex;
+                render_pass.draw(
+                    base_vertex..base_vertex + mesh.vertex_count,
+                    self.instances.clone(),
+                );
+            }
+        }
+    }
+}
+
+pub struct RenderPhase {
+    kind: PhaseKind,
+    items: Vec<PhaseItem>,
+}
+
+impl RenderPhase {
+    pub fn new(kind: PhaseKind) -> Self {
+        Self {
+            kind,
+            items: vec![],
+        }
+    }
+
+    pub fn kind(&self) -> PhaseKind {
+        self.kind
+    }
+
+    pub fn add(&mut self, item: PhaseItem) {
+        self.items.push(item);
+    }
+
+    pub fn items(&self) -> &[PhaseItem] {
+        &self.items
+    }
+
+    pub fn items_mut(&mut self) -> &mut [PhaseItem] {
+        &mut self.items
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    /// Orders the items by their sort key first. Opaque and alpha-mask items are then
+    /// grouped by shader, material and mesh to reduce state changes, and drawn front to
+    /// back within a group. Transparent items are drawn back to front.
+    pub fn sort(&mut self) {
+        match self.kind {
+            PhaseKind::Opaque | PhaseKind::AlphaMask => self.items.sort_by(|a, b| {
+                a.sort_key
+                    .cmp(&b.sort_key)
+                    .then_with(|| a.state_key().cmp(&b.state_key()))
+                    .then_with(|| a.depth.total_cmp(&b.depth))
+            }),
+            PhaseKind::Transparent => self.items.sort_by(|a, b| {
+                a.sort_key
+                    .cmp(&b.sort_key)
+                    .then_with(|| b.depth.total_cmp(&a.depth))
+                    .then_with(|| a.state_key().cmp(&b.state_key()))
+            }),
+        }
+    }
+
+    /// Merges consecutive items with the same shader, material and mesh whose instances
+    /// are adjacent in the instance buffer.
+    pub fn batches(&self) -> Vec<DrawBatch> {
+        let mut batches: Vec<DrawBatch> = vec![];
+
+        for item in self.items.iter() {
+            if let Some(batch) = batches.last_mut()
+                && batch.mesh == item.mesh
+                && batch.shader == item.shader
+                && batch.material == item.material
+                && batch.instances.end == item.instance_index
+            {
+                batch.instances.end += 1;
+                continue;
+            }
+
+            batches.push(DrawBatch {
+                draw_item: item.draw_item,
+                mesh: item.mesh,
+                shader: item.shader,
+                material: item.material,
+                instances: item.instance_index..item.instance_index + 1,
+            });
+        }
+
+        batches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame_graph::{
+        Index, RawResourceHandle, RenderPassCommand, TransientBufferDescriptor,
+    };
+
+    fn item(draw_item: usize, mesh: usize, material: u64, depth: f32) -> PhaseItem {
+        PhaseItem {
+            draw_item,
+            mesh: ResourceId::new(mesh),
+            shader: 0,
+            material,
+            sort_key: 0,
+            depth,
+            instance_index: 0,
+        }
+    }
+
+    fn assign_instances(phase: &mut RenderPhase) {
+        for (index, item) in phase.items_mut().iter_mut().enumerate() {
+            item.instance_index = index as u32;
+        }
+    }
+
+    fn draw_items(phase: &RenderPhase) -> Vec<usize> {
+        phase.items().iter().map(|item| item.draw_item).collect()
+    }
+
+    #[test]
+    fn opaque_items_are_grouped_by_state_then_front_to_back() {
+        let mut phase = RenderPhase::new(PhaseKind::Opaque);
+        phase.add(item(0, 1, 0, 5.0));
+        phase.add(item(1, 0, 0, 9.0));
+        phase.add(item(2, 1, 0, 1.0));
+        phase.add(item(3, 0, 0, 2.0));
+
+        phase.sort();
+
+        assert_eq!(draw_items(&phase), vec![3, 1, 2, 0]);
+    }
+
+    #[test]
+    fn transparent_items_are_drawn_back_to_front() {
+        let mut phase = RenderPhase::new(PhaseKind::Transparent);
+        phase.add(item(0, 0, 0, 1.0));
+        phase.add(item(1, 1, 0, 3.0));
+        phase.add(item(2, 0, 0, 2.0));
+
+        phase.sort();
+
+        assert_eq!(draw_items(&phase), vec![1, 2, 0]);
+    }
+
+    #[test]
+    fn sort_key_takes_precedence() {
+        let mut phase = RenderPhase::new(PhaseKind::Transparent);
+        phase.add(item(0, 0, 0, 10.0));
+        let mut first = item(1, 0, 0, 1.0);
+        first.sort_key = -1;
+        phase.add(first);
+
+        phase.sort();
+
+        assert_eq!(draw_items(&phase), vec![1, 0]);
+    }
+
+    #[test]
+    fn items_sharing_mesh_and_material_are_batched() {
+        let mut phase = RenderPhase::new(PhaseKind::Opaque);
+        phase.add(item(0, 0, 0, 1.0));
+        phase.add(item(1, 1, 0, 1.0));
+        phase.add(item(2, 0, 0, 2.0));
+        phase.add(item(3, 0, 1, 3.0));
+        phase.add(item(4, 0, 0, 3.0));
+
+        phase.sort();
+        assign_instances(&mut phase);
+
+        let batches = phase.batches();
+
+        assert_eq!(
+            batches
+                .iter()
+                .map(|batch| (batch.draw_item, batch.instances.clone()))
+                .collect::<Vec<_>>(),
+            vec![(0, 0..3), (1, 3..4), (3, 4..5)]
+        );
+    }
+
+    #[test]
+    fn transparent_batches_keep_draw_order() {
+        let mut phase = RenderPhase::new(PhaseKind::Transparent);
+        phase.add(item(0, 0, 0, 3.0));
+        phase.add(item(1, 1, 0, 2.0));
+        phase.add(item(2, 0, 0, 1.0));
+
+        phase.sort();
+        assign_instances(&mut phase);
+
+        assert_eq!(phase.batches().len(), 3);
+    }
+
+    #[derive(Default)]
+    struct CommandCounter(usize);
+
+    impl RenderPassExt for CommandCounter {
+        fn push<T: RenderPassCommand>(&mut self, _value: T) {
+            self.0 += 1;
+        }
+    }
+
+    #[test]
+    fn batches_skip_redundant_state_changes() {
+        let buffer = |slot| {
+            ResourceRef::new(
+                RawResourceHandle {
+                    index: Index::new(slot),
+                    version: 0,
+                },
+                TransientBufferDescriptor::External,
+            )
+        };
+
+        let mesh = MeshBufferRefs {
+            vertex_buffer: buffer(0),
+            vertex_buffer_size: 64,
+            vertex_count: 3,
+            base_vertex: 0,
+            index_buffer: None,
+        };
+        let instance_buffer = buffer(1);
+
+        let mut phase = RenderPhase::new(PhaseKind::Opaque);
+        phase.add(item(0, 0, 0, 1.0));
+        phase.add(item(1, 0, 1, 1.0));
+        assign_instances(&mut phase);
+
+        let mut counter = CommandCounter::default();
+        let mut render_pass = TrackedRenderPass::new(&mut counter);
+
+        // Meshes allocated from the same slab keep the buffers bound.
+        let slab_neighbour = MeshBufferRefs {
+            vertex_buffer: buffer(0),
+            vertex_buffer_size: 64,
+            vertex_count: 3,
+            base_vertex: 3,
+            index_buffer: None,
+        };
+        assert!(slab_neighbour.shares_buffers(&mesh));
+
+        for batch in phase.batches() {
+            batch.render(&mut render_pass, 0, &mesh, &instance_buffer, 0..128);
+        }
+        phase.batches()[0].render(
+            &mut render_pass,
+            0,
+            &slab_neighbour,
+            &instance_buffer,
+            0..128,
+        );
+
+        // Pipeline, two vertex buffers and a draw, then only the other two draws.
+        assert_eq!(counter.0, 6);
+    }
+}
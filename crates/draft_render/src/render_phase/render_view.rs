@@ -0,0 +1,430 @@
+use std::{num::NonZeroU64, ops::Range};
+
+use bytemuck::cast_slice;
+use draft_graphics::{BindGroup, BindGroupLayout, Color, RenderDevice, TextureFormat, TextureView};
+use draft_mesh::{Mesh, MeshBounds};
+use fyrox_resource::core::algebra::{Matrix4, Vector3, Vector4};
+use wgpu::{Extent3d, LoadOp, Operations, ShaderStages, StoreOp, TextureDimension, TextureUsages};
+
+use crate::{
+    camera::{Camera, Frustum, Msaa, PhysicalViewport, Projection, RenderTarget, ViewUniform},
+    frame_graph::{
+        ExternalBuffer, FrameGraph, ManualTextureDescriptor, PassNodeBuilderExt, RenderPassExt,
+        TransientRenderPassColorAttachment, TransientTextureDescriptor, TransientTextureViewHandle,
+        TransientTextureViewHandleDescriptor,
+    },
+    post_process::PostProcessSettings,
+    render_phase::{DrawItem, LayerMask, PhaseItem, PhaseKind, RenderPhase},
+    render_world::{BindGroupCache, DynamicBufferAllocator, ResourceId},
+};
+
+/// Bind group index of the view uniform in every view pass.
+pub const VIEW_BIND_GROUP: u32 = 0;
+
+/// Format of the texture that views with post-processing draw into.
+pub const HDR_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// Entries of the view uniform bind group layout shared by all view pipelines.
+pub fn view_bind_group_layout_entries() -> [wgpu::BindGroupLayoutEntry; 1] {
+    [wgpu::BindGroupLayoutEntry {
+        binding: 0,
+        visibility: ShaderStages::VERTEX_FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: true,
+            min_binding_size: NonZeroU64::new(size_of::<ViewUniform>() as u64),
+        },
+        count: None,
+    }]
+}
+
+/// The texture a view renders into this frame.
+#[derive(Clone)]
+pub struct ViewTarget {
+    /// Name of the target, the same for every view that renders into it.
+    pub label: String,
+    pub view: TextureView,
+    pub format: TextureFormat,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// The textures the passes of a view draw into, see [`RenderView::main_texture`].
+#[derive(Clone)]
+pub struct MainTexture {
+    /// The HDR texture of views with post-processing, the view target otherwise.
+    pub texture: TransientTextureViewHandle,
+    /// Drawn into by multisampled views and resolved into `texture` by every pass.
+    pub multisampled: Option<TransientTextureViewHandle>,
+}
+
+/// The draw items visible to a single camera, along with their instance data and the
+/// view uniform.
+pub struct RenderView {
+    /// Unique name of the view within the frame, used to name its passes.
+    pub label: String,
+    pub target: RenderTarget,
+    pub view_target: ViewTarget,
+    pub viewport: PhysicalViewport,
+    pub clear_color: Option<Color>,
+    pub priority: i32,
+    pub layer_mask: LayerMask,
+    pub render_pipeline: String,
+    pub projection: Projection,
+    /// Multisampling of the view, after falling back to what its formats support.
+    pub msaa: Msaa,
+    pub post_process: Option<PostProcessSettings>,
+    /// Whether the opaque and alpha-mask items of the view are culled on the GPU,
+    /// after falling back to CPU culling on devices that don't support it.
+    pub gpu_culling: bool,
+    pub view_matrix: Matrix4<f32>,
+    pub uniform: ViewUniform,
+    pub bind_group: Option<BindGroup>,
+    /// Dynamic offset of the view uniform in the buffer of the bind group.
+    pub uniform_offset: u32,
+    pub opaque_phase: RenderPhase,
+    pub alpha_mask_phase: RenderPhase,
+    pub transparent_phase: RenderPhase,
+    /// Opaque and alpha-mask items outside of the frustum that may still cast shadows
+    /// into it. Only drawn into shadow maps.
+    pub shadow_caster_phase: RenderPhase,
+    /// Number of items that passed the layer mask but are outside of the frustum.
+    pub culled_count: usize,
+    /// Buffer of the storage allocator that holds the instances of the view.
+    pub instance_buffer: Option<ExternalBuffer>,
+    /// Byte range of the instances of the view in `instance_buffer`.
+    pub instance_range: Range<u64>,
+}
+
+impl RenderView {
+    pub fn new(label: String, camera: &Camera, view_target: ViewTarget) -> Self {
+        let viewport = camera
+            .viewport
+            .physical(view_target.width, view_target.height);
+        let uniform = ViewUniform::new(camera, &viewport);
+
+        Self {
+            label,
+            target: camera.target.clone(),
+            view_target,
+            viewport,
+            clear_color: camera.clear_color,
+            priority: camera.priority,
+            layer_mask: camera.layer_mask,
+            render_pipeline: camera.render_pipeline.clone(),
+            projection: camera.projection,
+            msaa: Msaa::Off,
+            post_process: camera.post_process.clone(),
+            gpu_culling: false,
+            view_matrix: Matrix4::from(uniform.view),
+            uniform,
+            bind_group: None,
+            uniform_offset: 0,
+            opaque_phase: RenderPhase::new(PhaseKind::Opaque),
+            alpha_mask_phase: RenderPhase::new(PhaseKind::AlphaMask),
+            transparent_phase: RenderPhase::new(PhaseKind::Transparent),
+            shadow_caster_phase: RenderPhase::new(PhaseKind::Opaque),
+            culled_count: 0,
+            instance_buffer: None,
+            instance_range: 0..0,
+        }
+    }
+
+    pub fn phase(&self, kind: PhaseKind) -> &RenderPhase {
+        match kind {
+            PhaseKind::Opaque => &self.opaque_phase,
+            PhaseKind::AlphaMask => &self.alpha_mask_phase,
+            PhaseKind::Transparent => &self.transparent_phase,
+        }
+    }
+
+    pub fn phase_mut(&mut self, kind: PhaseKind) -> &mut RenderPhase {
+        match kind {
+            PhaseKind::Opaque => &mut self.opaque_phase,
+            PhaseKind::AlphaMask => &mut self.alpha_mask_phase,
+            PhaseKind::Transparent => &mut self.transparent_phase,
+        }
+    }
+
+    fn phases_mut(&mut self) -> [&mut RenderPhase; 4] {
+        [
+            &mut self.opaque_phase,
+            &mut self.alpha_mask_phase,
+            &mut self.transparent_phase,
+            &mut self.shadow_caster_phase,
+        ]
+    }
+
+    /// Number of items in the phases drawn by the camera.
+    pub fn visible_count(&self) -> usize {
+        self.opaque_phase.len() + self.alpha_mask_phase.len() + self.transparent_phase.len()
+    }
+
+    /// Format of the texture the passes of the view draw into.
+    pub fn main_format(&self) -> TextureFormat {
+        match self.post_process {
+            Some(_) => HDR_FORMAT,
+            None => self.view_target.format,
+        }
+    }
+
+    /// The textures the passes of the view draw into. Views with post-processing draw
+    /// into an HDR texture, other views draw into their target. Multisampled views
+    /// draw into a multisampled texture, that of views without post-processing is
+    /// shared by the views of the target. Textures are shared by the passes of the
+    /// frame through their names.
+    pub fn main_texture(&self, frame_graph: &mut FrameGraph) -> MainTexture {
+        let texture = match self.post_process {
+            Some(_) => self.color_texture(frame_graph, format!("{}_hdr", self.label), 1),
+            None => TransientTextureViewHandle::TextureView(self.view_target.view.clone()),
+        };
+
+        let multisampled = self.msaa.is_enabled().then(|| {
+            let name = match self.post_process {
+                Some(_) => format!("{}_msaa", self.label),
+                None => format!("{}_msaa_{}", self.view_target.label, self.msaa.samples()),
+            };
+            self.color_texture(frame_graph, name, self.msaa.samples())
+        });
+
+        MainTexture {
+            texture,
+            multisampled,
+        }
+    }
+
+    fn color_texture(
+        &self,
+        frame_graph: &mut FrameGraph,
+        name: String,
+        sample_count: u32,
+    ) -> TransientTextureViewHandle {
+        let usage = match sample_count {
+            1 => TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            _ => TextureUsages::RENDER_ATTACHMENT,
+        };
+
+        let texture = frame_graph.get_or_create(
+            &name,
+            TransientTextureDescriptor::Manual(ManualTextureDescriptor {
+                label: Some(name.clone()),
+                size: Extent3d {
+                    width: self.view_target.width,
+                    height: self.view_target.height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count,
+                dimension: TextureDimension::D2,
+                format: self.main_format(),
+                usage,
+            }),
+        );
+
+        TransientTextureViewHandle::Descriptor(TransientTextureViewHandleDescriptor {
+            texture,
+            desc: Default::default(),
+        })
+    }
+
+    /// A color attachment of the main texture that keeps its contents. Clearing is done
+    /// once per view by the [`ClearNode`](crate::render_pipeline::ClearNode).
+    pub fn color_attachment<B: PassNodeBuilderExt>(
+        &self,
+        builder: &mut B,
+        main_texture: &MainTexture,
+    ) -> TransientRenderPassColorAttachment {
+        self.color_attachment_with_load(builder, main_texture, LoadOp::Load)
+    }
+
+    /// A color attachment of the main texture. Multisampled attachments are resolved
+    /// into the main texture at the end of the pass, overwriting it.
+    pub fn color_attachment_with_load<B: PassNodeBuilderExt>(
+        &self,
+        builder: &mut B,
+        main_texture: &MainTexture,
+        load: LoadOp<Color>,
+    ) -> TransientRenderPassColorAttachment {
+        let drawn_texture = main_texture
+            .multisampled
+            .as_ref()
+            .unwrap_or(&main_texture.texture);
+
+        // Loading the drawn texture depends on the passes that drew into it before.
+        if let (LoadOp::Load, TransientTextureViewHandle::Descriptor(drawn_texture)) =
+            (load, drawn_texture)
+        {
+            builder.read(drawn_texture.texture.clone());
+        }
+
+        let view = builder.write_texture_handle(drawn_texture);
+        let resolve_target = main_texture
+            .multisampled
+            .as_ref()
+            .map(|_| builder.write_texture_handle(&main_texture.texture));
+
+        TransientRenderPassColorAttachment {
+            view,
+            depth_slice: None,
+            resolve_target,
+            ops: Operations {
+                load,
+                store: StoreOp::Store,
+            },
+        }
+    }
+
+    /// Restricts drawing to the viewport of the view and binds the view uniform.
+    pub fn begin<P: RenderPassExt>(&self, render_pass: &mut P) {
+        render_pass.set_viewport(
+            self.viewport.x as f32,
+            self.viewport.y as f32,
+            self.viewport.width as f32,
+            self.viewport.height as f32,
+            0.0,
+            1.0,
+        );
+
+        if let Some(bind_group) = &self.bind_group {
+            render_pass.set_gpu_bind_group(VIEW_BIND_GROUP, bind_group, &[self.uniform_offset]);
+        }
+    }
+
+    /// Sorts the visible items whose mesh and material are ready into the phase that
+    /// matches the alpha mode of their material.
+    ///
+    /// `bounds` are the world space bounds of the items, items outside of the frustum
+    /// of the view are culled. When `shadow_caster_distance` is set, culled opaque and
+    /// alpha-mask items within that distance of the camera go into the shadow caster
+    /// phase instead. Views with GPU culling only cull their transparent items here.
+    pub fn queue(
+        &mut self,
+        draw_items: &[DrawItem],
+        meshes: &[Option<ResourceId<Mesh>>],
+        bounds: &[Option<MeshBounds>],
+        shadow_caster_distance: Option<f32>,
+    ) {
+        let frustum = Frustum::from_view_projection(&Matrix4::from(self.uniform.view_projection));
+        let [x, y, z, _] = self.uniform.world_position;
+        let camera_position = Vector3::new(x, y, z);
+
+        for (index, ((draw_item, mesh), bounds)) in draw_items
+            .iter()
+            .zip(meshes.iter())
+            .zip(bounds.iter())
+            .enumerate()
+        {
+            let Some(mesh) = mesh else {
+                continue;
+            };
+
+            if !draw_item.layer_mask.intersects(&self.layer_mask) || !draw_item.material.is_ok() {
+                continue;
+            }
+
+            let material = draw_item.material.data_ref();
+            let kind = PhaseKind::from(material.alpha_mode());
+
+            let is_visible = (self.gpu_culling && kind != PhaseKind::Transparent)
+                || bounds.is_none_or(|bounds| frustum.intersects(&bounds));
+            let is_shadow_caster = !is_visible
+                && kind != PhaseKind::Transparent
+                && bounds
+                    .zip(shadow_caster_distance)
+                    .is_some_and(|(bounds, distance)| {
+                        // Distance from the camera to the surface of the bounding sphere.
+                        (bounds.sphere.center - camera_position).norm() - bounds.sphere.radius
+                            <= distance
+                    });
+
+            if !is_visible {
+                self.culled_count += 1;
+
+                if !is_shadow_caster {
+                    continue;
+                }
+            }
+
+            let position =
+                self.view_matrix * draw_item.world_matrix * Vector4::new(0.0, 0.0, 0.0, 1.0);
+
+            let item = PhaseItem {
+                draw_item: index,
+                mesh: *mesh,
+                shader: material.shader().key(),
+                material: draw_item.material.key(),
+                sort_key: draw_item.sort_key,
+                // The camera looks along the negative z axis in view space.
+                depth: -position.z,
+                instance_index: 0,
+            };
+
+            if is_visible {
+                self.phase_mut(kind).add(item);
+            } else {
+                self.shadow_caster_phase.add(item);
+            }
+        }
+
+        for phase in self.phases_mut() {
+            phase.sort();
+        }
+    }
+
+    /// Writes the world matrices of the phase items into a slice of the storage
+    /// allocator. Items are written in phase order, so batches get adjacent instances.
+    /// Views with GPU culling also bind the slice to the culling pass.
+    pub fn prepare_instances(
+        &mut self,
+        draw_items: &[DrawItem],
+        storage_allocator: &mut DynamicBufferAllocator,
+        device: &RenderDevice,
+    ) {
+        self.instance_buffer = None;
+        self.instance_range = 0..0;
+
+        let mut data = vec![];
+        let mut instance_index = 0;
+
+        for phase in self.phases_mut() {
+            for item in phase.items_mut() {
+                item.instance_index = instance_index;
+                instance_index += 1;
+                data.extend_from_slice(draw_items[item.draw_item].world_matrix.as_slice());
+            }
+        }
+
+        if data.is_empty() {
+            return;
+        }
+
+        let instances = storage_allocator.allocate(cast_slice(&data), device);
+
+        let offset = instances.offset as u64;
+        self.instance_range = offset..offset + instances.size;
+        self.instance_buffer = Some(ExternalBuffer::new("instance_buffer", instances.buffer));
+    }
+
+    /// Uploads the view uniform and creates the bind group passes bind at
+    /// [`VIEW_BIND_GROUP`].
+    pub fn prepare_uniform(
+        &mut self,
+        device: &RenderDevice,
+        layout: &BindGroupLayout,
+        uniform_allocator: &mut DynamicBufferAllocator,
+        bind_group_cache: &mut BindGroupCache,
+    ) {
+        let uniform = uniform_allocator.allocate_value(&self.uniform, device);
+
+        self.bind_group = Some(bind_group_cache.get_or_create(
+            "view_bind_group",
+            layout,
+            &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform.binding(),
+            }],
+            device,
+        ));
+        self.uniform_offset = uniform.offset;
+    }
+}
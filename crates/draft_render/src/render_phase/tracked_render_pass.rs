@@ -0,0 +1,83 @@
+use wgpu::IndexFormat;
+
+use crate::frame_graph::{
+    RawResourceHandle, RenderPassCommand, RenderPassExt, ResourceRead, ResourceRef, TransientBuffer,
+};
+
+type BufferBinding = (RawResourceHandle, u64, u64);
+
+/// Wraps a render pass and drops commands that would bind the state that is already
+/// bound.
+pub struct TrackedRenderPass<'a, P: RenderPassExt> {
+    render_pass: &'a mut P,
+    pipeline: Option<usize>,
+    vertex_buffers: Vec<Option<BufferBinding>>,
+    index_buffer: Option<(BufferBinding, IndexFormat)>,
+}
+
+impl<'a, P: RenderPassExt> TrackedRenderPass<'a, P> {
+    pub fn new(render_pass: &'a mut P) -> Self {
+        Self {
+            render_pass,
+            pipeline: None,
+            vertex_buffers: vec![],
+            index_buffer: None,
+        }
+    }
+}
+
+impl<P: RenderPassExt> RenderPassExt for TrackedRenderPass<'_, P> {
+    fn push<T: RenderPassCommand>(&mut self, value: T) {
+        self.render_pass.push(value);
+    }
+
+    fn set_render_pipeline(&mut self, pipeline_id: usize) {
+        if self.pipeline == Some(pipeline_id) {
+            return;
+        }
+
+        self.pipeline = Some(pipeline_id);
+        self.render_pass.set_render_pipeline(pipeline_id);
+    }
+
+    fn set_vertex_buffer(
+        &mut self,
+        slot: u32,
+        buffer_ref: &ResourceRef<TransientBuffer, ResourceRead>,
+        offset: u64,
+        size: u64,
+    ) {
+        let binding = Some((buffer_ref.raw.clone(), offset, size));
+        let slot_index = slot as usize;
+
+        if self.vertex_buffers.get(slot_index) == Some(&binding) {
+            return;
+        }
+
+        if self.vertex_buffers.len() <= slot_index {
+            self.vertex_buffers.resize(slot_index + 1, None);
+        }
+
+        self.vertex_buffers[slot_index] = binding;
+        self.render_pass
+            .set_vertex_buffer(slot, buffer_ref, offset, size);
+    }
+
+    fn set_index_buffer(
+        &mut self,
+        buffer_ref: &ResourceRef<TransientBuffer, ResourceRead>,
+        index_format: IndexFormat,
+        offset: u64,
+        size: u64,
+    ) {
+        let binding = ((buffer_ref.raw.clone(), offset, size), index_format);
+
+        if self.index_buffer.as_ref() == Some(&binding) {
+            return;
+        }
+
+        self.index_buffer = Some(binding);
+        self.render_pass
+            .set_index_buffer(buffer_ref, index_format, offset, size);
+    }
+}
@@ -0,0 +1,40 @@
+use wgpu::LoadOp;
+
+use crate::{
+    FrameworkError,
+    render_pipeline::{Node, RenderPipelineRunContext, SlotLabel},
+};
+
+/// Slot written by the nodes that draw into the view targets.
+pub const VIEW_TARGET: SlotLabel = "view_target";
+
+/// Clears the target of the view when it has a clear color.
+pub struct ClearNode;
+
+impl Node for ClearNode {
+    fn outputs(&self) -> Vec<SlotLabel> {
+        vec![VIEW_TARGET]
+    }
+
+    fn run(&self, context: &mut RenderPipelineRunContext) -> Result<(), FrameworkError> {
+        let view = &context.render_world.views()[context.view_index];
+
+        let Some(clear_color) = view.clear_color else {
+            return Ok(());
+        };
+
+        let main_texture = view.main_texture(context.frame_graph);
+        let pass_name = format!("{}_clear", view.label);
+        let mut pass_builder = context.frame_graph.create_pass_builder(&pass_name);
+        let mut render_pass_builder = pass_builder.create_render_pass_builder(&pass_name);
+
+        let color_attachment = view.color_attachment_with_load(
+            &mut render_pass_builder,
+            &main_texture,
+            LoadOp::Clear(clear_color),
+        );
+        render_pass_builder.add_color_attachment(color_attachment);
+
+        Ok(())
+    }
+}
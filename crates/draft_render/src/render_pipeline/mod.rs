@@ -0,0 +1,341 @@
+mod clear_node;
+mod node;
+
+use std::{cmp::Reverse, collections::BinaryHeap, collections::HashMap};
+
+pub use clear_node::*;
+pub use node::*;
+
+use crate::FrameworkError;
+
+#[derive(Default)]
+pub struct RenderPipelineContainer {
+    pipelines: HashMap<String, RenderPipeline>,
+}
+
+impl RenderPipelineContainer {
+    pub fn insert(&mut self, name: &str, pipeline: RenderPipeline) {
+        self.pipelines.insert(name.to_string(), pipeline);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&RenderPipeline> {
+        self.pipelines.get(name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut RenderPipeline> {
+        self.pipelines.get_mut(name)
+    }
+}
+
+pub struct RenderPipelineNode {
+    pub name: String,
+    pub node: Box<dyn Node>,
+}
+
+struct SlotDependencies {
+    /// Nodes that read what each node writes.
+    dependents: Vec<Vec<usize>>,
+    /// Number of writers each node waits for.
+    in_degrees: Vec<usize>,
+    /// Inputs that no node of the pipeline writes before they are read.
+    unsatisfied: Vec<(usize, SlotLabel)>,
+}
+
+#[derive(Default)]
+pub struct RenderPipeline {
+    nodes: Vec<RenderPipelineNode>,
+}
+
+impl RenderPipeline {
+    pub fn add_node<N: Node>(&mut self, name: &str, node: N) -> &mut Self {
+        self.add_boxed_node(name, Box::new(node))
+    }
+
+    pub fn add_boxed_node(&mut self, name: &str, node: Box<dyn Node>) -> &mut Self {
+        self.nodes.push(RenderPipelineNode {
+            name: name.to_string(),
+            node,
+        });
+        self
+    }
+
+    pub fn add_sub_pipeline(&mut self, name: &str, pipeline: RenderPipeline) -> &mut Self {
+        self.add_node(name, pipeline)
+    }
+
+    pub fn remove_node(&mut self, name: &str) -> Option<Box<dyn Node>> {
+        let index = self.nodes.iter().position(|node| node.name == name)?;
+        Some(self.nodes.remove(index).node)
+    }
+
+    pub fn contains_node(&self, name: &str) -> bool {
+        self.nodes.iter().any(|node| node.name == name)
+    }
+
+    pub fn get_node<N: Node>(&self, name: &str) -> Option<&N> {
+        self.nodes
+            .iter()
+            .find(|node| node.name == name)
+            .and_then(|node| node.node.downcast_ref::<N>())
+    }
+
+    pub fn get_node_mut<N: Node>(&mut self, name: &str) -> Option<&mut N> {
+        self.nodes
+            .iter_mut()
+            .find(|node| node.name == name)
+            .and_then(|node| node.node.downcast_mut::<N>())
+    }
+
+    pub fn get_sub_pipeline_mut(&mut self, name: &str) -> Option<&mut RenderPipeline> {
+        self.get_node_mut::<RenderPipeline>(name)
+    }
+
+    /// Returns the node names in execution order. Inputs that no node of the pipeline
+    /// writes are left to the enclosing pipeline, see [`Node::inputs`].
+    pub fn node_names(&self) -> Result<Vec<&str>, FrameworkError> {
+        Ok(self
+            .sorted_nodes(false)?
+            .into_iter()
+            .map(|index| self.nodes[index].name.as_str())
+            .collect())
+    }
+
+    /// Links every input to the nodes that write it before it is read. A node that
+    /// also writes the slot reads what the nodes added before it wrote.
+    fn dependencies(&self) -> SlotDependencies {
+        let slots = self
+            .nodes
+            .iter()
+            .map(|node| (node.node.inputs(), node.node.outputs()))
+            .collect::<Vec<_>>();
+
+        let mut writers: HashMap<SlotLabel, Vec<usize>> = HashMap::new();
+        for (index, (_, outputs)) in slots.iter().enumerate() {
+            for output in outputs {
+                writers.entry(output).or_default().push(index);
+            }
+        }
+
+        let mut dependencies = SlotDependencies {
+            dependents: vec![vec![]; self.nodes.len()],
+            in_degrees: vec![0; self.nodes.len()],
+            unsatisfied: vec![],
+        };
+
+        for (consumer, (inputs, outputs)) in slots.iter().enumerate() {
+            for input in inputs {
+                let writes_input = outputs.contains(input);
+                let producers = writers
+                    .get(input)
+                    .into_iter()
+                    .flatten()
+                    .copied()
+                    .filter(|&producer| {
+                        producer != consumer && (!writes_input || producer < consumer)
+                    })
+                    .collect::<Vec<_>>();
+
+                if producers.is_empty() {
+                    dependencies.unsatisfied.push((consumer, *input));
+                }
+
+                for producer in producers {
+                    dependencies.dependents[producer].push(consumer);
+                    dependencies.in_degrees[consumer] += 1;
+                }
+            }
+        }
+
+        dependencies
+    }
+
+    /// Orders the nodes topologically by their slots. Among the nodes that are ready
+    /// to run, the one added first runs first. With `check_inputs`, every input has
+    /// to be written by a node of the pipeline.
+    fn sorted_nodes(&self, check_inputs: bool) -> Result<Vec<usize>, FrameworkError> {
+        let SlotDependencies {
+            dependents,
+            mut in_degrees,
+            unsatisfied,
+        } = self.dependencies();
+
+        if check_inputs && let Some((node, slot)) = unsatisfied.first() {
+            return Err(FrameworkError::RenderPipelineUnsatisfiedInput {
+                node: self.nodes[*node].name.clone(),
+                slot: slot.to_string(),
+            });
+        }
+
+        let mut ready = (0..self.nodes.len())
+            .filter(|index| in_degrees[*index] == 0)
+            .map(Reverse)
+            .collect::<BinaryHeap<_>>();
+        let mut order = Vec::with_capacity(self.nodes.len());
+
+        while let Some(Reverse(next)) = ready.pop() {
+            order.push(next);
+
+            for &dependent in dependents[next].iter() {
+                in_degrees[dependent] -= 1;
+                if in_degrees[dependent] == 0 {
+                    ready.push(Reverse(dependent));
+                }
+            }
+        }
+
+        if order.len() < self.nodes.len() {
+            let names = (0..self.nodes.len())
+                .filter(|index| in_degrees[*index] > 0)
+                .map(|index| self.nodes[index].name.clone())
+                .collect();
+            return Err(FrameworkError::RenderPipelineCycle(names));
+        }
+
+        Ok(order)
+    }
+
+    /// Runs the nodes in slot order. Every input has to be written by a node of the
+    /// pipeline.
+    pub fn run(&self, context: &mut RenderPipelineRunContext) -> Result<(), FrameworkError> {
+        self.run_nodes(context, true)
+    }
+
+    fn run_nodes(
+        &self,
+        context: &mut RenderPipelineRunContext,
+        check_inputs: bool,
+    ) -> Result<(), FrameworkError> {
+        for index in self.sorted_nodes(check_inputs)? {
+            self.nodes[index].node.run(context)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Node for RenderPipeline {
+    /// Slots read by nodes of the pipeline before any of its nodes writes them.
+    fn inputs(&self) -> Vec<SlotLabel> {
+        let mut inputs = vec![];
+
+        for (_, input) in self.dependencies().unsatisfied {
+            if !inputs.contains(&input) {
+                inputs.push(input);
+            }
+        }
+
+        inputs
+    }
+
+    fn outputs(&self) -> Vec<SlotLabel> {
+        let mut outputs = vec![];
+
+        for node in self.nodes.iter() {
+            for output in node.node.outputs() {
+                if !outputs.contains(&output) {
+                    outputs.push(output);
+                }
+            }
+        }
+
+        outputs
+    }
+
+    /// The enclosing pipeline orders this one after the writers of its inputs.
+    fn run(&self, context: &mut RenderPipelineRunContext) -> Result<(), FrameworkError> {
+        self.run_nodes(context, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct SlotNode {
+        inputs: Vec<SlotLabel>,
+        outputs: Vec<SlotLabel>,
+    }
+
+    impl Node for SlotNode {
+        fn inputs(&self) -> Vec<SlotLabel> {
+            self.inputs.clone()
+        }
+
+        fn outputs(&self) -> Vec<SlotLabel> {
+            self.outputs.clone()
+        }
+
+        fn run(&self, _context: &mut RenderPipelineRunContext) -> Result<(), FrameworkError> {
+            Ok(())
+        }
+    }
+
+    fn node(inputs: &[SlotLabel], outputs: &[SlotLabel]) -> SlotNode {
+        SlotNode {
+            inputs: inputs.to_vec(),
+            outputs: outputs.to_vec(),
+        }
+    }
+
+    fn sorted_names(pipeline: &RenderPipeline) -> Result<Vec<&str>, FrameworkError> {
+        Ok(pipeline
+            .sorted_nodes(true)?
+            .into_iter()
+            .map(|index| pipeline.nodes[index].name.as_str())
+            .collect())
+    }
+
+    #[test]
+    fn readers_run_after_writers() {
+        let mut pipeline = RenderPipeline::default();
+        pipeline
+            .add_node("clear", node(&[], &["target"]))
+            .add_node("main", node(&["depth", "target"], &["target"]))
+            .add_node("depth", node(&[], &["depth"]));
+
+        assert_eq!(sorted_names(&pipeline).unwrap(), ["clear", "depth", "main"]);
+    }
+
+    #[test]
+    fn independent_nodes_keep_insertion_order() {
+        let mut pipeline = RenderPipeline::default();
+        pipeline
+            .add_node("clear", node(&[], &["target"]))
+            .add_node("first", node(&["target"], &["target"]))
+            .add_node("unrelated", node(&[], &["other"]))
+            .add_node("second", node(&["target"], &["target"]));
+
+        assert_eq!(
+            sorted_names(&pipeline).unwrap(),
+            ["clear", "first", "unrelated", "second"]
+        );
+    }
+
+    #[test]
+    fn cycles_are_errors() {
+        let mut pipeline = RenderPipeline::default();
+        pipeline
+            .add_node("clear", node(&[], &["target"]))
+            .add_node("a", node(&["b"], &["a"]))
+            .add_node("b", node(&["a"], &["b"]));
+
+        match sorted_names(&pipeline) {
+            Err(FrameworkError::RenderPipelineCycle(names)) => assert_eq!(names, ["a", "b"]),
+            other => panic!("Expected a cycle, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn unwritten_inputs_are_errors_unless_external() {
+        let mut pipeline = RenderPipeline::default();
+        pipeline.add_node("sprite", node(&["target"], &["target"]));
+
+        assert!(matches!(
+            sorted_names(&pipeline),
+            Err(FrameworkError::RenderPipelineUnsatisfiedInput { node, slot })
+                if node == "sprite" && slot == "target"
+        ));
+        assert_eq!(pipeline.node_names().unwrap(), ["sprite"]);
+        assert_eq!(pipeline.inputs(), ["target"]);
+    }
+}
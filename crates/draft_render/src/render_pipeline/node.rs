@@ -0,0 +1,37 @@
+use std::any::Any;
+
+use downcast_rs::{Downcast, impl_downcast};
+use draft_graphics::RenderServer;
+
+use crate::{FrameworkError, frame_graph::FrameGraph, render_world::RenderWorld};
+
+pub type SlotLabel = &'static str;
+
+pub struct RenderPipelineRunContext<'a> {
+    pub frame_graph: &'a mut FrameGraph,
+    pub render_world: &'a mut RenderWorld,
+    pub render_server: &'a RenderServer,
+    /// Index of the view being rendered in
+    /// [`RenderWorld::views`](crate::render_world::RenderWorld::views). A pipeline runs
+    /// once for every view that uses it.
+    pub view_index: usize,
+}
+
+/// A single step of a [`RenderPipeline`](crate::render_pipeline::RenderPipeline).
+///
+/// Nodes are ordered by their slots: a node that reads a slot runs after every node
+/// that writes it. A node that both reads and writes the same slot runs after the
+/// writers that were added before it.
+pub trait Node: Any + Send + Sync + Downcast {
+    fn inputs(&self) -> Vec<SlotLabel> {
+        vec![]
+    }
+
+    fn outputs(&self) -> Vec<SlotLabel> {
+        vec![]
+    }
+
+    fn run(&self, context: &mut RenderPipelineRunContext) -> Result<(), FrameworkError>;
+}
+
+impl_downcast!(Node);
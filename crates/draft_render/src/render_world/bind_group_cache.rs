@@ -0,0 +1,352 @@
+use std::{collections::HashMap, num::NonZeroU64};
+
+use draft_graphics::{
+    AddressMode, BindGroup, BindGroupLayout, CompareFunction, FilterMode, MipmapFilterMode,
+    RenderDevice, Sampler, SamplerDescriptor, TextureView,
+};
+use wgpu::{BindGroupEntry, BindGroupLayoutEntry, BindingResource, Buffer, SamplerBorderColor};
+
+use crate::render_world::TimeToLive;
+
+/// Creates each distinct bind group layout once. Layouts with the same entries are
+/// the same layout, whichever feature asked for them.
+#[derive(Default)]
+pub struct BindGroupLayoutCache {
+    layouts: HashMap<Vec<BindGroupLayoutEntry>, BindGroupLayout>,
+}
+
+impl BindGroupLayoutCache {
+    pub fn get_or_create(
+        &mut self,
+        label: &str,
+        entries: &[BindGroupLayoutEntry],
+        device: &RenderDevice,
+    ) -> BindGroupLayout {
+        if let Some(layout) = self.layouts.get(entries) {
+            return layout.clone();
+        }
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(label),
+            entries,
+        });
+        self.layouts.insert(entries.to_vec(), layout.clone());
+
+        layout
+    }
+
+    pub fn len(&self) -> usize {
+        self.layouts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layouts.is_empty()
+    }
+}
+
+/// The fields of a [`SamplerDescriptor`] that affect sampling, without the label.
+/// Level of detail clamps are compared by their bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SamplerKey {
+    pub address_modes: [AddressMode; 3],
+    pub mag_filter: FilterMode,
+    pub min_filter: FilterMode,
+    pub mipmap_filter: MipmapFilterMode,
+    pub lod_clamp: [u32; 2],
+    pub compare: Option<CompareFunction>,
+    pub anisotropy_clamp: u16,
+    pub border_color: Option<SamplerBorderColor>,
+}
+
+impl From<&SamplerDescriptor<'_>> for SamplerKey {
+    fn from(desc: &SamplerDescriptor<'_>) -> Self {
+        Self {
+            address_modes: [
+                desc.address_mode_u,
+                desc.address_mode_v,
+                desc.address_mode_w,
+            ],
+            mag_filter: desc.mag_filter,
+            min_filter: desc.min_filter,
+            mipmap_filter: desc.mipmap_filter,
+            lod_clamp: [desc.lod_min_clamp.to_bits(), desc.lod_max_clamp.to_bits()],
+            compare: desc.compare,
+            anisotropy_clamp: desc.anisotropy_clamp,
+            border_color: desc.border_color,
+        }
+    }
+}
+
+/// Creates each distinct sampler once. Samplers are small and few, so they are kept
+/// for the lifetime of the cache.
+#[derive(Default)]
+pub struct SamplerCache {
+    samplers: HashMap<SamplerKey, Sampler>,
+}
+
+impl SamplerCache {
+    pub fn get_or_create(&mut self, desc: &SamplerDescriptor, device: &RenderDevice) -> Sampler {
+        self.samplers
+            .entry(SamplerKey::from(desc))
+            .or_insert_with(|| device.create_sampler(desc))
+            .clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.samplers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samplers.is_empty()
+    }
+}
+
+/// Identity of a resource bound by a bind group.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub enum BindingKey {
+    Buffer {
+        buffer: Buffer,
+        offset: u64,
+        size: Option<NonZeroU64>,
+    },
+    TextureView(TextureView),
+    Sampler(Sampler),
+}
+
+impl BindingKey {
+    /// `None` for arrays of resources, which are not cached.
+    fn new(resource: &BindingResource) -> Option<Self> {
+        match resource {
+            BindingResource::Buffer(binding) => Some(BindingKey::Buffer {
+                buffer: binding.buffer.clone(),
+                offset: binding.offset,
+                size: binding.size,
+            }),
+            BindingResource::TextureView(view) => Some(BindingKey::TextureView((*view).clone())),
+            BindingResource::Sampler(sampler) => Some(BindingKey::Sampler((*sampler).clone())),
+            _ => None,
+        }
+    }
+}
+
+/// A resource dropped by a render world cache. Bind groups that bind it are dropped
+/// from the [`BindGroupCache`] too.
+#[derive(Clone)]
+pub enum EvictedResource {
+    Buffer(Buffer),
+    TextureView(TextureView),
+}
+
+impl EvictedResource {
+    fn is_bound_by(&self, key: &BindingKey) -> bool {
+        match (self, key) {
+            (EvictedResource::Buffer(evicted), BindingKey::Buffer { buffer, .. }) => {
+                evicted == buffer
+            }
+            (EvictedResource::TextureView(evicted), BindingKey::TextureView(view)) => {
+                evicted == view
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct BindGroupKey {
+    layout: BindGroupLayout,
+    entries: Vec<(u32, BindingKey)>,
+}
+
+struct CachedBindGroup {
+    bind_group: BindGroup,
+    time_to_live: TimeToLive,
+}
+
+/// Reuses bind groups across frames, keyed by their layout and the identity of
+/// their resources. Bind groups are dropped when one of their resources is evicted
+/// or when they were not used within their time to live.
+#[derive(Default)]
+pub struct BindGroupCache {
+    bind_groups: HashMap<BindGroupKey, CachedBindGroup>,
+}
+
+impl BindGroupCache {
+    pub fn get_or_create(
+        &mut self,
+        label: &str,
+        layout: &BindGroupLayout,
+        entries: &[BindGroupEntry],
+        device: &RenderDevice,
+    ) -> BindGroup {
+        let create = || {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(label),
+                layout,
+                entries,
+            })
+        };
+
+        let Some(entries) = entries
+            .iter()
+            .map(|entry| Some((entry.binding, BindingKey::new(&entry.resource)?)))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return create();
+        };
+
+        let key = BindGroupKey {
+            layout: layout.clone(),
+            entries,
+        };
+
+        let cached = self
+            .bind_groups
+            .entry(key)
+            .or_insert_with(|| CachedBindGroup {
+                bind_group: create(),
+                time_to_live: TimeToLive::default(),
+            });
+        cached.time_to_live = TimeToLive::default();
+
+        cached.bind_group.clone()
+    }
+
+    /// Ages the bind groups by `dt` seconds, dropping the ones not used within their
+    /// time to live.
+    pub fn update(&mut self, dt: f32) {
+        self.bind_groups.retain(|_, cached| {
+            *cached.time_to_live -= dt;
+            *cached.time_to_live > 0.0
+        });
+    }
+
+    /// Drops the bind groups that bind any of the evicted resources.
+    pub fn evict(&mut self, evicted: &[EvictedResource]) {
+        if evicted.is_empty() {
+            return;
+        }
+
+        self.bind_groups.retain(|key, _| {
+            !key.entries
+                .iter()
+                .any(|(_, binding)| evicted.iter().any(|resource| resource.is_bound_by(binding)))
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.bind_groups.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bind_groups.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use draft_graphics::{RenderServer, RenderServerSettings};
+    use fyrox_resource::core::futures::executor::block_on;
+    use wgpu::ShaderStages;
+
+    use super::*;
+
+    #[test]
+    fn sampler_keys_ignore_labels() {
+        let desc = SamplerDescriptor {
+            label: Some("a"),
+            mag_filter: FilterMode::Linear,
+            ..Default::default()
+        };
+        let relabeled = SamplerDescriptor {
+            label: Some("b"),
+            ..desc.clone()
+        };
+        let clamped = SamplerDescriptor {
+            lod_max_clamp: 4.0,
+            ..desc.clone()
+        };
+
+        assert_eq!(SamplerKey::from(&desc), SamplerKey::from(&relabeled));
+        assert_ne!(SamplerKey::from(&desc), SamplerKey::from(&clamped));
+    }
+
+    #[test]
+    fn bind_groups_of_evicted_resources_are_dropped() {
+        let settings = RenderServerSettings {
+            force_fallback_adapter: true,
+            ..Default::default()
+        };
+        let Some(render_server) = block_on(RenderServer::try_initialize(&settings)) else {
+            return;
+        };
+        let device = &render_server.device;
+
+        let entries = [BindGroupLayoutEntry {
+            binding: 0,
+            visibility: ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        }];
+        let mut layouts = BindGroupLayoutCache::default();
+        let layout = layouts.get_or_create("layout", &entries, device);
+        assert_eq!(layouts.get_or_create("other", &entries, device), layout);
+
+        let mut samplers = SamplerCache::default();
+        let sampler = samplers.get_or_create(&SamplerDescriptor::default(), device);
+        assert_eq!(
+            samplers.get_or_create(&SamplerDescriptor::default(), device),
+            sampler
+        );
+
+        let mut bind_groups = BindGroupCache::default();
+        let bind_group_entries = [BindGroupEntry {
+            binding: 0,
+            resource: BindingResource::Sampler(&sampler),
+        }];
+        let bind_group = bind_groups.get_or_create("group", &layout, &bind_group_entries, device);
+        assert_eq!(
+            bind_groups.get_or_create("group", &layout, &bind_group_entries, device),
+            bind_group
+        );
+        assert_eq!(bind_groups.len(), 1);
+
+        bind_groups.update(TimeToLive::default().0 / 2.0);
+        assert_eq!(bind_groups.len(), 1);
+        bind_groups.update(TimeToLive::default().0);
+        assert!(bind_groups.is_empty());
+
+        let buffer = device.create_gpu_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM,
+            mapped_at_creation: false,
+        });
+        let buffer_layout = layouts.get_or_create(
+            "buffer_layout",
+            &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            device,
+        );
+        bind_groups.get_or_create(
+            "buffer_group",
+            &buffer_layout,
+            &[BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+            device,
+        );
+        bind_groups.get_or_create("group", &layout, &bind_group_entries, device);
+
+        bind_groups.evict(&[EvictedResource::Buffer(buffer)]);
+        assert_eq!(bind_groups.len(), 1);
+    }
+}
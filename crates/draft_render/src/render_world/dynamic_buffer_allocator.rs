@@ -0,0 +1,294 @@
+use std::{
+    num::NonZeroU64,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use bytemuck::{Pod, bytes_of};
+use draft_graphics::{RenderDevice, RenderQueue};
+use wgpu::{BindingResource, Buffer, BufferBinding, BufferUsages, COPY_BUFFER_ALIGNMENT};
+
+/// Size of the buffers slices are handed out from. Larger allocations get a buffer
+/// of their own.
+pub const DYNAMIC_BUFFER_BLOCK_SIZE: u64 = 1 << 20;
+
+/// What the buffers of a [`DynamicBufferAllocator`] are bound as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DynamicBufferKind {
+    Uniform,
+    /// Storage buffers are also bound as vertex buffers for instance data.
+    Storage,
+}
+
+impl DynamicBufferKind {
+    fn usage(self) -> BufferUsages {
+        match self {
+            DynamicBufferKind::Uniform => BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            DynamicBufferKind::Storage => {
+                BufferUsages::STORAGE | BufferUsages::VERTEX | BufferUsages::COPY_DST
+            }
+        }
+    }
+
+    /// Alignment of the dynamic offsets required by the device.
+    fn offset_alignment(self, limits: &wgpu::Limits) -> u64 {
+        match self {
+            DynamicBufferKind::Uniform => limits.min_uniform_buffer_offset_alignment as u64,
+            DynamicBufferKind::Storage => limits.min_storage_buffer_offset_alignment as u64,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            DynamicBufferKind::Uniform => "dynamic_uniform_buffer",
+            DynamicBufferKind::Storage => "dynamic_storage_buffer",
+        }
+    }
+}
+
+/// A slice of a shared buffer, valid until the end of the frame it was allocated
+/// in. Bind groups bind [`DynamicBufferSlice::binding`] with `has_dynamic_offset`
+/// and pass [`DynamicBufferSlice::offset`] when they are set.
+#[derive(Clone)]
+pub struct DynamicBufferSlice {
+    pub buffer: Buffer,
+    /// Dynamic offset of the slice in the buffer.
+    pub offset: u32,
+    pub size: u64,
+}
+
+impl DynamicBufferSlice {
+    /// Binding of the slice at offset zero, the dynamic offset is added when the
+    /// bind group is set.
+    pub fn binding(&self) -> BindingResource<'_> {
+        BindingResource::Buffer(BufferBinding {
+            buffer: &self.buffer,
+            offset: 0,
+            size: NonZeroU64::new(self.size),
+        })
+    }
+}
+
+/// A buffer and the contents written to it this frame.
+struct DynamicBufferBlock {
+    buffer: Buffer,
+    data: Vec<u8>,
+}
+
+impl DynamicBufferBlock {
+    fn capacity(&self) -> u64 {
+        self.buffer.size()
+    }
+}
+
+/// Offset of the next slice of `size` bytes in a block whose contents end at `end`,
+/// `None` when it doesn't fit.
+fn fit(end: u64, size: u64, alignment: u64, capacity: u64) -> Option<u64> {
+    let offset = end.next_multiple_of(alignment);
+    (offset + size <= capacity).then_some(offset)
+}
+
+/// Hands out aligned slices of large buffers for per-draw data. The buffers of a
+/// frame are recycled once the GPU has finished the submission that used them.
+pub struct DynamicBufferAllocator {
+    kind: DynamicBufferKind,
+    block_size: u64,
+    /// Blocks with slices allocated this frame, the last one is filled next.
+    active: Vec<DynamicBufferBlock>,
+    /// Blocks of submitted frames, free once their flag is set.
+    in_flight: Vec<(Arc<AtomicBool>, Vec<DynamicBufferBlock>)>,
+    free: Vec<DynamicBufferBlock>,
+}
+
+impl DynamicBufferAllocator {
+    pub fn new(kind: DynamicBufferKind) -> Self {
+        Self::with_block_size(kind, DYNAMIC_BUFFER_BLOCK_SIZE)
+    }
+
+    pub fn with_block_size(kind: DynamicBufferKind, block_size: u64) -> Self {
+        Self {
+            kind,
+            block_size,
+            active: vec![],
+            in_flight: vec![],
+            free: vec![],
+        }
+    }
+
+    pub fn kind(&self) -> DynamicBufferKind {
+        self.kind
+    }
+
+    /// Number of buffers owned by the allocator, in use or not.
+    pub fn buffer_count(&self) -> usize {
+        self.active.len()
+            + self.free.len()
+            + self
+                .in_flight
+                .iter()
+                .map(|(_, blocks)| blocks.len())
+                .sum::<usize>()
+    }
+
+    /// Copies `data` into a slice aligned to the offset alignment of the device. The
+    /// contents are uploaded by [`DynamicBufferAllocator::write`].
+    pub fn allocate(&mut self, data: &[u8], device: &RenderDevice) -> DynamicBufferSlice {
+        let alignment = self
+            .kind
+            .offset_alignment(&device.wgpu_device().limits())
+            .max(COPY_BUFFER_ALIGNMENT);
+        let size = (data.len() as u64).max(COPY_BUFFER_ALIGNMENT);
+
+        let offset = self
+            .active
+            .last()
+            .and_then(|block| fit(block.data.len() as u64, size, alignment, block.capacity()));
+        let offset = match offset {
+            Some(offset) => offset,
+            None => {
+                let block = self.take_block(size, device);
+                self.active.push(block);
+                0
+            }
+        };
+
+        let block = self.active.last_mut().expect("Block was just added");
+        block.data.resize(offset as usize, 0);
+        block.data.extend_from_slice(data);
+        block.data.resize((offset + size) as usize, 0);
+
+        DynamicBufferSlice {
+            buffer: block.buffer.clone(),
+            offset: offset as u32,
+            size,
+        }
+    }
+
+    pub fn allocate_value<T: Pod>(
+        &mut self,
+        value: &T,
+        device: &RenderDevice,
+    ) -> DynamicBufferSlice {
+        self.allocate(bytes_of(value), device)
+    }
+
+    fn take_block(&mut self, size: u64, device: &RenderDevice) -> DynamicBufferBlock {
+        if let Some(index) = self.free.iter().position(|block| block.capacity() >= size) {
+            return self.free.swap_remove(index);
+        }
+
+        DynamicBufferBlock {
+            buffer: device.create_gpu_buffer(&wgpu::BufferDescriptor {
+                label: Some(self.kind.label()),
+                size: size.max(self.block_size),
+                usage: self.kind.usage(),
+                mapped_at_creation: false,
+            }),
+            data: vec![],
+        }
+    }
+
+    /// Uploads the slices allocated this frame, before the frame is submitted.
+    pub fn write(&mut self, queue: &RenderQueue) {
+        for block in self.active.iter_mut() {
+            let size = (block.data.len() as u64).next_multiple_of(COPY_BUFFER_ALIGNMENT);
+            block.data.resize(size as usize, 0);
+
+            if !block.data.is_empty() {
+                queue.write_buffer(&block.buffer, 0, &block.data);
+            }
+        }
+    }
+
+    /// Hands the buffers of the frame to the GPU, after the frame is submitted. They
+    /// are recycled once the submission is done.
+    pub fn retire(&mut self, queue: &RenderQueue) {
+        if self.active.is_empty() {
+            return;
+        }
+
+        let done = Arc::new(AtomicBool::new(false));
+        let flag = done.clone();
+        queue.on_submitted_work_done(move || flag.store(true, Ordering::Release));
+
+        self.in_flight
+            .push((done, std::mem::take(&mut self.active)));
+    }
+
+    /// Frees the buffers of the submissions the GPU has finished.
+    pub fn recycle(&mut self) {
+        let mut index = 0;
+        while index < self.in_flight.len() {
+            if self.in_flight[index].0.load(Ordering::Acquire) {
+                let (_, blocks) = self.in_flight.swap_remove(index);
+                self.free.extend(blocks.into_iter().map(|mut block| {
+                    block.data.clear();
+                    block
+                }));
+            } else {
+                index += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use draft_graphics::{RenderServer, RenderServerSettings};
+    use fyrox_resource::core::futures::executor::block_on;
+
+    use super::*;
+
+    #[test]
+    fn slices_are_aligned_within_the_block() {
+        assert_eq!(fit(0, 64, 256, 1024), Some(0));
+        assert_eq!(fit(64, 64, 256, 1024), Some(256));
+        assert_eq!(fit(768, 256, 256, 1024), Some(768));
+        assert_eq!(fit(772, 4, 256, 1024), None);
+    }
+
+    #[test]
+    fn buffers_are_recycled_after_the_submission_is_done() {
+        let settings = RenderServerSettings {
+            force_fallback_adapter: true,
+            ..Default::default()
+        };
+        let Some(render_server) = block_on(RenderServer::try_initialize(&settings)) else {
+            return;
+        };
+        let (device, queue) = (&render_server.device, &render_server.queue);
+        let alignment = device
+            .wgpu_device()
+            .limits()
+            .min_uniform_buffer_offset_alignment;
+
+        let mut allocator = DynamicBufferAllocator::with_block_size(
+            DynamicBufferKind::Uniform,
+            alignment as u64 * 2,
+        );
+
+        let first = allocator.allocate_value(&[1.0f32; 4], device);
+        let second = allocator.allocate_value(&[2.0f32; 4], device);
+        let third = allocator.allocate(&[0; 12], device);
+        assert_eq!(
+            (first.offset, second.offset, third.offset),
+            (0, alignment, 0)
+        );
+        assert_eq!(third.size, 12);
+        assert_eq!(allocator.buffer_count(), 2);
+
+        allocator.write(queue);
+        queue.submit([]);
+        allocator.retire(queue);
+        device
+            .wgpu_device()
+            .poll(wgpu::PollType::wait_indefinitely())
+            .unwrap();
+        allocator.recycle();
+
+        allocator.allocate_value(&[3.0f32; 4], device);
+        assert_eq!(allocator.buffer_count(), 2);
+    }
+}
@@ -0,0 +1,58 @@
+use draft_graphics::Color;
+
+use crate::{
+    IWorld, RenderContext,
+    camera::Camera,
+    core_2d::Sprite,
+    core_3d::{Light, ShadowSettings},
+    render_phase::DrawItem,
+};
+
+/// Everything a world submitted for one frame. It is filled on the main thread by
+/// [`IWorld::render`] and moved into the [`RenderWorld`](super::RenderWorld) that
+/// renders it, possibly on the render thread.
+#[derive(Default)]
+pub struct ExtractedFrame {
+    pub(super) draw_items: Vec<DrawItem>,
+    pub(super) sprites: Vec<Sprite>,
+    pub(super) cameras: Vec<Camera>,
+    pub(super) lights: Vec<Light>,
+    /// Settings kept across frames are only sent when the world changed them.
+    pub(super) ambient_light: Option<Color>,
+    pub(super) shadow_settings: Option<ShadowSettings>,
+}
+
+impl ExtractedFrame {
+    /// Runs [`IWorld::render`] into a new frame.
+    pub fn extract<W: IWorld>(world: &W) -> Self {
+        let mut frame = ExtractedFrame::default();
+
+        world.render(&mut RenderContext { frame: &mut frame });
+
+        frame
+    }
+
+    pub fn draw(&mut self, draw_item: DrawItem) {
+        self.draw_items.push(draw_item);
+    }
+
+    pub fn draw_sprite(&mut self, sprite: Sprite) {
+        self.sprites.push(sprite);
+    }
+
+    pub fn add_camera(&mut self, camera: Camera) {
+        self.cameras.push(camera);
+    }
+
+    pub fn add_light(&mut self, light: Light) {
+        self.lights.push(light);
+    }
+
+    pub fn set_ambient_light(&mut self, ambient_light: Color) {
+        self.ambient_light = Some(ambient_light);
+    }
+
+    pub fn set_shadow_settings(&mut self, shadow_settings: ShadowSettings) {
+        self.shadow_settings = Some(shadow_settings);
+    }
+}
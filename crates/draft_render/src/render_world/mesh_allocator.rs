@@ -0,0 +1,560 @@
+use std::{
+    collections::HashMap,
+    ops::Range,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use draft_graphics::{RenderDevice, RenderQueue};
+use draft_mesh::MeshVertexBufferLayoutRef;
+use wgpu::{BufferUsages, COPY_BUFFER_ALIGNMENT, IndexFormat};
+
+use crate::{frame_graph::ExternalBuffer, render_world::EvictedResource};
+
+/// Index slabs are allocated in words, so every allocation can be written and copied
+/// even with 16 bit indices.
+const INDEX_SLAB_ELEMENT_SIZE: u64 = 4;
+
+static NEXT_SLAB_ID: AtomicU64 = AtomicU64::new(0);
+
+/// How the [`MeshCache`](super::MeshCache) stores the buffers of meshes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum MeshAllocationMode {
+    /// Every mesh gets buffers of its own.
+    #[default]
+    Dedicated,
+    /// Meshes with the same vertex layout share large slab buffers, so draws of
+    /// different meshes only rebind their buffers when the layout changes.
+    Slab,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshAllocatorSettings {
+    pub mode: MeshAllocationMode,
+    /// Size of a slab in bytes. Meshes that don't fit get a slab sized to them.
+    pub slab_size: u64,
+    /// Slabs whose free space is fragmented above this ratio are compacted, see
+    /// [`FreeListAllocator::fragmentation`].
+    pub defragment_threshold: f32,
+    /// Slabs are only compacted once the free bytes outside of their largest free
+    /// range reach this size, so a few small holes don't copy a whole slab.
+    pub defragment_min_wasted_bytes: u64,
+    /// Bytes of allocations copied by compaction in a single frame. The first slab
+    /// of a frame is compacted even when it copies more, the remaining slabs wait for
+    /// later frames.
+    pub defragment_max_bytes_per_frame: u64,
+}
+
+impl Default for MeshAllocatorSettings {
+    fn default() -> Self {
+        Self {
+            mode: MeshAllocationMode::default(),
+            slab_size: 16 * 1024 * 1024,
+            defragment_threshold: 0.5,
+            defragment_min_wasted_bytes: 1024 * 1024,
+            defragment_max_bytes_per_frame: 8 * 1024 * 1024,
+        }
+    }
+}
+
+/// A first-fit allocator over a range of elements.
+#[derive(Debug, Clone)]
+pub struct FreeListAllocator {
+    capacity: u32,
+    /// Sorted free ranges, adjacent ranges are merged.
+    free: Vec<Range<u32>>,
+}
+
+impl FreeListAllocator {
+    pub fn new(capacity: u32) -> Self {
+        Self {
+            capacity,
+            free: (capacity > 0).then_some(0..capacity).into_iter().collect(),
+        }
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    pub fn free_count(&self) -> u32 {
+        self.free.iter().map(|range| range.len() as u32).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.free_count() == self.capacity
+    }
+
+    pub fn largest_free(&self) -> u32 {
+        self.free
+            .iter()
+            .map(|range| range.len() as u32)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The share of the free space that is not part of the largest free range. Zero
+    /// when the free space is contiguous.
+    pub fn fragmentation(&self) -> f32 {
+        match self.free_count() {
+            0 => 0.0,
+            free => 1.0 - self.largest_free() as f32 / free as f32,
+        }
+    }
+
+    /// Allocates `count` elements from the first free range they fit in.
+    pub fn allocate(&mut self, count: u32) -> Option<Range<u32>> {
+        if count == 0 {
+            return None;
+        }
+
+        let index = self
+            .free
+            .iter()
+            .position(|range| range.len() as u32 >= count)?;
+        let range = &mut self.free[index];
+        let start = range.start;
+
+        range.start += count;
+        if range.start == range.end {
+            self.free.remove(index);
+        }
+
+        Some(start..start + count)
+    }
+
+    pub fn free(&mut self, range: Range<u32>) {
+        if range.is_empty() {
+            return;
+        }
+
+        let index = self.free.partition_point(|free| free.start < range.start);
+
+        debug_assert!(index == 0 || self.free[index - 1].end <= range.start);
+        debug_assert!(index == self.free.len() || range.end <= self.free[index].start);
+
+        let merges_previous = index > 0 && self.free[index - 1].end == range.start;
+        let merges_next = index < self.free.len() && self.free[index].start == range.end;
+
+        match (merges_previous, merges_next) {
+            (true, true) => {
+                self.free[index - 1].end = self.free[index].end;
+                self.free.remove(index);
+            }
+            (true, false) => self.free[index - 1].end = range.end,
+            (false, true) => self.free[index].start = range.start,
+            (false, false) => self.free.insert(index, range),
+        }
+    }
+
+    /// The allocated ranges, with adjacent allocations merged.
+    pub fn used(&self) -> Vec<Range<u32>> {
+        let mut used = vec![];
+        let mut start = 0;
+
+        for free in self.free.iter() {
+            if free.start > start {
+                used.push(start..free.start);
+            }
+            start = free.end;
+        }
+
+        if start < self.capacity {
+            used.push(start..self.capacity);
+        }
+
+        used
+    }
+}
+
+/// The slabs a mesh buffer can be allocated from. Vertex buffers share slabs with
+/// buffers of the same layout, index buffers with buffers of the same format.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MeshSlabKey {
+    Vertex(MeshVertexBufferLayoutRef),
+    Index(IndexFormat),
+}
+
+impl MeshSlabKey {
+    fn element_size(&self) -> u64 {
+        match self {
+            MeshSlabKey::Vertex(layout) => layout.layout().array_stride,
+            MeshSlabKey::Index(_) => INDEX_SLAB_ELEMENT_SIZE,
+        }
+    }
+
+    fn usage(&self) -> BufferUsages {
+        let usage = BufferUsages::COPY_DST | BufferUsages::COPY_SRC;
+
+        match self {
+            MeshSlabKey::Vertex(_) => usage | BufferUsages::VERTEX,
+            MeshSlabKey::Index(_) => usage | BufferUsages::INDEX,
+        }
+    }
+}
+
+/// Elements of a slab that hold the buffer of a mesh.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshSlabAllocation {
+    pub key: MeshSlabKey,
+    pub slab: u64,
+    pub range: Range<u32>,
+}
+
+impl MeshSlabAllocation {
+    /// Offset of the allocation in the slab buffer, in bytes.
+    pub fn byte_offset(&self) -> u64 {
+        self.range.start as u64 * self.key.element_size()
+    }
+
+    pub fn byte_size(&self) -> u64 {
+        self.range.len() as u64 * self.key.element_size()
+    }
+}
+
+/// Where a defragmented slab moved its allocations.
+pub struct MeshSlabRelocation {
+    pub slab: u64,
+    pub buffer: ExternalBuffer,
+    /// The used ranges of the slab before compaction and where they start now.
+    moves: Vec<(Range<u32>, u32)>,
+}
+
+impl MeshSlabRelocation {
+    /// Moves an allocation of the defragmented slab. Returns `false` for allocations
+    /// of other slabs.
+    pub fn relocate(&self, allocation: &mut MeshSlabAllocation) -> bool {
+        if allocation.slab != self.slab {
+            return false;
+        }
+
+        allocation.range = relocated(&self.moves, allocation.range.clone());
+        true
+    }
+}
+
+fn relocated(moves: &[(Range<u32>, u32)], range: Range<u32>) -> Range<u32> {
+    match moves.iter().find(|(from, _)| from.contains(&range.start)) {
+        Some((from, to)) => {
+            let start = to + (range.start - from.start);
+            start..start + range.len() as u32
+        }
+        None => range,
+    }
+}
+
+struct MeshSlab {
+    id: u64,
+    buffer: ExternalBuffer,
+    allocator: FreeListAllocator,
+}
+
+impl MeshSlab {
+    /// Free bytes outside of the largest free range.
+    fn wasted_bytes(&self, element_size: u64) -> u64 {
+        (self.allocator.free_count() - self.allocator.largest_free()) as u64 * element_size
+    }
+
+    fn used_bytes(&self, element_size: u64) -> u64 {
+        (self.allocator.capacity() - self.allocator.free_count()) as u64 * element_size
+    }
+}
+
+/// Suballocates the vertex and index buffers of meshes out of shared slab buffers.
+#[derive(Default)]
+pub struct MeshAllocator {
+    settings: MeshAllocatorSettings,
+    slabs: HashMap<MeshSlabKey, Vec<MeshSlab>>,
+    evicted: Vec<EvictedResource>,
+}
+
+impl MeshAllocator {
+    pub fn settings(&self) -> &MeshAllocatorSettings {
+        &self.settings
+    }
+
+    /// Changes the settings of new allocations. Meshes keep their buffers until they
+    /// outgrow them.
+    pub fn set_settings(&mut self, settings: MeshAllocatorSettings) {
+        self.settings = settings;
+    }
+
+    /// Number of slab buffers that are alive.
+    pub fn slab_count(&self) -> usize {
+        self.slabs.values().map(Vec::len).sum()
+    }
+
+    /// Slab buffers released or replaced by compaction since the last call.
+    pub fn take_evicted(&mut self) -> Vec<EvictedResource> {
+        std::mem::take(&mut self.evicted)
+    }
+
+    /// Allocates at least `size` bytes from a slab of `key` and returns the allocation
+    /// along with the slab buffer. `None` when the data can't be suballocated, leaving
+    /// the mesh to a buffer of its own.
+    pub fn allocate(
+        &mut self,
+        key: MeshSlabKey,
+        size: u64,
+        device: &RenderDevice,
+    ) -> Option<(MeshSlabAllocation, ExternalBuffer)> {
+        let element_size = key.element_size();
+        if size == 0 || element_size == 0 || !element_size.is_multiple_of(COPY_BUFFER_ALIGNMENT) {
+            return None;
+        }
+
+        let count = size.div_ceil(element_size) as u32;
+        let slabs = self.slabs.entry(key.clone()).or_default();
+
+        let (slab, range) = match slabs
+            .iter_mut()
+            .find_map(|slab| slab.allocator.allocate(count).map(|range| (slab.id, range)))
+        {
+            Some(allocation) => allocation,
+            None => {
+                let capacity = ((self.settings.slab_size / element_size) as u32).max(count);
+                let buffer = device.create_gpu_buffer(&wgpu::BufferDescriptor {
+                    label: Some("mesh_slab_buffer"),
+                    size: capacity as u64 * element_size,
+                    usage: key.usage(),
+                    mapped_at_creation: false,
+                });
+
+                let mut slab = MeshSlab {
+                    id: NEXT_SLAB_ID.fetch_add(1, Ordering::Relaxed),
+                    buffer: ExternalBuffer::new("mesh_slab_buffer", buffer),
+                    allocator: FreeListAllocator::new(capacity),
+                };
+                let range = slab.allocator.allocate(count)?;
+                let id = slab.id;
+                slabs.push(slab);
+
+                (id, range)
+            }
+        };
+
+        let slab_buffer = slabs.iter().find(|other| other.id == slab)?.buffer.clone();
+
+        Some((MeshSlabAllocation { key, slab, range }, slab_buffer))
+    }
+
+    /// Returns the elements of an allocation to its slab. Slabs left empty are
+    /// released, unless they are the last one of their key.
+    pub fn free(&mut self, allocation: &MeshSlabAllocation) {
+        let Some(slabs) = self.slabs.get_mut(&allocation.key) else {
+            return;
+        };
+
+        let Some(index) = slabs.iter().position(|slab| slab.id == allocation.slab) else {
+            return;
+        };
+
+        slabs[index].allocator.free(allocation.range.clone());
+
+        if slabs[index].allocator.is_empty() && slabs.len() > 1 {
+            let slab = slabs.remove(index);
+            self.evicted
+                .push(EvictedResource::Buffer(slab.buffer.buffer.resource.clone()));
+        }
+    }
+
+    /// Compacts the slabs whose fragmentation and wasted bytes are above the
+    /// thresholds of the settings into new buffers, within the copy budget of a frame.
+    /// The allocations of the returned slabs must be relocated.
+    pub fn defragment(
+        &mut self,
+        device: &RenderDevice,
+        queue: &RenderQueue,
+    ) -> Vec<MeshSlabRelocation> {
+        let mut relocations = vec![];
+        let mut encoder = None;
+        let mut copied_bytes = 0;
+
+        for (key, slabs) in self.slabs.iter_mut() {
+            let element_size = key.element_size();
+
+            for slab in slabs.iter_mut() {
+                if slab.allocator.fragmentation() <= self.settings.defragment_threshold
+                    || slab.wasted_bytes(element_size) < self.settings.defragment_min_wasted_bytes
+                {
+                    continue;
+                }
+
+                let used_bytes = slab.used_bytes(element_size);
+                if copied_bytes > 0
+                    && copied_bytes + used_bytes > self.settings.defragment_max_bytes_per_frame
+                {
+                    continue;
+                }
+                copied_bytes += used_bytes;
+
+                let buffer = device.create_gpu_buffer(&wgpu::BufferDescriptor {
+                    label: Some("mesh_slab_buffer"),
+                    size: slab.buffer.size(),
+                    usage: key.usage(),
+                    mapped_at_creation: false,
+                });
+
+                let encoder = encoder.get_or_insert_with(|| {
+                    device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                        label: Some("mesh_slab_defragment"),
+                    })
+                });
+
+                let mut allocator = FreeListAllocator::new(slab.allocator.capacity());
+                let mut moves = vec![];
+
+                for used in slab.allocator.used() {
+                    let Some(to) = allocator.allocate(used.len() as u32) else {
+                        continue;
+                    };
+
+                    encoder.copy_buffer_to_buffer(
+                        &slab.buffer.buffer.resource,
+                        used.start as u64 * element_size,
+                        &buffer,
+                        to.start as u64 * element_size,
+                        used.len() as u64 * element_size,
+                    );
+                    moves.push((used, to.start));
+                }
+
+                let replaced = std::mem::replace(
+                    &mut slab.buffer,
+                    ExternalBuffer::new("mesh_slab_buffer", buffer),
+                );
+                self.evicted
+                    .push(EvictedResource::Buffer(replaced.buffer.resource.clone()));
+                slab.allocator = allocator;
+
+                relocations.push(MeshSlabRelocation {
+                    slab: slab.id,
+                    buffer: slab.buffer.clone(),
+                    moves,
+                });
+            }
+        }
+
+        if let Some(encoder) = encoder {
+            queue.submit([encoder.finish()]);
+        }
+
+        relocations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use draft_graphics::{RenderServer, RenderServerSettings};
+    use fyrox_resource::core::futures::executor::block_on;
+
+    use super::*;
+
+    #[test]
+    fn free_list_merges_freed_ranges() {
+        let mut allocator = FreeListAllocator::new(100);
+
+        let a = allocator.allocate(10).unwrap();
+        let b = allocator.allocate(20).unwrap();
+        let c = allocator.allocate(30).unwrap();
+        assert_eq!((a.clone(), b.clone(), c.clone()), (0..10, 10..30, 30..60));
+        assert!(allocator.allocate(50).is_none());
+
+        allocator.free(b);
+        assert_eq!(allocator.free_count(), 60);
+        assert_eq!(allocator.used(), [0..10, 30..60]);
+        // 20 of the 60 free elements are outside of the largest free range.
+        assert!((allocator.fragmentation() - 1.0 / 3.0).abs() < 1e-6);
+
+        // First fit reuses the hole.
+        assert_eq!(allocator.allocate(5), Some(10..15));
+        allocator.free(10..15);
+
+        allocator.free(a);
+        allocator.free(c);
+        assert!(allocator.is_empty());
+        assert_eq!(allocator.largest_free(), 100);
+        assert_eq!(allocator.fragmentation(), 0.0);
+        assert!(allocator.used().is_empty());
+    }
+
+    #[test]
+    fn compaction_moves_allocations_with_their_used_range() {
+        let mut allocator = FreeListAllocator::new(100);
+        let ranges = [5, 20, 10, 10].map(|count| allocator.allocate(count).unwrap());
+        allocator.free(ranges[0].clone());
+        allocator.free(ranges[2].clone());
+
+        let mut compacted = FreeListAllocator::new(allocator.capacity());
+        let moves = allocator
+            .used()
+            .into_iter()
+            .map(|used| {
+                let to = compacted.allocate(used.len() as u32).unwrap();
+                (used, to.start)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(moves, [(5..25, 0), (35..45, 20)]);
+
+        assert_eq!(relocated(&moves, ranges[1].clone()), 0..20);
+        assert_eq!(relocated(&moves, ranges[3].clone()), 20..30);
+        assert_eq!(compacted.fragmentation(), 0.0);
+
+        let allocation = MeshSlabAllocation {
+            key: MeshSlabKey::Index(IndexFormat::Uint16),
+            slab: 0,
+            range: 20..30,
+        };
+        assert_eq!(allocation.byte_offset(), 80);
+    }
+
+    #[test]
+    fn defragment_skips_small_holes_and_caps_copies() {
+        let settings = RenderServerSettings {
+            force_fallback_adapter: true,
+            ..Default::default()
+        };
+        let Some(render_server) = block_on(RenderServer::try_initialize(&settings)) else {
+            return;
+        };
+        let (device, queue) = (&render_server.device, &render_server.queue);
+
+        let mut allocator = MeshAllocator::default();
+        allocator.set_settings(MeshAllocatorSettings {
+            mode: MeshAllocationMode::Slab,
+            slab_size: 1024,
+            defragment_threshold: 0.5,
+            defragment_min_wasted_bytes: 256,
+            defragment_max_bytes_per_frame: 256,
+        });
+        let key = MeshSlabKey::Index(IndexFormat::Uint32);
+
+        // Three holes of 32 bytes are fragmented, but waste only 64 bytes.
+        let small = [32, 32, 32, 32, 32, 864]
+            .map(|size| allocator.allocate(key.clone(), size, device).unwrap().0);
+        for allocation in small.iter().step_by(2).take(3) {
+            allocator.free(allocation);
+        }
+        assert!(allocator.defragment(device, queue).is_empty());
+        for allocation in small.iter().skip(1).step_by(2) {
+            allocator.free(allocation);
+        }
+        assert_eq!(allocator.slab_count(), 1);
+
+        // Two slabs with three holes of 128 bytes each waste 256 bytes.
+        let large = [128; 16].map(|size| allocator.allocate(key.clone(), size, device).unwrap().0);
+        assert_eq!(allocator.slab_count(), 2);
+        for allocation in large
+            .iter()
+            .filter(|allocation| [0, 64, 128].contains(&allocation.range.start))
+        {
+            allocator.free(allocation);
+        }
+
+        // Each slab copies more than the budget, so one slab is compacted per frame.
+        assert_eq!(allocator.defragment(device, queue).len(), 1);
+        assert_eq!(allocator.take_evicted().len(), 1);
+        assert_eq!(allocator.defragment(device, queue).len(), 1);
+        assert_eq!(allocator.take_evicted().len(), 1);
+        assert!(allocator.defragment(device, queue).is_empty());
+    }
+}
@@ -1,138 +1,542 @@
-use draft_graphics::{BufferInitDescriptor, RenderDevice};
-use draft_mesh::{IndexBuffer, Mesh, MeshResource, VertexBuffer};
-use wgpu::{Buffer, BufferUsages};
+use std::ops::Range;
+
+use draft_graphics::{RenderDevice, RenderQueue};
+use draft_mesh::{
+    DirtyRange, IndexBuffer, Indices, Mesh, MeshBounds, MeshResource, MeshVertexBufferLayoutRef,
+    MeshVertexBufferLayouts,
+};
+use wgpu::{BufferUsages, COPY_BUFFER_ALIGNMENT, IndexFormat};
 
 use crate::{
     FrameworkError,
-    render_world::{ResourceId, TemporaryCache},
+    frame_graph::{ExternalBuffer, PassNodeBuilderExt},
+    render_phase::{IndexBufferRef, MeshBufferRefs},
+    render_world::{
+        EvictedResource, MeshAllocationMode, MeshAllocator, MeshAllocatorSettings,
+        MeshSlabAllocation, MeshSlabKey, MeshSlabRelocation, ResourceId, TemporaryCache,
+    },
 };
 
 pub struct VertexBufferRenderData {
     pub modifications_count: u64,
-    pub buffer: Buffer,
+    pub buffer: ExternalBuffer,
+    /// Elements of the slab that hold the vertices, `None` when the mesh has a buffer
+    /// of its own.
+    pub allocation: Option<MeshSlabAllocation>,
+    pub vertex_count: u32,
+    pub layout: MeshVertexBufferLayoutRef,
+}
+
+impl VertexBufferRenderData {
+    /// Index of the first vertex of the mesh in its buffer.
+    pub fn base_vertex(&self) -> u32 {
+        self.allocation
+            .as_ref()
+            .map_or(0, |allocation| allocation.range.start)
+    }
 }
 
 pub struct IndexBufferRenderData {
     pub modifications_count: u64,
-    pub buffer: Buffer,
+    pub buffer: ExternalBuffer,
+    /// Elements of the slab that hold the indices, `None` when the mesh has a buffer
+    /// of its own.
+    pub allocation: Option<MeshSlabAllocation>,
+    pub index_count: u32,
+    pub index_format: IndexFormat,
+}
+
+impl IndexBufferRenderData {
+    /// Index of the first index of the mesh in its buffer.
+    pub fn first_index(&self) -> u32 {
+        self.allocation.as_ref().map_or(0, |allocation| {
+            (allocation.byte_offset() / self.index_format.byte_size() as u64) as u32
+        })
+    }
 }
 
 pub struct MeshRenderData {
     pub vertex_buffer: VertexBufferRenderData,
     pub index_buffer: Option<IndexBufferRenderData>,
+    /// Bounds of the positions in local space, recomputed with the vertex buffer.
+    /// Meshes without positions are never culled.
+    pub bounds: Option<MeshBounds>,
+}
+
+impl MeshRenderData {
+    /// Imports the mesh buffers into the pass being built.
+    pub fn read<B: PassNodeBuilderExt>(&self, builder: &mut B) -> MeshBufferRefs {
+        MeshBufferRefs {
+            vertex_buffer: builder.read_material(&self.vertex_buffer.buffer),
+            vertex_buffer_size: self.vertex_buffer.buffer.size(),
+            vertex_count: self.vertex_buffer.vertex_count,
+            base_vertex: self.vertex_buffer.base_vertex(),
+            index_buffer: self
+                .index_buffer
+                .as_ref()
+                .map(|index_buffer| IndexBufferRef {
+                    buffer: builder.read_material(&index_buffer.buffer),
+                    size: index_buffer.buffer.size(),
+                    format: index_buffer.index_format,
+                    count: index_buffer.index_count,
+                    first_index: index_buffer.first_index(),
+                }),
+        }
+    }
+
+    /// Buffers of the mesh that are dropped with it. Slab buffers outlive the mesh.
+    fn owned_buffers(&self) -> impl Iterator<Item = &ExternalBuffer> {
+        let vertex_buffer = self
+            .vertex_buffer
+            .allocation
+            .is_none()
+            .then_some(&self.vertex_buffer.buffer);
+        let index_buffer = self
+            .index_buffer
+            .as_ref()
+            .filter(|index_buffer| index_buffer.allocation.is_none())
+            .map(|index_buffer| &index_buffer.buffer);
+
+        vertex_buffer.into_iter().chain(index_buffer)
+    }
+
+    fn free(&self, allocator: &mut MeshAllocator) {
+        let index_allocation = self
+            .index_buffer
+            .as_ref()
+            .and_then(|index_buffer| index_buffer.allocation.as_ref());
+
+        for allocation in self.vertex_buffer.allocation.iter().chain(index_allocation) {
+            allocator.free(allocation);
+        }
+    }
+
+    fn relocate(&mut self, relocations: &[MeshSlabRelocation]) {
+        relocate(
+            &mut self.vertex_buffer.buffer,
+            &mut self.vertex_buffer.allocation,
+            relocations,
+        );
+
+        if let Some(index_buffer) = &mut self.index_buffer {
+            relocate(
+                &mut index_buffer.buffer,
+                &mut index_buffer.allocation,
+                relocations,
+            );
+        }
+    }
+}
+
+fn relocate(
+    buffer: &mut ExternalBuffer,
+    allocation: &mut Option<MeshSlabAllocation>,
+    relocations: &[MeshSlabRelocation],
+) {
+    let Some(allocation) = allocation else {
+        return;
+    };
+
+    if let Some(relocation) = relocations
+        .iter()
+        .find(|relocation| relocation.relocate(allocation))
+    {
+        *buffer = relocation.buffer.clone();
+    }
+}
+
+/// Creates and updates the GPU buffers of meshes. Buffers of their own that meshes
+/// outgrow or drop are pushed to `evicted`.
+struct MeshUploader<'a> {
+    allocator: &'a mut MeshAllocator,
+    evicted: &'a mut Vec<EvictedResource>,
+    device: &'a RenderDevice,
+    queue: &'a RenderQueue,
+}
+
+impl MeshUploader<'_> {
+    /// Uploads mesh data into a new buffer of at least `capacity` bytes. In
+    /// [`MeshAllocationMode::Slab`] the buffer is suballocated from a slab, unless the
+    /// data can't be.
+    fn create(
+        &mut self,
+        label: &str,
+        data: &[u8],
+        capacity: u64,
+        key: MeshSlabKey,
+        usage: BufferUsages,
+    ) -> (ExternalBuffer, Option<MeshSlabAllocation>) {
+        let size = (data.len() as u64)
+            .max(capacity)
+            .next_multiple_of(COPY_BUFFER_ALIGNMENT);
+
+        let slab = (self.allocator.settings().mode == MeshAllocationMode::Slab)
+            .then(|| self.allocator.allocate(key, size, self.device))
+            .flatten();
+
+        let (buffer, allocation) = match slab {
+            Some((allocation, buffer)) => (buffer, Some(allocation)),
+            None => {
+                let buffer = self.device.create_gpu_buffer(&wgpu::BufferDescriptor {
+                    label: None,
+                    size,
+                    usage: usage | BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                });
+
+                (ExternalBuffer::new(label, buffer), None)
+            }
+        };
+
+        self.write(&buffer, allocation.as_ref(), data, 0..data.len());
+
+        (buffer, allocation)
+    }
+
+    /// Writes the dirty bytes of `data` into the existing buffer of a mesh when the
+    /// data still fits. Otherwise the data moves to a new buffer with twice the
+    /// capacity, so meshes that keep growing are reallocated rarely.
+    #[allow(clippy::too_many_arguments)]
+    fn update(
+        &mut self,
+        buffer: &mut ExternalBuffer,
+        allocation: &mut Option<MeshSlabAllocation>,
+        label: &str,
+        data: &[u8],
+        dirty: DirtyRange,
+        key: MeshSlabKey,
+        usage: BufferUsages,
+    ) {
+        let capacity = allocation
+            .as_ref()
+            .map_or_else(|| buffer.size(), MeshSlabAllocation::byte_size);
+        let fits = data.len() as u64 <= capacity
+            && allocation
+                .as_ref()
+                .is_none_or(|allocation| allocation.key == key);
+
+        if !fits {
+            self.release(buffer, allocation.take());
+
+            (*buffer, *allocation) = self.create(label, data, capacity * 2, key, usage);
+            return;
+        }
+
+        let range = match dirty {
+            DirtyRange::None => return,
+            DirtyRange::Bytes(range) => range.start.min(data.len())..range.end.min(data.len()),
+            DirtyRange::All => 0..data.len(),
+        };
+
+        self.write(buffer, allocation.as_ref(), data, range);
+    }
+
+    /// Returns a slab allocation to its slab, or evicts a buffer of its own.
+    fn release(&mut self, buffer: &ExternalBuffer, allocation: Option<MeshSlabAllocation>) {
+        match allocation {
+            Some(allocation) => self.allocator.free(&allocation),
+            None => self
+                .evicted
+                .push(EvictedResource::Buffer(buffer.buffer.resource.clone())),
+        }
+    }
+
+    /// Writes `range` of `data` to where the mesh starts in `buffer`. The range is
+    /// widened to whole words, as queue writes require.
+    fn write(
+        &self,
+        buffer: &ExternalBuffer,
+        allocation: Option<&MeshSlabAllocation>,
+        data: &[u8],
+        range: Range<usize>,
+    ) {
+        let alignment = COPY_BUFFER_ALIGNMENT as usize;
+        let start = range.start - range.start % alignment;
+        let end = range.end.next_multiple_of(alignment);
+        if start >= end {
+            return;
+        }
+
+        let offset = allocation.map_or(0, MeshSlabAllocation::byte_offset) + start as u64;
+
+        if end <= data.len() {
+            self.queue
+                .write_buffer(&buffer.buffer.resource, offset, &data[start..end]);
+        } else {
+            let mut padded = data[start..].to_vec();
+            padded.resize(end - start, 0);
+            self.queue
+                .write_buffer(&buffer.buffer.resource, offset, &padded);
+        }
+    }
+}
+
+fn index_count_and_format(index_buffer: &IndexBuffer) -> (u32, IndexFormat) {
+    match index_buffer.indices() {
+        Indices::U16(indices) => (indices.len() as u32, IndexFormat::Uint16),
+        Indices::U32(indices) => (indices.len() as u32, IndexFormat::Uint32),
+    }
 }
 
 fn create_index_buffer_render_data(
     index_buffer: &IndexBuffer,
-    device: &RenderDevice,
-) -> Result<IndexBufferRenderData, FrameworkError> {
-    let data = index_buffer.create_packed_index_buffer_data();
+    uploader: &mut MeshUploader,
+) -> IndexBufferRenderData {
+    let (index_count, index_format) = index_count_and_format(index_buffer);
 
-    let buffer = device.create_gpu_buffer_init(&BufferInitDescriptor {
-        label: None,
-        contents: &data,
-        usage: BufferUsages::INDEX,
-    });
+    let (buffer, allocation) = uploader.create(
+        "mesh_index_buffer",
+        &index_buffer.create_packed_index_buffer_data(),
+        0,
+        MeshSlabKey::Index(index_format),
+        BufferUsages::INDEX,
+    );
 
-    Ok(IndexBufferRenderData {
+    IndexBufferRenderData {
         modifications_count: index_buffer.modifications_counter,
         buffer,
-    })
+        allocation,
+        index_count,
+        index_format,
+    }
 }
 
-fn create_vertex_buffer_render_data(
-    vertex_buffer: &VertexBuffer,
-    device: &RenderDevice,
-) -> Result<VertexBufferRenderData, FrameworkError> {
-    let data = vertex_buffer.create_packed_vertex_buffer_data();
+fn create_mesh_render_data(
+    mesh: &Mesh,
+    layouts: &mut MeshVertexBufferLayouts,
+    uploader: &mut MeshUploader,
+) -> MeshRenderData {
+    let layout = mesh.vertex_buffer.get_mesh_vertex_buffer_layout(layouts);
 
-    let buffer = device.create_gpu_buffer_init(&BufferInitDescriptor {
-        label: None,
-        contents: &data,
-        usage: BufferUsages::VERTEX,
-    });
+    let (buffer, allocation) = uploader.create(
+        "mesh_vertex_buffer",
+        &mesh.vertex_buffer.create_packed_vertex_buffer_data(),
+        0,
+        MeshSlabKey::Vertex(layout.clone()),
+        BufferUsages::VERTEX,
+    );
 
-    Ok(VertexBufferRenderData {
-        modifications_count: vertex_buffer.modifications_counter,
-        buffer,
-    })
+    MeshRenderData {
+        vertex_buffer: VertexBufferRenderData {
+            modifications_count: mesh.vertex_buffer.modifications_counter,
+            buffer,
+            allocation,
+            vertex_count: mesh.vertex_buffer.count_vertices() as u32,
+            layout,
+        },
+        index_buffer: mesh
+            .index_buffer
+            .as_ref()
+            .map(|index_buffer| create_index_buffer_render_data(index_buffer, uploader)),
+        bounds: mesh.compute_bounds(),
+    }
 }
 
-fn create_mesh_render_data(
-    mesh: &Mesh,
-    device: &RenderDevice,
-) -> Result<MeshRenderData, FrameworkError> {
-    let vertex_buffer = create_vertex_buffer_render_data(&mesh.vertex_buffer, device)?;
+impl MeshRenderData {
+    /// Uploads the buffers of the mesh that were modified since they were last
+    /// uploaded, writing into the existing buffers when the data still fits.
+    fn update(
+        &mut self,
+        mesh: &Mesh,
+        layouts: &mut MeshVertexBufferLayouts,
+        uploader: &mut MeshUploader,
+    ) {
+        let vertex_buffer = &mut self.vertex_buffer;
+        if mesh.vertex_buffer.modifications_counter != vertex_buffer.modifications_count {
+            let layout = mesh.vertex_buffer.get_mesh_vertex_buffer_layout(layouts);
+            let dirty = mesh
+                .vertex_buffer
+                .dirty_range_since(vertex_buffer.modifications_count);
 
-    let index_buffer = match &mesh.index_buffer {
-        Some(index_buffer) => Some(create_index_buffer_render_data(index_buffer, device)?),
-        None => None,
-    };
+            if dirty != DirtyRange::None {
+                uploader.update(
+                    &mut vertex_buffer.buffer,
+                    &mut vertex_buffer.allocation,
+                    "mesh_vertex_buffer",
+                    &mesh.vertex_buffer.create_packed_vertex_buffer_data(),
+                    dirty,
+                    MeshSlabKey::Vertex(layout.clone()),
+                    BufferUsages::VERTEX,
+                );
+                self.bounds = mesh.compute_bounds();
+            }
 
-    Ok(MeshRenderData {
-        vertex_buffer,
-        index_buffer,
-    })
+            vertex_buffer.modifications_count = mesh.vertex_buffer.modifications_counter;
+            vertex_buffer.vertex_count = mesh.vertex_buffer.count_vertices() as u32;
+            vertex_buffer.layout = layout;
+        }
+
+        match (&mesh.index_buffer, &mut self.index_buffer) {
+            (Some(index_buffer), Some(render_data)) => {
+                if index_buffer.modifications_counter == render_data.modifications_count {
+                    return;
+                }
+
+                let (index_count, index_format) = index_count_and_format(index_buffer);
+                uploader.update(
+                    &mut render_data.buffer,
+                    &mut render_data.allocation,
+                    "mesh_index_buffer",
+                    &index_buffer.create_packed_index_buffer_data(),
+                    DirtyRange::All,
+                    MeshSlabKey::Index(index_format),
+                    BufferUsages::INDEX,
+                );
+
+                render_data.modifications_count = index_buffer.modifications_counter;
+                render_data.index_count = index_count;
+                render_data.index_format = index_format;
+            }
+            (Some(index_buffer), None) => {
+                self.index_buffer = Some(create_index_buffer_render_data(index_buffer, uploader));
+            }
+            (None, Some(render_data)) => {
+                uploader.release(&render_data.buffer, render_data.allocation.take());
+                self.index_buffer = None;
+            }
+            (None, None) => {}
+        }
+    }
 }
 
 #[derive(Default)]
 pub struct MeshCache {
     cache: TemporaryCache<MeshRenderData>,
+    layouts: MeshVertexBufferLayouts,
+    allocator: MeshAllocator,
+    evicted: Vec<EvictedResource>,
 }
 
 impl MeshCache {
+    pub fn update(&mut self, dt: f32) {
+        let allocator = &mut self.allocator;
+        let evicted = &mut self.evicted;
+        self.cache.update_with(dt, |mesh_render_data| {
+            mesh_render_data.free(allocator);
+            evicted.extend(
+                mesh_render_data
+                    .owned_buffers()
+                    .map(|buffer| EvictedResource::Buffer(buffer.buffer.resource.clone())),
+            );
+        });
+    }
+
+    /// Buffers dropped since the last call, from evicted meshes and from slabs that
+    /// were released or replaced by compaction.
+    pub fn take_evicted(&mut self) -> Vec<EvictedResource> {
+        let mut evicted = std::mem::take(&mut self.evicted);
+        evicted.extend(self.allocator.take_evicted());
+        evicted
+    }
+
+    pub fn alive_count(&self) -> usize {
+        self.cache.alive_count()
+    }
+
+    pub fn allocator(&self) -> &MeshAllocator {
+        &self.allocator
+    }
+
+    pub fn set_allocator_settings(&mut self, settings: MeshAllocatorSettings) {
+        self.allocator.set_settings(settings);
+    }
+
+    /// Compacts fragmented slabs and moves the meshes allocated from them.
+    pub fn defragment(&mut self, device: &RenderDevice, queue: &RenderQueue) {
+        let relocations = self.allocator.defragment(device, queue);
+        if relocations.is_empty() {
+            return;
+        }
+
+        for entry in self.cache.buffer.iter_mut() {
+            entry.value.relocate(&relocations);
+        }
+    }
+
+    pub fn get(&self, id: &ResourceId<Mesh>) -> Option<&MeshRenderData> {
+        self.cache.buffer.get_raw(id.slot).map(|entry| &entry.value)
+    }
+
     pub fn get_create_mesh(
         &mut self,
         mesh: &MeshResource,
         device: &RenderDevice,
+        queue: &RenderQueue,
     ) -> Result<ResourceId<Mesh>, FrameworkError> {
         if !mesh.is_ok() {
             return Err(FrameworkError::MeshNotLoaded);
         }
 
         let mesh = mesh.data_ref();
+        let layouts = &mut self.layouts;
+        let mut uploader = MeshUploader {
+            allocator: &mut self.allocator,
+            evicted: &mut self.evicted,
+            device,
+            queue,
+        };
 
-        match self
-            .cache
-            .get_mut_or_insert_with(&mesh.cache_index, Default::default(), || {
-                create_mesh_render_data(&mesh, device)
-            }) {
-            Ok(mesh_render_data) => {
-                if mesh.vertex_buffer.modifications_counter
-                    != mesh_render_data.vertex_buffer.modifications_count
-                {
-                    mesh_render_data.vertex_buffer =
-                        create_vertex_buffer_render_data(&mesh.vertex_buffer, device)?;
-                }
-
-                if mesh.index_buffer.is_none() && mesh_render_data.index_buffer.is_some() {
-                    mesh_render_data.index_buffer = None
-                } else if mesh.index_buffer.is_some() && mesh_render_data.index_buffer.is_none() {
-                    let index_buffer = match &mesh.index_buffer {
-                        Some(index_buffer) => {
-                            Some(create_index_buffer_render_data(index_buffer, device)?)
-                        }
-                        None => None,
-                    };
-                    mesh_render_data.index_buffer = index_buffer;
-                } else if mesh.index_buffer.is_some() && mesh_render_data.index_buffer.is_some() {
-                    let mesh_index_buffer = mesh.index_buffer.as_ref().unwrap();
-                    let mesh_render_data_index_buffer =
-                        mesh_render_data.index_buffer.as_mut().unwrap();
-
-                    if mesh_index_buffer.modifications_counter
-                        != mesh_render_data_index_buffer.modifications_count
-                    {
-                        *mesh_render_data_index_buffer =
-                            create_index_buffer_render_data(mesh_index_buffer, device)?;
-                    }
-                }
+        let mut created = false;
+        let mesh_render_data = self.cache.get_mut_or_insert_with::<_, FrameworkError>(
+            &mesh.cache_index,
+            Default::default(),
+            || {
+                created = true;
+                Ok(create_mesh_render_data(&mesh, layouts, &mut uploader))
+            },
+        )?;
 
-                Ok(ResourceId::new(mesh.cache_index.get()))
-            }
-            Err(e) => {
-                return Err(e);
-            }
+        if !created {
+            mesh_render_data.update(&mesh, layouts, &mut uploader);
         }
+
+        Ok(ResourceId::new(mesh.cache_index.get()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use draft_graphics::{PrimitiveTopology, RenderServer, RenderServerSettings};
+    use draft_mesh::VertexAttributeValues;
+    use fyrox_resource::core::futures::executor::block_on;
+
+    use super::*;
+
+    #[test]
+    fn outgrown_buffers_are_evicted() {
+        let settings = RenderServerSettings {
+            force_fallback_adapter: true,
+            ..Default::default()
+        };
+        let Some(render_server) = block_on(RenderServer::try_initialize(&settings)) else {
+            return;
+        };
+        let (device, queue) = (&render_server.device, &render_server.queue);
+
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            VertexAttributeValues::Float32x3(vec![[0.0; 3]; 3]),
+        );
+        mesh.set_indices_with_u16(&[0, 1, 2]);
+        let mesh = MeshResource::new_embedded(mesh);
+
+        let mut mesh_cache = MeshCache::default();
+        let id = mesh_cache.get_create_mesh(&mesh, device, queue).unwrap();
+        let index_buffer = |mesh_cache: &MeshCache| {
+            let index_buffer = mesh_cache.get(&id).unwrap().index_buffer.as_ref();
+            index_buffer.unwrap().buffer.buffer.resource.clone()
+        };
+        let outgrown = index_buffer(&mesh_cache);
+
+        mesh.data_ref().set_indices_with_u16(&[0, 1, 2, 2, 1, 0]);
+        mesh_cache.get_create_mesh(&mesh, device, queue).unwrap();
+        mesh.data_ref().set_indices_with_u16(&[2, 1, 0]);
+        mesh_cache.get_create_mesh(&mesh, device, queue).unwrap();
+
+        let evicted = mesh_cache.take_evicted();
+        assert_eq!(evicted.len(), 1);
+        assert!(matches!(&evicted[0], EvictedResource::Buffer(buffer) if *buffer == outgrown));
+        assert_ne!(index_buffer(&mesh_cache), outgrown);
     }
 }
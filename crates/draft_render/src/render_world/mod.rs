@@ -1,21 +1,48 @@
+mod bind_group_cache;
+mod dynamic_buffer_allocator;
+mod extracted_frame;
+mod mesh_allocator;
 mod mesh_cache;
-mod render_window;
-mod temporary_cache;
+mod offscreen_target;
 mod pipeline_cache;
+mod render_window;
 mod shader_cache;
+mod temporary_cache;
+mod texture_cache;
 
-use std::marker::PhantomData;
+use std::{collections::HashMap, marker::PhantomData};
 
-use crate::FrameworkError;
-use draft_graphics::{RenderDevice, RenderServer};
+use crate::{
+    FrameworkError,
+    camera::{Camera, Msaa, RenderTarget},
+    core_2d::{Sprite, SpriteRenderer},
+    core_3d::{DEPTH_FORMAT, Light, MeshRenderer, ShadowAllocation, ShadowSettings},
+    frame_graph::PipelineContainer,
+    post_process::PostProcessRenderer,
+    render_phase::{DrawItem, RenderView, ViewTarget, view_bind_group_layout_entries},
+};
+use draft_graphics::{
+    BindGroup, BindGroupLayout, Color, RenderDevice, RenderQueue, RenderServer, Sampler,
+    SamplerDescriptor, TextureFormat,
+};
+use draft_image::{Image, ImageResource};
 use draft_mesh::{Mesh, MeshResource};
+use draft_shader::{Shader, ShaderResource};
 use draft_window::SystemWindowManager;
+use fyrox_resource::core::{log::Log, pool::Handle};
+use wgpu::TextureFormatFeatureFlags;
 
+pub use bind_group_cache::*;
+pub use dynamic_buffer_allocator::*;
+pub use extracted_frame::*;
+pub use mesh_allocator::*;
 pub use mesh_cache::*;
-pub use render_window::*;
-pub use temporary_cache::*;
+pub use offscreen_target::*;
 pub use pipeline_cache::*;
+pub use render_window::*;
 pub use shader_cache::*;
+pub use temporary_cache::*;
+pub use texture_cache::*;
 
 pub struct ResourceId<T> {
     pub slot: usize,
@@ -36,49 +63,620 @@ impl<T> ResourceId<T> {
     }
 }
 
+impl<T> Clone for ResourceId<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for ResourceId<T> {}
+
+impl<T> PartialEq for ResourceId<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.slot == other.slot
+    }
+}
+
+impl<T> Eq for ResourceId<T> {}
+
+impl<T> std::fmt::Debug for ResourceId<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ResourceId").field(&self.slot).finish()
+    }
+}
+
 impl<T> Default for ResourceId<T> {
     fn default() -> Self {
         ResourceId::INVAID
     }
 }
 
+/// Number of draw items the cameras of the last prepared frame saw and culled,
+/// summed over every view.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct VisibilityStats {
+    pub visible: usize,
+    pub culled: usize,
+}
+
+/// Number of alive entries in each of the GPU resource caches.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RenderWorldCacheStats {
+    pub mesh_count: usize,
+    pub shader_count: usize,
+    pub texture_count: usize,
+    pub bind_group_count: usize,
+}
+
 pub struct RenderWorld {
     mesh_cache: MeshCache,
+    shader_cache: ShaderCache,
+    texture_cache: TextureCache,
+    pipeline_cache: PipelineCache,
     windows: RenderWindowContainer,
+    window_events: Vec<RenderWindowEvent>,
+    offscreen_targets: OffscreenTargetContainer,
+    draw_items: Vec<DrawItem>,
+    sprites: Vec<Sprite>,
+    cameras: Vec<Camera>,
+    lights: Vec<Light>,
+    ambient_light: Color,
+    shadow_settings: ShadowSettings,
+    views: Vec<RenderView>,
+    visibility_stats: VisibilityStats,
+    bind_group_layout_cache: BindGroupLayoutCache,
+    bind_group_cache: BindGroupCache,
+    uniform_allocator: DynamicBufferAllocator,
+    storage_allocator: DynamicBufferAllocator,
+    /// Supported multisampling of each requested setting and color format.
+    msaa_fallbacks: HashMap<(Msaa, TextureFormat), Msaa>,
+    /// Set once the missing GPU culling support of the device has been reported.
+    gpu_culling_fallback_reported: bool,
+    sprite_renderer: SpriteRenderer,
+    mesh_renderer: MeshRenderer,
+    post_process_renderer: PostProcessRenderer,
 }
 
 impl RenderWorld {
     pub fn empty() -> RenderWorld {
         Self {
             mesh_cache: MeshCache::default(),
+            shader_cache: ShaderCache::default(),
+            texture_cache: TextureCache::default(),
+            pipeline_cache: PipelineCache::default(),
             windows: RenderWindowContainer::default(),
+            window_events: vec![],
+            offscreen_targets: OffscreenTargetContainer::default(),
+            draw_items: vec![],
+            sprites: vec![],
+            cameras: vec![],
+            lights: vec![],
+            ambient_light: Color {
+                r: 0.03,
+                g: 0.03,
+                b: 0.03,
+                a: 1.0,
+            },
+            shadow_settings: ShadowSettings::default(),
+            views: vec![],
+            visibility_stats: VisibilityStats::default(),
+            bind_group_layout_cache: BindGroupLayoutCache::default(),
+            bind_group_cache: BindGroupCache::default(),
+            uniform_allocator: DynamicBufferAllocator::new(DynamicBufferKind::Uniform),
+            storage_allocator: DynamicBufferAllocator::new(DynamicBufferKind::Storage),
+            msaa_fallbacks: HashMap::new(),
+            gpu_culling_fallback_reported: false,
+            sprite_renderer: SpriteRenderer::default(),
+            mesh_renderer: MeshRenderer::default(),
+            post_process_renderer: PostProcessRenderer::default(),
         }
     }
 
-    pub fn prepare_windows(
+    /// Ages the temporary caches by `dt` seconds. Entries that were not used within
+    /// their time to live are dropped together with their GPU resources, and so are
+    /// the cached bind groups that bind them.
+    pub fn update(&mut self, dt: f32) {
+        self.mesh_cache.update(dt);
+        self.shader_cache.update(dt);
+        self.texture_cache.update(dt);
+
+        self.bind_group_cache.update(dt);
+        self.bind_group_cache.evict(&self.mesh_cache.take_evicted());
+        self.bind_group_cache
+            .evict(&self.texture_cache.take_evicted());
+    }
+
+    pub fn cache_stats(&self) -> RenderWorldCacheStats {
+        RenderWorldCacheStats {
+            mesh_count: self.mesh_cache.alive_count(),
+            shader_count: self.shader_cache.alive_count(),
+            texture_count: self.texture_cache.alive_count(),
+            bind_group_count: self.bind_group_cache.len(),
+        }
+    }
+
+    pub fn visibility_stats(&self) -> VisibilityStats {
+        self.visibility_stats
+    }
+
+    /// Adds what a world submitted for the frame, see [`ExtractedFrame::extract`].
+    pub fn insert_frame(&mut self, frame: ExtractedFrame) {
+        self.draw_items.extend(frame.draw_items);
+        self.sprites.extend(frame.sprites);
+        self.cameras.extend(frame.cameras);
+        self.lights.extend(frame.lights);
+
+        if let Some(ambient_light) = frame.ambient_light {
+            self.ambient_light = ambient_light;
+        }
+        if let Some(shadow_settings) = frame.shadow_settings {
+            self.shadow_settings = shadow_settings;
+        }
+    }
+
+    pub fn draw(&mut self, draw_item: DrawItem) {
+        self.draw_items.push(draw_item);
+    }
+
+    pub fn draw_items(&self) -> &[DrawItem] {
+        &self.draw_items
+    }
+
+    pub fn draw_sprite(&mut self, sprite: Sprite) {
+        self.sprites.push(sprite);
+    }
+
+    pub fn sprites(&self) -> &[Sprite] {
+        &self.sprites
+    }
+
+    pub fn sprite_renderer(&self) -> &SpriteRenderer {
+        &self.sprite_renderer
+    }
+
+    pub fn add_camera(&mut self, camera: Camera) {
+        self.cameras.push(camera);
+    }
+
+    pub fn cameras(&self) -> &[Camera] {
+        &self.cameras
+    }
+
+    pub fn add_light(&mut self, light: Light) {
+        self.lights.push(light);
+    }
+
+    pub fn lights(&self) -> &[Light] {
+        &self.lights
+    }
+
+    /// Light that reaches every surface evenly. Unlike lights it is kept across
+    /// frames.
+    pub fn set_ambient_light(&mut self, ambient_light: Color) {
+        self.ambient_light = ambient_light;
+    }
+
+    pub fn ambient_light(&self) -> Color {
+        self.ambient_light
+    }
+
+    /// Shadow map sizes, cascades and light limits, kept across frames.
+    pub fn set_shadow_settings(&mut self, shadow_settings: ShadowSettings) {
+        self.shadow_settings = shadow_settings;
+    }
+
+    pub fn shadow_settings(&self) -> &ShadowSettings {
+        &self.shadow_settings
+    }
+
+    /// Per-frame uniform data, such as view and material parameters, bound with
+    /// dynamic offsets.
+    pub fn uniform_allocator_mut(&mut self) -> &mut DynamicBufferAllocator {
+        &mut self.uniform_allocator
+    }
+
+    /// Per-frame storage data bound with dynamic offsets.
+    pub fn storage_allocator_mut(&mut self) -> &mut DynamicBufferAllocator {
+        &mut self.storage_allocator
+    }
+
+    /// Frees the dynamic buffers of the frames the GPU has finished.
+    pub fn recycle_dynamic_buffers(&mut self) {
+        self.uniform_allocator.recycle();
+        self.storage_allocator.recycle();
+    }
+
+    /// Uploads the dynamic buffers of the frame, before it is submitted.
+    pub fn write_dynamic_buffers(&mut self, queue: &RenderQueue) {
+        self.uniform_allocator.write(queue);
+        self.storage_allocator.write(queue);
+    }
+
+    /// Keeps the dynamic buffers of the submitted frame until the GPU is done with
+    /// them.
+    pub fn retire_dynamic_buffers(&mut self, queue: &RenderQueue) {
+        self.uniform_allocator.retire(queue);
+        self.storage_allocator.retire(queue);
+    }
+
+    /// How meshes uploaded from now on store their buffers.
+    pub fn set_mesh_allocator_settings(&mut self, settings: MeshAllocatorSettings) {
+        self.mesh_cache.set_allocator_settings(settings);
+    }
+
+    pub fn mesh_renderer(&self) -> &MeshRenderer {
+        &self.mesh_renderer
+    }
+
+    pub fn post_process_renderer(&self) -> &PostProcessRenderer {
+        &self.post_process_renderer
+    }
+
+    /// Views of the frame, one for every camera with a ready target, in ascending
+    /// camera priority.
+    pub fn views(&self) -> &[RenderView] {
+        &self.views
+    }
+
+    /// Layout of the view uniform bind group shared by all view pipelines.
+    pub fn view_bind_group_layout(&mut self, device: &RenderDevice) -> BindGroupLayout {
+        self.get_or_create_bind_group_layout(
+            "view_bind_group_layout",
+            &view_bind_group_layout_entries(),
+            device,
+        )
+    }
+
+    /// The layout with the given entries, created on first use and shared with every
+    /// other caller asking for the same entries.
+    pub fn get_or_create_bind_group_layout(
+        &mut self,
+        label: &str,
+        entries: &[wgpu::BindGroupLayoutEntry],
+        device: &RenderDevice,
+    ) -> BindGroupLayout {
+        self.bind_group_layout_cache
+            .get_or_create(label, entries, device)
+    }
+
+    /// The sampler with the given settings, shared with the textures of the
+    /// [`TextureCache`] that sample the same way.
+    pub fn get_or_create_sampler(
+        &mut self,
+        desc: &SamplerDescriptor,
+        device: &RenderDevice,
+    ) -> Sampler {
+        self.texture_cache
+            .samplers_mut()
+            .get_or_create(desc, device)
+    }
+
+    /// A bind group of `layout` with the given resources, reused across frames until
+    /// it is unused for its time to live or one of its resources is evicted.
+    pub fn get_or_create_bind_group(
+        &mut self,
+        label: &str,
+        layout: &BindGroupLayout,
+        entries: &[wgpu::BindGroupEntry],
+        device: &RenderDevice,
+    ) -> BindGroup {
+        self.bind_group_cache
+            .get_or_create(label, layout, entries, device)
+    }
+
+    pub fn mesh_cache(&self) -> &MeshCache {
+        &self.mesh_cache
+    }
+
+    /// The highest multisampling up to `msaa` that the device supports for views with
+    /// the given color format and the depth format.
+    pub fn supported_msaa(
         &mut self,
+        msaa: Msaa,
+        format: TextureFormat,
         render_server: &RenderServer,
-        system_window_manager: &SystemWindowManager,
-    ) {
-        for (handle, system_window) in system_window_manager.state().pool().pair_iter() {
-            let render_window = self
-                .windows
-                .get_or_create(render_server, handle, system_window);
+    ) -> Msaa {
+        *self
+            .msaa_fallbacks
+            .entry((msaa, format))
+            .or_insert_with(|| {
+                let color = render_server.texture_format_features(format);
+                let depth = render_server.texture_format_features(DEPTH_FORMAT);
+
+                let supported = msaa.fallback(|samples| {
+                    color.flags.sample_count_supported(samples)
+                        && color
+                            .flags
+                            .contains(TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE)
+                        && depth.flags.sample_count_supported(samples)
+                });
+
+                if supported != msaa {
+                    Log::warn(format!(
+                        "{msaa:?} is not supported for {format:?} targets, using {supported:?}."
+                    ));
+                }
+
+                supported
+            })
+    }
+
+    /// Whether views can cull on the GPU, warns once when a camera asks for it on a
+    /// device that doesn't support it.
+    pub fn supports_gpu_culling(&mut self, render_server: &RenderServer) -> bool {
+        let supported = render_server.supports_gpu_culling();
+
+        if !supported && !self.gpu_culling_fallback_reported {
+            self.gpu_culling_fallback_reported = true;
+            Log::warn("GPU culling is not supported by the device, culling on the CPU.");
+        }
+
+        supported
+    }
+
+    /// Uploads the meshes of the submitted draw items and sorts the items into a view
+    /// for every camera, culling those outside of its frustum. Items whose mesh is
+    /// still loading are skipped this frame, as are cameras whose target is not
+    /// available.
+    pub fn prepare_views(&mut self, render_server: &RenderServer) {
+        let (device, queue) = (&render_server.device, &render_server.queue);
+        self.views.clear();
+        self.mesh_cache.defragment(device, queue);
+
+        let meshes = self
+            .draw_items
+            .iter()
+            .map(|draw_item| {
+                match self
+                    .mesh_cache
+                    .get_create_mesh(&draw_item.mesh, device, queue)
+                {
+                    Ok(mesh) => Some(mesh),
+                    Err(FrameworkError::MeshNotLoaded) => None,
+                    Err(e) => {
+                        Log::err(format!("Failed to upload mesh: {e}"));
+                        None
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let bounds = self
+            .draw_items
+            .iter()
+            .zip(meshes.iter())
+            .map(|(draw_item, mesh)| {
+                let bounds = self.mesh_cache.get(mesh.as_ref()?)?.bounds?;
+                Some(bounds.transform(&draw_item.world_matrix))
+            })
+            .collect::<Vec<_>>();
+        let shadow_allocation = ShadowAllocation::new(&self.lights, &self.shadow_settings);
+
+        let layout = self.view_bind_group_layout(device);
+        let mut cameras = std::mem::take(&mut self.cameras);
+        cameras.sort_by_key(|camera| camera.priority);
+
+        for (index, camera) in cameras.iter().enumerate() {
+            let Some(view_target) = self.view_target(&camera.target, device, queue) else {
+                continue;
+            };
+
+            let mut view = RenderView::new(format!("view_{index}"), camera, view_target);
+            if view.viewport.is_empty() {
+                continue;
+            }
+
+            view.msaa = self.supported_msaa(camera.msaa, view.main_format(), render_server);
+            view.gpu_culling = camera.gpu_culling && self.supports_gpu_culling(render_server);
+
+            let shadow_caster_distance = shadow_allocation.caster_distance(
+                &self.lights,
+                &self.shadow_settings,
+                &camera.world_matrix.column(3).xyz(),
+            );
+
+            view.queue(&self.draw_items, &meshes, &bounds, shadow_caster_distance);
+            view.prepare_instances(&self.draw_items, &mut self.storage_allocator, device);
+            view.prepare_uniform(
+                device,
+                &layout,
+                &mut self.uniform_allocator,
+                &mut self.bind_group_cache,
+            );
+            self.views.push(view);
+        }
+
+        self.cameras = cameras;
+
+        self.visibility_stats = VisibilityStats {
+            visible: self.views.iter().map(RenderView::visible_count).sum(),
+            culled: self.views.iter().map(|view| view.culled_count).sum(),
+        };
+
+        self.mesh_renderer.prepare(
+            &self.draw_items,
+            &self.lights,
+            self.ambient_light,
+            &self.shadow_settings,
+            &self.views,
+            layout.clone(),
+            &self.mesh_cache,
+            &mut self.texture_cache,
+            &mut self.pipeline_cache,
+            &mut self.uniform_allocator,
+            &mut self.bind_group_cache,
+            device,
+            queue,
+        );
+
+        self.sprite_renderer.prepare(
+            &self.sprites,
+            &self.views,
+            layout,
+            &mut self.texture_cache,
+            &mut self.pipeline_cache,
+            &mut self.bind_group_cache,
+            device,
+            queue,
+        );
+
+        self.post_process_renderer.prepare(
+            &self.views,
+            &mut self.texture_cache,
+            &mut self.pipeline_cache,
+            device,
+            queue,
+        );
+    }
+
+    fn view_target(
+        &mut self,
+        target: &RenderTarget,
+        device: &RenderDevice,
+        queue: &RenderQueue,
+    ) -> Option<ViewTarget> {
+        match target {
+            RenderTarget::Window(handle) => {
+                let window = self.windows.get(handle)?;
+
+                Some(ViewTarget {
+                    label: format!("window_{}_{}", handle.index(), handle.generation()),
+                    view: window.swap_chain_texture_view()?,
+                    format: window.view_format,
+                    width: window.physical_width,
+                    height: window.physical_height,
+                })
+            }
+            RenderTarget::Image(image) => {
+                let id = match self.texture_cache.get_create_texture(image, device, queue) {
+                    Ok(id) => id,
+                    Err(FrameworkError::ImageNotLoaded) => return None,
+                    Err(e) => {
+                        Log::err(format!("Failed to upload camera target: {e}"));
+                        return None;
+                    }
+                };
+
+                let texture = self.texture_cache.get(&id)?;
+                if !texture.render_target {
+                    Log::err("Camera target image is not a render target.");
+                    return None;
+                }
 
-            render_window.spawn_swapchain_texture();
+                Some(ViewTarget {
+                    label: format!("image_{}", id.slot),
+                    view: texture.view.clone(),
+                    format: texture.format.into(),
+                    width: texture.kind.width(),
+                    height: texture.kind.height(),
+                })
+            }
+            RenderTarget::Offscreen(handle) => {
+                let target = self.offscreen_targets.get(*handle)?;
+
+                Some(ViewTarget {
+                    label: format!("offscreen_{}_{}", handle.index(), handle.generation()),
+                    view: target.view.clone(),
+                    format: OFFSCREEN_TARGET_FORMAT,
+                    width: target.width,
+                    height: target.height,
+                })
+            }
         }
     }
 
-    pub fn clear_windows(
+    /// Drops the draw items, sprites, cameras, lights and views of the finished frame.
+    pub fn clear_draws(&mut self) {
+        self.draw_items.clear();
+        self.sprites.clear();
+        self.cameras.clear();
+        self.lights.clear();
+        self.views.clear();
+    }
+
+    pub fn windows(&self) -> &RenderWindowContainer {
+        &self.windows
+    }
+
+    /// Creates a texture that cameras can render into without any window.
+    pub fn create_offscreen_target(
+        &mut self,
+        width: u32,
+        height: u32,
+        device: &RenderDevice,
+    ) -> Handle<OffscreenTarget> {
+        self.offscreen_targets
+            .add(OffscreenTarget::new(width, height, device))
+    }
+
+    pub fn remove_offscreen_target(&mut self, handle: Handle<OffscreenTarget>) {
+        self.offscreen_targets.remove(handle);
+    }
+
+    pub fn offscreen_target(&self, handle: Handle<OffscreenTarget>) -> Option<&OffscreenTarget> {
+        self.offscreen_targets.get(handle)
+    }
+
+    /// Reads the last frame rendered into an offscreen target back as RGBA8 bytes.
+    pub fn read_offscreen_target(
+        &self,
+        handle: Handle<OffscreenTarget>,
+        render_server: &RenderServer,
+    ) -> Option<Result<Vec<u8>, FrameworkError>> {
+        let target = self.offscreen_targets.get(handle)?;
+
+        Some(target.read_rgba8(&render_server.device, &render_server.queue))
+    }
+
+    pub fn pipeline_container(&self) -> &PipelineContainer {
+        self.pipeline_cache.pipeline_container()
+    }
+
+    pub fn pipeline_cache(&self) -> &PipelineCache {
+        &self.pipeline_cache
+    }
+
+    pub fn pipeline_cache_mut(&mut self) -> &mut PipelineCache {
+        &mut self.pipeline_cache
+    }
+
+    pub fn process_pipeline_queue(&mut self, device: &RenderDevice) {
+        self.pipeline_cache
+            .process_queue(device, &mut self.shader_cache);
+    }
+
+    pub fn prepare_windows(
         &mut self,
         render_server: &RenderServer,
         system_window_manager: &SystemWindowManager,
     ) {
-        for (handle, system_window) in system_window_manager.state().pool().pair_iter() {
-            let render_window = self
-                .windows
-                .get_or_create(render_server, handle, system_window);
+        self.window_events.clear();
+
+        let state = system_window_manager.state();
+        let pool = state.pool();
+        for handle in self
+            .windows
+            .remove_closed(|handle| pool.is_valid_handle(handle))
+        {
+            self.window_events
+                .push(RenderWindowEvent::Closed { handle });
+        }
+
+        for (handle, system_window) in pool.pair_iter() {
+            if let Some(event) = self.windows.prepare(render_server, handle, system_window) {
+                self.window_events.push(event);
+            }
+        }
+    }
+
+    /// Changes of the windows found by the last [`RenderWorld::prepare_windows`].
+    pub fn window_events(&self) -> &[RenderWindowEvent] {
+        &self.window_events
+    }
 
+    /// Presents the textures acquired by [`RenderWorld::prepare_windows`].
+    pub fn clear_windows(&mut self) {
+        for render_window in self.windows.iter_mut() {
             render_window.clear_swapchain_texture();
         }
     }
@@ -87,7 +685,29 @@ impl RenderWorld {
         &mut self,
         mesh: &MeshResource,
         device: &RenderDevice,
+        queue: &RenderQueue,
     ) -> Result<ResourceId<Mesh>, FrameworkError> {
-        self.mesh_cache.get_create_mesh(mesh, device)
+        self.mesh_cache.get_create_mesh(mesh, device, queue)
+    }
+
+    pub fn get_create_shader(
+        &mut self,
+        shader: &ShaderResource,
+        device: &RenderDevice,
+    ) -> Result<ResourceId<Shader>, FrameworkError> {
+        self.shader_cache.get_create_shader(device, shader)
+    }
+
+    pub fn get_create_texture(
+        &mut self,
+        image: &ImageResource,
+        device: &RenderDevice,
+        queue: &RenderQueue,
+    ) -> Result<ResourceId<Image>, FrameworkError> {
+        self.texture_cache.get_create_texture(image, device, queue)
+    }
+
+    pub fn texture_cache(&self) -> &TextureCache {
+        &self.texture_cache
     }
 }
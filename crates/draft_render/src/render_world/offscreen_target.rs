@@ -0,0 +1,178 @@
+use std::sync::mpsc;
+
+use draft_graphics::{
+    Extent3d, RenderDevice, RenderQueue, Texture, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureUsages, TextureView, TextureViewDescriptor,
+};
+use fyrox_resource::core::pool::{Handle, Pool};
+use wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+
+use crate::FrameworkError;
+
+/// Format of the offscreen targets, read back as RGBA8 bytes.
+pub const OFFSCREEN_TARGET_FORMAT: TextureFormat = TextureFormat::Rgba8UnormSrgb;
+
+const OFFSCREEN_TARGET_PIXEL_SIZE: u32 = 4;
+
+/// A texture owned by the render world that cameras render into without a window,
+/// see [`RenderTarget::Offscreen`](crate::camera::RenderTarget::Offscreen).
+pub struct OffscreenTarget {
+    pub texture: Texture,
+    pub view: TextureView,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl OffscreenTarget {
+    pub fn new(width: u32, height: u32, device: &RenderDevice) -> Self {
+        let texture = device.create_gpu_texture(&TextureDescriptor {
+            label: Some("offscreen_target"),
+            size: Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: OFFSCREEN_TARGET_FORMAT,
+            usage: TextureUsages::RENDER_ATTACHMENT
+                | TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&TextureViewDescriptor::default());
+
+        Self {
+            texture,
+            view,
+            width: width.max(1),
+            height: height.max(1),
+        }
+    }
+
+    /// Copies the last rendered frame to CPU memory as tightly packed RGBA8 rows,
+    /// blocking until the GPU has finished the frame.
+    pub fn read_rgba8(
+        &self,
+        device: &RenderDevice,
+        queue: &RenderQueue,
+    ) -> Result<Vec<u8>, FrameworkError> {
+        let row_size = self.width * OFFSCREEN_TARGET_PIXEL_SIZE;
+        let padded_row_size = padded_bytes_per_row(row_size);
+
+        let buffer = device.create_gpu_buffer(&wgpu::BufferDescriptor {
+            label: Some("offscreen_target_readback"),
+            size: (padded_row_size * self.height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("offscreen_target_readback"),
+        });
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_size),
+                    rows_per_image: Some(self.height),
+                },
+            },
+            Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit([encoder.finish()]);
+
+        let slice = buffer.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+
+        device
+            .wgpu_device()
+            .poll(wgpu::PollType::wait_indefinitely())
+            .map_err(|e| FrameworkError::Readback(e.to_string()))?;
+
+        receiver
+            .recv()
+            .map_err(|e| FrameworkError::Readback(e.to_string()))?
+            .map_err(|e| FrameworkError::Readback(e.to_string()))?;
+
+        let data = unpad_rows(
+            &slice.get_mapped_range(),
+            row_size as usize,
+            padded_row_size as usize,
+            self.height as usize,
+        );
+        buffer.unmap();
+
+        Ok(data)
+    }
+}
+
+/// Row size of a texture to buffer copy, rounded up to the alignment required by
+/// the copy.
+fn padded_bytes_per_row(row_size: u32) -> u32 {
+    row_size.div_ceil(COPY_BYTES_PER_ROW_ALIGNMENT) * COPY_BYTES_PER_ROW_ALIGNMENT
+}
+
+/// Drops the padding at the end of every row.
+fn unpad_rows(data: &[u8], row_size: usize, padded_row_size: usize, height: usize) -> Vec<u8> {
+    data.chunks(padded_row_size)
+        .take(height)
+        .flat_map(|row| &row[..row_size])
+        .copied()
+        .collect()
+}
+
+#[derive(Default)]
+pub struct OffscreenTargetContainer {
+    targets: Pool<OffscreenTarget>,
+}
+
+impl OffscreenTargetContainer {
+    pub fn add(&mut self, target: OffscreenTarget) -> Handle<OffscreenTarget> {
+        self.targets.spawn(target)
+    }
+
+    pub fn remove(&mut self, handle: Handle<OffscreenTarget>) -> Option<OffscreenTarget> {
+        self.targets.try_free(handle).ok()
+    }
+
+    pub fn get(&self, handle: Handle<OffscreenTarget>) -> Option<&OffscreenTarget> {
+        self.targets.try_borrow(handle).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_are_padded_to_copy_alignment() {
+        assert_eq!(padded_bytes_per_row(4), 256);
+        assert_eq!(padded_bytes_per_row(256), 256);
+        assert_eq!(padded_bytes_per_row(260), 512);
+    }
+
+    #[test]
+    fn unpadding_keeps_pixels_of_every_row() {
+        let mut data = vec![0xff; 2 * 8];
+        data[..4].copy_from_slice(&[1, 2, 3, 4]);
+        data[8..12].copy_from_slice(&[5, 6, 7, 8]);
+
+        assert_eq!(unpad_rows(&data, 4, 8, 2), [1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+}
@@ -1,11 +1,47 @@
-use draft_graphics::{DepthStencilState, MultisampleState, PrimitiveState, RenderPipeline};
+use std::{collections::HashMap, hash::Hash};
 
-pub struct GpuVertexState {}
+use draft_graphics::{
+    BindGroupLayout, ColorTargetState, DepthStencilState, MultisampleState, Pipeline,
+    PrimitiveState, RenderDevice, RenderPipeline,
+};
+use draft_mesh::{MeshVertexBufferLayoutRef, MissingVertexAttributeError, VertexBufferLayout};
+use draft_shader::{ShaderDefVal, ShaderResource};
+use thiserror::Error;
+use wgpu::ComputePipeline;
 
-pub struct GpuFragmentState {}
+use crate::{frame_graph::PipelineContainer, render_world::ShaderCache};
 
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub struct CachedPipelineId(usize);
+
+impl CachedPipelineId {
+    pub const INVALID: CachedPipelineId = CachedPipelineId(usize::MAX);
+
+    pub fn id(&self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GpuVertexState {
+    pub shader: ShaderResource,
+    pub entry_point: Option<String>,
+    pub shader_defs: Vec<ShaderDefVal>,
+    pub buffers: Vec<VertexBufferLayout>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GpuFragmentState {
+    pub shader: ShaderResource,
+    pub entry_point: Option<String>,
+    pub shader_defs: Vec<ShaderDefVal>,
+    pub targets: Vec<Option<ColorTargetState>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct GpuRenderPipelineDescriptor {
     pub label: String,
+    pub layout: Vec<BindGroupLayout>,
     pub vertex: GpuVertexState,
     pub primitive: PrimitiveState,
     pub depth_stencil: Option<DepthStencilState>,
@@ -13,14 +49,501 @@ pub struct GpuRenderPipelineDescriptor {
     pub fragment: Option<GpuFragmentState>,
 }
 
-pub struct PipelineCache {}
+#[derive(Clone, Debug, PartialEq)]
+pub struct GpuComputePipelineDescriptor {
+    pub label: String,
+    pub layout: Vec<BindGroupLayout>,
+    pub shader: ShaderResource,
+    pub entry_point: Option<String>,
+    pub shader_defs: Vec<ShaderDefVal>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PipelineDescriptor {
+    RenderPipelineDescriptor(Box<GpuRenderPipelineDescriptor>),
+    ComputePipelineDescriptor(Box<GpuComputePipelineDescriptor>),
+}
+
+impl PipelineDescriptor {
+    pub fn label(&self) -> &str {
+        match self {
+            PipelineDescriptor::RenderPipelineDescriptor(descriptor) => &descriptor.label,
+            PipelineDescriptor::ComputePipelineDescriptor(descriptor) => &descriptor.label,
+        }
+    }
+
+    pub fn shaders(&self) -> Vec<&ShaderResource> {
+        match self {
+            PipelineDescriptor::RenderPipelineDescriptor(descriptor) => {
+                std::iter::once(&descriptor.vertex.shader)
+                    .chain(
+                        descriptor
+                            .fragment
+                            .as_ref()
+                            .map(|fragment| &fragment.shader),
+                    )
+                    .collect()
+            }
+            PipelineDescriptor::ComputePipelineDescriptor(descriptor) => vec![&descriptor.shader],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Error)]
+pub enum PipelineCacheError {
+    #[error("Shader of pipeline failed to load.")]
+    ShaderLoadFailed,
+    #[error("Failed to compile shader: {0}")]
+    ShaderCompilation(String),
+    #[error("Failed to create pipeline: {0}")]
+    CreatePipeline(String),
+}
+
+#[derive(Debug, Clone)]
+pub enum CachedPipelineState {
+    /// Waiting for its shaders to load.
+    Queued,
+    Ok,
+    Err(PipelineCacheError),
+}
+
+struct CachedPipeline {
+    descriptor: PipelineDescriptor,
+    state: CachedPipelineState,
+}
+
+/// The pipelines created from a shader, and the modifications counter of the shader
+/// the last time the queue was processed.
+#[derive(Default)]
+struct ShaderUsers {
+    modifications_counter: Option<u64>,
+    pipelines: Vec<CachedPipelineId>,
+}
+
+/// Hands out pipeline ids immediately and compiles the pipelines once their shaders
+/// are loaded. Compiled pipelines are stored in a [`PipelineContainer`] at the index
+/// of their id, so the frame graph can look them up.
+///
+/// When a shader is modified, the pipelines created from it are queued again. The
+/// previous pipeline stays in the container until the new one is created.
+#[derive(Default)]
+pub struct PipelineCache {
+    pipelines: Vec<CachedPipeline>,
+    waiting_pipelines: Vec<CachedPipelineId>,
+    shader_users: HashMap<ShaderResource, ShaderUsers>,
+    pipeline_container: PipelineContainer,
+}
 
 impl PipelineCache {
-    pub fn create_render_pipeline(
+    fn queue_pipeline(&mut self, descriptor: PipelineDescriptor) -> CachedPipelineId {
+        let id = CachedPipelineId(self.pipelines.len());
+
+        for shader in descriptor.shaders() {
+            let users = self.shader_users.entry(shader.clone()).or_default();
+            if !users.pipelines.contains(&id) {
+                users.pipelines.push(id);
+            }
+        }
+
+        self.pipelines.push(CachedPipeline {
+            descriptor,
+            state: CachedPipelineState::Queued,
+        });
+        self.waiting_pipelines.push(id);
+
+        id
+    }
+
+    pub fn queue_render_pipeline(
+        &mut self,
+        descriptor: GpuRenderPipelineDescriptor,
+    ) -> CachedPipelineId {
+        self.queue_pipeline(PipelineDescriptor::RenderPipelineDescriptor(Box::new(
+            descriptor,
+        )))
+    }
+
+    pub fn queue_compute_pipeline(
         &mut self,
-        _desc: &GpuRenderPipelineDescriptor,
-    ) -> RenderPipeline {
-        // self.device.create_render_pipelie(&desc.create_desc())
-        todo!()
+        descriptor: GpuComputePipelineDescriptor,
+    ) -> CachedPipelineId {
+        self.queue_pipeline(PipelineDescriptor::ComputePipelineDescriptor(Box::new(
+            descriptor,
+        )))
+    }
+
+    pub fn get_render_pipeline_state(&self, id: CachedPipelineId) -> Option<&CachedPipelineState> {
+        self.pipelines.get(id.0).map(|pipeline| &pipeline.state)
+    }
+
+    pub fn get_render_pipeline_descriptor(
+        &self,
+        id: CachedPipelineId,
+    ) -> Option<&GpuRenderPipelineDescriptor> {
+        match &self.pipelines.get(id.0)?.descriptor {
+            PipelineDescriptor::RenderPipelineDescriptor(descriptor) => Some(descriptor),
+            PipelineDescriptor::ComputePipelineDescriptor(_) => None,
+        }
+    }
+
+    pub fn get_render_pipeline(&self, id: CachedPipelineId) -> Option<&RenderPipeline> {
+        self.pipeline_container.get_render_pipeline(id.0)
+    }
+
+    pub fn get_compute_pipeline(&self, id: CachedPipelineId) -> Option<&ComputePipeline> {
+        self.pipeline_container.get_compute_pipeline(id.0)
+    }
+
+    pub fn pipeline_container(&self) -> &PipelineContainer {
+        &self.pipeline_container
+    }
+
+    /// Queues again the pipelines whose shaders were modified since the last time the
+    /// queue was processed.
+    fn queue_modified_pipelines(&mut self) {
+        for (shader, users) in self.shader_users.iter_mut() {
+            if !shader.is_ok() {
+                continue;
+            }
+
+            let modifications_counter = shader.data_ref().modifications_counter;
+            let previous = users.modifications_counter.replace(modifications_counter);
+            if previous.is_none_or(|previous| previous == modifications_counter) {
+                continue;
+            }
+
+            for id in users.pipelines.iter() {
+                let pipeline = &mut self.pipelines[id.0];
+                if !matches!(pipeline.state, CachedPipelineState::Queued) {
+                    pipeline.state = CachedPipelineState::Queued;
+                    self.waiting_pipelines.push(*id);
+                }
+            }
+        }
+    }
+
+    pub fn process_queue(&mut self, device: &RenderDevice, shader_cache: &mut ShaderCache) {
+        self.queue_modified_pipelines();
+
+        let waiting_pipelines = std::mem::take(&mut self.waiting_pipelines);
+
+        for id in waiting_pipelines {
+            let pipeline = &mut self.pipelines[id.0];
+
+            let result = match &pipeline.descriptor {
+                PipelineDescriptor::RenderPipelineDescriptor(descriptor) => {
+                    create_render_pipeline(device, shader_cache, descriptor)
+                        .map(|pipeline| pipeline.map(Pipeline::RenderPipeline))
+                }
+                PipelineDescriptor::ComputePipelineDescriptor(descriptor) => {
+                    create_compute_pipeline(device, shader_cache, descriptor)
+                        .map(|pipeline| pipeline.map(Pipeline::ComputePipeline))
+                }
+            };
+
+            match result {
+                Ok(Some(created_pipeline)) => {
+                    pipeline.state = CachedPipelineState::Ok;
+                    self.pipeline_container.set(id.0, created_pipeline);
+                }
+                Ok(None) => {
+                    self.waiting_pipelines.push(id);
+                }
+                Err(e) => {
+                    fyrox_resource::core::log::Log::err(format!(
+                        "Failed to create pipeline {}: {e}",
+                        pipeline.descriptor.label()
+                    ));
+                    pipeline.state = CachedPipelineState::Err(e);
+                }
+            }
+        }
+    }
+}
+
+fn is_shader_ready(shader: &ShaderResource) -> Result<bool, PipelineCacheError> {
+    if shader.is_failed_to_load() {
+        return Err(PipelineCacheError::ShaderLoadFailed);
+    }
+
+    Ok(shader.is_ok())
+}
+
+/// Returns `Ok(None)` while the shaders of the pipeline are still loading.
+fn create_render_pipeline(
+    device: &RenderDevice,
+    shader_cache: &mut ShaderCache,
+    descriptor: &GpuRenderPipelineDescriptor,
+) -> Result<Option<RenderPipeline>, PipelineCacheError> {
+    if !is_shader_ready(&descriptor.vertex.shader)? {
+        return Ok(None);
+    }
+
+    if let Some(fragment) = &descriptor.fragment
+        && !is_shader_ready(&fragment.shader)?
+    {
+        return Ok(None);
+    }
+
+    let vertex_module = shader_cache
+        .get_shader_module(
+            device,
+            &descriptor.vertex.shader,
+            &descriptor.vertex.shader_defs,
+        )
+        .map_err(|e| PipelineCacheError::ShaderCompilation(e.to_string()))?;
+
+    let fragment_module = match &descriptor.fragment {
+        Some(fragment) => Some(
+            shader_cache
+                .get_shader_module(device, &fragment.shader, &fragment.shader_defs)
+                .map_err(|e| PipelineCacheError::ShaderCompilation(e.to_string()))?,
+        ),
+        None => None,
+    };
+
+    let vertex_attributes = descriptor
+        .vertex
+        .buffers
+        .iter()
+        .map(|layout| {
+            layout
+                .attributes
+                .iter()
+                .map(|attribute| wgpu::VertexAttribute::from(*attribute))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let vertex_buffer_layouts = descriptor
+        .vertex
+        .buffers
+        .iter()
+        .zip(vertex_attributes.iter())
+        .map(|(layout, attributes)| wgpu::VertexBufferLayout {
+            array_stride: layout.array_stride,
+            step_mode: layout.step_mode,
+            attributes,
+        })
+        .collect::<Vec<_>>();
+
+    let bind_group_layouts = descriptor.layout.iter().map(Some).collect::<Vec<_>>();
+
+    device
+        .catch_validation_error(|_| {
+            let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some(&descriptor.label),
+                bind_group_layouts: &bind_group_layouts,
+                immediate_size: 0,
+            });
+
+            device.create_render_pipelie(&wgpu::RenderPipelineDescriptor {
+                label: Some(&descriptor.label),
+                layout: Some(&layout),
+                vertex: wgpu::VertexState {
+                    module: &vertex_module,
+                    entry_point: descriptor.vertex.entry_point.as_deref(),
+                    compilation_options: Default::default(),
+                    buffers: &vertex_buffer_layouts,
+                },
+                primitive: descriptor.primitive,
+                depth_stencil: descriptor.depth_stencil.clone(),
+                multisample: descriptor.multisample,
+                fragment: descriptor
+                    .fragment
+                    .as_ref()
+                    .zip(fragment_module.as_ref())
+                    .map(|(fragment, module)| wgpu::FragmentState {
+                        module,
+                        entry_point: fragment.entry_point.as_deref(),
+                        compilation_options: Default::default(),
+                        targets: &fragment.targets,
+                    }),
+                multiview_mask: None,
+                cache: None,
+            })
+        })
+        .map(Some)
+        .map_err(|e| PipelineCacheError::CreatePipeline(e.to_string()))
+}
+
+/// Returns `Ok(None)` while the shader of the pipeline is still loading.
+fn create_compute_pipeline(
+    device: &RenderDevice,
+    shader_cache: &mut ShaderCache,
+    descriptor: &GpuComputePipelineDescriptor,
+) -> Result<Option<ComputePipeline>, PipelineCacheError> {
+    if !is_shader_ready(&descriptor.shader)? {
+        return Ok(None);
+    }
+
+    let module = shader_cache
+        .get_shader_module(device, &descriptor.shader, &descriptor.shader_defs)
+        .map_err(|e| PipelineCacheError::ShaderCompilation(e.to_string()))?;
+
+    let bind_group_layouts = descriptor.layout.iter().map(Some).collect::<Vec<_>>();
+
+    device
+        .catch_validation_error(|_| {
+            let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some(&descriptor.label),
+                bind_group_layouts: &bind_group_layouts,
+                immediate_size: 0,
+            });
+
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(&descriptor.label),
+                layout: Some(&layout),
+                module: &module,
+                entry_point: descriptor.entry_point.as_deref(),
+                compilation_options: Default::default(),
+                cache: None,
+            })
+        })
+        .map(Some)
+        .map_err(|e| PipelineCacheError::CreatePipeline(e.to_string()))
+}
+
+pub trait SpecializedRenderPipeline {
+    type Key: Clone + Hash + PartialEq + Eq;
+
+    fn specialize(&self, key: Self::Key) -> GpuRenderPipelineDescriptor;
+}
+
+pub struct SpecializedRenderPipelines<S: SpecializedRenderPipeline> {
+    cache: HashMap<S::Key, CachedPipelineId>,
+}
+
+impl<S: SpecializedRenderPipeline> Default for SpecializedRenderPipelines<S> {
+    fn default() -> Self {
+        Self {
+            cache: HashMap::default(),
+        }
+    }
+}
+
+impl<S: SpecializedRenderPipeline> SpecializedRenderPipelines<S> {
+    pub fn specialize(
+        &mut self,
+        pipeline_cache: &mut PipelineCache,
+        specialize_pipeline: &S,
+        key: S::Key,
+    ) -> CachedPipelineId {
+        *self.cache.entry(key.clone()).or_insert_with(|| {
+            pipeline_cache.queue_render_pipeline(specialize_pipeline.specialize(key))
+        })
+    }
+}
+
+pub trait SpecializedMeshPipeline {
+    type Key: Clone + Hash + PartialEq + Eq;
+
+    fn specialize(
+        &self,
+        key: Self::Key,
+        layout: &MeshVertexBufferLayoutRef,
+    ) -> Result<GpuRenderPipelineDescriptor, MissingVertexAttributeError>;
+}
+
+pub struct SpecializedMeshPipelines<S: SpecializedMeshPipeline> {
+    cache: HashMap<(S::Key, MeshVertexBufferLayoutRef), CachedPipelineId>,
+}
+
+impl<S: SpecializedMeshPipeline> Default for SpecializedMeshPipelines<S> {
+    fn default() -> Self {
+        Self {
+            cache: HashMap::default(),
+        }
+    }
+}
+
+impl<S: SpecializedMeshPipeline> SpecializedMeshPipelines<S> {
+    pub fn specialize(
+        &mut self,
+        pipeline_cache: &mut PipelineCache,
+        specialize_pipeline: &S,
+        key: S::Key,
+        layout: &MeshVertexBufferLayoutRef,
+    ) -> Result<CachedPipelineId, MissingVertexAttributeError> {
+        let cache_key = (key.clone(), layout.clone());
+
+        if let Some(id) = self.cache.get(&cache_key) {
+            return Ok(*id);
+        }
+
+        let descriptor = specialize_pipeline.specialize(key, layout)?;
+        let id = pipeline_cache.queue_render_pipeline(descriptor);
+        self.cache.insert(cache_key, id);
+
+        Ok(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use draft_graphics::{RenderServer, RenderServerSettings};
+    use draft_shader::Shader;
+    use fyrox_resource::core::futures::executor::block_on;
+
+    use super::*;
+
+    const COMPUTE_SHADER: &str = "@compute @workgroup_size(1) fn main() {}";
+
+    #[test]
+    fn modified_shaders_queue_their_pipelines_again() {
+        let settings = RenderServerSettings {
+            force_fallback_adapter: true,
+            ..Default::default()
+        };
+        let Some(render_server) = block_on(RenderServer::try_initialize(&settings)) else {
+            return;
+        };
+        let device = &render_server.device;
+
+        let shader = ShaderResource::new_embedded(Shader {
+            source: COMPUTE_SHADER.into(),
+            ..Default::default()
+        });
+        let mut shader_cache = ShaderCache::default();
+        let mut pipeline_cache = PipelineCache::default();
+        let id = pipeline_cache.queue_compute_pipeline(GpuComputePipelineDescriptor {
+            label: "pipeline".to_string(),
+            layout: vec![],
+            shader: shader.clone(),
+            entry_point: Some("main".to_string()),
+            shader_defs: vec![],
+        });
+
+        pipeline_cache.process_queue(device, &mut shader_cache);
+        assert!(matches!(
+            pipeline_cache.get_render_pipeline_state(id),
+            Some(CachedPipelineState::Ok)
+        ));
+
+        pipeline_cache.process_queue(device, &mut shader_cache);
+        assert!(pipeline_cache.waiting_pipelines.is_empty());
+
+        {
+            let mut shader = shader.data_ref();
+            shader.source = "@compute fn main(".into();
+            shader.modifications_counter += 1;
+        }
+        pipeline_cache.process_queue(device, &mut shader_cache);
+        assert!(matches!(
+            pipeline_cache.get_render_pipeline_state(id),
+            Some(CachedPipelineState::Err(_))
+        ));
+        assert!(pipeline_cache.get_compute_pipeline(id).is_some());
+
+        {
+            let mut shader = shader.data_ref();
+            shader.source = COMPUTE_SHADER.into();
+            shader.modifications_counter += 1;
+        }
+        pipeline_cache.process_queue(device, &mut shader_cache);
+        assert!(matches!(
+            pipeline_cache.get_render_pipeline_state(id),
+            Some(CachedPipelineState::Ok)
+        ));
     }
 }
@@ -1,9 +1,139 @@
 use std::collections::HashMap;
 
 use draft_graphics::{RenderDevice, RenderServer, Surface, SurfaceConfiguration, SurfaceTexture};
-use draft_window::SystemWindow;
-use fyrox_resource::core::pool::Handle;
-use wgpu::{CurrentSurfaceTexture, TextureFormat};
+use draft_window::{PresentMode, SurfaceFormat, SurfacePreferences, SystemWindow, WindowAlphaMode};
+use fyrox_resource::core::{log::Log, pool::Handle};
+use wgpu::{
+    CompositeAlphaMode, CurrentSurfaceTexture, SurfaceCapabilities, TextureFormat, TextureView,
+};
+
+/// Changes of the render windows found while preparing a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderWindowEvent {
+    /// The physical size of the window changed. A size of zero means the window is
+    /// minimized and not rendered.
+    Resized {
+        handle: Handle<SystemWindow>,
+        width: u32,
+        height: u32,
+    },
+    /// The window was despawned and its surface dropped.
+    Closed { handle: Handle<SystemWindow> },
+}
+
+/// Present modes tried in order for each preference. Fifo is supported everywhere.
+fn present_mode_chain(present_mode: PresentMode) -> &'static [wgpu::PresentMode] {
+    match present_mode {
+        PresentMode::Vsync => &[wgpu::PresentMode::FifoRelaxed, wgpu::PresentMode::Fifo],
+        PresentMode::NoVsync => &[
+            wgpu::PresentMode::Immediate,
+            wgpu::PresentMode::Mailbox,
+            wgpu::PresentMode::Fifo,
+        ],
+        PresentMode::Mailbox => &[wgpu::PresentMode::Mailbox, wgpu::PresentMode::Fifo],
+    }
+}
+
+fn select_present_mode(
+    present_mode: PresentMode,
+    supported: &[wgpu::PresentMode],
+) -> wgpu::PresentMode {
+    present_mode_chain(present_mode)
+        .iter()
+        .find(|present_mode| supported.contains(present_mode))
+        .copied()
+        .unwrap_or(wgpu::PresentMode::Fifo)
+}
+
+/// The format of the surface and the format its textures are viewed with. Falls
+/// back to the first supported format, viewed as sRGB.
+fn select_format(
+    format: SurfaceFormat,
+    supported: &[TextureFormat],
+) -> Option<(TextureFormat, TextureFormat)> {
+    let is_8_bit = |format: &&TextureFormat| {
+        matches!(
+            format.remove_srgb_suffix(),
+            TextureFormat::Bgra8Unorm | TextureFormat::Rgba8Unorm
+        )
+    };
+    let srgb = || {
+        supported
+            .iter()
+            .find(is_8_bit)
+            .map(|format| (*format, format.add_srgb_suffix()))
+    };
+
+    let selected = match format {
+        SurfaceFormat::Srgb => srgb(),
+        SurfaceFormat::Linear => supported
+            .iter()
+            .filter(is_8_bit)
+            .find(|format| !format.is_srgb())
+            .map(|format| (*format, *format)),
+        SurfaceFormat::Hdr10 => supported
+            .iter()
+            .find(|format| **format == TextureFormat::Rgb10a2Unorm)
+            .map(|format| (*format, *format))
+            .or_else(srgb),
+    };
+
+    selected.or_else(|| {
+        supported
+            .first()
+            .map(|format| (*format, format.add_srgb_suffix()))
+    })
+}
+
+fn select_alpha_mode(
+    alpha_mode: WindowAlphaMode,
+    supported: &[CompositeAlphaMode],
+) -> CompositeAlphaMode {
+    let preferred: &[CompositeAlphaMode] = match alpha_mode {
+        WindowAlphaMode::Opaque => &[CompositeAlphaMode::Opaque],
+        WindowAlphaMode::Transparent => &[
+            CompositeAlphaMode::PreMultiplied,
+            CompositeAlphaMode::PostMultiplied,
+            CompositeAlphaMode::Inherit,
+        ],
+    };
+
+    preferred
+        .iter()
+        .chain(supported)
+        .find(|alpha_mode| supported.contains(alpha_mode))
+        .copied()
+        .unwrap_or(CompositeAlphaMode::Auto)
+}
+
+/// The configuration closest to the preferences that the surface supports, with
+/// the format the surface textures are viewed with.
+fn surface_configuration(
+    capabilities: &SurfaceCapabilities,
+    preferences: &SurfacePreferences,
+    width: u32,
+    height: u32,
+) -> (SurfaceConfiguration, TextureFormat) {
+    let (format, view_format) = select_format(preferences.format, &capabilities.formats)
+        .expect("No supported formats for surface");
+
+    let surface_config = wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format,
+        view_formats: if view_format == format {
+            vec![]
+        } else {
+            vec![view_format]
+        },
+        alpha_mode: select_alpha_mode(preferences.alpha_mode, &capabilities.alpha_modes),
+        width,
+        height,
+        desired_maximum_frame_latency: preferences.max_frame_latency.max(1),
+        present_mode: select_present_mode(preferences.present_mode, &capabilities.present_modes),
+    };
+
+    (surface_config, view_format)
+}
 
 pub struct RenderWindow {
     pub handle: Handle<SystemWindow>,
@@ -12,8 +142,18 @@ pub struct RenderWindow {
     pub surface_config: SurfaceConfiguration,
     pub surface: Surface<'static>,
     pub surface_format: TextureFormat,
+    /// Format of the views of the surface textures that are rendered into.
+    pub view_format: TextureFormat,
+    /// The preferences the surface was configured with.
+    pub surface_preferences: SurfacePreferences,
 
     pub swap_chain_texture: Option<SurfaceTexture>,
+    /// Set when the last acquired texture no longer matched the surface, the surface
+    /// is configured again before the next frame.
+    needs_configure: bool,
+    /// Declared after the surface, so the surface is dropped before the window it
+    /// was created for.
+    pub system_window: SystemWindow,
 }
 
 impl RenderWindow {
@@ -23,45 +163,128 @@ impl RenderWindow {
         system_window: &SystemWindow,
     ) -> Self {
         let size = system_window.get_window().get_physical_size();
-        let surface = render_server.create_surface(&system_window);
-        let caps = surface.get_capabilities(&render_server.adapter);
-
-        let formats = caps.formats;
-        let format = *formats.first().expect("No supported formats for surface");
-
-        let surface_config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: format,
-            view_formats: vec![format.add_srgb_suffix()],
-            alpha_mode: wgpu::CompositeAlphaMode::Auto,
-            width: size.width,
-            height: size.height,
-            desired_maximum_frame_latency: 2,
-            present_mode: wgpu::PresentMode::AutoVsync,
-        };
+        let surface = render_server.create_surface(system_window);
+        let capabilities = surface.get_capabilities(&render_server.adapter);
+        let surface_preferences = system_window.surface_preferences();
+        let (surface_config, view_format) =
+            surface_configuration(&capabilities, &surface_preferences, size.width, size.height);
 
         Self {
             handle,
+            system_window: system_window.clone(),
             physical_width: size.width,
             physical_height: size.height,
+            surface_format: surface_config.format,
+            view_format,
+            surface_preferences,
             surface_config,
             surface,
-            surface_format: format,
             swap_chain_texture: None,
+            needs_configure: false,
         }
     }
 
+    /// Whether the window has no area to render into, as when it is minimized.
+    pub fn is_minimized(&self) -> bool {
+        self.physical_width == 0 || self.physical_height == 0
+    }
+
     pub fn configure(&self, device: &RenderDevice) {
+        // Surfaces can't be configured with a zero size, they are configured again
+        // once the window is restored.
+        if self.is_minimized() {
+            return;
+        }
+
         self.surface
             .configure(device.wgpu_device(), &self.surface_config);
     }
 
-    pub fn spawn_swapchain_texture(&mut self) {
-        if let CurrentSurfaceTexture::Success(swap_chain_texture) =
-            self.surface.get_current_texture()
-        {
-            self.swap_chain_texture = Some(swap_chain_texture);
+    /// Configures the surface with new preferences.
+    pub fn set_surface_preferences(
+        &mut self,
+        surface_preferences: SurfacePreferences,
+        render_server: &RenderServer,
+    ) {
+        let capabilities = self.surface.get_capabilities(&render_server.adapter);
+        let (surface_config, view_format) = surface_configuration(
+            &capabilities,
+            &surface_preferences,
+            self.physical_width,
+            self.physical_height,
+        );
+
+        self.surface_format = surface_config.format;
+        self.view_format = view_format;
+        self.surface_preferences = surface_preferences;
+        self.surface_config = surface_config;
+        self.configure(&render_server.device);
+    }
+
+    /// Updates the size of the surface, `true` when it changed.
+    pub fn resize(&mut self, width: u32, height: u32, device: &RenderDevice) -> bool {
+        if width == self.physical_width && height == self.physical_height {
+            return false;
         }
+
+        self.physical_width = width;
+        self.physical_height = height;
+        self.surface_config.width = width;
+        self.surface_config.height = height;
+        self.configure(device);
+
+        true
+    }
+
+    /// Acquires the texture of the next frame. Outdated surfaces are configured again
+    /// and lost surfaces are recreated before trying once more. The frame is skipped
+    /// when the window is minimized or the texture can't be acquired.
+    pub fn spawn_swapchain_texture(&mut self, render_server: &RenderServer) {
+        if self.is_minimized() {
+            return;
+        }
+
+        if self.needs_configure {
+            self.needs_configure = false;
+            self.configure(&render_server.device);
+        }
+
+        for _ in 0..2 {
+            match self.surface.get_current_texture() {
+                CurrentSurfaceTexture::Success(swap_chain_texture) => {
+                    self.swap_chain_texture = Some(swap_chain_texture);
+                    return;
+                }
+                CurrentSurfaceTexture::Suboptimal(swap_chain_texture) => {
+                    self.swap_chain_texture = Some(swap_chain_texture);
+                    self.needs_configure = true;
+                    return;
+                }
+                CurrentSurfaceTexture::Outdated => {
+                    self.configure(&render_server.device);
+                }
+                CurrentSurfaceTexture::Lost => {
+                    self.surface = render_server.create_surface(&self.system_window);
+                    self.configure(&render_server.device);
+                }
+                CurrentSurfaceTexture::Timeout | CurrentSurfaceTexture::Occluded => return,
+                CurrentSurfaceTexture::Validation => {
+                    Log::err("Failed to acquire the surface texture of a window.");
+                    return;
+                }
+            }
+        }
+    }
+
+    pub fn swap_chain_texture_view(&self) -> Option<TextureView> {
+        self.swap_chain_texture.as_ref().map(|swap_chain_texture| {
+            swap_chain_texture
+                .texture
+                .create_view(&wgpu::TextureViewDescriptor {
+                    format: Some(self.view_format),
+                    ..Default::default()
+                })
+        })
     }
 
     pub fn clear_swapchain_texture(&mut self) {
@@ -77,18 +300,149 @@ pub struct RenderWindowContainer {
 }
 
 impl RenderWindowContainer {
+    pub fn get(&self, handle: &Handle<SystemWindow>) -> Option<&RenderWindow> {
+        self.windows.get(handle)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &RenderWindow> {
+        self.windows.values()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut RenderWindow> {
+        self.windows.values_mut()
+    }
+
+    /// Drops the windows whose system windows were despawned and returns their
+    /// handles.
+    pub fn remove_closed(
+        &mut self,
+        is_open: impl Fn(Handle<SystemWindow>) -> bool,
+    ) -> Vec<Handle<SystemWindow>> {
+        let closed = self
+            .windows
+            .keys()
+            .copied()
+            .filter(|handle| !is_open(*handle))
+            .collect::<Vec<_>>();
+
+        for handle in &closed {
+            self.windows.remove(handle);
+        }
+
+        closed
+    }
+
     pub fn get_or_create(
         &mut self,
         render_server: &RenderServer,
         handle: Handle<SystemWindow>,
         window: &SystemWindow,
     ) -> &mut RenderWindow {
-        if !self.windows.contains_key(&handle) {
-            let render_window = RenderWindow::initialize(render_server, handle, &window);
+        self.windows.entry(handle).or_insert_with(|| {
+            let render_window = RenderWindow::initialize(render_server, handle, window);
             render_window.configure(&render_server.device);
-            self.windows.insert(handle.clone(), render_window);
+            render_window
+        })
+    }
+
+    /// Follows the size of the system window and acquires the texture of the next
+    /// frame.
+    pub fn prepare(
+        &mut self,
+        render_server: &RenderServer,
+        handle: Handle<SystemWindow>,
+        window: &SystemWindow,
+    ) -> Option<RenderWindowEvent> {
+        let size = window.get_window().get_physical_size();
+        let render_window = self.get_or_create(render_server, handle, window);
+
+        let surface_preferences = window.surface_preferences();
+        if surface_preferences != render_window.surface_preferences {
+            render_window.set_surface_preferences(surface_preferences, render_server);
         }
 
-        self.windows.get_mut(&handle).unwrap()
+        let resized = render_window.resize(size.width, size.height, &render_server.device);
+        render_window.spawn_swapchain_texture(render_server);
+
+        resized.then_some(RenderWindowEvent::Resized {
+            handle,
+            width: size.width,
+            height: size.height,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn present_modes_fall_back_along_their_chain() {
+        let supported = [wgpu::PresentMode::Fifo, wgpu::PresentMode::Mailbox];
+
+        assert_eq!(
+            select_present_mode(PresentMode::NoVsync, &supported),
+            wgpu::PresentMode::Mailbox
+        );
+        assert_eq!(
+            select_present_mode(PresentMode::Vsync, &supported),
+            wgpu::PresentMode::Fifo
+        );
+        assert_eq!(
+            select_present_mode(PresentMode::Mailbox, &[wgpu::PresentMode::Fifo]),
+            wgpu::PresentMode::Fifo
+        );
+    }
+
+    #[test]
+    fn formats_follow_the_preferred_color_space() {
+        let supported = [
+            TextureFormat::Rgba16Float,
+            TextureFormat::Bgra8UnormSrgb,
+            TextureFormat::Bgra8Unorm,
+        ];
+
+        assert_eq!(
+            select_format(SurfaceFormat::Srgb, &supported),
+            Some((TextureFormat::Bgra8UnormSrgb, TextureFormat::Bgra8UnormSrgb))
+        );
+        assert_eq!(
+            select_format(SurfaceFormat::Linear, &supported),
+            Some((TextureFormat::Bgra8Unorm, TextureFormat::Bgra8Unorm))
+        );
+        // Without a 10-bit format HDR10 falls back to sRGB.
+        assert_eq!(
+            select_format(SurfaceFormat::Hdr10, &supported),
+            Some((TextureFormat::Bgra8UnormSrgb, TextureFormat::Bgra8UnormSrgb))
+        );
+        assert_eq!(
+            select_format(SurfaceFormat::Hdr10, &[TextureFormat::Rgb10a2Unorm]),
+            Some((TextureFormat::Rgb10a2Unorm, TextureFormat::Rgb10a2Unorm))
+        );
+        assert_eq!(
+            select_format(SurfaceFormat::Srgb, &[TextureFormat::Rgba8Unorm]),
+            Some((TextureFormat::Rgba8Unorm, TextureFormat::Rgba8UnormSrgb))
+        );
+    }
+
+    #[test]
+    fn transparent_windows_use_a_blending_alpha_mode() {
+        let supported = [
+            CompositeAlphaMode::Opaque,
+            CompositeAlphaMode::PostMultiplied,
+        ];
+
+        assert_eq!(
+            select_alpha_mode(WindowAlphaMode::Transparent, &supported),
+            CompositeAlphaMode::PostMultiplied
+        );
+        assert_eq!(
+            select_alpha_mode(WindowAlphaMode::Opaque, &supported),
+            CompositeAlphaMode::Opaque
+        );
+        assert_eq!(
+            select_alpha_mode(WindowAlphaMode::Transparent, &[CompositeAlphaMode::Opaque]),
+            CompositeAlphaMode::Opaque
+        );
     }
 }
@@ -1,5 +1,7 @@
+use std::collections::HashMap;
+
 use draft_graphics::{RenderDevice, ShaderModule, ShaderModuleDescriptor};
-use draft_shader::{Shader, ShaderResource};
+use draft_shader::{Shader, ShaderDefVal, ShaderResource};
 
 use crate::{
     FrameworkError,
@@ -7,56 +9,106 @@ use crate::{
 };
 
 pub struct ShaderRenderData {
-    pub shader_module: ShaderModule,
+    pub shader_modules: HashMap<Vec<ShaderDefVal>, ShaderModule>,
     pub modifications_counter: u64,
 }
 
-pub fn create_shader_render_data(
-    device: &RenderDevice,
-    shader: &Shader,
-) -> Result<ShaderRenderData, FrameworkError> {
-    let shader_module = device.create_shader_module(ShaderModuleDescriptor {
-        label: None,
-        source: shader.source.get_shader_soource(),
-    });
+impl ShaderRenderData {
+    pub fn get_or_create_shader_module(
+        &mut self,
+        device: &RenderDevice,
+        shader: &Shader,
+        shader_defs: &[ShaderDefVal],
+    ) -> Result<ShaderModule, FrameworkError> {
+        if let Some(shader_module) = self.shader_modules.get(shader_defs) {
+            return Ok(shader_module.clone());
+        }
+
+        let source = shader
+            .source
+            .get_shader_source_with_defs(shader_defs)
+            .map_err(|e| FrameworkError::ShaderCompilation(e.to_string()))?;
+
+        let shader_module = device
+            .catch_validation_error(|_| {
+                device.create_shader_module(ShaderModuleDescriptor {
+                    label: None,
+                    source,
+                })
+            })
+            .map_err(|e| FrameworkError::ShaderCompilation(e.to_string()))?;
+
+        self.shader_modules
+            .insert(shader_defs.to_vec(), shader_module.clone());
 
+        Ok(shader_module)
+    }
+}
+
+/// Shader modules are compiled lazily, once per set of shader defs.
+pub fn create_shader_render_data(shader: &Shader) -> Result<ShaderRenderData, FrameworkError> {
     Ok(ShaderRenderData {
-        shader_module,
+        shader_modules: HashMap::new(),
         modifications_counter: shader.modifications_counter,
     })
 }
 
+#[derive(Default)]
 pub struct ShaderCache {
     cache: TemporaryCache<ShaderRenderData>,
 }
 
 impl ShaderCache {
+    pub fn update(&mut self, dt: f32) {
+        self.cache.update(dt);
+    }
+
+    pub fn alive_count(&self) -> usize {
+        self.cache.alive_count()
+    }
+
+    /// Creates the cache entry of the shader and compiles its module without shader defs.
     pub fn get_create_shader(
         &mut self,
         device: &RenderDevice,
         shader: &ShaderResource,
     ) -> Result<ResourceId<Shader>, FrameworkError> {
+        self.get_shader_module(device, shader, &[])?;
+
+        Ok(ResourceId::new(shader.data_ref().cache_index.get()))
+    }
+
+    pub fn get_shader_module(
+        &mut self,
+        device: &RenderDevice,
+        shader: &ShaderResource,
+        shader_defs: &[ShaderDefVal],
+    ) -> Result<ShaderModule, FrameworkError> {
+        let shader_render_data = self.get_create_shader_render_data(shader)?;
+
+        shader_render_data.get_or_create_shader_module(device, &shader.data_ref(), shader_defs)
+    }
+
+    fn get_create_shader_render_data(
+        &mut self,
+        shader: &ShaderResource,
+    ) -> Result<&mut ShaderRenderData, FrameworkError> {
         if !shader.is_ok() {
             return Err(FrameworkError::ShaderNotLoaded);
         }
 
         let shader = shader.data_ref();
 
-        match self
-            .cache
-            .get_mut_or_insert_with(&shader.cache_index, Default::default(), || {
-                create_shader_render_data(device, &shader)
-            }) {
-            Ok(shader_render_data) => {
-                if shader_render_data.modifications_counter != shader.modifications_counter {
-                    *shader_render_data = create_shader_render_data(device, &shader)?;
-                }
-
-                Ok(ResourceId::new(shader.cache_index.get()))
-            }
-            Err(e) => {
-                return Err(e);
-            }
+        let shader_render_data =
+            self.cache
+                .get_mut_or_insert_with(&shader.cache_index, Default::default(), || {
+                    create_shader_render_data(&shader)
+                })?;
+
+        if shader_render_data.modifications_counter != shader.modifications_counter {
+            *shader_render_data = create_shader_render_data(&shader)?;
         }
+
+        Ok(shader_render_data)
     }
 }
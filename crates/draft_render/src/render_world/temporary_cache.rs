@@ -1,6 +1,12 @@
-use std::{ops::{Deref, DerefMut}, sync::Arc};
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
 
-use fyrox_resource::{core::sparse::{AtomicIndex, SparseBuffer}, entry::DEFAULT_RESOURCE_LIFETIME};
+use fyrox_resource::{
+    core::sparse::{AtomicIndex, SparseBuffer},
+    entry::DEFAULT_RESOURCE_LIFETIME,
+};
 
 #[derive(Copy, Clone, PartialEq)]
 pub struct TimeToLive(pub f32);
@@ -152,15 +158,22 @@ impl<T> TemporaryCache<T> {
     }
 
     pub fn update(&mut self, dt: f32) {
+        self.update_with(dt, |_| {});
+    }
+
+    /// Like [`Self::update`], handing every expired value to `on_expired` before it is
+    /// dropped.
+    pub fn update_with(&mut self, dt: f32, mut on_expired: impl FnMut(&mut T)) {
         for entry in self.buffer.iter_mut() {
             *entry.time_to_live -= dt;
         }
 
         for i in 0..self.buffer.len() {
-            if let Some(entry) = self.buffer.get_raw(i) {
-                if *entry.time_to_live <= 0.0 {
-                    self.buffer.free_raw(i);
-                }
+            if let Some(entry) = self.buffer.get_mut_raw(i)
+                && *entry.time_to_live <= 0.0
+            {
+                on_expired(&mut entry.value);
+                self.buffer.free_raw(i);
             }
         }
     }
@@ -176,4 +189,4 @@ impl<T> TemporaryCache<T> {
     pub fn remove(&mut self, index: &AtomicIndex) {
         self.buffer.free(index);
     }
-}
\ No newline at end of file
+}
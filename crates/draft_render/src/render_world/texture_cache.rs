@@ -0,0 +1,216 @@
+use draft_graphics::{
+    Extent3d, RenderDevice, RenderQueue, Sampler, SamplerDescriptor, Texture, TextureDescriptor,
+    TextureDimension, TextureUsages, TextureView, TextureViewDescriptor, TextureViewDimension,
+};
+use draft_image::{Image, ImageFormat, ImageKind, ImageResource};
+
+use crate::{
+    FrameworkError,
+    render_world::{EvictedResource, ResourceId, SamplerCache, TemporaryCache},
+};
+
+pub struct TextureRenderData {
+    pub texture: Texture,
+    pub view: TextureView,
+    pub sampler: Sampler,
+    pub kind: ImageKind,
+    pub format: ImageFormat,
+    pub mip_count: u32,
+    pub render_target: bool,
+    pub modifications_counter: u64,
+    pub sampler_modifications_counter: u64,
+}
+
+impl TextureRenderData {
+    fn is_compatible(&self, image: &Image) -> bool {
+        self.kind == image.kind()
+            && self.format == image.format()
+            && self.mip_count == image.mip_count()
+            && self.render_target == image.is_render_target()
+    }
+}
+
+fn create_sampler(image: &Image, samplers: &mut SamplerCache, device: &RenderDevice) -> Sampler {
+    let sampler = image.sampler();
+
+    samplers.get_or_create(
+        &SamplerDescriptor {
+            label: None,
+            address_mode_u: sampler.address_mode_u.into(),
+            address_mode_v: sampler.address_mode_v.into(),
+            address_mode_w: sampler.address_mode_w.into(),
+            mag_filter: sampler.mag_filter.into(),
+            min_filter: sampler.min_filter.into(),
+            mipmap_filter: sampler.mipmap_filter.into(),
+            ..Default::default()
+        },
+        device,
+    )
+}
+
+fn write_image_data(image: &Image, texture: &Texture, queue: &RenderQueue) {
+    let kind = image.kind();
+    let pixel_size = image.format().pixel_size() as u32;
+
+    for layer in 0..kind.layer_count() {
+        for mip_level in 0..image.mip_count() {
+            let (width, height, depth) = kind.mip_level_size(mip_level);
+
+            queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture,
+                    mip_level,
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: layer,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                image.mip_level_data(layer, mip_level),
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(width * pixel_size),
+                    rows_per_image: Some(height),
+                },
+                Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: depth,
+                },
+            );
+        }
+    }
+}
+
+fn create_texture_render_data(
+    image: &Image,
+    samplers: &mut SamplerCache,
+    device: &RenderDevice,
+    queue: &RenderQueue,
+) -> Result<TextureRenderData, FrameworkError> {
+    let kind = image.kind();
+
+    let mut usage = TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST;
+    if image.is_render_target() {
+        usage |= TextureUsages::RENDER_ATTACHMENT;
+    }
+
+    let (dimension, view_dimension) = match kind {
+        ImageKind::D2 { .. } => (TextureDimension::D2, TextureViewDimension::D2),
+        ImageKind::D2Array { .. } => (TextureDimension::D2, TextureViewDimension::D2Array),
+        ImageKind::Cube { .. } => (TextureDimension::D2, TextureViewDimension::Cube),
+        ImageKind::D3 { .. } => (TextureDimension::D3, TextureViewDimension::D3),
+    };
+
+    let texture = device.create_gpu_texture(&TextureDescriptor {
+        label: None,
+        size: Extent3d {
+            width: kind.width(),
+            height: kind.height(),
+            depth_or_array_layers: kind.depth() * kind.layer_count(),
+        },
+        mip_level_count: image.mip_count(),
+        sample_count: 1,
+        dimension,
+        format: image.format().into(),
+        usage,
+        view_formats: &[],
+    });
+
+    write_image_data(image, &texture, queue);
+
+    let view = texture.create_view(&TextureViewDescriptor {
+        dimension: Some(view_dimension),
+        ..Default::default()
+    });
+
+    Ok(TextureRenderData {
+        texture,
+        view,
+        sampler: create_sampler(image, samplers, device),
+        kind,
+        format: image.format(),
+        mip_count: image.mip_count(),
+        render_target: image.is_render_target(),
+        modifications_counter: image.modifications_counter,
+        sampler_modifications_counter: image.sampler_modifications_counter,
+    })
+}
+
+#[derive(Default)]
+pub struct TextureCache {
+    cache: TemporaryCache<TextureRenderData>,
+    samplers: SamplerCache,
+    evicted: Vec<EvictedResource>,
+}
+
+impl TextureCache {
+    pub fn update(&mut self, dt: f32) {
+        let evicted = &mut self.evicted;
+        self.cache.update_with(dt, |texture_render_data| {
+            evicted.push(EvictedResource::TextureView(
+                texture_render_data.view.clone(),
+            ));
+        });
+    }
+
+    /// Views of the textures evicted or recreated since the last call.
+    pub fn take_evicted(&mut self) -> Vec<EvictedResource> {
+        std::mem::take(&mut self.evicted)
+    }
+
+    /// Samplers of the textures, shared by all textures with the same settings.
+    pub fn samplers_mut(&mut self) -> &mut SamplerCache {
+        &mut self.samplers
+    }
+
+    pub fn alive_count(&self) -> usize {
+        self.cache.alive_count()
+    }
+
+    pub fn get(&self, id: &ResourceId<Image>) -> Option<&TextureRenderData> {
+        self.cache.buffer.get_raw(id.slot).map(|entry| &entry.value)
+    }
+
+    /// Uploads the image on first use. Later modifications of the pixel data are written
+    /// into the existing texture as long as its size, format and mip count are unchanged,
+    /// and a sampler change only recreates the sampler.
+    pub fn get_create_texture(
+        &mut self,
+        image: &ImageResource,
+        device: &RenderDevice,
+        queue: &RenderQueue,
+    ) -> Result<ResourceId<Image>, FrameworkError> {
+        if !image.is_ok() {
+            return Err(FrameworkError::ImageNotLoaded);
+        }
+
+        let image = image.data_ref();
+        let samplers = &mut self.samplers;
+
+        let texture_render_data =
+            self.cache
+                .get_mut_or_insert_with(&image.cache_index, Default::default(), || {
+                    create_texture_render_data(&image, samplers, device, queue)
+                })?;
+
+        if !texture_render_data.is_compatible(&image) {
+            let recreated = create_texture_render_data(&image, samplers, device, queue)?;
+            let previous = std::mem::replace(texture_render_data, recreated);
+            self.evicted
+                .push(EvictedResource::TextureView(previous.view));
+        } else if texture_render_data.modifications_counter != image.modifications_counter {
+            write_image_data(&image, &texture_render_data.texture, queue);
+            texture_render_data.modifications_counter = image.modifications_counter;
+        }
+
+        if texture_render_data.sampler_modifications_counter != image.sampler_modifications_counter
+        {
+            texture_render_data.sampler = create_sampler(&image, samplers, device);
+            texture_render_data.sampler_modifications_counter = image.sampler_modifications_counter;
+        }
+
+        Ok(ResourceId::new(image.cache_index.get()))
+    }
+}
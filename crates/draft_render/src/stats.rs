@@ -0,0 +1,145 @@
+use std::collections::VecDeque;
+
+use crate::{
+    frame_graph::FrameGraphStats,
+    render_world::{RenderWorldCacheStats, VisibilityStats},
+};
+
+/// Number of frames [`RenderStatsHistory`] keeps by default.
+pub const DEFAULT_RENDER_STATS_HISTORY: usize = 120;
+
+/// Statistics of a single rendered frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct RenderStats {
+    /// Seconds since the previous frame, zero for the first one.
+    pub frame_time: f32,
+    pub frame_graph: FrameGraphStats,
+    pub visibility: VisibilityStats,
+    /// Alive entries of the GPU resource caches at the end of the frame.
+    pub caches: RenderWorldCacheStats,
+    /// Bytes written from the CPU into buffers and textures during the frame.
+    pub bytes_uploaded: u64,
+}
+
+/// Statistics of the last rendered frames, oldest first. Once full, every new frame
+/// drops the oldest one.
+#[derive(Debug, Clone)]
+pub struct RenderStatsHistory {
+    frames: VecDeque<RenderStats>,
+    capacity: usize,
+}
+
+impl Default for RenderStatsHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_RENDER_STATS_HISTORY)
+    }
+}
+
+impl RenderStatsHistory {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+
+        Self {
+            frames: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Changes the number of kept frames, dropping the oldest ones that no longer fit.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+
+        while self.frames.len() > self.capacity {
+            self.frames.pop_front();
+        }
+    }
+
+    pub fn push(&mut self, stats: RenderStats) {
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+
+        self.frames.push_back(stats);
+    }
+
+    /// Statistics of the last rendered frame.
+    pub fn latest(&self) -> Option<&RenderStats> {
+        self.frames.back()
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &RenderStats> {
+        self.frames.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Average time between the kept frames in seconds.
+    pub fn average_frame_time(&self) -> f32 {
+        if self.frames.is_empty() {
+            return 0.0;
+        }
+
+        self.frames
+            .iter()
+            .map(|stats| stats.frame_time)
+            .sum::<f32>()
+            / self.frames.len() as f32
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(frame_time: f32) -> RenderStats {
+        RenderStats {
+            frame_time,
+            ..Default::default()
+        }
+    }
+
+    fn frame_times(history: &RenderStatsHistory) -> Vec<f32> {
+        history.iter().map(|stats| stats.frame_time).collect()
+    }
+
+    #[test]
+    fn full_history_drops_the_oldest_frame() {
+        let mut history = RenderStatsHistory::new(3);
+        for frame_time in [1.0, 2.0, 3.0, 4.0] {
+            history.push(frame(frame_time));
+        }
+
+        assert_eq!(frame_times(&history), [2.0, 3.0, 4.0]);
+        assert_eq!(history.latest().unwrap().frame_time, 4.0);
+        assert_eq!(history.average_frame_time(), 3.0);
+    }
+
+    #[test]
+    fn shrinking_keeps_the_latest_frames() {
+        let mut history = RenderStatsHistory::new(4);
+        for frame_time in [1.0, 2.0, 3.0, 4.0] {
+            history.push(frame(frame_time));
+        }
+
+        history.set_capacity(2);
+        assert_eq!(frame_times(&history), [3.0, 4.0]);
+
+        history.set_capacity(0);
+        assert_eq!(history.capacity(), 1);
+        assert_eq!(frame_times(&history), [4.0]);
+    }
+}
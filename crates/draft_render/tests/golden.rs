@@ -0,0 +1,210 @@
+//! Golden images of the renderer features, see [`draft_render::golden`]. Run with
+//! `DRAFT_BLESS=1` to update the references in `tests/golden`.
+
+use draft_graphics::{Color, PrimitiveTopology};
+use draft_image::{Image, ImageFormat, ImageKind, ImageResource};
+use draft_material::{Material, MaterialResource};
+use draft_mesh::{Mesh, MeshResource, VertexAttributeValues};
+use draft_render::{
+    camera::{Camera, PerspectiveProjection},
+    core_2d::Sprite,
+    core_3d::{Light, StandardMaterial},
+    golden::{GoldenOutcome, GoldenTest},
+    post_process::{PostProcessSettings, VignetteSettings},
+    render_phase::DrawItem,
+};
+use fyrox_resource::core::algebra::{Matrix4, Rotation3, Vector2, Vector3};
+
+const REFERENCE_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+const OUTPUT_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../target/golden");
+
+fn golden_test(name: &str) -> GoldenTest {
+    GoldenTest::new(name, REFERENCE_DIR).with_output_dir(OUTPUT_DIR)
+}
+
+fn assert_golden(outcome: Result<GoldenOutcome, draft_render::golden::GoldenError>) {
+    if let Err(e) = outcome {
+        panic!("{e}");
+    }
+}
+
+fn checker_image() -> ImageResource {
+    let data = [
+        [255, 255, 255, 255],
+        [40, 40, 40, 255],
+        [40, 40, 40, 255],
+        [255, 255, 255, 255],
+    ]
+    .concat();
+    let image = Image::new(
+        ImageKind::D2 {
+            width: 2,
+            height: 2,
+        },
+        ImageFormat::Rgba8UnormSrgb,
+        1,
+        data,
+    )
+    .unwrap();
+
+    ImageResource::new_embedded(image)
+}
+
+/// A unit cube centered at the origin, with a separate set of vertices per face.
+fn cube() -> MeshResource {
+    let faces = [
+        (Vector3::<f32>::x(), Vector3::y()),
+        (-Vector3::x(), Vector3::y()),
+        (Vector3::y(), Vector3::z()),
+        (-Vector3::y(), Vector3::z()),
+        (Vector3::z(), Vector3::y()),
+        (-Vector3::z(), Vector3::y()),
+    ];
+
+    let mut positions = vec![];
+    let mut normals = vec![];
+    let mut uvs = vec![];
+    let mut indices = vec![];
+    for (normal, up) in faces {
+        let right = up.cross(&normal);
+        let first = positions.len() as u16;
+        for (u, v) in [(0.0f32, 1.0), (1.0, 1.0), (1.0, 0.0), (0.0, 0.0)] {
+            let position = (normal + right * (u * 2.0 - 1.0) + up * (1.0 - v * 2.0)) * 0.5;
+            positions.push([position.x, position.y, position.z]);
+            normals.push([normal.x, normal.y, normal.z]);
+            uvs.push([u, v]);
+        }
+        indices.extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_POSITION,
+        VertexAttributeValues::Float32x3(positions),
+    );
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_NORMAL,
+        VertexAttributeValues::Float32x3(normals),
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, VertexAttributeValues::Float32x2(uvs));
+    mesh.set_indices_with_u16(&indices);
+
+    MeshResource::new_embedded(mesh)
+}
+
+fn material(standard: StandardMaterial) -> MaterialResource {
+    MaterialResource::new_embedded(Material::from(standard))
+}
+
+fn camera_3d(target: impl Into<draft_render::camera::RenderTarget>) -> Camera {
+    Camera::new(target)
+        .with_projection(PerspectiveProjection::default())
+        .with_world_matrix(Matrix4::new_translation(&Vector3::new(0.0, 0.0, 3.0)))
+        .with_clear_color(Some(Color {
+            r: 0.1,
+            g: 0.1,
+            b: 0.15,
+            a: 1.0,
+        }))
+}
+
+fn tilted() -> Matrix4<f32> {
+    Rotation3::from_euler_angles(0.5, 0.7, 0.0).to_homogeneous()
+}
+
+#[test]
+fn sprites() {
+    let image = checker_image();
+
+    assert_golden(golden_test("sprites").run(move |context, target| {
+        context.add_camera(Camera::new_2d(target));
+        context.draw_sprite(
+            Sprite::new(
+                image.clone(),
+                Matrix4::new_translation(&Vector3::new(-12.0, 0.0, 0.0)),
+            )
+            .with_custom_size(Vector2::new(24.0, 24.0)),
+        );
+        context.draw_sprite(
+            Sprite::new(
+                image.clone(),
+                Matrix4::new_translation(&Vector3::new(14.0, 8.0, 1.0)),
+            )
+            .with_custom_size(Vector2::new(16.0, 32.0))
+            .with_color(Color {
+                r: 1.0,
+                g: 0.3,
+                b: 0.2,
+                a: 1.0,
+            })
+            .with_flip(true, false),
+        );
+    }));
+}
+
+#[test]
+fn lighting() {
+    let mesh = cube();
+    let material = material(StandardMaterial {
+        base_color: Color {
+            r: 0.8,
+            g: 0.6,
+            b: 0.3,
+            a: 1.0,
+        },
+        roughness: 0.4,
+        ..Default::default()
+    });
+
+    assert_golden(golden_test("lighting").run(move |context, target| {
+        context.add_camera(camera_3d(target));
+        context.add_light(
+            Light::directional(Color::WHITE, 2.0)
+                .with_world_matrix(Rotation3::from_euler_angles(-0.8, 0.4, 0.0).to_homogeneous()),
+        );
+        context.add_light(
+            Light::point(
+                Color {
+                    r: 0.2,
+                    g: 0.4,
+                    b: 1.0,
+                    a: 1.0,
+                },
+                8.0,
+                5.0,
+            )
+            .with_world_matrix(Matrix4::new_translation(&Vector3::new(-1.5, -1.0, 1.5))),
+        );
+        context.draw(DrawItem::new(mesh.clone(), material.clone(), tilted()));
+    }));
+}
+
+#[test]
+fn post_effects() {
+    let mesh = cube();
+    let material = material(StandardMaterial {
+        base_color: Color::BLACK,
+        emissive: Color {
+            r: 4.0,
+            g: 2.0,
+            b: 0.5,
+            a: 1.0,
+        },
+        ..Default::default()
+    });
+
+    assert_golden(golden_test("post_effects").run(move |context, target| {
+        context.add_camera(
+            camera_3d(target).with_post_process(Some(
+                PostProcessSettings::default()
+                    .with_exposure(0.5)
+                    .with_vignette(Some(VignetteSettings::default())),
+            )),
+        );
+        context.draw(DrawItem::new(
+            mesh.clone(),
+            material.clone(),
+            tilted() * Matrix4::new_scaling(0.6),
+        ));
+    }));
+}
@@ -1,4 +1,5 @@
 mod loader;
+mod shader_def;
 
 use std::{path::Path, sync::Arc};
 
@@ -13,11 +14,14 @@ use thiserror::Error;
 pub type ShaderResource = Resource<Shader>;
 
 pub use loader::*;
+pub use shader_def::*;
 
 #[derive(Debug, Error)]
 pub enum ShaderError {
     #[error("A file load error has occurred {0:?}")]
     Io(#[from] FileError),
+    #[error("Failed to preprocess shader: {0}")]
+    Preprocess(String),
 }
 
 #[derive(Debug, Clone, Default, Reflect, TypeUuidProvider)]
@@ -69,8 +73,16 @@ impl ResourceData for Shader {
 }
 
 impl Visit for Shader {
-    fn visit(&mut self, _name: &str, _visitor: &mut Visitor) -> VisitResult {
-        todo!()
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        let mut region = visitor.enter_region(name)?;
+
+        self.source.visit("Source", &mut region)?;
+
+        if region.is_reading() {
+            self.modifications_counter += 1;
+        }
+
+        Ok(())
     }
 }
 
@@ -80,6 +92,7 @@ pub enum Source {
 }
 
 impl Source {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(str: &str) -> Self {
         Source::Wgsl(str.to_string())
     }
@@ -89,6 +102,21 @@ impl Source {
             Self::Wgsl(wgsl) => ShaderSource::Wgsl(wgsl.into()),
         }
     }
+
+    pub fn get_shader_source_with_defs(
+        &self,
+        shader_defs: &[ShaderDefVal],
+    ) -> Result<ShaderSource<'static>, ShaderError> {
+        match self {
+            Self::Wgsl(wgsl) => Ok(ShaderSource::Wgsl(preprocess(wgsl, shader_defs)?.into())),
+        }
+    }
+}
+
+impl From<&str> for Source {
+    fn from(str: &str) -> Self {
+        Source::Wgsl(str.to_string())
+    }
 }
 
 impl Default for Source {
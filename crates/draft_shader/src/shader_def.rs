@@ -0,0 +1,302 @@
+use crate::ShaderError;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ShaderDefVal {
+    Bool(String, bool),
+    Int(String, i32),
+    UInt(String, u32),
+}
+
+impl From<&str> for ShaderDefVal {
+    fn from(key: &str) -> Self {
+        ShaderDefVal::Bool(key.to_string(), true)
+    }
+}
+
+impl From<String> for ShaderDefVal {
+    fn from(key: String) -> Self {
+        ShaderDefVal::Bool(key, true)
+    }
+}
+
+impl ShaderDefVal {
+    pub fn name(&self) -> &str {
+        match self {
+            ShaderDefVal::Bool(name, _) => name,
+            ShaderDefVal::Int(name, _) => name,
+            ShaderDefVal::UInt(name, _) => name,
+        }
+    }
+
+    pub fn is_defined(&self) -> bool {
+        match self {
+            ShaderDefVal::Bool(_, value) => *value,
+            _ => true,
+        }
+    }
+
+    pub fn value_as_string(&self) -> String {
+        match self {
+            ShaderDefVal::Bool(_, value) => value.to_string(),
+            ShaderDefVal::Int(_, value) => value.to_string(),
+            ShaderDefVal::UInt(_, value) => format!("{value}u"),
+        }
+    }
+}
+
+struct Scope {
+    active: bool,
+    parent_active: bool,
+    has_else: bool,
+}
+
+enum Directive<'a> {
+    IfDef(&'a str),
+    IfNDef(&'a str),
+    Else,
+    EndIf,
+}
+
+fn preprocess_error(message: String, line_index: usize) -> ShaderError {
+    ShaderError::Preprocess(format!("{message} at line {}", line_index + 1))
+}
+
+/// Reads the directive of a line. Lines whose first word doesn't start with `#` are
+/// shader code, and so are lines that start with a `#{NAME}` substitution.
+fn parse_directive(line: &str, line_index: usize) -> Result<Option<Directive<'_>>, ShaderError> {
+    let mut tokens = line.split_whitespace();
+    let Some(keyword) = tokens
+        .next()
+        .filter(|token| token.starts_with('#') && !token.starts_with("#{"))
+    else {
+        return Ok(None);
+    };
+    let argument = tokens.next();
+
+    if let Some(token) = tokens.next() {
+        return Err(preprocess_error(
+            format!("Unexpected {token} after {keyword}"),
+            line_index,
+        ));
+    }
+
+    match (keyword, argument) {
+        ("#ifdef", Some(name)) => Ok(Some(Directive::IfDef(name))),
+        ("#ifndef", Some(name)) => Ok(Some(Directive::IfNDef(name))),
+        ("#ifdef" | "#ifndef", None) => Err(preprocess_error(
+            format!("Missing shader def name after {keyword}"),
+            line_index,
+        )),
+        ("#else", None) => Ok(Some(Directive::Else)),
+        ("#endif", None) => Ok(Some(Directive::EndIf)),
+        ("#else" | "#endif", Some(token)) => Err(preprocess_error(
+            format!("Unexpected {token} after {keyword}"),
+            line_index,
+        )),
+        _ => Err(preprocess_error(
+            format!("Unknown directive {keyword}"),
+            line_index,
+        )),
+    }
+}
+
+/// Resolves `#ifdef`, `#ifndef`, `#else` and `#endif` directives, and replaces
+/// `#{NAME}` with the value of the matching shader def.
+pub fn preprocess(source: &str, shader_defs: &[ShaderDefVal]) -> Result<String, ShaderError> {
+    let is_defined = |name: &str| {
+        shader_defs
+            .iter()
+            .any(|def| def.name() == name && def.is_defined())
+    };
+
+    let mut scopes: Vec<Scope> = vec![];
+    let mut output = String::with_capacity(source.len());
+
+    for (line_index, line) in source.lines().enumerate() {
+        let active = scopes.last().is_none_or(|scope| scope.active);
+
+        match parse_directive(line, line_index)? {
+            Some(Directive::IfDef(name)) => scopes.push(Scope {
+                active: active && is_defined(name),
+                parent_active: active,
+                has_else: false,
+            }),
+            Some(Directive::IfNDef(name)) => scopes.push(Scope {
+                active: active && !is_defined(name),
+                parent_active: active,
+                has_else: false,
+            }),
+            Some(Directive::Else) => {
+                let Some(scope) = scopes.last_mut().filter(|scope| !scope.has_else) else {
+                    return Err(preprocess_error("Unexpected #else".to_string(), line_index));
+                };
+                scope.has_else = true;
+                scope.active = scope.parent_active && !scope.active;
+            }
+            Some(Directive::EndIf) if scopes.pop().is_none() => {
+                return Err(preprocess_error(
+                    "Unexpected #endif".to_string(),
+                    line_index,
+                ));
+            }
+            Some(Directive::EndIf) => {}
+            None if active => {
+                output.push_str(&substitute(line, shader_defs, line_index)?);
+                output.push('\n');
+            }
+            None => {}
+        }
+    }
+
+    if !scopes.is_empty() {
+        return Err(ShaderError::Preprocess(
+            "Missing #endif at the end of the shader".to_string(),
+        ));
+    }
+
+    Ok(output)
+}
+
+fn substitute(
+    line: &str,
+    shader_defs: &[ShaderDefVal],
+    line_index: usize,
+) -> Result<String, ShaderError> {
+    let mut result = String::with_capacity(line.len());
+    let mut rest = line;
+
+    while let Some(start) = rest.find("#{") {
+        result.push_str(&rest[..start]);
+
+        let Some(end) = rest[start..].find('}') else {
+            return Err(preprocess_error(
+                "Unclosed shader def substitution".to_string(),
+                line_index,
+            ));
+        };
+
+        let name = &rest[start + 2..start + end];
+        let Some(def) = shader_defs.iter().find(|def| def.name() == name) else {
+            return Err(preprocess_error(
+                format!("Unknown shader def {name}"),
+                line_index,
+            ));
+        };
+
+        result.push_str(&def.value_as_string());
+        rest = &rest[start + end + 1..];
+    }
+
+    result.push_str(rest);
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_message(result: Result<String, ShaderError>) -> String {
+        match result {
+            Err(ShaderError::Preprocess(message)) => message,
+            other => panic!("Expected a preprocess error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn nested_conditionals_follow_their_parents() {
+        let source = "\
+a
+#ifdef OUTER
+b
+    #ifndef INNER
+c
+    #else
+d
+    #endif
+#else
+e
+    #ifdef INNER
+f
+    #endif
+#endif
+g
+";
+
+        assert_eq!(preprocess(source, &[]).unwrap(), "a\ne\ng\n");
+        assert_eq!(
+            preprocess(source, &["OUTER".into()]).unwrap(),
+            "a\nb\nc\ng\n"
+        );
+        assert_eq!(
+            preprocess(source, &["OUTER".into(), "INNER".into()]).unwrap(),
+            "a\nb\nd\ng\n"
+        );
+        assert_eq!(
+            preprocess(source, &[ShaderDefVal::Bool("OUTER".into(), false)]).unwrap(),
+            "a\ne\ng\n"
+        );
+    }
+
+    #[test]
+    fn unbalanced_conditionals_are_errors() {
+        assert_eq!(
+            error_message(preprocess("a\n#endif\n", &[])),
+            "Unexpected #endif at line 2"
+        );
+        assert_eq!(
+            error_message(preprocess("#ifdef A\na\n", &[])),
+            "Missing #endif at the end of the shader"
+        );
+        assert_eq!(
+            error_message(preprocess("#ifdef A\n#else\n#else\n#endif\n", &[])),
+            "Unexpected #else at line 3"
+        );
+    }
+
+    #[test]
+    fn directives_are_whole_words() {
+        assert_eq!(
+            error_message(preprocess("#ifdefA\n#endif\n", &[])),
+            "Unknown directive #ifdefA at line 1"
+        );
+        assert_eq!(
+            error_message(preprocess("#ifdef\n#endif\n", &[])),
+            "Missing shader def name after #ifdef at line 1"
+        );
+        assert_eq!(
+            error_message(preprocess("#ifdef A B\n#endif\n", &[])),
+            "Unexpected B after #ifdef at line 1"
+        );
+        assert_eq!(
+            error_message(preprocess("#ifdef A\n#endif A\n", &[])),
+            "Unexpected A after #endif at line 2"
+        );
+    }
+
+    #[test]
+    fn substitutions_use_the_def_values() {
+        let shader_defs = [
+            ShaderDefVal::Int("OFFSET".into(), -2),
+            ShaderDefVal::UInt("COUNT".into(), 4),
+            ShaderDefVal::Bool("ENABLED".into(), true),
+        ];
+
+        assert_eq!(
+            preprocess(
+                "const A = #{OFFSET} + #{COUNT};\n#{ENABLED}\n",
+                &shader_defs
+            )
+            .unwrap(),
+            "const A = -2 + 4u;\ntrue\n"
+        );
+        assert_eq!(
+            error_message(preprocess("let a = #{MISSING};\n", &shader_defs)),
+            "Unknown shader def MISSING at line 1"
+        );
+        assert_eq!(
+            error_message(preprocess("let a = #{COUNT;\n", &shader_defs)),
+            "Unclosed shader def substitution at line 1"
+        );
+    }
+}
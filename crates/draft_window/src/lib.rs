@@ -1,3 +1,5 @@
+mod surface;
+
 use std::{any::Any, sync::Arc};
 
 use fyrox_core::{
@@ -6,6 +8,7 @@ use fyrox_core::{
     pool::{Handle, Pool},
 };
 pub use raw_window_handle::{HasDisplayHandle, RawDisplayHandle, RawWindowHandle};
+pub use surface::*;
 
 pub struct Window {}
 
@@ -20,21 +23,45 @@ pub struct PhysicalSize {
     pub height: u32,
 }
 
-pub struct SystemWindow(Arc<dyn ISystemWindow>);
+pub struct SystemWindow {
+    window: Arc<dyn ISystemWindow>,
+    /// Shared by the clones of the window, so the renderer sees changes.
+    surface_preferences: Arc<Mutex<SurfacePreferences>>,
+}
 
 impl Clone for SystemWindow {
     fn clone(&self) -> Self {
-        SystemWindow(self.0.clone())
+        SystemWindow {
+            window: self.window.clone(),
+            surface_preferences: self.surface_preferences.clone(),
+        }
     }
 }
 
 impl SystemWindow {
     pub fn new<W: ISystemWindow>(window: W) -> Self {
-        Self(Arc::new(window))
+        Self {
+            window: Arc::new(window),
+            surface_preferences: Default::default(),
+        }
+    }
+
+    pub fn with_surface_preferences(self, surface_preferences: SurfacePreferences) -> Self {
+        self.set_surface_preferences(surface_preferences);
+        self
     }
 
     pub fn get_window(&self) -> Arc<dyn ISystemWindow> {
-        self.0.clone()
+        self.window.clone()
+    }
+
+    pub fn surface_preferences(&self) -> SurfacePreferences {
+        *self.surface_preferences.safe_lock()
+    }
+
+    /// The surface of the window is configured again on the next rendered frame.
+    pub fn set_surface_preferences(&self, surface_preferences: SurfacePreferences) {
+        *self.surface_preferences.safe_lock() = surface_preferences;
     }
 }
 
@@ -57,6 +84,11 @@ impl SystemWindowManager {
         let mut guard = self.state.lock();
         guard.spawn_window(window)
     }
+
+    pub fn despawn_window(&mut self, handle: Handle<SystemWindow>) -> Option<SystemWindow> {
+        let mut guard = self.state.lock();
+        guard.despawn_window(handle)
+    }
 }
 
 impl Clone for SystemWindowManager {
@@ -78,10 +110,26 @@ impl SystemWindowManagerState {
         &self.pool
     }
 
+    pub fn primary(&self) -> Handle<SystemWindow> {
+        self.primary
+    }
+
     pub fn spawn_window(&mut self, window: SystemWindow) -> Handle<SystemWindow> {
         self.pool.spawn(window)
     }
 
+    /// Removes a window from the pool. The renderer drops the surface of the window
+    /// on the next frame. Despawning the primary window leaves no primary window.
+    pub fn despawn_window(&mut self, handle: Handle<SystemWindow>) -> Option<SystemWindow> {
+        let window = self.pool.try_free(handle).ok()?;
+
+        if handle == self.primary {
+            self.primary = Handle::NONE;
+        }
+
+        Some(window)
+    }
+
     pub fn spawn_primary_window(&mut self, window: SystemWindow) -> Handle<SystemWindow> {
         let handle = self.spawn_window(window);
 
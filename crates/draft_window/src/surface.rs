@@ -0,0 +1,78 @@
+/// How frames are presented to the window. Modes the surface doesn't support fall
+/// back to the next mode of their chain and finally to [`PresentMode::Vsync`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PresentMode {
+    /// Waits for the vertical blank, late frames may tear where supported.
+    #[default]
+    Vsync,
+    /// Presents immediately and may tear, falls back to [`PresentMode::Mailbox`].
+    NoVsync,
+    /// Replaces the queued frame with the latest one without tearing.
+    Mailbox,
+}
+
+/// Format of the swapchain textures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SurfaceFormat {
+    /// 8-bit color that is converted to sRGB when written.
+    #[default]
+    Srgb,
+    /// 8-bit color written as it is.
+    Linear,
+    /// 10-bit color for HDR displays, falls back to [`SurfaceFormat::Srgb`].
+    Hdr10,
+}
+
+/// How the window is composited with what is behind it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WindowAlphaMode {
+    #[default]
+    Opaque,
+    /// The alpha of the frame makes the window transparent, where the platform
+    /// supports it.
+    Transparent,
+}
+
+/// How the surface of a window is configured. Changes are applied on the next
+/// rendered frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SurfacePreferences {
+    pub present_mode: PresentMode,
+    pub format: SurfaceFormat,
+    pub alpha_mode: WindowAlphaMode,
+    /// Frames that may be queued for presentation, lower values reduce latency.
+    pub max_frame_latency: u32,
+}
+
+impl Default for SurfacePreferences {
+    fn default() -> Self {
+        Self {
+            present_mode: PresentMode::default(),
+            format: SurfaceFormat::default(),
+            alpha_mode: WindowAlphaMode::default(),
+            max_frame_latency: 2,
+        }
+    }
+}
+
+impl SurfacePreferences {
+    pub fn with_present_mode(mut self, present_mode: PresentMode) -> Self {
+        self.present_mode = present_mode;
+        self
+    }
+
+    pub fn with_format(mut self, format: SurfaceFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn with_alpha_mode(mut self, alpha_mode: WindowAlphaMode) -> Self {
+        self.alpha_mode = alpha_mode;
+        self
+    }
+
+    pub fn with_max_frame_latency(mut self, max_frame_latency: u32) -> Self {
+        self.max_frame_latency = max_frame_latency;
+        self
+    }
+}
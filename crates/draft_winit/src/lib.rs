@@ -1,5 +1,5 @@
-use draft_app::{App, AppInitializeParams, GraphicsContextParams, Plugin};
-use draft_graphics::RenderServer;
+use draft_app::{App, AppEvent, AppInitializeParams, GraphicsContextParams, Plugin};
+use draft_graphics::{RenderServer, RenderServerSettings};
 use draft_window::{
     HasDisplayHandle, ISystemWindow, PhysicalSize, RawDisplayHandle, RawWindowHandle, SystemWindow,
 };
@@ -44,9 +44,9 @@ impl WinitAppRunnerState {
 
 fn create_render_server(
     _graphics_context_params: &GraphicsContextParams,
-    window: SystemWindow,
+    _window: SystemWindow,
 ) -> RenderServer {
-    block_on(RenderServer::initialize(window))
+    block_on(RenderServer::initialize(&RenderServerSettings::default()))
 }
 
 impl ApplicationHandler for WinitAppRunnerState {
@@ -80,7 +80,8 @@ impl ApplicationHandler for WinitAppRunnerState {
     ) {
         match event {
             WindowEvent::CloseRequested => {
-                event_loop.exit();
+                let primary_window = self.app.primary_window();
+                self.app.close_window(primary_window);
             }
 
             WindowEvent::RedrawRequested => {
@@ -89,6 +90,12 @@ impl ApplicationHandler for WinitAppRunnerState {
 
             _ => {}
         }
+
+        for event in self.app.take_events() {
+            if event == AppEvent::PrimaryWindowClosed {
+                event_loop.exit();
+            }
+        }
     }
 }
 
@@ -1,25 +1,36 @@
 use draft::{
     DefaultPlugins,
     app::App,
-    render::{IWorld, RenderContext},
+    render::{IWorld, RenderContext, camera::Camera},
+    window::SystemWindowManager,
 };
 
-pub struct SceneTree {}
+pub struct SceneTree {
+    system_window_manager: SystemWindowManager,
+}
 
 impl SceneTree {
-    pub fn new() -> SceneTree {
-        SceneTree {}
+    pub fn new(system_window_manager: SystemWindowManager) -> SceneTree {
+        SceneTree {
+            system_window_manager,
+        }
     }
 }
 
 impl IWorld for SceneTree {
-    fn render(&self, _context: &mut RenderContext) {}
+    fn render(&self, context: &mut RenderContext) {
+        let primary_window = self.system_window_manager.state().primary();
+
+        if primary_window.is_some() {
+            context.add_camera(Camera::new(primary_window));
+        }
+    }
 }
 
 fn main() {
     let mut app = App::new();
 
-    app.set_world(SceneTree {});
+    app.set_world(SceneTree::new(app.system_window_manager().clone()));
     app.add_plugin(DefaultPlugins);
 
     app.run();